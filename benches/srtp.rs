@@ -0,0 +1,58 @@
+//! SRTP protect/unprotect throughput for the crypto backend in use.
+//!
+//! ```text
+//! cargo bench --bench srtp --features _internal_test_exports
+//! cargo bench --bench srtp --features _internal_test_exports,rust-crypto
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use str0m::_internal_test_exports::SrtpContext;
+use str0m::crypto::{KeyingMaterial, SrtpProfile};
+use str0m::rtp::{ExtensionMap, RtpHeader};
+
+const PAYLOAD_LEN: usize = 1200;
+
+fn rtp_packet() -> (Vec<u8>, RtpHeader) {
+    // V=2, PT=96, seq=1, ts=1, ssrc=0x12345678
+    let mut buf = vec![
+        0x80, 0x60, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78,
+    ];
+    buf.extend((0..PAYLOAD_LEN).map(|i| i as u8));
+
+    let header = RtpHeader::parse(&buf, &ExtensionMap::empty()).expect("header to parse");
+
+    (buf, header)
+}
+
+fn srtp(c: &mut Criterion) {
+    let mut group = c.benchmark_group("srtp");
+    group.throughput(Throughput::Bytes(PAYLOAD_LEN as u64));
+
+    let (buf, header) = rtp_packet();
+
+    for profile in SrtpProfile::ALL {
+        let mat = KeyingMaterial::new(&vec![7; profile.keying_material_len()]);
+        let mut ctx_tx = SrtpContext::new(*profile, &mat, true);
+        let mut ctx_rx = SrtpContext::new(*profile, &mat, true);
+
+        group.bench_function(BenchmarkId::new("protect_rtp", profile), |b| {
+            b.iter(|| ctx_tx.protect_rtp(&buf, &header, 1))
+        });
+
+        let protected = ctx_tx.protect_rtp(&buf, &header, 1);
+
+        group.bench_function(BenchmarkId::new("unprotect_rtp", profile), |b| {
+            b.iter(|| {
+                ctx_rx
+                    .unprotect_rtp(&protected, &header, 1)
+                    .expect("unprotect")
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, srtp);
+criterion_main!(benches);
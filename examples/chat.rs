@@ -17,8 +17,9 @@ use str0m::change::{SdpAnswer, SdpOffer, SdpPendingOffer};
 use str0m::channel::{ChannelData, ChannelId};
 use str0m::media::MediaKind;
 use str0m::media::{Direction, KeyframeRequest, MediaData, Mid, Rid};
+use str0m::net::{Protocol, Receive};
 use str0m::Event;
-use str0m::{net::Receive, Candidate, IceConnectionState, Input, Output, Rtc, RtcError};
+use str0m::{Candidate, IceConnectionState, Input, Output, Rtc, RtcError};
 
 mod util;
 
@@ -240,6 +241,7 @@ fn read_socket_input<'a>(socket: &UdpSocket, buf: &'a mut Vec<u8>) -> Option<Inp
             return Some(Input::Receive(
                 Instant::now(),
                 Receive {
+                    proto: Protocol::Udp,
                     source,
                     destination: socket.local_addr().unwrap(),
                     contents,
@@ -11,7 +11,7 @@ use rouille::Server;
 use rouille::{Request, Response};
 
 use str0m::change::SdpOffer;
-use str0m::net::Receive;
+use str0m::net::{Protocol, Receive};
 use str0m::IceConnectionState;
 use str0m::{Candidate, Event, Input, Output, Rtc, RtcError};
 
@@ -124,6 +124,7 @@ fn run(mut rtc: Rtc, socket: UdpSocket) -> Result<(), RtcError> {
                 Input::Receive(
                     Instant::now(),
                     Receive {
+                        proto: Protocol::Udp,
                         source,
                         destination: socket.local_addr().unwrap(),
                         contents: buf.as_slice().try_into()?,
@@ -1,9 +1,10 @@
 use crate::channel::ChannelId;
 use crate::dtls::Fingerprint;
-use crate::ice::IceCreds;
+use crate::ice::{Candidate, IceCreds};
 use crate::media::{Media, MediaKind};
 use crate::rtp_::{Mid, Rid, Ssrc};
 use crate::sctp::ChannelConfig;
+use crate::sdes::SrtpMasterKey;
 use crate::streams::{StreamRx, StreamTx, DEFAULT_RTX_CACHE_DURATION};
 use crate::Rtc;
 use crate::RtcError;
@@ -72,6 +73,32 @@ impl<'a> DirectApi<'a> {
         self.rtc.ice.set_remote_credentials(remote_ice_credentials);
     }
 
+    /// Sets whether the remote ICE agent supports renomination.
+    ///
+    /// This is what `a=ice-options:renomination` tells in SDP.
+    /// [`NominationPolicy::Renomination`][crate::NominationPolicy::Renomination] is only
+    /// used when both sides support it.
+    pub fn set_remote_ice_renomination(&mut self, enabled: bool) {
+        self.rtc.ice.set_remote_renomination(enabled);
+    }
+
+    /// Switches sending to the candidate pair of a local and a remote candidate.
+    ///
+    /// This can be used to prefer one network over another, like Wi-Fi over cellular,
+    /// regardless of candidate priorities. The candidates are typically taken from
+    /// [`Rtc::ice_stats()`][crate::Rtc::ice_stats].
+    ///
+    /// The pair must have had a successful connectivity check, and this side must be
+    /// the ICE controlling agent. The pair stays selected until it fails. The remote
+    /// agent only follows the switch with
+    /// [`NominationPolicy::Renomination`][crate::NominationPolicy::Renomination],
+    /// see [`DirectApi::set_remote_ice_renomination()`].
+    ///
+    /// Returns `true` if the pair was selected.
+    pub fn select_ice_pair(&mut self, local: &Candidate, remote: &Candidate) -> bool {
+        self.rtc.ice.select_pair(local, remote)
+    }
+
     /// Returns a reference to the local DTLS fingerprint used by this peer connection.
     ///
     /// The DTLS fingerprint is a hash of the local SSL/TLS certificate used to authenticate the
@@ -86,8 +113,18 @@ impl<'a> DirectApi<'a> {
     }
 
     /// Sets the remote DTLS fingerprint.
+    ///
+    /// This replaces any fingerprints set before.
     pub fn set_remote_fingerprint(&mut self, dtls_fingerprint: Fingerprint) {
-        self.rtc.remote_fingerprint = Some(dtls_fingerprint);
+        self.rtc.remote_fingerprints = vec![dtls_fingerprint];
+    }
+
+    /// Adds a remote DTLS fingerprint.
+    ///
+    /// The remote certificate is accepted if any of the fingerprints match. They can use
+    /// different hash functions, `sha-1`, `sha-224`, `sha-256`, `sha-384` or `sha-512`.
+    pub fn add_remote_fingerprint(&mut self, dtls_fingerprint: Fingerprint) {
+        self.rtc.remote_fingerprints.push(dtls_fingerprint);
     }
 
     /// Start the DTLS subsystem.
@@ -95,6 +132,37 @@ impl<'a> DirectApi<'a> {
         self.rtc.init_dtls(active)
     }
 
+    /// Sets the SRTP master keys when using SDES instead of DTLS.
+    ///
+    /// The `local` key protects what we send and the `remote` key is what the remote peer
+    /// sends with. Both keys must be for the same profile. This requires
+    /// [`MediaProtection::Sdes`][crate::MediaProtection::Sdes].
+    pub fn set_srtp_master_keys(
+        &mut self,
+        local: SrtpMasterKey,
+        remote: SrtpMasterKey,
+    ) -> Result<(), RtcError> {
+        let Some(sdes) = &mut self.rtc.sdes else {
+            return Err(RtcError::SrtpMasterKeys("SDES is not enabled".into()));
+        };
+
+        if local.profile() != remote.profile() {
+            return Err(RtcError::SrtpMasterKeys(format!(
+                "Different profiles {:?} and {:?}",
+                local.profile(),
+                remote.profile()
+            )));
+        }
+
+        if let Some((local, remote)) = sdes.set_keys(local, remote) {
+            self.rtc
+                .session
+                .set_srtp_master_keys(local.as_ref(), remote.as_ref());
+        }
+
+        Ok(())
+    }
+
     /// Start the SCTP over DTLS.
     pub fn start_sctp(&mut self, client: bool) {
         self.rtc.init_sctp(client)
@@ -117,6 +185,13 @@ impl<'a> DirectApi<'a> {
         self.rtc.ice.set_ice_lite(ice_lite);
     }
 
+    /// Set whether the remote accepts two-byte header extensions (a=extmap-allow-mixed).
+    ///
+    /// Without it, header extension values longer than 16 bytes are not sent.
+    pub fn set_remote_extmap_allow_mixed(&mut self, allow_mixed: bool) {
+        self.rtc.session.remote_extmap_allow_mixed = allow_mixed;
+    }
+
     /// Enable twcc feedback.
     pub fn enable_twcc_feedback(&mut self) {
         self.rtc.session.enable_twcc_feedback()
@@ -19,5 +19,7 @@ pub use sdp::{SdpAnswer, SdpApi, SdpOffer, SdpPendingOffer};
 mod direct;
 pub use direct::DirectApi;
 
-pub use crate::dtls::{DtlsCert, Fingerprint};
+pub use crate::dtls::Fingerprint;
+#[cfg(feature = "openssl")]
+pub use crate::dtls::{DtlsCert, DtlsCertOptions, DtlsPKeyType};
 pub use crate::ice::IceCreds;
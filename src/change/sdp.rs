@@ -14,10 +14,12 @@ use crate::packet::MediaKind;
 use crate::rtp_::Rid;
 use crate::rtp_::{Direction, Extension, ExtensionMap, Mid, Pt, Ssrc};
 use crate::sctp::ChannelConfig;
+use crate::sdes::SdesCrypto;
 use crate::sdp::SimulcastGroups;
 use crate::sdp::{self, MediaAttribute, MediaLine, MediaType, Msid, Sdp};
 use crate::sdp::{Proto, SessionAttribute, Setup};
 use crate::session::Session;
+use crate::MediaProtection;
 use crate::Rtc;
 use crate::RtcError;
 
@@ -75,30 +77,43 @@ impl<'a> SdpApi<'a> {
 
         add_ice_details(self.rtc, &offer, None)?;
 
-        if self.rtc.remote_fingerprint.is_none() {
-            if let Some(f) = offer.fingerprint() {
-                self.rtc.remote_fingerprint = Some(f);
-            } else {
+        if self.rtc.media_protection == MediaProtection::DtlsSrtp
+            && self.rtc.remote_fingerprints.is_empty()
+        {
+            let fingerprints = offer.fingerprints();
+            if fingerprints.is_empty() {
                 self.rtc.disconnect();
                 return Err(RtcError::RemoteSdp("missing a=fingerprint".into()));
             }
+            self.rtc.remote_fingerprints = fingerprints;
         }
 
-        if !self.rtc.dtls.is_inited() {
+        if !self.rtc.is_secure_inited() {
             // The side that makes the first offer is the controlling side.
             self.rtc.ice.set_controlling(false);
         }
 
-        // Ensure setup=active/passive is corresponding remote and init dtls.
-        init_dtls(self.rtc, &offer)?;
+        match self.rtc.media_protection {
+            MediaProtection::Sdes => {
+                // Pick SRTP keys from the a=crypto lines.
+                init_sdes(self.rtc, &offer, true)?;
+            }
+            MediaProtection::Unencrypted => init_unencrypted(self.rtc, &offer)?,
+            MediaProtection::DtlsSrtp => {
+                // Ensure setup=active/passive is corresponding remote and init dtls.
+                init_dtls(self.rtc, &offer)?;
+            }
+        }
 
         // Modify session with offer
         apply_offer(&mut self.rtc.session, offer)?;
 
-        // Handle potentially new m=application line.
-        let client = self.rtc.dtls.is_active().expect("DTLS active to be set");
-        if self.rtc.session.app().is_some() {
-            self.rtc.init_sctp(client);
+        // Handle potentially new m=application line. Data channels require DTLS.
+        if self.rtc.media_protection == MediaProtection::DtlsSrtp {
+            let client = self.rtc.dtls.is_active().expect("DTLS active to be set");
+            if self.rtc.session.app().is_some() {
+                self.rtc.init_sctp(client);
+            }
         }
 
         let params = AsSdpParams::new(self.rtc, None);
@@ -145,16 +160,27 @@ impl<'a> SdpApi<'a> {
 
         add_ice_details(self.rtc, &answer, Some(&pending))?;
 
-        // Ensure setup=active/passive is corresponding remote and init dtls.
-        init_dtls(self.rtc, &answer)?;
+        match self.rtc.media_protection {
+            MediaProtection::Sdes => {
+                // Find the answered a=crypto matching our offer.
+                init_sdes(self.rtc, &answer, false)?;
+            }
+            MediaProtection::Unencrypted => init_unencrypted(self.rtc, &answer)?,
+            MediaProtection::DtlsSrtp => {
+                // Ensure setup=active/passive is corresponding remote and init dtls.
+                init_dtls(self.rtc, &answer)?;
+            }
+        }
 
-        if self.rtc.remote_fingerprint.is_none() {
-            if let Some(f) = answer.fingerprint() {
-                self.rtc.remote_fingerprint = Some(f);
-            } else {
+        if self.rtc.media_protection == MediaProtection::DtlsSrtp
+            && self.rtc.remote_fingerprints.is_empty()
+        {
+            let fingerprints = answer.fingerprints();
+            if fingerprints.is_empty() {
                 self.rtc.disconnect();
                 return Err(RtcError::RemoteSdp("missing a=fingerprint".into()));
             }
+            self.rtc.remote_fingerprints = fingerprints;
         }
 
         // Split out new channels, since that is not handled by the Session.
@@ -163,10 +189,12 @@ impl<'a> SdpApi<'a> {
         // Modify session with answer
         apply_answer(&mut self.rtc.session, pending.changes, answer)?;
 
-        // Handle potentially new m=application line.
-        let client = self.rtc.dtls.is_active().expect("DTLS to be inited");
-        if self.rtc.session.app().is_some() {
-            self.rtc.init_sctp(client);
+        // Handle potentially new m=application line. Data channels require DTLS.
+        if self.rtc.media_protection == MediaProtection::DtlsSrtp {
+            let client = self.rtc.dtls.is_active().expect("DTLS to be inited");
+            if self.rtc.session.app().is_some() {
+                self.rtc.init_sctp(client);
+            }
         }
 
         for (id, config) in new_channels {
@@ -560,11 +588,15 @@ fn apply_direct_changes(rtc: &mut Rtc, mut changes: Changes) {
 }
 
 fn create_offer(rtc: &mut Rtc, changes: &Changes) -> SdpOffer {
-    if !rtc.dtls.is_inited() {
+    if !rtc.is_secure_inited() {
         // The side that makes the first offer is the controlling side.
         rtc.ice.set_controlling(true);
     }
 
+    if let Some(sdes) = &mut rtc.sdes {
+        sdes.create_offer();
+    }
+
     let params = AsSdpParams::new(rtc, Some(changes));
     let sdp = as_sdp(&rtc.session, params);
 
@@ -610,14 +642,56 @@ fn add_ice_details(
     }
 
     rtc.ice.set_remote_credentials(creds);
+    rtc.ice
+        .set_remote_renomination(sdp.has_ice_option("renomination"));
 
     for r in sdp.ice_candidates() {
         rtc.ice.add_remote_candidate(r.clone());
     }
 
+    negotiate_rtcp_mux(rtc, sdp);
+
     Ok(())
 }
 
+/// Decide whether RTP and RTCP are multiplexed from the remote SDP. Without rtcp-mux,
+/// RTCP uses ICE component 2.
+///
+/// https://www.rfc-editor.org/rfc/rfc5761#section-5.1.1
+fn negotiate_rtcp_mux(rtc: &mut Rtc, sdp: &Sdp) {
+    if rtc.rtcp_mux == Some(true) {
+        // Once negotiated, rtcp-mux is used for the rest of the session.
+        if !sdp.rtcp_mux() {
+            debug!("Ignore missing a=rtcp-mux in remote SDP");
+        }
+        return;
+    }
+
+    if sdp.rtcp_mux() {
+        info!("Negotiated rtcp-mux");
+        rtc.rtcp_mux = Some(true);
+        rtc.rtcp_send_addr = None;
+        rtc.ice.set_rtcp_mux(true);
+        return;
+    }
+
+    if rtc.rtcp_mux.is_none() {
+        info!("Negotiated separate RTP and RTCP");
+        rtc.rtcp_mux = Some(false);
+    }
+
+    // Peers that don't signal candidates for the RTCP component tell the
+    // address in a=rtcp instead.
+    let has_rtcp_candidate = sdp.ice_candidates().any(|c| c.component_id() == 2);
+    if let (Some(addr), false) = (sdp.rtcp_addr(), has_rtcp_candidate) {
+        match Candidate::host(addr).and_then(|c| c.with_component_id(2)) {
+            Ok(c) => rtc.ice.add_remote_candidate(c),
+            // Typically the 0.0.0.0 placeholder.
+            Err(_) => trace!("Ignore a=rtcp address: {}", addr),
+        }
+    }
+}
+
 fn init_dtls(rtc: &mut Rtc, remote_sdp: &Sdp) -> Result<(), RtcError> {
     let setup = match remote_sdp.setup() {
         Some(v) => match v {
@@ -638,6 +712,47 @@ fn init_dtls(rtc: &mut Rtc, remote_sdp: &Sdp) -> Result<(), RtcError> {
     Ok(())
 }
 
+fn init_sdes(rtc: &mut Rtc, remote_sdp: &Sdp, is_offer: bool) -> Result<(), RtcError> {
+    let Some(sdes) = &mut rtc.sdes else {
+        return Ok(());
+    };
+
+    let (proto, crypto) = remote_sdp.crypto();
+
+    let keys = if is_offer {
+        sdes.accept_offer(proto, &crypto)
+    } else {
+        sdes.accept_answer(&crypto)
+    }
+    .map_err(RtcError::RemoteSdp)?;
+
+    if let Some((local, remote)) = keys {
+        if let Some(key) = local.as_ref().or(remote.as_ref()) {
+            info!("SDES SRTP profile: {:?}", key.profile());
+        }
+        rtc.session
+            .set_srtp_master_keys(local.as_ref(), remote.as_ref());
+    }
+
+    Ok(())
+}
+
+fn init_unencrypted(rtc: &mut Rtc, remote_sdp: &Sdp) -> Result<(), RtcError> {
+    let (proto, _) = remote_sdp.crypto();
+
+    // Don't silently drop encryption the remote side expects.
+    if proto != Proto::RtpAvpf {
+        return Err(RtcError::RemoteSdp(format!(
+            "Expected RTP/AVPF for unencrypted media, got: {}",
+            proto.proto_line()
+        )));
+    }
+
+    rtc.unencrypted_inited = true;
+
+    Ok(())
+}
+
 fn as_sdp(session: &Session, params: AsSdpParams) -> Sdp {
     let (media_lines, mids, stream_ids) = {
         let mut v = as_media_lines(session);
@@ -674,13 +789,16 @@ fn as_sdp(session: &Session, params: AsSdpParams) -> Sdp {
                     ssrcs.extend(pending.ssrcs_for_mid(m.mid()))
                 }
 
-                let params: Vec<_> = session
+                let payload_params: Vec<_> = session
                     .codec_config
                     .all_for_kind(m.kind())
                     .cloned()
                     .collect();
 
-                m.as_media_line(attrs, &ssrcs, &session.exts, &params)
+                let mut line = m.as_media_line(attrs, &ssrcs, &session.exts, &payload_params);
+                params.apply_rtcp_mux(&mut line);
+                params.apply_media_protection(&mut line);
+                line
             })
             .collect::<Vec<_>>();
 
@@ -712,6 +830,13 @@ fn as_sdp(session: &Session, params: AsSdpParams) -> Sdp {
         },
     ];
 
+    // We parse two-byte header extensions, used for long values. An answer
+    // only includes it if the offer did, see RFC 8285.
+    let is_offer = params.pending.is_some();
+    if is_offer || session.remote_extmap_allow_mixed {
+        attrs.push(SessionAttribute::ExtmapAllowMixed);
+    }
+
     if session.ice_lite {
         attrs.push(SessionAttribute::IceLite);
     }
@@ -959,6 +1084,8 @@ fn add_new_lines(
 /// Update session level properties like
 /// Extensions from offer or answer.
 fn update_session(session: &mut Session, sdp: &Sdp) {
+    session.remote_extmap_allow_mixed = sdp.session.extmap_allow_mixed();
+
     // Does any m-line contain a a=rtcp-fb:xx transport-cc?
     let has_transport_cc = sdp
         .media_lines
@@ -1131,6 +1258,7 @@ impl AsSdpMediaLine for (Mid, usize) {
             disabled: false,
             proto: Proto::Sctp,
             pts: vec![],
+            connection: None,
             bw: None,
             attrs,
         }
@@ -1256,6 +1384,7 @@ impl AsSdpMediaLine for Media {
             disabled: false,
             proto: Proto::Srtp,
             pts,
+            connection: None,
             bw: None,
             attrs,
         }
@@ -1274,8 +1403,12 @@ impl From<MediaKind> for MediaType {
 struct AsSdpParams<'a, 'b> {
     pub candidates: Vec<Candidate>,
     pub creds: IceCreds,
+    pub rtcp_mux: bool,
+    pub rtcp: Option<String>,
     pub fingerprint: &'a Fingerprint,
     pub setup: Setup,
+    pub media_proto: Option<(Proto, Vec<SdesCrypto>)>,
+    pub renomination: bool,
     pub pending: Option<&'b Changes>,
 }
 
@@ -1304,15 +1437,48 @@ impl<'a, 'b> AsSdpParams<'a, 'b> {
             )
         };
 
+        // Until rtcp-mux is negotiated, we offer both a=rtcp-mux and a=rtcp.
+        let rtcp_mux = rtc.rtcp_mux != Some(false);
+        let rtcp = if rtc.rtcp_mux == Some(true) {
+            None
+        } else {
+            // The best candidate for the RTCP component, like the c= line does for RTP.
+            let best = candidates
+                .iter()
+                .filter(|c| c.component_id() == 2 && !c.discarded())
+                .max_by_key(|c| c.prio());
+            Some(match best {
+                Some(c) => {
+                    let addr = c.addr();
+                    let ip = if addr.is_ipv4() { "IP4" } else { "IP6" };
+                    format!("{} IN {} {}", addr.port(), ip, addr.ip())
+                }
+                None => "9 IN IP4 0.0.0.0".into(),
+            })
+        };
+
+        let candidates = candidates
+            .into_iter()
+            .filter(|c| rtcp.is_some() || c.component_id() == 1)
+            .collect();
+
         AsSdpParams {
             candidates,
             creds,
+            rtcp_mux,
+            rtcp,
             fingerprint: rtc.dtls.local_fingerprint(),
             setup: match rtc.dtls.is_active() {
                 Some(true) => Setup::Active,
                 Some(false) => Setup::Passive,
                 None => Setup::ActPass,
             },
+            media_proto: match rtc.media_protection {
+                MediaProtection::Sdes => rtc.sdes.as_ref().map(|s| (s.proto(), s.local().to_vec())),
+                MediaProtection::Unencrypted => Some((Proto::RtpAvpf, vec![])),
+                MediaProtection::DtlsSrtp => None,
+            },
+            renomination: rtc.ice.local_renomination(),
             pending,
         }
     }
@@ -1331,12 +1497,49 @@ impl<'a, 'b> AsSdpParams<'a, 'b> {
 
         v.push(IceUfrag(self.creds.ufrag.clone()));
         v.push(IcePwd(self.creds.pass.clone()));
-        v.push(IceOptions("trickle".into()));
+        if self.renomination {
+            v.push(IceOptions("trickle renomination".into()));
+        } else {
+            v.push(IceOptions("trickle".into()));
+        }
         v.push(Fingerprint(self.fingerprint.clone()));
         v.push(Setup(self.setup));
 
         v
     }
+
+    /// Without rtcp-mux, audio/video m-lines say where RTCP goes in a=rtcp.
+    fn apply_rtcp_mux(&self, line: &mut MediaLine) {
+        if !line.typ.is_media() {
+            return;
+        }
+
+        if let Some(rtcp) = &self.rtcp {
+            line.attrs.insert(0, MediaAttribute::Rtcp(rtcp.clone()));
+        }
+
+        if !self.rtcp_mux {
+            line.attrs.retain(|a| *a != MediaAttribute::RtcpMux);
+        }
+    }
+
+    /// Without DTLS, audio/video m-lines drop the DTLS details. With SDES they carry
+    /// a=crypto keys instead.
+    fn apply_media_protection(&self, line: &mut MediaLine) {
+        let Some((proto, crypto)) = &self.media_proto else {
+            return;
+        };
+
+        if !line.typ.is_media() {
+            return;
+        }
+
+        line.proto = *proto;
+        line.attrs
+            .retain(|a| !matches!(a, MediaAttribute::Fingerprint(_) | MediaAttribute::Setup(_)));
+        line.attrs
+            .extend(crypto.iter().cloned().map(MediaAttribute::Crypto));
+    }
 }
 
 impl fmt::Debug for SdpPendingOffer {
@@ -1493,8 +1696,9 @@ impl Change {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "openssl"))]
 mod test {
+    use crate::crypto::{SrtpMasterKey, SrtpProfile};
     use crate::format::Codec;
     use crate::sdp::RtpMap;
 
@@ -1549,6 +1753,72 @@ mod test {
         assert_eq!(new_offer.media_lines.len(), 2);
     }
 
+    #[test]
+    fn sdes_reoffer_keeps_srtcp_index() {
+        let config = || {
+            Rtc::builder()
+                .set_media_protection(crate::MediaProtection::Sdes)
+                .set_srtp_profiles(&[SrtpProfile::Aes128CmSha1_80])
+        };
+        let mut rtc1 = config().build();
+        let mut rtc2 = config().build();
+
+        // The index is in the clear, before the 10 byte auth tag.
+        let next_srtcp_index = |rtc: &mut Rtc| {
+            let rr = [0x80, 201, 0, 1, 0, 0, 0, 1];
+            let out = rtc.session.srtp_tx().unwrap().protect_rtcp(&rr);
+            let i = out.len() - 14;
+            u32::from_be_bytes([out[i], out[i + 1], out[i + 2], out[i + 3]]) & 0x7fff_ffff
+        };
+
+        let mut change = rtc2.sdp_api();
+        change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+        let (offer, _) = change.apply().unwrap();
+        let old_key = offer.crypto().1[0].key.to_inline();
+        let offer_str = offer.to_sdp_string();
+        let answer = rtc1.sdp_api().accept_offer(offer).unwrap();
+
+        assert_eq!(next_srtcp_index(&mut rtc1), 0);
+        assert_eq!(next_srtcp_index(&mut rtc1), 1);
+
+        // Same offer, but with a new remote key.
+        let new_key = SrtpMasterKey::random(SrtpProfile::Aes128CmSha1_80).to_inline();
+        let reoffer = offer_str.replace(&old_key, &new_key);
+        let reoffer = SdpOffer::from_sdp_string(&reoffer).unwrap();
+        let reanswer = rtc1.sdp_api().accept_offer(reoffer).unwrap();
+
+        // Our key and SRTCP index carry on.
+        assert_eq!(answer.crypto().1, reanswer.crypto().1);
+        assert_eq!(next_srtcp_index(&mut rtc1), 2);
+    }
+
+    #[test]
+    fn answer_extmap_allow_mixed_only_if_offered() {
+        let mut rtc1 = Rtc::new();
+        let mut rtc2 = Rtc::new();
+
+        let mut change = rtc1.sdp_api();
+        change.add_media(MediaKind::Video, Direction::SendRecv, None, None);
+        let (offer, _) = change.apply().unwrap();
+        assert!(offer.session.extmap_allow_mixed());
+
+        let offer_str = offer
+            .to_sdp_string()
+            .replace("a=extmap-allow-mixed\r\n", "");
+        let answer = rtc2
+            .sdp_api()
+            .accept_offer(SdpOffer::from_sdp_string(&offer_str).unwrap())
+            .unwrap();
+
+        assert!(!answer.session.extmap_allow_mixed());
+        assert!(!rtc2.session.remote_extmap_allow_mixed);
+
+        let answer = rtc2.sdp_api().accept_offer(offer).unwrap();
+
+        assert!(answer.session.extmap_allow_mixed());
+        assert!(rtc2.session.remote_extmap_allow_mixed);
+    }
+
     #[test]
     fn test_rtp_payload_priority() {
         let mut rtc1 = Rtc::builder()
@@ -1,29 +1,48 @@
-use openssl::error::ErrorStack;
-use openssl::ssl::SslContext;
 use std::collections::VecDeque;
 use std::fmt;
-use std::io::{self, ErrorKind, Read, Write};
+use std::io;
 use std::net::SocketAddr;
+use std::ops::Deref;
+use std::panic::UnwindSafe;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 use crate::io::{DatagramRecv, DatagramSend, Receive, DATAGRAM_MTU_WARN};
 
+#[cfg(feature = "openssl")]
 mod ossl;
-use ossl::{dtls_create_ctx, dtls_ssl_create, TlsStream};
-
-pub use ossl::DtlsCert;
-pub(crate) use ossl::KeyingMaterial;
+#[cfg(feature = "openssl")]
+pub use ossl::{DtlsCert, DtlsCertOptions, DtlsPKeyType};
 
 /// Errors that can arise in DTLS.
 #[derive(Debug, Error)]
 pub enum DtlsError {
     /// Some error from OpenSSL layer (used for DTLS).
+    #[cfg(feature = "openssl")]
     #[error("{0}")]
-    OpenSsl(#[from] ErrorStack),
+    OpenSsl(#[from] openssl::error::ErrorStack),
 
     /// Other IO errors.
     #[error("{0}")]
     Io(#[from] io::Error),
+
+    /// The certificate or private key can't be used.
+    #[error("DTLS certificate: {0}")]
+    Certificate(String),
+
+    /// Some error from another DTLS implementation.
+    #[error("{0}")]
+    Other(String),
+
+    /// The handshake failed, such as the remote peer sending an alert.
+    #[error("DTLS handshake failed: {0}")]
+    HandshakeFailed(String),
+
+    /// The handshake did not complete within the configured time.
+    ///
+    /// See [`RtcConfig::set_dtls_handshake_timeout()`][crate::RtcConfig::set_dtls_handshake_timeout].
+    #[error("DTLS handshake timed out after {0:?}")]
+    HandshakeTimeout(Duration),
 }
 
 impl DtlsError {
@@ -50,6 +69,53 @@ pub struct Fingerprint {
     pub bytes: Vec<u8>,
 }
 
+impl Fingerprint {
+    /// Tells if this fingerprint, typically from the SDP, is of the remote certificate.
+    ///
+    /// `observed` is the fingerprint the DTLS session made of the remote certificate.
+    /// When the hash functions differ, the DER encoded `certificate` is hashed instead.
+    pub(crate) fn verify(&self, observed: &Fingerprint, certificate: Option<&[u8]>) -> bool {
+        if self.hash_func.eq_ignore_ascii_case(&observed.hash_func) {
+            return self.bytes == observed.bytes;
+        }
+
+        let Some(certificate) = certificate else {
+            debug!(
+                "No remote certificate to check {} fingerprint",
+                self.hash_func
+            );
+            return false;
+        };
+
+        match digest(&self.hash_func, certificate) {
+            Some(v) => v == self.bytes,
+            None => {
+                debug!("Unsupported fingerprint hash function: {}", self.hash_func);
+                false
+            }
+        }
+    }
+}
+
+/// Hash with one of the SHA functions in RFC 8122.
+///
+/// sha-1 is weak, and only accepted for interop with gateways that advertise nothing else.
+/// md2 and md5 are broken and not accepted.
+fn digest(hash_func: &str, data: &[u8]) -> Option<Vec<u8>> {
+    use sha2::Digest;
+
+    let v = match hash_func.to_ascii_lowercase().as_str() {
+        "sha-1" => sha1::Sha1::digest(data).to_vec(),
+        "sha-224" => sha2::Sha224::digest(data).to_vec(),
+        "sha-256" => sha2::Sha256::digest(data).to_vec(),
+        "sha-384" => sha2::Sha384::digest(data).to_vec(),
+        "sha-512" => sha2::Sha512::digest(data).to_vec(),
+        _ => return None,
+    };
+
+    Some(v)
+}
+
 impl ToString for Fingerprint {
     /// Convert to the hex string you find in SDP
     fn to_string(&self) -> String {
@@ -87,52 +153,288 @@ impl std::str::FromStr for Fingerprint {
     }
 }
 
+/// SRTP protection profile negotiated in the DTLS handshake.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum SrtpProfile {
+    /// `SRTP_AES128_CM_SHA1_80`, see [RFC 5764][1].
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc5764#section-4.1.2
     Aes128CmSha1_80,
+    /// `SRTP_AES256_CM_SHA1_80`, see [RFC 6188][1].
+    ///
+    /// There is no DTLS-SRTP protection profile for this cipher, which means it is
+    /// never negotiated in the DTLS handshake. It can be used with SDES.
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc6188
+    Aes256CmSha1_80,
+    /// `SRTP_AEAD_AES_128_GCM`, see [RFC 7714][1].
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7714#section-14.2
     AeadAes128Gcm,
+    /// `SRTP_AEAD_AES_256_GCM`, see [RFC 7714][1].
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7714#section-14.2
+    AeadAes256Gcm,
 }
 
 impl SrtpProfile {
-    // All the profiles we support, ordered from most preferred to least.
-    pub(crate) const ALL: &[SrtpProfile] =
+    /// All the profiles we support, ordered from most preferred to least.
+    pub const ALL: &'static [SrtpProfile] = &[
+        SrtpProfile::AeadAes256Gcm,
+        SrtpProfile::AeadAes128Gcm,
+        SrtpProfile::Aes256CmSha1_80,
+        SrtpProfile::Aes128CmSha1_80,
+    ];
+
+    /// The profiles offered in the DTLS handshake unless configured otherwise.
+    ///
+    /// See [`RtcConfig::set_srtp_profiles()`][crate::RtcConfig::set_srtp_profiles].
+    pub const DEFAULT: &'static [SrtpProfile] =
         &[SrtpProfile::AeadAes128Gcm, SrtpProfile::Aes128CmSha1_80];
 
     /// The length of keying material to extract from the DTLS session in bytes.
-    #[rustfmt::skip]
-    pub(crate) fn keying_material_len(&self) -> usize {
+    pub fn keying_material_len(&self) -> usize {
+        // MASTER_KEY_LEN * 2 + MASTER_SALT * 2
+        self.master_key_len() * 2 + self.master_salt_len() * 2
+    }
+
+    /// The length of the SRTP master key in bytes.
+    pub fn master_key_len(&self) -> usize {
+        // TODO: This is a duplication of info that is held in srtp.rs, because we
+        // don't want a dependency in that direction.
         match self {
-             // MASTER_KEY_LEN * 2 + MASTER_SALT * 2
-             // TODO: This is a duplication of info that is held in srtp.rs, because we
-             // don't want a dependency in that direction.
-            SrtpProfile::Aes128CmSha1_80 => 16 * 2 + 14 * 2,
-            SrtpProfile::AeadAes128Gcm   => 16 * 2 + 12 * 2,
+            SrtpProfile::Aes128CmSha1_80 | SrtpProfile::AeadAes128Gcm => 16,
+            SrtpProfile::Aes256CmSha1_80 | SrtpProfile::AeadAes256Gcm => 32,
+        }
+    }
+
+    /// The length of the SRTP master salt in bytes.
+    pub fn master_salt_len(&self) -> usize {
+        match self {
+            SrtpProfile::Aes128CmSha1_80 | SrtpProfile::Aes256CmSha1_80 => 14,
+            SrtpProfile::AeadAes128Gcm | SrtpProfile::AeadAes256Gcm => 12,
         }
     }
 
     /// What this profile is called in OpenSSL parlance.
-    pub(crate) fn openssl_name(&self) -> &'static str {
+    ///
+    /// `None` if the profile can't be negotiated with DTLS-SRTP.
+    #[cfg(feature = "openssl")]
+    pub(crate) fn openssl_name(&self) -> Option<&'static str> {
+        match self {
+            SrtpProfile::Aes128CmSha1_80 => Some("SRTP_AES128_CM_SHA1_80"),
+            SrtpProfile::Aes256CmSha1_80 => None,
+            SrtpProfile::AeadAes128Gcm => Some("SRTP_AEAD_AES_128_GCM"),
+            SrtpProfile::AeadAes256Gcm => Some("SRTP_AEAD_AES_256_GCM"),
+        }
+    }
+
+    /// The crypto-suite name used in SDES `a=crypto` lines.
+    pub(crate) fn sdes_name(&self) -> &'static str {
         match self {
-            SrtpProfile::Aes128CmSha1_80 => "SRTP_AES128_CM_SHA1_80",
-            SrtpProfile::AeadAes128Gcm => "SRTP_AEAD_AES_128_GCM",
+            SrtpProfile::Aes128CmSha1_80 => "AES_CM_128_HMAC_SHA1_80",
+            SrtpProfile::Aes256CmSha1_80 => "AES_256_CM_HMAC_SHA1_80",
+            SrtpProfile::AeadAes128Gcm => "AEAD_AES_128_GCM",
+            SrtpProfile::AeadAes256Gcm => "AEAD_AES_256_GCM",
         }
     }
+
+    /// Look up a profile from an SDES crypto-suite name.
+    pub(crate) fn from_sdes_name(name: &str) -> Option<SrtpProfile> {
+        SrtpProfile::ALL
+            .iter()
+            .copied()
+            .find(|p| p.sdes_name() == name)
+    }
 }
 
 impl fmt::Display for SrtpProfile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SrtpProfile::Aes128CmSha1_80 => write!(f, "SRTP_AES128_CM_SHA1_80"),
+            SrtpProfile::Aes256CmSha1_80 => write!(f, "SRTP_AES256_CM_SHA1_80"),
             SrtpProfile::AeadAes128Gcm => write!(f, "SRTP_AEAD_AES_128_GCM"),
+            SrtpProfile::AeadAes256Gcm => write!(f, "SRTP_AEAD_AES_256_GCM"),
         }
     }
 }
 
+/// What was negotiated in a DTLS handshake.
+///
+/// Available with [`Rtc::dtls_info()`][crate::Rtc::dtls_info] and in
+/// [`Event::DtlsConnected`][crate::Event::DtlsConnected] once the handshake is complete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DtlsInfo {
+    /// The protocol version, such as `DTLSv1.2`.
+    pub version: String,
+
+    /// The cipher suite, such as `ECDHE-ECDSA-AES128-GCM-SHA256`.
+    ///
+    /// The name is as given by the DTLS implementation.
+    pub cipher: String,
+
+    /// The negotiated SRTP protection profile.
+    pub srtp_profile: SrtpProfile,
+
+    /// Whether we are the DTLS client, i.e. the side that started the handshake.
+    pub active: bool,
+
+    /// The remote certificate chain, DER encoded, starting with the peer certificate.
+    ///
+    /// With WebRTC, this is normally a single self signed certificate.
+    pub remote_certificates: Vec<Vec<u8>>,
+}
+
+/// Keying material used as master key for SRTP.
+pub struct KeyingMaterial(Vec<u8>);
+
+impl KeyingMaterial {
+    /// Creates keying material, as exported from the DTLS session.
+    ///
+    /// The length must be [`SrtpProfile::keying_material_len()`].
+    pub fn new(m: &[u8]) -> Self {
+        KeyingMaterial(m.into())
+    }
+}
+
+impl Deref for KeyingMaterial {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Debug for KeyingMaterial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyingMaterial")
+    }
+}
+
+/// A DTLS implementation.
+///
+/// The provider creates one [`DtlsSession`] per [`Rtc`][crate::Rtc] instance. Use
+/// [`RtcConfig::set_dtls_provider()`][crate::RtcConfig::set_dtls_provider] to use
+/// another implementation than the built in OpenSSL one.
+pub trait DtlsProvider: fmt::Debug + Send + Sync {
+    /// Creates a new DTLS session.
+    ///
+    /// The session offers the `srtp_profiles` in the handshake, most preferred first.
+    fn new_session(&self, srtp_profiles: &[SrtpProfile])
+        -> Result<Box<dyn DtlsSession>, DtlsError>;
+}
+
+/// One DTLS session, as created by a [`DtlsProvider`].
+///
+/// The session is Sans I/O. Incoming datagrams are given to
+/// [`DtlsSession::handle_receive()`], and outgoing datagrams are polled with
+/// [`DtlsSession::poll_datagram()`]. The handshake must negotiate DTLS-SRTP
+/// ([RFC 5764][1]) with one of the profiles given to [`DtlsProvider::new_session()`].
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc5764
+pub trait DtlsSession: Send + Sync + UnwindSafe {
+    /// Fingerprint of the local certificate.
+    fn local_fingerprint(&self) -> Fingerprint;
+
+    /// Set whether this side initiates the handshake (client), or not (server).
+    ///
+    /// This is called exactly once before handshaking.
+    fn set_active(&mut self, active: bool);
+
+    /// Whether this side initiates the handshake, if set.
+    fn is_active(&self) -> Option<bool>;
+
+    /// Progress the handshake as far as possible with the received datagrams.
+    ///
+    /// Returns `true` once the handshake is complete.
+    fn handshake(&mut self) -> Result<bool, DtlsError>;
+
+    /// Resend the last handshake flight.
+    ///
+    /// This is called when the retransmission timer expires without a reply from the
+    /// remote peer. The datagrams are then polled with [`DtlsSession::poll_datagram()`].
+    fn retransmit(&mut self);
+
+    /// Tells if a new handshake flight was written since the last call.
+    ///
+    /// Retransmissions from [`DtlsSession::retransmit()`] are not new flights. The
+    /// retransmission timer starts over with each new flight.
+    fn take_new_flight(&mut self) -> bool;
+
+    /// Tells if the handshake is complete.
+    fn is_connected(&self) -> bool;
+
+    /// Takes the SRTP keying material, the negotiated profile and the fingerprint
+    /// of the remote certificate.
+    ///
+    /// This is available once, when the handshake is complete.
+    fn take_srtp_keying_material(&mut self) -> Option<(KeyingMaterial, SrtpProfile, Fingerprint)>;
+
+    /// What was negotiated in the handshake, once it is complete.
+    ///
+    /// Implementations that can't tell return `None`.
+    fn info(&self) -> Option<DtlsInfo> {
+        None
+    }
+
+    /// Handle an incoming DTLS datagram.
+    fn handle_receive(&mut self, datagram: &[u8]) -> Result<(), DtlsError>;
+
+    /// Poll decrypted application data, if the last received datagram had any.
+    fn poll_data(&mut self) -> Result<Option<Vec<u8>>, DtlsError>;
+
+    /// Handle application data to be sent encrypted.
+    fn handle_input(&mut self, data: &[u8]) -> Result<(), DtlsError>;
+
+    /// Poll for the next datagram to send.
+    fn poll_datagram(&mut self) -> Option<DatagramSend>;
+}
+
+/// Initial timeout before resending a handshake flight, see [RFC 6347][1].
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc6347#section-4.2.4.1
+const RETRANSMIT_INITIAL: Duration = Duration::from_secs(1);
+
+/// The retransmission timeout doubles up to this.
+const RETRANSMIT_MAX: Duration = Duration::from_secs(60);
+
+/// Timers while handshaking.
+struct HandshakeTimer {
+    /// When the handshake started.
+    started: Instant,
+
+    /// When to next resend the last flight.
+    retransmit: Instant,
+
+    /// The current retransmission timeout.
+    interval: Duration,
+}
+
+impl HandshakeTimer {
+    fn new(now: Instant) -> Self {
+        HandshakeTimer {
+            started: now,
+            retransmit: now + RETRANSMIT_INITIAL,
+            interval: RETRANSMIT_INITIAL,
+        }
+    }
+
+    /// A new flight was sent, start over with the initial timeout.
+    fn restart(&mut self, now: Instant) {
+        self.interval = RETRANSMIT_INITIAL;
+        self.retransmit = now + self.interval;
+    }
+
+    /// No reply in time, double the timeout.
+    fn back_off(&mut self, now: Instant) {
+        self.interval = (self.interval * 2).min(RETRANSMIT_MAX);
+        self.retransmit = now + self.interval;
+    }
+}
+
 /// Encapsulation of DTLS.
 pub struct Dtls {
-    /// Certificate for the DTLS session.
-    _cert: DtlsCert,
-
     /// The fingerprint of the certificate.
     fingerprint: Fingerprint,
 
@@ -142,14 +444,17 @@ pub struct Dtls {
     /// Remote fingerprint.
     remote_fingerprint: Option<Fingerprint>,
 
-    /// Context belongs together with Fingerprint.
-    ///
-    /// This just needs to be kept alive since it pins the entire openssl context
-    /// from which `Ssl` is created.
-    _context: SslContext,
+    /// What was negotiated, once handshaken.
+    info: Option<DtlsInfo>,
+
+    /// Max time for the handshake to complete.
+    handshake_timeout: Duration,
+
+    /// Retransmission and handshake timeout, while handshaking.
+    timer: Option<HandshakeTimer>,
 
-    /// The actual openssl TLS stream.
-    tls: TlsStream<IoBuffer>,
+    /// The actual DTLS implementation.
+    session: Box<dyn DtlsSession>,
 
     /// Outgoing events, ready to be polled.
     events: VecDeque<DtlsEvent>,
@@ -177,17 +482,21 @@ impl Dtls {
     ///
     /// `active` indicates whether this side should initiate the handshake or not.
     /// This in turn is governed by the `a=setup` SDP attribute.
-    pub fn new(cert: DtlsCert, fingerprint_verification: bool) -> Result<Self, DtlsError> {
-        let fingerprint = cert.fingerprint();
-        let context = dtls_create_ctx(&cert)?;
-        let ssl = dtls_ssl_create(&context)?;
+    pub fn new(
+        provider: &dyn DtlsProvider,
+        srtp_profiles: &[SrtpProfile],
+        fingerprint_verification: bool,
+        handshake_timeout: Duration,
+    ) -> Result<Self, DtlsError> {
+        let session = provider.new_session(srtp_profiles)?;
         Ok(Dtls {
-            _cert: cert,
-            fingerprint,
+            fingerprint: session.local_fingerprint(),
             fingerprint_verification,
             remote_fingerprint: None,
-            _context: context,
-            tls: TlsStream::new(ssl, IoBuffer::default()),
+            info: None,
+            handshake_timeout,
+            timer: None,
+            session,
             events: VecDeque::new(),
         })
     }
@@ -196,7 +505,7 @@ impl Dtls {
     ///
     /// Once true, we cannot do `set_active` anymore.
     pub fn is_inited(&self) -> bool {
-        self.tls.is_inited()
+        self.session.is_active().is_some()
     }
 
     /// Set whether this instance is active or passive.
@@ -204,12 +513,12 @@ impl Dtls {
     /// i.e. initiating the client hello or not. This must be called
     /// exactly once before starting to handshake (I/O).
     pub fn set_active(&mut self, active: bool) {
-        self.tls.set_active(active);
+        self.session.set_active(active);
     }
 
     /// If set_active, returns what was set.
     pub fn is_active(&self) -> Option<bool> {
-        self.tls.is_active()
+        self.session.is_active()
     }
 
     /// The local fingerprint.
@@ -224,9 +533,29 @@ impl Dtls {
         &self.remote_fingerprint
     }
 
+    /// What was negotiated, once handshaken.
+    pub fn info(&self) -> Option<&DtlsInfo> {
+        self.info.as_ref()
+    }
+
+    /// Tells if any of the `expected` fingerprints match the remote certificate.
+    pub fn verify_remote_fingerprint(&self, expected: &[Fingerprint]) -> bool {
+        let Some(observed) = &self.remote_fingerprint else {
+            return false;
+        };
+
+        let certificate = self
+            .info
+            .as_ref()
+            .and_then(|i| i.remote_certificates.first())
+            .map(|c| &c[..]);
+
+        expected.iter().any(|f| f.verify(observed, certificate))
+    }
+
     /// Poll for the next datagram to send.
     pub fn poll_datagram(&mut self) -> Option<DatagramSend> {
-        let x = self.tls.inner_mut().pop_outgoing();
+        let x = self.session.poll_datagram();
         if let Some(x) = &x {
             if x.len() > DATAGRAM_MTU_WARN {
                 warn!("DTLS above MTU {}: {}", DATAGRAM_MTU_WARN, x.len());
@@ -247,11 +576,11 @@ impl Dtls {
 
     /// Handling incoming data to be sent as DTLS datagrams.
     pub fn handle_input(&mut self, data: &[u8]) -> Result<(), DtlsError> {
-        Ok(self.tls.write_all(data)?)
+        self.session.handle_input(data)
     }
 
     /// Handles an incoming DTLS datagrams.
-    pub fn handle_receive(&mut self, r: Receive) -> Result<(), DtlsError> {
+    pub fn handle_receive(&mut self, now: Instant, r: Receive) -> Result<(), DtlsError> {
         let message = match r.contents {
             DatagramRecv::Dtls(v) => v,
             _ => {
@@ -260,24 +589,23 @@ impl Dtls {
             }
         };
 
-        self.tls.inner_mut().set_incoming(message);
+        self.session.handle_receive(message)?;
 
         if self.handle_handshake()? {
+            // A received datagram that doesn't complete the remote flight, or is a
+            // duplicate, must not reset the backoff.
+            if self.session.take_new_flight() {
+                if let Some(timer) = &mut self.timer {
+                    timer.restart(now);
+                }
+            }
             // early return as long as we're handshaking
             return Ok(());
         }
 
-        let mut buf = vec![0; 2000];
-        let n = match self.tls.read(&mut buf) {
-            Ok(v) => v,
-            Err(e) if e.kind() == ErrorKind::WouldBlock => {
-                return Ok(());
-            }
-            Err(e) => return Err(e.into()),
-        };
-        buf.truncate(n);
-
-        self.events.push_back(DtlsEvent::Data(buf));
+        if let Some(data) = self.session.poll_data()? {
+            self.events.push_back(DtlsEvent::Data(data));
+        }
 
         Ok(())
     }
@@ -286,18 +614,24 @@ impl Dtls {
     ///
     /// Once handshaken, this becomes a noop.
     pub fn handle_handshake(&mut self) -> Result<bool, DtlsError> {
-        if self.tls.is_handshaken() {
+        if self.session.is_connected() {
             // Nice. Nothing to do.
             Ok(false)
-        } else if self.tls.complete_handshake_until_block()? {
+        } else if self
+            .session
+            .handshake()
+            .map_err(|e| DtlsError::HandshakeFailed(e.to_string()))?
+        {
             self.events.push_back(DtlsEvent::Connected);
+            self.timer = None;
 
             let (keying_material, srtp_profile, fingerprint) = self
-                .tls
+                .session
                 .take_srtp_keying_material()
                 .expect("Exported keying material");
 
             self.remote_fingerprint = Some(fingerprint.clone());
+            self.info = self.session.info();
 
             if self.fingerprint_verification {
                 self.events
@@ -312,59 +646,46 @@ impl Dtls {
         }
     }
 
-    pub(crate) fn is_connected(&self) -> bool {
-        self.tls.is_connected()
-    }
-}
-
-#[derive(Default)]
-struct IoBuffer {
-    pub incoming: Vec<u8>,
-    pub outgoing: VecDeque<DatagramSend>,
-}
-
-impl IoBuffer {
-    fn set_incoming(&mut self, buf: &[u8]) {
-        assert!(self.incoming.is_empty());
-        self.incoming.resize(buf.len(), 0);
-        self.incoming.copy_from_slice(buf);
-    }
+    /// Drive the handshake timers.
+    ///
+    /// Resends the last flight when the retransmission timer expires, and fails with
+    /// [`DtlsError::HandshakeTimeout`] if the handshake doesn't complete in time. The
+    /// timers start on the first call after [`Dtls::set_active()`].
+    pub fn handle_timeout(&mut self, now: Instant) -> Result<(), DtlsError> {
+        if !self.is_inited() || self.is_connected() {
+            return Ok(());
+        }
 
-    fn pop_outgoing(&mut self) -> Option<DatagramSend> {
-        self.outgoing.pop_front()
-    }
-}
+        if self.timer.is_none() {
+            // The first flight, if any, was sent before the timer started.
+            self.session.take_new_flight();
+        }
+        let timer = self.timer.get_or_insert_with(|| HandshakeTimer::new(now));
 
-impl io::Read for IoBuffer {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let n = self.incoming.len();
+        if now >= timer.started + self.handshake_timeout {
+            return Err(DtlsError::HandshakeTimeout(self.handshake_timeout));
+        }
 
-        if n == 0 {
-            return Err(io::Error::new(io::ErrorKind::WouldBlock, "WouldBlock"));
+        if now < timer.retransmit {
+            return Ok(());
         }
 
-        // read buffer must read entire packet in one go.
-        // we can't fragment incoming datagrams.
-        assert!(buf.len() >= n);
+        timer.back_off(now);
+        debug!("DTLS retransmit, next timeout in {:?}", timer.interval);
 
-        buf[0..n].copy_from_slice(&self.incoming);
-        self.incoming.truncate(0);
+        self.session.retransmit();
 
-        Ok(n)
+        Ok(())
     }
-}
 
-impl io::Write for IoBuffer {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let dsend = buf.to_vec().into();
-
-        self.outgoing.push_back(dsend);
-
-        Ok(buf.len())
+    /// The next time [`Dtls::handle_timeout()`] needs to be called.
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        let timer = self.timer.as_ref()?;
+        Some(timer.retransmit.min(timer.started + self.handshake_timeout))
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+    pub(crate) fn is_connected(&self) -> bool {
+        self.session.is_connected()
     }
 }
 
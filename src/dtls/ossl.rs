@@ -1,6 +1,6 @@
 use openssl::asn1::{Asn1Integer, Asn1Time};
 use openssl::bn::BigNum;
-use openssl::ec::EcKey;
+use openssl::ec::{EcGroup, EcKey};
 use openssl::hash::MessageDigest;
 use openssl::nid::Nid;
 use openssl::pkey::{PKey, Private};
@@ -10,18 +10,17 @@ use openssl::ssl::{HandshakeError, MidHandshakeSslStream, Ssl, SslStream};
 use openssl::ssl::{SslContext, SslContextBuilder, SslMethod, SslOptions, SslVerifyMode};
 use openssl::x509::X509;
 
-use std::io;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
 use std::mem;
-use std::ops::Deref;
 use std::panic::UnwindSafe;
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
-use crate::io::DATAGRAM_MTU;
+use crate::io::{DatagramSend, DATAGRAM_MTU};
 
-use super::DtlsError;
-use super::Fingerprint;
-use super::SrtpProfile;
+use super::{DtlsError, DtlsInfo, DtlsProvider, DtlsSession};
+use super::{Fingerprint, KeyingMaterial, SrtpProfile};
 
 const RSA_F4: u32 = 0x10001;
 const DTLS_CIPHERS: &str = "EECDH+AESGCM:EDH+AESGCM:AES256+EECDH:AES256+EDH";
@@ -33,22 +32,131 @@ const DTLS_KEY_LABEL: &str = "EXTRACTOR-dtls_srtp";
 // }
 
 /// Certificate used for DTLS.
+///
+/// Creating a certificate takes some time, and the fingerprint identifies the peer.
+/// A server can create one certificate and use it for all [`Rtc`][crate::Rtc] instances
+/// with [`RtcConfig::set_dtls_cert()`][crate::RtcConfig::set_dtls_cert]. To keep the
+/// fingerprint across restarts, the certificate can be persisted as PEM or DER.
+///
+/// ```
+/// # use str0m::change::DtlsCert;
+/// let cert = DtlsCert::new();
+///
+/// let key = cert.private_key_pem().unwrap();
+/// let x509 = cert.certificate_pem().unwrap();
+///
+/// let loaded = DtlsCert::from_pem(&key, &x509).unwrap();
+/// assert_eq!(cert.fingerprint(), loaded.fingerprint());
+/// ```
 #[derive(Debug, Clone)]
 pub struct DtlsCert {
     pkey: PKey<Private>,
     x509: X509,
 }
 
+/// Type of private key for a generated [`DtlsCert`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum DtlsPKeyType {
+    /// ECDSA with the P-256 curve. This is what browsers use.
+    #[default]
+    EcDsaP256,
+    /// RSA with a 2048 bit key. This is much slower to generate than ECDSA.
+    Rsa2048,
+}
+
+/// Options for generating a self signed [`DtlsCert`].
+///
+/// ```
+/// # use std::time::Duration;
+/// # use str0m::change::{DtlsCert, DtlsCertOptions, DtlsPKeyType};
+/// let options = DtlsCertOptions::new()
+///     .set_pkey_type(DtlsPKeyType::EcDsaP256)
+///     .set_validity(Duration::from_secs(365 * 24 * 3600));
+///
+/// let cert = DtlsCert::with_options(options).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DtlsCertOptions {
+    pkey_type: DtlsPKeyType,
+    validity: Duration,
+}
+
+impl DtlsCertOptions {
+    /// Creates options with default values.
+    pub fn new() -> Self {
+        DtlsCertOptions::default()
+    }
+
+    /// Set the type of private key.
+    ///
+    /// Defaults to [`DtlsPKeyType::EcDsaP256`].
+    pub fn set_pkey_type(mut self, pkey_type: DtlsPKeyType) -> Self {
+        self.pkey_type = pkey_type;
+        self
+    }
+
+    /// The type of private key.
+    ///
+    /// ```
+    /// # use str0m::change::{DtlsCertOptions, DtlsPKeyType};
+    /// assert_eq!(DtlsCertOptions::new().pkey_type(), DtlsPKeyType::EcDsaP256);
+    /// ```
+    pub fn pkey_type(&self) -> DtlsPKeyType {
+        self.pkey_type
+    }
+
+    /// Set for how long the certificate is valid, counted from when it is created.
+    ///
+    /// Defaults to 7 days.
+    pub fn set_validity(mut self, validity: Duration) -> Self {
+        self.validity = validity;
+        self
+    }
+
+    /// For how long the certificate is valid.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::change::DtlsCertOptions;
+    /// assert_eq!(DtlsCertOptions::new().validity(), Duration::from_secs(7 * 24 * 3600));
+    /// ```
+    pub fn validity(&self) -> Duration {
+        self.validity
+    }
+}
+
+impl Default for DtlsCertOptions {
+    fn default() -> Self {
+        DtlsCertOptions {
+            pkey_type: DtlsPKeyType::default(),
+            validity: Duration::from_secs(7 * 24 * 3600),
+        }
+    }
+}
+
 impl DtlsCert {
     /// Creates a new (self signed) DTLS certificate.
+    ///
+    /// Uses the default [`DtlsCertOptions`].
     pub fn new() -> Self {
-        Self::self_signed().expect("create dtls cert")
+        Self::with_options(DtlsCertOptions::default()).expect("create dtls cert")
     }
 
-    fn self_signed() -> Result<Self, DtlsError> {
-        let f4 = BigNum::from_u32(RSA_F4).unwrap();
-        let key = Rsa::generate_with_e(2048, &f4)?;
-        let pkey = PKey::from_rsa(key)?;
+    /// Creates a new (self signed) DTLS certificate with options.
+    pub fn with_options(options: DtlsCertOptions) -> Result<Self, DtlsError> {
+        let pkey = match options.pkey_type {
+            DtlsPKeyType::EcDsaP256 => {
+                let group = EcGroup::from_curve_name(DTLS_EC_CURVE)?;
+                let key = EcKey::generate(&group)?;
+                PKey::from_ec_key(key)?
+            }
+            DtlsPKeyType::Rsa2048 => {
+                let f4 = BigNum::from_u32(RSA_F4).unwrap();
+                let key = Rsa::generate_with_e(2048, &f4)?;
+                PKey::from_rsa(key)?
+            }
+        };
 
         let mut x509b = X509::builder()?;
 
@@ -62,16 +170,59 @@ impl DtlsCert {
         x509b.set_serial_number(&serial)?;
         let before = Asn1Time::from_unix(unix_time() - 3600)?;
         x509b.set_not_before(&before)?;
-        let after = Asn1Time::days_from_now(7)?;
+        let after = Asn1Time::from_unix(unix_time() + options.validity.as_secs() as i64)?;
         x509b.set_not_after(&after)?;
         x509b.set_pubkey(&pkey)?;
 
-        x509b.sign(&pkey, MessageDigest::sha1())?;
+        x509b.sign(&pkey, MessageDigest::sha256())?;
         let x509 = x509b.build();
 
         Ok(DtlsCert { pkey, x509 })
     }
 
+    /// Loads a certificate from a PEM encoded private key and X.509 certificate.
+    pub fn from_pem(private_key: &[u8], certificate: &[u8]) -> Result<Self, DtlsError> {
+        let pkey = PKey::private_key_from_pem(private_key)?;
+        let x509 = X509::from_pem(certificate)?;
+        Self::from_parts(pkey, x509)
+    }
+
+    /// Loads a certificate from a DER encoded private key and X.509 certificate.
+    pub fn from_der(private_key: &[u8], certificate: &[u8]) -> Result<Self, DtlsError> {
+        let pkey = PKey::private_key_from_der(private_key)?;
+        let x509 = X509::from_der(certificate)?;
+        Self::from_parts(pkey, x509)
+    }
+
+    fn from_parts(pkey: PKey<Private>, x509: X509) -> Result<Self, DtlsError> {
+        if !x509.public_key()?.public_eq(&pkey) {
+            return Err(DtlsError::Certificate(
+                "private key doesn't match the certificate".into(),
+            ));
+        }
+        Ok(DtlsCert { pkey, x509 })
+    }
+
+    /// The private key, PEM encoded (PKCS#8).
+    pub fn private_key_pem(&self) -> Result<Vec<u8>, DtlsError> {
+        Ok(self.pkey.private_key_to_pem_pkcs8()?)
+    }
+
+    /// The X.509 certificate, PEM encoded.
+    pub fn certificate_pem(&self) -> Result<Vec<u8>, DtlsError> {
+        Ok(self.x509.to_pem()?)
+    }
+
+    /// The private key, DER encoded.
+    pub fn private_key_der(&self) -> Result<Vec<u8>, DtlsError> {
+        Ok(self.pkey.private_key_to_der()?)
+    }
+
+    /// The X.509 certificate, DER encoded.
+    pub fn certificate_der(&self) -> Result<Vec<u8>, DtlsError> {
+        Ok(self.x509.to_der()?)
+    }
+
     /// Produce a (public) fingerprint of the cert.
     ///
     /// This is sent via SDP to the other peer to lock down the DTLS
@@ -89,7 +240,10 @@ impl DtlsCert {
     }
 }
 
-pub fn dtls_create_ctx(cert: &DtlsCert) -> Result<SslContext, DtlsError> {
+pub fn dtls_create_ctx(
+    cert: &DtlsCert,
+    srtp_profiles: &[SrtpProfile],
+) -> Result<SslContext, DtlsError> {
     // TODO: Technically we want to disallow DTLS < 1.2, but that requires
     // us to use this commented out unsafe. We depend on browsers disallowing
     // it instead.
@@ -100,11 +254,17 @@ pub fn dtls_create_ctx(cert: &DtlsCert) -> Result<SslContext, DtlsError> {
     let srtp_profiles = {
         // Rust can't join directly to a string, need to allocate a vec first :(
         // This happens very rarely so the extra allocations don't matter
-        let all: Vec<_> = SrtpProfile::ALL
+        let all: Vec<_> = srtp_profiles
             .iter()
-            .map(SrtpProfile::openssl_name)
+            .filter_map(SrtpProfile::openssl_name)
             .collect();
 
+        if all.is_empty() {
+            return Err(DtlsError::Other(
+                "No SRTP profile that can be negotiated with DTLS".into(),
+            ));
+        }
+
         all.join(":")
     };
     ctx.set_tlsext_use_srtp(&srtp_profiles)?;
@@ -137,28 +297,241 @@ pub fn dtls_ssl_create(ctx: &SslContext) -> Result<Ssl, DtlsError> {
     Ok(ssl)
 }
 
-/// Keying material used as master key for SRTP.
-pub struct KeyingMaterial(Vec<u8>);
+impl DtlsProvider for DtlsCert {
+    fn new_session(
+        &self,
+        srtp_profiles: &[SrtpProfile],
+    ) -> Result<Box<dyn DtlsSession>, DtlsError> {
+        let context = dtls_create_ctx(self, srtp_profiles)?;
+        let ssl = dtls_ssl_create(&context)?;
+        Ok(Box::new(OsslSession {
+            fingerprint: self.fingerprint(),
+            _context: context,
+            tls: TlsStream::new(ssl, IoBuffer::default()),
+        }))
+    }
+}
+
+/// DTLS session using OpenSSL.
+struct OsslSession {
+    /// The fingerprint of the certificate.
+    fingerprint: Fingerprint,
+
+    /// Context belongs together with Fingerprint.
+    ///
+    /// This just needs to be kept alive since it pins the entire openssl context
+    /// from which `Ssl` is created.
+    _context: SslContext,
+
+    /// The actual openssl TLS stream.
+    tls: TlsStream<IoBuffer>,
+}
+
+impl DtlsSession for OsslSession {
+    fn local_fingerprint(&self) -> Fingerprint {
+        self.fingerprint.clone()
+    }
+
+    fn set_active(&mut self, active: bool) {
+        self.tls.set_active(active);
+    }
+
+    fn is_active(&self) -> Option<bool> {
+        self.tls.is_active()
+    }
+
+    fn handshake(&mut self) -> Result<bool, DtlsError> {
+        let done = self.tls.complete_handshake_until_block()?;
+        let io = self.tls.inner_mut();
+        // OpenSSL writes a new flight in one go, anything after is its own retransmit.
+        io.writing_flight = false;
+        io.dropping = false;
+        if done {
+            // No more flights to resend.
+            io.flight = None;
+        }
+        Ok(done)
+    }
+
+    fn retransmit(&mut self) {
+        self.tls.inner_mut().resend_flight();
+    }
+
+    fn take_new_flight(&mut self) -> bool {
+        mem::take(&mut self.tls.inner_mut().flight_written)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.tls.is_connected()
+    }
+
+    fn take_srtp_keying_material(&mut self) -> Option<(KeyingMaterial, SrtpProfile, Fingerprint)> {
+        self.tls.take_srtp_keying_material()
+    }
+
+    fn info(&self) -> Option<DtlsInfo> {
+        self.tls.info()
+    }
+
+    fn handle_receive(&mut self, datagram: &[u8]) -> Result<(), DtlsError> {
+        self.tls.inner_mut().set_incoming(datagram);
+        Ok(())
+    }
+
+    fn poll_data(&mut self) -> Result<Option<Vec<u8>>, DtlsError> {
+        let mut buf = vec![0; 2000];
+        let n = match self.tls.read(&mut buf) {
+            Ok(v) => v,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        buf.truncate(n);
+
+        Ok(Some(buf))
+    }
+
+    fn handle_input(&mut self, data: &[u8]) -> Result<(), DtlsError> {
+        Ok(self.tls.write_all(data)?)
+    }
 
-impl KeyingMaterial {
-    #[cfg(test)]
-    pub fn new(m: &[u8]) -> Self {
-        KeyingMaterial(m.into())
+    fn poll_datagram(&mut self) -> Option<DatagramSend> {
+        self.tls.inner_mut().pop_outgoing()
     }
 }
 
-impl Deref for KeyingMaterial {
-    type Target = [u8];
+struct IoBuffer {
+    pub incoming: Vec<u8>,
+    pub outgoing: VecDeque<DatagramSend>,
+
+    /// The datagrams of the last handshake flight, `None` once handshaken.
+    pub flight: Option<Vec<Vec<u8>>>,
+
+    /// Set when receiving, the next write starts a new flight.
+    new_flight: bool,
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    /// Set while OpenSSL writes a new flight.
+    writing_flight: bool,
+
+    /// Set while OpenSSL retransmits a flight on its own timer.
+    dropping: bool,
+
+    /// Set when a new flight is written, until taken.
+    pub flight_written: bool,
+}
+
+impl Default for IoBuffer {
+    fn default() -> Self {
+        IoBuffer {
+            incoming: vec![],
+            outgoing: VecDeque::new(),
+            flight: Some(vec![]),
+            new_flight: true,
+            writing_flight: false,
+            dropping: false,
+            flight_written: false,
+        }
     }
 }
 
-impl std::fmt::Debug for KeyingMaterial {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "KeyingMaterial")
+impl IoBuffer {
+    fn set_incoming(&mut self, buf: &[u8]) {
+        assert!(self.incoming.is_empty());
+        self.incoming.resize(buf.len(), 0);
+        self.incoming.copy_from_slice(buf);
+        self.new_flight = true;
     }
+
+    fn pop_outgoing(&mut self) -> Option<DatagramSend> {
+        self.outgoing.pop_front()
+    }
+
+    fn resend_flight(&mut self) {
+        let Some(flight) = &self.flight else {
+            return;
+        };
+        for d in flight {
+            self.outgoing.push_back(d.clone().into());
+        }
+    }
+}
+
+impl io::Read for IoBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.incoming.len();
+
+        if n == 0 {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "WouldBlock"));
+        }
+
+        // read buffer must read entire packet in one go.
+        // we can't fragment incoming datagrams.
+        assert!(buf.len() >= n);
+
+        buf[0..n].copy_from_slice(&self.incoming);
+        self.incoming.truncate(0);
+
+        Ok(n)
+    }
+}
+
+impl io::Write for IoBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(flight) = &mut self.flight {
+            // OpenSSL resends flights on a timer using the wall clock, but resending is
+            // driven by Dtls::handle_timeout(). OpenSSL's own retransmits are dropped.
+            if self.new_flight {
+                self.new_flight = false;
+                let resent = flight.first().map(|f| same_first_record(f, buf));
+                if resent == Some(true) {
+                    self.dropping = true;
+                } else {
+                    flight.clear();
+                    self.writing_flight = true;
+                    self.flight_written = true;
+                }
+            } else if !self.writing_flight {
+                self.dropping = true;
+            }
+
+            if self.dropping {
+                trace!("Drop OpenSSL DTLS retransmit: {}", buf.len());
+                return Ok(buf.len());
+            }
+
+            flight.push(buf.to_vec());
+        }
+
+        let dsend = buf.to_vec().into();
+
+        self.outgoing.push_back(dsend);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Tells if two datagrams start with the same DTLS record, apart from the sequence number.
+///
+/// The first record of a flight is never encrypted, and a retransmit only has a new
+/// record sequence number.
+fn same_first_record(a: &[u8], b: &[u8]) -> bool {
+    // content type (1), version (2), epoch (2), sequence number (6), length (2)
+    const HEADER_LEN: usize = 13;
+
+    if a.len() < HEADER_LEN || b.len() < HEADER_LEN {
+        return false;
+    }
+    let len = HEADER_LEN + u16::from_be_bytes([a[11], a[12]]) as usize;
+    if a.len() < len || b.len() < len {
+        return false;
+    }
+
+    a[..5] == b[..5] && a[11..len] == b[11..len]
 }
 
 pub struct TlsStream<S> {
@@ -192,10 +565,6 @@ where
         }
     }
 
-    pub fn is_inited(&self) -> bool {
-        self.active.is_some()
-    }
-
     pub fn is_active(&self) -> Option<bool> {
         self.active
     }
@@ -224,10 +593,6 @@ where
         }
     }
 
-    pub fn is_handshaken(&self) -> bool {
-        matches!(self.state, State::Established(_))
-    }
-
     pub fn handshaken(&mut self) -> Result<&mut SslStream<S>, io::Error> {
         let active = self.is_active().expect("set_active must be called");
         let v = self.state.handshaken(active)?;
@@ -248,6 +613,40 @@ where
         self.keying_mat.take()
     }
 
+    /// What was negotiated, once the handshake is complete.
+    pub fn info(&self) -> Option<DtlsInfo> {
+        let State::Established(stream) = &self.state else {
+            return None;
+        };
+        let ssl = stream.ssl();
+
+        let srtp_profile = ssl
+            .selected_srtp_profile()
+            .and_then(|p| SrtpProfile::try_from(p.id()).ok())?;
+
+        // On the server side, the peer chain does not include the peer certificate.
+        let mut remote_certificates = vec![];
+        let peer = ssl.peer_certificate();
+        let chain = ssl.peer_cert_chain().into_iter().flatten();
+        for x509 in peer.iter().map(|p| &**p).chain(chain) {
+            let der = x509.to_der().ok()?;
+            if !remote_certificates.contains(&der) {
+                remote_certificates.push(der);
+            }
+        }
+
+        Some(DtlsInfo {
+            version: ssl.version_str().to_string(),
+            cipher: ssl
+                .current_cipher()
+                .map(|c| c.name().to_string())
+                .unwrap_or_default(),
+            srtp_profile,
+            active: self.active?,
+            remote_certificates,
+        })
+    }
+
     pub fn inner_mut(&mut self) -> &mut S {
         match &mut self.state {
             State::Init(_, s) => s,
@@ -337,7 +736,7 @@ fn export_srtp_keying_material<S>(
     let mut buf = vec![0_u8; srtp_profile.keying_material_len()];
     ssl.export_keying_material(&mut buf, DTLS_KEY_LABEL, None)?;
 
-    let mat = KeyingMaterial(buf);
+    let mat = KeyingMaterial::new(&buf);
 
     Ok((mat, srtp_profile, fp))
 }
@@ -371,6 +770,7 @@ impl TryFrom<SrtpProfileId> for SrtpProfile {
         match value {
             SrtpProfileId::SRTP_AES128_CM_SHA1_80 => Ok(SrtpProfile::Aes128CmSha1_80),
             SrtpProfileId::SRTP_AEAD_AES_128_GCM => Ok(SrtpProfile::AeadAes128Gcm),
+            SrtpProfileId::SRTP_AEAD_AES_256_GCM => Ok(SrtpProfile::AeadAes256Gcm),
             x => Err(io::Error::new(
                 io::ErrorKind::Other,
                 format!("Unsupported SRTP profile {:x}", x.as_raw()),
@@ -391,3 +791,239 @@ pub fn unix_time() -> i64 {
         .unwrap()
         .as_secs() as i64
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn der_roundtrip() {
+        for pkey_type in [DtlsPKeyType::EcDsaP256, DtlsPKeyType::Rsa2048] {
+            let options = DtlsCertOptions::new().set_pkey_type(pkey_type);
+            let cert = DtlsCert::with_options(options).unwrap();
+
+            let key = cert.private_key_der().unwrap();
+            let x509 = cert.certificate_der().unwrap();
+            let loaded = DtlsCert::from_der(&key, &x509).unwrap();
+
+            assert_eq!(cert.fingerprint(), loaded.fingerprint());
+        }
+    }
+
+    #[test]
+    fn reject_mismatched_key() {
+        let cert1 = DtlsCert::new();
+        let cert2 = DtlsCert::new();
+
+        let key = cert1.private_key_pem().unwrap();
+        let x509 = cert2.certificate_pem().unwrap();
+
+        assert!(matches!(
+            DtlsCert::from_pem(&key, &x509),
+            Err(DtlsError::Certificate(_))
+        ));
+    }
+
+    fn connect(a: &mut dyn DtlsSession, b: &mut dyn DtlsSession) {
+        a.set_active(true);
+        b.set_active(false);
+
+        // Shuttle datagrams between the sessions until both are handshaken.
+        for _ in 0..20 {
+            a.handshake().unwrap();
+            while let Some(d) = a.poll_datagram() {
+                b.handle_receive(&d).unwrap();
+                b.handshake().unwrap();
+            }
+            while let Some(d) = b.poll_datagram() {
+                a.handle_receive(&d).unwrap();
+                a.handshake().unwrap();
+            }
+            if a.is_connected() && b.is_connected() {
+                break;
+            }
+        }
+        assert!(a.is_connected() && b.is_connected());
+    }
+
+    #[test]
+    fn provider_sessions_handshake() {
+        let cert_a = DtlsCert::new();
+        let cert_b = DtlsCert::new();
+
+        let mut a = cert_a.new_session(SrtpProfile::DEFAULT).unwrap();
+        let mut b = cert_b.new_session(SrtpProfile::DEFAULT).unwrap();
+        connect(&mut *a, &mut *b);
+
+        let (mat_a, profile_a, fp_a) = a.take_srtp_keying_material().unwrap();
+        let (mat_b, profile_b, fp_b) = b.take_srtp_keying_material().unwrap();
+        assert_eq!(&*mat_a, &*mat_b);
+        assert_eq!(profile_a, profile_b);
+        assert_eq!(fp_a, cert_b.fingerprint());
+        assert_eq!(fp_b, cert_a.fingerprint());
+        assert!(a.take_srtp_keying_material().is_none());
+
+        a.handle_input(b"hello").unwrap();
+        let d = a.poll_datagram().unwrap();
+        b.handle_receive(&d).unwrap();
+        assert_eq!(b.poll_data().unwrap().as_deref(), Some(&b"hello"[..]));
+        assert_eq!(b.poll_data().unwrap(), None);
+    }
+
+    #[test]
+    fn negotiate_aead_aes_256_gcm() {
+        let cert_a = DtlsCert::new();
+        let cert_b = DtlsCert::new();
+
+        let mut a = cert_a.new_session(SrtpProfile::ALL).unwrap();
+        let mut b = cert_b
+            .new_session(&[SrtpProfile::AeadAes256Gcm, SrtpProfile::AeadAes128Gcm])
+            .unwrap();
+        connect(&mut *a, &mut *b);
+
+        let (mat_a, profile_a, _) = a.take_srtp_keying_material().unwrap();
+        let (mat_b, profile_b, _) = b.take_srtp_keying_material().unwrap();
+        assert_eq!(profile_a, SrtpProfile::AeadAes256Gcm);
+        assert_eq!(profile_b, SrtpProfile::AeadAes256Gcm);
+        assert_eq!(mat_a.len(), 88);
+        assert_eq!(&*mat_a, &*mat_b);
+    }
+
+    #[test]
+    fn session_info() {
+        let cert_a = DtlsCert::new();
+        let cert_b = DtlsCert::new();
+
+        let mut a = cert_a.new_session(SrtpProfile::DEFAULT).unwrap();
+        let mut b = cert_b.new_session(SrtpProfile::DEFAULT).unwrap();
+        assert!(a.info().is_none());
+        connect(&mut *a, &mut *b);
+
+        let info_a = a.info().unwrap();
+        let info_b = b.info().unwrap();
+
+        assert_eq!(info_a.version, "DTLSv1.2");
+        assert_eq!(info_a.cipher, info_b.cipher);
+        assert_eq!(info_a.srtp_profile, SrtpProfile::AeadAes128Gcm);
+        assert!(info_a.active);
+        assert!(!info_b.active);
+        assert_eq!(
+            info_a.remote_certificates,
+            vec![cert_b.certificate_der().unwrap()]
+        );
+        assert_eq!(
+            info_b.remote_certificates,
+            vec![cert_a.certificate_der().unwrap()]
+        );
+    }
+
+    /// A fake DTLS datagram with a single handshake record.
+    fn record(seq: u8, body: &[u8]) -> Vec<u8> {
+        let mut v = vec![22, 0xfe, 0xfd, 0, 0, 0, 0, 0, 0, 0, seq];
+        v.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        v.extend_from_slice(body);
+        v
+    }
+
+    #[test]
+    fn drop_openssl_retransmit() {
+        let mut io = IoBuffer::default();
+
+        // The first flight, written in one handshake call.
+        io.write_all(&record(0, b"hello")).unwrap();
+        io.write_all(&record(1, b"more")).unwrap();
+        io.writing_flight = false;
+        assert!(mem::take(&mut io.flight_written));
+        assert_eq!(io.outgoing.len(), 2);
+        io.outgoing.clear();
+
+        // OpenSSL's timer resends in a later call, with new sequence numbers.
+        io.write_all(&record(2, b"hello")).unwrap();
+        io.write_all(&record(3, b"more")).unwrap();
+        io.writing_flight = false;
+        io.dropping = false;
+        assert!(io.outgoing.is_empty());
+
+        // Also when it happens after receiving something that isn't a new flight.
+        io.set_incoming(b"dup");
+        io.incoming.clear();
+        io.write_all(&record(4, b"hello")).unwrap();
+        io.write_all(&record(5, b"more")).unwrap();
+        io.writing_flight = false;
+        io.dropping = false;
+        assert!(io.outgoing.is_empty());
+        assert!(!io.flight_written);
+        assert_eq!(io.flight.as_ref().unwrap()[0], record(0, b"hello"));
+
+        // A new flight replaces the cached one.
+        io.set_incoming(b"reply");
+        io.incoming.clear();
+        io.write_all(&record(6, b"next")).unwrap();
+        assert!(io.flight_written);
+        assert_eq!(io.outgoing.len(), 1);
+        assert_eq!(io.flight.as_ref().unwrap(), &vec![record(6, b"next")]);
+    }
+
+    #[test]
+    fn retransmit_on_timeout() {
+        use crate::dtls::Dtls;
+        use crate::io::{Protocol, Receive};
+        use std::time::Instant;
+
+        let cert_a = DtlsCert::new();
+        let cert_b = DtlsCert::new();
+        let timeout = Duration::from_secs(30);
+        let mut a = Dtls::new(&cert_a, SrtpProfile::DEFAULT, true, timeout).unwrap();
+        let mut b = Dtls::new(&cert_b, SrtpProfile::DEFAULT, true, timeout).unwrap();
+        a.set_active(true);
+        b.set_active(false);
+
+        let addr = "1.1.1.1:1000".parse().unwrap();
+        let receive = |dtls: &mut Dtls, now: Instant, d: &[u8]| {
+            let r = Receive::new(Protocol::Udp, addr, addr, d).unwrap();
+            dtls.handle_receive(now, r).unwrap();
+        };
+
+        let now = Instant::now();
+        a.handle_handshake().unwrap();
+        a.handle_timeout(now).unwrap();
+        let hello = a.poll_datagram().unwrap();
+        assert!(a.poll_datagram().is_none());
+        assert_eq!(a.poll_timeout(), Some(now + Duration::from_secs(1)));
+
+        // Nothing happens until the timer expires, then it doubles.
+        a.handle_timeout(now + Duration::from_millis(999)).unwrap();
+        assert!(a.poll_datagram().is_none());
+        a.handle_timeout(now + Duration::from_secs(1)).unwrap();
+        assert_eq!(&*a.poll_datagram().unwrap(), &*hello);
+        assert_eq!(a.poll_timeout(), Some(now + Duration::from_secs(3)));
+
+        // The server's flight makes a send a new flight, which restarts the backoff.
+        let t = now + Duration::from_secs(2);
+        receive(&mut b, t, &hello);
+        let mut flight = vec![];
+        while let Some(d) = b.poll_datagram() {
+            flight.push(d.to_vec());
+        }
+        for d in &flight {
+            receive(&mut a, t, d);
+        }
+        assert!(a.poll_datagram().is_some());
+        while a.poll_datagram().is_some() {}
+        assert_eq!(a.poll_timeout(), Some(t + Duration::from_secs(1)));
+
+        // A duplicate isn't a new flight, and doesn't restart the backoff.
+        receive(&mut a, t + Duration::from_millis(500), &flight[0]);
+        assert!(a.poll_datagram().is_none());
+        assert_eq!(a.poll_timeout(), Some(t + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn no_dtls_srtp_profile() {
+        let cert = DtlsCert::new();
+        assert!(matches!(
+            cert.new_session(&[SrtpProfile::Aes256CmSha1_80]),
+            Err(DtlsError::Other(_))
+        ));
+    }
+}
@@ -11,7 +11,8 @@ use crate::sdp::FormatParam;
 
 // These really don't belong anywhere, but I guess they're kind of related
 // to codecs etc.
-pub use crate::packet::{CodecExtra, Vp8CodecExtra};
+pub use crate::packet::{CodecExtra, H264CodecExtra, Vp8CodecExtra};
+pub use crate::packet::{Vp9CodecExtra, Vp9PictureGroupPicture, Vp9ScalabilityStructure};
 
 /// Session config for all codecs.
 #[derive(Debug, Clone, Default)]
@@ -103,13 +104,9 @@ pub struct CodecSpec {
 pub enum Codec {
     Opus,
     H264,
-    // TODO show this when we support h265.
-    #[doc(hidden)]
     H265,
     Vp8,
     Vp9,
-    // TODO show this when we support Av1.
-    #[doc(hidden)]
     Av1,
     /// Technically not a codec, but used in places where codecs go
     /// in `a=rtpmap` lines.
@@ -153,8 +150,29 @@ pub struct FormatParams {
     /// * 64 00 1f - 6400=high (H)                  1f=level 3.1
     pub profile_level_id: Option<u32>,
 
-    /// VP9 profile id.
+    /// VP9 and H265 profile id.
+    ///
+    /// For H265 1 is Main and 2 is Main 10.
     pub profile_id: Option<u32>,
+
+    /// H265 level id, 30 times the level, i.e. 93 for level 3.1.
+    pub level_id: Option<u8>,
+
+    /// H265 tier flag, 0 for the Main tier and 1 for the High tier.
+    pub tier_flag: Option<u8>,
+
+    /// AV1 profile.
+    ///
+    /// * 0 - Main.
+    /// * 1 - High.
+    /// * 2 - Professional.
+    pub profile: Option<u8>,
+
+    /// AV1 level index, the level is `2.0 + level_idx / 4`.
+    pub level_idx: Option<u8>,
+
+    /// AV1 tier, 0 for the Main tier and 1 for the High tier.
+    pub tier: Option<u8>,
 }
 
 impl PayloadParams {
@@ -274,6 +292,14 @@ impl PayloadParams {
             return Self::match_h264_score(c0, c1);
         }
 
+        if c0.codec == Codec::H265 {
+            return Self::match_h265_score(c0, c1);
+        }
+
+        if c0.codec == Codec::Av1 {
+            return Self::match_av1_score(c0, c1);
+        }
+
         // TODO: Fuzzy matching for any other audio codecs
         // TODO: Fuzzy matching for video
 
@@ -329,6 +355,30 @@ impl PayloadParams {
         Some(100)
     }
 
+    fn match_h265_score(c0: CodecSpec, c1: CodecSpec) -> Option<usize> {
+        // The default profile is 1 (Main) and tier 0. https://www.rfc-editor.org/rfc/rfc7798#section-7.1
+        // The level is the highest the receiver can decode, senders can go lower.
+        if c0.format.profile_id.unwrap_or(1) != c1.format.profile_id.unwrap_or(1) {
+            return None;
+        }
+
+        if c0.format.tier_flag.unwrap_or(0) != c1.format.tier_flag.unwrap_or(0) {
+            return None;
+        }
+
+        Some(100)
+    }
+
+    fn match_av1_score(c0: CodecSpec, c1: CodecSpec) -> Option<usize> {
+        // The default profile is 0. https://aomediacodec.github.io/av1-rtp-spec/#72-sdp-parameters
+        // The level and tier only tell what the receiver can decode.
+        if c0.format.profile.unwrap_or(0) != c1.format.profile.unwrap_or(0) {
+            return None;
+        }
+
+        Some(100)
+    }
+
     fn update_param(
         &mut self,
         remote_pts: &[PayloadParams],
@@ -404,7 +454,7 @@ impl CodecConfig {
 
         c.enable_vp8(true);
         c.enable_h264(true);
-        // c.add_default_av1();
+        c.enable_av1(true);
         c.enable_vp9(true);
 
         c
@@ -535,19 +585,47 @@ impl CodecConfig {
         }
     }
 
-    // TODO: AV1 depacketizer/packetizer.
-    //
-    // /// Add a default AV1 payload type.
-    // pub fn add_default_av1(&mut self) {
-    //     self.add_config(
-    //         41.into(),
-    //         Some(42.into()),
-    //         Codec::Av1,
-    //         90_000,
-    //         None,
-    //         FormatParams::default(),
-    //     )
-    // }
+    /// Add a default H265 payload type.
+    pub fn enable_h265(&mut self, enabled: bool) {
+        self.params.retain(|c| c.spec.codec != Codec::H265);
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            102.into(),
+            Some(103.into()),
+            Codec::H265,
+            90_000,
+            None,
+            FormatParams {
+                profile_id: Some(1),
+                level_id: Some(93),
+                tier_flag: Some(0),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Add a default AV1 payload type.
+    pub fn enable_av1(&mut self, enabled: bool) {
+        self.params.retain(|c| c.spec.codec != Codec::Av1);
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            41.into(),
+            Some(42.into()),
+            Codec::Av1,
+            90_000,
+            None,
+            FormatParams {
+                profile: Some(0),
+                level_idx: Some(5),
+                tier: Some(0),
+                ..Default::default()
+            },
+        )
+    }
 
     /// Add a default VP9 payload type.
     pub fn enable_vp9(&mut self, enabled: bool) {
@@ -773,6 +851,11 @@ impl FormatParams {
             PacketizationMode(v) => self.packetization_mode = Some(*v),
             ProfileLevelId(v) => self.profile_level_id = Some(*v),
             ProfileId(v) => self.profile_id = Some(*v),
+            LevelId(v) => self.level_id = Some(*v),
+            TierFlag(v) => self.tier_flag = Some(*v),
+            Profile(v) => self.profile = Some(*v),
+            LevelIdx(v) => self.level_idx = Some(*v),
+            Tier(v) => self.tier = Some(*v),
             Apt(_) => {}
             Unknown => {}
         }
@@ -797,9 +880,24 @@ impl FormatParams {
         if let Some(v) = self.profile_level_id {
             r.push(ProfileLevelId(v));
         }
+        if let Some(v) = self.level_id {
+            r.push(LevelId(v));
+        }
         if let Some(v) = self.profile_id {
             r.push(ProfileId(v));
         }
+        if let Some(v) = self.tier_flag {
+            r.push(TierFlag(v));
+        }
+        if let Some(v) = self.level_idx {
+            r.push(LevelIdx(v));
+        }
+        if let Some(v) = self.profile {
+            r.push(Profile(v));
+        }
+        if let Some(v) = self.tier {
+            r.push(Tier(v));
+        }
 
         r
     }
@@ -906,6 +1004,7 @@ mod test {
                 packetization_mode,
                 profile_level_id,
                 profile_id: None, // VP8
+                ..Default::default()
             },
         }
     }
@@ -950,4 +1049,27 @@ mod test {
             assert_eq!(matched, must_match, "{msg}\nc0: {c0:#?}\nc1: {c1:#?}");
         }
     }
+
+    #[test]
+    fn test_h265_profile_matching() {
+        let spec = |profile_id, level_id, tier_flag| CodecSpec {
+            codec: Codec::H265,
+            clock_rate: 90000,
+            channels: None,
+            format: FormatParams {
+                profile_id,
+                level_id,
+                tier_flag,
+                ..Default::default()
+            },
+        };
+
+        // Safari doesn't have to say profile-id=1, it's the default.
+        let c0 = spec(Some(1), Some(93), Some(0));
+        assert!(PayloadParams::match_h265_score(c0, spec(None, Some(120), None)).is_some());
+
+        // Main 10 and High tier.
+        assert!(PayloadParams::match_h265_score(c0, spec(Some(2), Some(93), Some(0))).is_none());
+        assert!(PayloadParams::match_h265_score(c0, spec(Some(1), Some(93), Some(1))).is_none());
+    }
 }
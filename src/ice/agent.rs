@@ -1,21 +1,19 @@
 use std::collections::{HashSet, VecDeque};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::time::{Duration, Instant};
 
 use rand::random;
 
-use crate::io::{DatagramRecv, Receive, Transmit, DATAGRAM_MTU};
+use crate::io::{DatagramRecv, Protocol, Receive, Transmit, DATAGRAM_MTU};
 use crate::io::{Id, DATAGRAM_MTU_WARN};
 use crate::io::{StunMessage, TransId, STUN_TIMEOUT};
+use crate::stats::IceCandidatePairStats;
 
-use super::candidate::{Candidate, CandidateKind};
+use super::candidate::{Candidate, CandidateKind, TcpType};
+use super::config::{IceConfig, NominationPolicy};
 use super::pair::{CandidatePair, CheckState, PairId};
-
-/// Timing advance (Ta) value.
-///
-/// ICE agents SHOULD use a default Ta value, 50 ms, but MAY use another
-/// value based on the characteristics of the associated data.
-const TIMING_ADVANCE: Duration = Duration::from_millis(50);
+use super::srflx::SrflxRequest;
+use super::turn::{TurnClient, TurnEvent, TurnServer};
 
 #[derive(Debug)]
 pub struct IceAgent {
@@ -52,6 +50,9 @@ pub struct IceAgent {
     /// Current state of the agent.
     state: IceConnectionState,
 
+    /// When the state went to disconnected, to time the change to failed.
+    disconnected_at: Option<Instant>,
+
     /// All local candidates, in the order they are "discovered" (either by
     /// adding explicitly using add_candidate, or via binding/allocation
     /// requests.
@@ -77,27 +78,68 @@ pub struct IceAgent {
     /// to dedupe [`IceAgentEvent::DiscoveredRecv`].
     discovered_recv: HashSet<SocketAddr>,
 
-    /// Currently nominated pair for sending. This is used to evaluate
-    /// if we get a better candidate for [`IceAgentEvent::NominatedSend`].
-    nominated_send: Option<PairId>,
+    /// Currently nominated pair for sending, per component (index is component id - 1).
+    /// This is used to evaluate if we get a better candidate for [`IceAgentEvent::NominatedSend`].
+    nominated_send: [Option<PairId>; 2],
+
+    /// Pair selected by the user to send on, per component. This overrides the
+    /// nomination policy for as long as the pair works.
+    selected_pair: [Option<PairId>; 2],
+
+    /// Last GOOG-NOMINATION value sent, for [`NominationPolicy::Renomination`].
+    nomination_count: u32,
+
+    /// Whether the remote agent supports renomination (a=ice-options:renomination).
+    remote_renomination: bool,
+
+    /// Number of components. 1 when RTP and RTCP are multiplexed (rtcp-mux), otherwise 2.
+    components: u16,
 
     /// Statistics counter for the agent.
     stats: IceAgentStats,
+
+    /// STUN servers to discover server reflexive candidates from.
+    stun_servers: Vec<SocketAddr>,
+
+    /// One binding request per (host candidate, STUN server).
+    srflx_requests: Vec<SrflxRequest>,
+
+    /// TURN servers to allocate relayed candidates from.
+    turn_servers: Vec<TurnServer>,
+
+    /// One TURN client per (host candidate, TURN server).
+    turn_clients: Vec<TurnClient>,
+
+    /// Timing and pacing parameters.
+    config: IceConfig,
+
+    /// Remote candidates with a hostname, waiting to be resolved by the user.
+    unresolved_candidates: Vec<Candidate>,
+
+    /// Whether local host candidates are signaled with generated mDNS hostnames.
+    mdns_host_candidates: bool,
 }
 
 #[derive(Debug)]
 struct StunRequest {
     now: Instant,
+    proto: Protocol,
     source: SocketAddr,
     destination: SocketAddr,
     trans_id: TransId,
     prio: u32,
     use_candidate: bool,
+    nomination: Option<u32>,
+    network_cost: Option<u16>,
     remote_ufrag: String,
 }
 
 const REMOTE_PEER_REFLEXIVE_TEMP_FOUNDATION: &str = "tmp_prflx";
 
+/// With [`NominationPolicy::Regular`], how long after the first working pair we wait
+/// for better pairs before nominating.
+const REGULAR_NOMINATION_WAIT: Duration = Duration::from_millis(500);
+
 /// States the ICE connection can be in.
 ///
 /// More details on connection states can be found in the [ICE RFC][1].
@@ -129,14 +171,16 @@ pub enum IceConnectionState {
     /// or during temporary disconnections. When the problem resolves, the connection
     /// may return to the connected state.
     Disconnected,
+
+    /// The connection has been disconnected for longer than the
+    /// [`IceConfig::failed_timeout()`][crate::IceConfig::failed_timeout].
+    ///
+    /// We never end trickle ice, so it's still possible to "come back" if more remote
+    /// candidates are added, but it is unlikely to happen on its own.
+    Failed,
     //
-    // NB: The failed and closed state doesn't really have a mapping in this implementation.
-    //     We never end trickle ice and it's always possible to "come back" if more remote
-    //     candidates are added.
+    // NB: The closed state doesn't really have a mapping in this implementation.
     //
-    // The ICE candidate has checked all candidates pairs against one another and has
-    // failed to find compatible matches.
-    // Failed,
     // The ICE agent has shut down and is no longer handling requests.
     // Closed,
 }
@@ -152,6 +196,11 @@ impl IceConnectionState {
     pub fn is_disconnected(&self) -> bool {
         *self == IceConnectionState::Disconnected
     }
+
+    /// Tells if this state is the failed state.
+    pub fn is_failed(&self) -> bool {
+        *self == IceConnectionState::Failed
+    }
 }
 
 /// Credentials for STUN packages.
@@ -204,6 +253,10 @@ pub enum IceAgentEvent {
     /// requiring an ICE restart. The application should always use the values
     /// of the last emitted event to send data.
     NominatedSend {
+        /// The component the pair is for, 1 for RTP and 2 for RTCP.
+        component_id: u16,
+        /// The protocol to send datagrams over.
+        proto: Protocol,
         /// The local socket address to send datagrams from.
         ///
         /// This will correspond to some local address added to
@@ -212,6 +265,36 @@ pub enum IceAgentEvent {
         /// The remote address to send datagrams to.
         destination: SocketAddr,
     },
+
+    /// Consent to send on the nominated pair expired.
+    ///
+    /// No binding response has been received within the consent timeout, which means
+    /// the remote peer is gone, or no longer wants our traffic. The application must
+    /// stop sending data to the destination until a new `NominatedSend` is emitted.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc7675
+    ConsentExpired {
+        /// The component of the expired pair.
+        component_id: u16,
+        /// The protocol of the expired pair.
+        proto: Protocol,
+        /// The local socket address that was used to send datagrams.
+        source: SocketAddr,
+        /// The remote address that no longer consents to receive.
+        destination: SocketAddr,
+    },
+
+    /// A remote candidate has a hostname that must be resolved.
+    ///
+    /// The candidate is not used until resolved with [`IceAgent::resolve_candidate`].
+    UnresolvedCandidate(Candidate),
+
+    /// A new local candidate was gathered by the agent.
+    ///
+    /// This happens for server reflexive candidates discovered via a STUN server,
+    /// and relayed candidates allocated via a TURN server. The candidate is already
+    /// added to the agent.
+    LocalCandidate(Candidate),
 }
 
 impl IceCreds {
@@ -245,6 +328,7 @@ impl IceAgent {
             controlling: false,
             control_tie_breaker: random(),
             state: IceConnectionState::New,
+            disconnected_at: None,
             local_candidates: vec![],
             remote_candidates: vec![],
             candidate_pairs: vec![],
@@ -252,8 +336,19 @@ impl IceAgent {
             events: VecDeque::new(),
             stun_server_queue: VecDeque::new(),
             discovered_recv: HashSet::new(),
-            nominated_send: None,
+            nominated_send: [None; 2],
+            selected_pair: [None; 2],
+            nomination_count: 0,
+            remote_renomination: false,
+            components: 1,
             stats: IceAgentStats::default(),
+            stun_servers: vec![],
+            srflx_requests: vec![],
+            turn_servers: vec![],
+            turn_clients: vec![],
+            config: IceConfig::default(),
+            unresolved_candidates: vec![],
+            mdns_host_candidates: false,
         }
     }
 
@@ -264,6 +359,107 @@ impl IceAgent {
         self.ice_lite = enabled;
     }
 
+    /// Set the timing and pacing parameters.
+    pub fn set_config(&mut self, config: IceConfig) {
+        self.config = config;
+    }
+
+    /// Set whether the remote agent supports renomination.
+    pub fn set_remote_renomination(&mut self, enabled: bool) {
+        self.remote_renomination = enabled;
+    }
+
+    /// Whether we tell the remote agent that we support renomination.
+    pub fn local_renomination(&self) -> bool {
+        self.config.nomination_policy() == NominationPolicy::Renomination
+    }
+
+    /// The nomination policy in use. Renomination needs support on both sides,
+    /// without it we fall back to continuous nomination.
+    fn nomination_policy(&self) -> NominationPolicy {
+        match self.config.nomination_policy() {
+            NominationPolicy::Renomination if !self.remote_renomination => {
+                NominationPolicy::Continuous
+            }
+            policy => policy,
+        }
+    }
+
+    /// Signal local host candidates with generated mDNS hostnames instead of IP addresses.
+    ///
+    /// Default is disabled.
+    pub fn set_mdns_host_candidates(&mut self, enabled: bool) {
+        self.mdns_host_candidates = enabled;
+    }
+
+    /// Whether RTP and RTCP are multiplexed on the same candidate pair.
+    ///
+    /// When disabled, there is a second component (2) for RTCP, with its own candidates
+    /// and nominated pair. Enabling it discards all candidates for component 2.
+    ///
+    /// Default is enabled.
+    pub fn set_rtcp_mux(&mut self, enabled: bool) {
+        if !enabled {
+            self.components = 2;
+            return;
+        }
+
+        if self.components == 1 {
+            return;
+        }
+
+        debug!("Discard candidates for RTCP component due to rtcp-mux");
+        self.components = 1;
+        self.nominated_send[1] = None;
+        self.selected_pair[1] = None;
+
+        let local_bases: Vec<_> = self
+            .local_candidates
+            .iter_mut()
+            .filter(|c| c.component_id() != 1 && !c.discarded())
+            .map(|c| {
+                c.set_discarded();
+                (c.base(), c.addr())
+            })
+            .collect();
+        for (base, addr) in local_bases {
+            self.srflx_requests.retain(|s| s.base() != base);
+            self.remove_turn_clients(base, addr);
+        }
+        for c in &mut self.remote_candidates {
+            if c.component_id() != 1 {
+                c.set_discarded();
+            }
+        }
+
+        let (locals, remotes) = (&self.local_candidates, &self.remote_candidates);
+        self.candidate_pairs.retain(|p| {
+            !p.local_candidate(locals).discarded() && !p.remote_candidate(remotes).discarded()
+        });
+    }
+
+    /// Tells whether RTP and RTCP are multiplexed on the same candidate pair.
+    pub fn rtcp_mux(&self) -> bool {
+        self.components == 1
+    }
+
+    /// The mDNS hostnames of the local candidates and the IP they resolve to.
+    pub fn local_mdns_names(&self) -> impl Iterator<Item = (&str, IpAddr)> + '_ {
+        let mut seen: Vec<&str> = vec![];
+        self.local_candidates
+            .iter()
+            .filter(|c| !c.discarded())
+            .filter_map(|c| Some((c.hostname()?, c.addr().ip())))
+            .filter(move |(h, _)| {
+                if seen.contains(h) {
+                    false
+                } else {
+                    seen.push(h);
+                    true
+                }
+            })
+    }
+
     /// Local ice credentials.
     pub fn local_credentials(&self) -> &IceCreds {
         &self.local_credentials
@@ -357,6 +553,56 @@ impl IceAgent {
         self.stats
     }
 
+    /// Stats for each candidate pair.
+    pub fn pair_stats(&self) -> Vec<IceCandidatePairStats> {
+        self.candidate_pairs
+            .iter()
+            .map(|p| {
+                let selected = self.nominated_send.contains(&Some(p.id()));
+                p.stats(&self.local_candidates, &self.remote_candidates, selected)
+            })
+            .collect()
+    }
+
+    /// Records data sent over the nominated pair of a component.
+    pub fn record_bytes_sent(&mut self, component_id: u16, bytes: usize) {
+        let Some(id) = self.nominated_send[component_idx(component_id)] else {
+            return;
+        };
+        if let Some(p) = self.candidate_pairs.iter_mut().find(|p| p.id() == id) {
+            p.record_bytes_sent(bytes);
+        }
+    }
+
+    /// Records data received from the remote peer.
+    ///
+    /// The data is attributed to the pair with the same addresses, preferably the
+    /// nominated pair.
+    pub fn record_bytes_recv(&mut self, r: &Receive, bytes: usize) {
+        if bytes == 0 {
+            return;
+        }
+
+        let nominated_send = self.nominated_send;
+        let (locals, remotes) = (&self.local_candidates, &self.remote_candidates);
+
+        let pair = self
+            .candidate_pairs
+            .iter_mut()
+            .filter(|p| {
+                let local = p.local_candidate(locals);
+                let remote = p.remote_candidate(remotes);
+                local.proto() == r.proto
+                    && local.base() == r.destination
+                    && remote.addr() == r.source
+            })
+            .max_by_key(|p| nominated_send.contains(&Some(p.id())));
+
+        if let Some(p) = pair {
+            p.record_bytes_recv(bytes);
+        }
+    }
+
     /// Adds a local candidate.
     ///
     /// Returns `false` if the candidate was not added because it is redundant.
@@ -367,6 +613,11 @@ impl IceAgent {
 
         let ip = c.addr().ip();
 
+        if c.component_id() > self.components {
+            debug!("Reject candidate for component without rtcp-mux: {:?}", c);
+            return false;
+        }
+
         if self.ice_lite {
             // Reject all non-host candidates.
             if c.kind() != CandidateKind::Host {
@@ -405,6 +656,9 @@ impl IceAgent {
         // 32768 - 49152 => prflx
         // 49152 - 65536 => host
         //
+        // Each interval is split in four by network cost, so that candidates on
+        // costly networks, like cellular, come after other candidates of the same type.
+        //
         // And furthermore we subdivide these to interleave IPv6 with IPv4
         // so that odd numbers are ipv6 and even are ipv4.
         //
@@ -421,7 +675,7 @@ impl IceAgent {
                 ServerReflexive => 32_767,
                 Relayed => 16_383,
             };
-            x - if ip.is_ipv6() { 0 } else { 1 }
+            x - if ip.is_ipv6() { 0 } else { 1 } - c.network_penalty() * 4096
         };
 
         // Count the number of existing candidates of the same kind.
@@ -430,6 +684,8 @@ impl IceAgent {
             .iter()
             .filter(|v| v.kind() == c.kind())
             .filter(|v| v.addr().is_ipv6() == ip.is_ipv6())
+            .filter(|v| v.component_id() == c.component_id())
+            .filter(|v| v.network_penalty() == c.network_penalty())
             .count() as u32;
 
         let pref = counter_start - same_kind * 2;
@@ -443,11 +699,10 @@ impl IceAgent {
         //
         // NB this must be done _after_ set_local_preference(), since the prio() used in the
         // elimination is calculated from that preference.
-        if let Some((idx, other)) = self
-            .local_candidates
-            .iter_mut()
-            .enumerate()
-            .find(|(_, v)| v.addr() == c.addr() && v.base() == c.base())
+        if let Some((idx, other)) =
+            self.local_candidates.iter_mut().enumerate().find(|(_, v)| {
+                v.addr() == c.addr() && v.base() == c.base() && v.proto() == c.proto()
+            })
         {
             if c.prio() < other.prio() {
                 // The new candidate is not better than what we already got.
@@ -470,15 +725,31 @@ impl IceAgent {
         // Tie this ufrag to this ICE-session.
         c.set_ufrag(&self.local_credentials.ufrag);
 
+        if self.mdns_host_candidates && c.kind() == CandidateKind::Host && c.hostname().is_none() {
+            // Candidates on the same IP share hostname.
+            let hostname = self
+                .local_candidates
+                .iter()
+                .filter(|v| v.addr().ip() == ip)
+                .find_map(|v| v.hostname())
+                .map(|h| h.to_string())
+                .unwrap_or_else(mdns_hostname);
+            c.set_hostname(hostname);
+        }
+
         // These are the indexes of the remote candidates this candidate should be paired with.
         let remote_idxs: Vec<_> = self
             .remote_candidates
             .iter()
             .enumerate()
-            .filter(|(_, v)| !v.discarded() && v.addr().is_ipv4() == ip.is_ipv4())
+            .filter(|(_, v)| !v.discarded() && can_pair(&c, v))
             .map(|(i, _)| i)
             .collect();
 
+        // STUN and TURN servers are only used over UDP.
+        let gather_base =
+            (c.kind() == CandidateKind::Host && c.proto() == Protocol::Udp).then(|| c.base());
+
         self.local_candidates.push(c);
 
         let local_idxs = [self.local_candidates.len() - 1];
@@ -494,9 +765,103 @@ impl IceAgent {
         // by the remote party, whether we form local pairs directly or later seems irrelevant.
         self.form_pairs(&local_idxs, &remote_idxs);
 
+        // Gather server reflexive and relayed candidates from this host candidate.
+        if let Some(base) = gather_base {
+            for i in 0..self.stun_servers.len() {
+                let server = self.stun_servers[i];
+                self.add_srflx_request(server, base);
+            }
+            for i in 0..self.turn_servers.len() {
+                let server = self.turn_servers[i].clone();
+                self.add_turn_client(server, base);
+            }
+        }
+
         true
     }
 
+    /// Adds a STUN server to discover server reflexive candidates from.
+    ///
+    /// A binding request is sent from every host candidate of the same IP family
+    /// as the server, including host candidates added later.
+    pub fn add_stun_server(&mut self, server: SocketAddr) {
+        if self.ice_lite {
+            debug!("Ignore STUN server due to ice-lite mode: {}", server);
+            return;
+        }
+
+        info!("Add STUN server: {}", server);
+
+        let bases: Vec<_> = self
+            .local_candidates
+            .iter()
+            .filter(|c| c.kind() == CandidateKind::Host && !c.discarded())
+            .map(|c| c.base())
+            .collect();
+
+        self.stun_servers.push(server);
+
+        for base in bases {
+            self.add_srflx_request(server, base);
+        }
+    }
+
+    fn add_srflx_request(&mut self, server: SocketAddr, base: SocketAddr) {
+        if server.is_ipv4() != base.is_ipv4() {
+            return;
+        }
+
+        let exists = self
+            .srflx_requests
+            .iter()
+            .any(|s| s.server() == server && s.base() == base);
+
+        if !exists {
+            self.srflx_requests.push(SrflxRequest::new(server, base));
+        }
+    }
+
+    /// Adds a TURN server to allocate relayed candidates from.
+    ///
+    /// An allocation is made from every host candidate of the same IP family
+    /// as the server, including host candidates added later.
+    pub fn add_turn_server(&mut self, server: TurnServer) {
+        if self.ice_lite {
+            debug!("Ignore TURN server due to ice-lite mode: {:?}", server);
+            return;
+        }
+
+        info!("Add TURN server: {:?}", server);
+
+        let bases: Vec<_> = self
+            .local_candidates
+            .iter()
+            .filter(|c| c.kind() == CandidateKind::Host && !c.discarded())
+            .map(|c| c.base())
+            .collect();
+
+        self.turn_servers.push(server.clone());
+
+        for base in bases {
+            self.add_turn_client(server.clone(), base);
+        }
+    }
+
+    fn add_turn_client(&mut self, server: TurnServer, local: SocketAddr) {
+        if server.addr().is_ipv4() != local.is_ipv4() {
+            return;
+        }
+
+        let exists = self
+            .turn_clients
+            .iter()
+            .any(|t| t.server_addr() == server.addr() && t.local() == local);
+
+        if !exists {
+            self.turn_clients.push(TurnClient::new(server, local));
+        }
+    }
+
     /// Adds a remote candidate.
     ///
     /// Returns `false` if the candidate was not added because it is redundant.
@@ -505,10 +870,13 @@ impl IceAgent {
     pub fn add_remote_candidate(&mut self, mut c: Candidate) {
         info!("Add remote candidate: {:?}", c);
 
-        // This is a a:rtcp-mux-only implementation. The only component
-        // we accept is 1 for RTP.
-        if c.component_id() != 1 {
-            debug!("Reject candidate for component other than 1: {:?}", c);
+        // With rtcp-mux, the only component we accept is 1 for RTP. Without, there
+        // is also 2 for RTCP.
+        if c.component_id() == 0 || c.component_id() > self.components {
+            debug!(
+                "Reject candidate for component other than 1 to {}: {:?}",
+                self.components, c
+            );
             return;
         }
 
@@ -528,6 +896,21 @@ impl IceAgent {
         // confusing inspecting the state.
         c.clear_ufrag();
 
+        if c.is_unresolved() {
+            if !self.unresolved_candidates.contains(&c) {
+                debug!("Remote candidate needs hostname resolving: {:?}", c);
+                self.unresolved_candidates.push(c.clone());
+                self.emit_event(IceAgentEvent::UnresolvedCandidate(c));
+
+                // Don't let the remote peer fill the memory with junk.
+                while self.unresolved_candidates.len() > 100 {
+                    let c = self.unresolved_candidates.remove(0);
+                    debug!("Remove overflow unresolved candidate {:?}", c);
+                }
+            }
+            return;
+        }
+
         let existing_prflx = self
             .remote_candidates
             .iter_mut()
@@ -536,10 +919,9 @@ impl IceAgent {
                 v.foundation() == REMOTE_PEER_REFLEXIVE_TEMP_FOUNDATION
                     && v.kind() == CandidateKind::PeerReflexive
                     && v.addr() == c.addr()
+                    && v.proto() == c.proto()
             });
 
-        let ipv4 = c.addr().is_ipv4();
-
         let remote_idx = if let Some((idx, existing)) = existing_prflx {
             // If any subsequent candidate exchanges contain this peer-reflexive
             // candidate, it will signal the actual foundation for the candidate.
@@ -555,11 +937,12 @@ impl IceAgent {
         };
 
         // These are the indexes of the local candidates this candidate should be paired with.
+        let remote = &self.remote_candidates[remote_idx];
         let local_idxs: Vec<_> = self
             .local_candidates
             .iter()
             .enumerate()
-            .filter(|(_, v)| !v.discarded() && v.addr().is_ipv4() == ipv4)
+            .filter(|(_, v)| !v.discarded() && can_pair(v, remote))
             .map(|(i, _)| i)
             .collect();
 
@@ -576,7 +959,7 @@ impl IceAgent {
 
                 let prio =
                     CandidatePair::calculate_prio(self.controlling, remote.prio(), local.prio());
-                let mut pair = CandidatePair::new(*local_idx, *remote_idx, prio);
+                let mut pair = CandidatePair::new(*local_idx, *remote_idx, prio, &self.config);
 
                 trace!("Form pair local: {:?} remote: {:?}", local, remote);
 
@@ -590,8 +973,9 @@ impl IceAgent {
                     let check_local = check.local_candidate(&self.local_candidates);
                     let check_remote = check.remote_candidate(&self.remote_candidates);
 
-                    let redundant =
-                        local.base() == check_local.base() && remote.addr() == check_remote.addr();
+                    let redundant = local.base() == check_local.base()
+                        && local.proto() == check_local.proto()
+                        && remote.addr() == check_remote.addr();
 
                     if redundant {
                         if check.prio() >= pair.prio() {
@@ -628,6 +1012,18 @@ impl IceAgent {
 
                 debug!("Add new pair {:?}", pair);
 
+                // The TURN server only relays traffic from peers we have a permission for.
+                if local.kind() == CandidateKind::Relayed {
+                    let relayed = local.addr();
+                    if let Some(t) = self
+                        .turn_clients
+                        .iter_mut()
+                        .find(|t| t.relayed() == Some(relayed))
+                    {
+                        t.ensure_permission(remote.addr().ip());
+                    }
+                }
+
                 // This is not a redundant pair, add it.
                 self.candidate_pairs.push(pair);
             }
@@ -651,6 +1047,31 @@ impl IceAgent {
         }
     }
 
+    /// Resolve the remote candidates with `hostname` to `ip`.
+    ///
+    /// Returns `false` if there were no unresolved candidates with the hostname.
+    pub fn resolve_candidate(&mut self, hostname: &str, ip: IpAddr) -> bool {
+        let (resolved, unresolved): (Vec<_>, Vec<_>) = self
+            .unresolved_candidates
+            .drain(..)
+            .partition(|c| c.hostname() == Some(hostname));
+        self.unresolved_candidates = unresolved;
+
+        if resolved.is_empty() {
+            debug!("No unresolved candidate for hostname: {}", hostname);
+            return false;
+        }
+
+        for c in resolved {
+            match c.resolved(ip) {
+                Ok(c) => self.add_remote_candidate(c),
+                Err(e) => debug!("Failed to resolve {}: {}", hostname, e),
+            }
+        }
+
+        true
+    }
+
     /// Invalidate a candidate and remove it from the connection.
     ///
     /// This is done for host candidates disappearing due to changes in the network
@@ -670,6 +1091,8 @@ impl IceAgent {
                 debug!("Local candidate to discard {:?}", other);
                 other.set_discarded();
                 self.discard_candidate_pairs(idx);
+                self.srflx_requests.retain(|s| s.base() != c.base());
+                self.remove_turn_clients(c.base(), c.addr());
                 return true;
             }
         }
@@ -678,6 +1101,82 @@ impl IceAgent {
         false
     }
 
+    /// Send on the candidate pair of a local and remote candidate, instead of the pair
+    /// picked by the nomination policy.
+    ///
+    /// Only the controlling agent can select a pair, and the pair must have had a
+    /// successful connectivity check. The selection lasts until the pair fails.
+    ///
+    /// Returns `true` if the pair was found and selected.
+    pub fn select_pair(&mut self, local: &Candidate, remote: &Candidate) -> bool {
+        if !self.controlling {
+            debug!("Only the controlling agent can select a pair");
+            return false;
+        }
+
+        let (locals, remotes) = (&self.local_candidates, &self.remote_candidates);
+        let maybe_pair = self.candidate_pairs.iter().find(|p| {
+            let l = p.local_candidate(locals);
+            let r = p.remote_candidate(remotes);
+            l.addr() == local.addr()
+                && l.base() == local.base()
+                && l.proto() == local.proto()
+                && r.addr() == remote.addr()
+                && r.proto() == remote.proto()
+        });
+
+        let Some(pair) = maybe_pair else {
+            debug!("No pair to select for: {:?} {:?}", local, remote);
+            return false;
+        };
+
+        if pair.state() != CheckState::Succeeded {
+            debug!("Pair to select is not working: {:?}", pair);
+            return false;
+        }
+
+        info!("Select pair: {:?}", pair);
+        let component_id = pair.local_candidate(locals).component_id();
+        self.selected_pair[component_idx(component_id)] = Some(pair.id());
+
+        if let Some(now) = self.last_now {
+            self.evaluate_nomination(now);
+        }
+
+        true
+    }
+
+    /// Remove TURN clients for a local socket, or a relayed address, and discard
+    /// the relayed candidates they allocated.
+    fn remove_turn_clients(&mut self, local: SocketAddr, relayed: SocketAddr) {
+        let mut removed = vec![];
+
+        self.turn_clients.retain(|t| {
+            let keep = t.local() != local && t.relayed() != Some(relayed);
+            if !keep {
+                debug!("Remove TURN client: {:?}", t.relayed());
+                removed.extend(t.relayed());
+            }
+            keep
+        });
+
+        for addr in removed {
+            self.discard_relayed_candidate(addr);
+        }
+    }
+
+    fn discard_relayed_candidate(&mut self, relayed: SocketAddr) {
+        let found = self.local_candidates.iter_mut().enumerate().find(|(_, v)| {
+            v.kind() == CandidateKind::Relayed && v.addr() == relayed && !v.discarded()
+        });
+
+        if let Some((idx, c)) = found {
+            debug!("Discard relayed candidate: {:?}", c);
+            c.set_discarded();
+            self.discard_candidate_pairs(idx);
+        }
+    }
+
     /// Restart ICE.
     ///
     /// This is useful when detecting a change in network interfaces, such as
@@ -696,7 +1195,9 @@ impl IceAgent {
 
         self.remote_credentials = None;
         self.remote_candidates.clear();
+        self.unresolved_candidates.clear();
         self.candidate_pairs.clear();
+        self.selected_pair = [None; 2];
         self.transmit.clear();
         self.events.clear();
         self.discovered_recv.clear();
@@ -709,6 +1210,8 @@ impl IceAgent {
             }
         } else {
             self.local_candidates.clear();
+            self.srflx_requests.clear();
+            self.turn_clients.clear();
         }
 
         self.local_credentials = local_credentials;
@@ -787,6 +1290,16 @@ impl IceAgent {
             }
         };
 
+        // Responses from STUN servers have no message integrity.
+        if let Some(idx) = self
+            .srflx_requests
+            .iter()
+            .position(|s| s.accepts_message(r.proto, r.source, r.destination, &message))
+        {
+            self.srflx_handle_response(idx, &message);
+            return;
+        }
+
         // Regardless of whether we have remote_creds at this point, we can
         // at least check the message integrity.
         if !self.accepts_message(&message) {
@@ -795,7 +1308,7 @@ impl IceAgent {
         }
 
         if message.is_binding_request() {
-            self.stun_server_handle_message(now, r.source, r.destination, message);
+            self.stun_server_handle_message(now, r.proto, r.source, r.destination, message);
         } else if message.is_successful_binding_response() {
             self.stun_client_handle_response(now, message);
         }
@@ -806,10 +1319,21 @@ impl IceAgent {
     }
 
     pub fn handle_timeout(&mut self, now: Instant) {
+        // Gathering via STUN and TURN servers is not paced by Ta.
+        for s in &mut self.srflx_requests {
+            s.handle_timeout(now, &self.config);
+        }
+        self.srflx_requests.retain(|s| !s.is_done());
+
+        for t in &mut self.turn_clients {
+            t.handle_timeout(now);
+        }
+        self.handle_turn_events();
+
         // The generation of ordinary and triggered connectivity checks is
         // governed by timer Ta.
         if let Some(last_now) = self.last_now {
-            let min_step = last_now + TIMING_ADVANCE;
+            let min_step = last_now + self.config.timing_advance();
             if now < min_step {
                 return;
             }
@@ -846,24 +1370,52 @@ impl IceAgent {
 
         self.last_now = Some(now);
 
-        self.evaluate_nomination();
+        self.evaluate_nomination(now);
 
         // prune failed candidates.
         let mut any_pruned = false;
+        let mut consent_expired = vec![];
+        let (locals, remotes) = (&self.local_candidates, &self.remote_candidates);
+        let nominated_send = self.nominated_send;
         self.candidate_pairs.retain(|p| {
             let keep = if self.ice_lite {
-                p.has_recent_remote_binding_request(now)
+                p.has_recent_remote_binding_request(now, &self.config)
             } else {
-                p.is_still_possible(now)
+                p.is_still_possible(now, &self.config)
             };
             if !keep {
                 debug!("Remove failed pair: {:?}", p);
                 any_pruned = true;
+                if p.is_consent_checked() && nominated_send.contains(&Some(p.id())) {
+                    let local = p.local_candidate(locals);
+                    let remote = p.remote_candidate(remotes);
+                    consent_expired.push((
+                        local.component_id(),
+                        local.proto(),
+                        local.base(),
+                        remote.addr(),
+                    ));
+                }
             }
             keep
         });
+        // Both components typically expire in the same tick when the peer goes away.
+        for (component_id, proto, source, destination) in consent_expired {
+            info!(
+                "Consent expired: {} -> {} over {}",
+                source, destination, proto
+            );
+            // Any other pair nominated after this is a new nomination.
+            self.nominated_send[component_idx(component_id)] = None;
+            self.emit_event(IceAgentEvent::ConsentExpired {
+                component_id,
+                proto,
+                source,
+                destination,
+            });
+        }
         if any_pruned {
-            self.evaluate_nomination();
+            self.evaluate_nomination(now);
             self.evaluate_state(now);
         }
 
@@ -892,7 +1444,7 @@ impl IceAgent {
             .candidate_pairs
             .iter_mut()
             .enumerate()
-            .map(|(i, c)| (i, c.next_binding_attempt(now)))
+            .map(|(i, c)| (i, c.next_binding_attempt(now, &self.config)))
             .min_by_key(|(_, t)| *t);
 
         if let Some((idx, deadline)) = next {
@@ -908,7 +1460,14 @@ impl IceAgent {
 
     /// Poll for the next datagram to send.
     pub fn poll_transmit(&mut self) -> Option<Transmit> {
-        let x = self.transmit.pop_front();
+        let x = match self.transmit.pop_front() {
+            Some(t) => Some(self.relay_transmit(t)),
+            None => self
+                .srflx_requests
+                .iter_mut()
+                .find_map(|s| s.poll_transmit())
+                .or_else(|| self.turn_clients.iter_mut().find_map(|t| t.poll_transmit())),
+        };
         if let Some(x) = &x {
             if x.contents.len() > DATAGRAM_MTU_WARN {
                 warn!("ICE above MTU {}: {}", DATAGRAM_MTU_WARN, x.contents.len());
@@ -930,7 +1489,7 @@ impl IceAgent {
 
         // We must empty the queued replies or stuff to send as soon as possible.
         if has_request || has_transmit {
-            return Some(last_now + TIMING_ADVANCE);
+            return Some(last_now + self.config.timing_advance());
         }
 
         // when do we need to handle the next candidate pair?
@@ -938,16 +1497,22 @@ impl IceAgent {
             // ice-lite doesn't do checks.
             None
         } else {
+            let config = &self.config;
             self.candidate_pairs
                 .iter_mut()
-                .map(|c| c.next_binding_attempt(last_now))
+                .flat_map(|c| {
+                    let next = c.next_binding_attempt(last_now, config);
+                    [Some(next), c.consent_expires(config)]
+                })
+                .flatten()
                 .min()
         };
 
         // Time must advance with at least Ta.
         let next = if let Some(next) = maybe_next {
-            if next < last_now + TIMING_ADVANCE {
-                last_now + TIMING_ADVANCE
+            let min_step = last_now + self.config.timing_advance();
+            if next < min_step {
+                min_step
             } else {
                 next
             }
@@ -956,7 +1521,145 @@ impl IceAgent {
             last_now + Duration::from_secs(3)
         };
 
-        Some(next)
+        // Disconnected goes to failed, also paced by Ta.
+        let next = match (self.state, self.disconnected_at) {
+            (IceConnectionState::Disconnected, Some(t)) => {
+                let failed_at = t + self.config.failed_timeout();
+                next.min(failed_at.max(last_now + self.config.timing_advance()))
+            }
+            _ => next,
+        };
+
+        let srflx = self
+            .srflx_requests
+            .iter()
+            .filter_map(|s| s.poll_timeout(last_now, &self.config));
+
+        let gather = self
+            .turn_clients
+            .iter()
+            .filter_map(|t| t.poll_timeout(last_now))
+            .chain(srflx)
+            .min();
+
+        Some(gather.map(|t| t.min(next)).unwrap_or(next))
+    }
+
+    /// Tells whether the datagram is a response from one of our STUN servers.
+    pub fn accepts_srflx_response(&self, r: &Receive) -> bool {
+        self.srflx_requests.iter().any(|s| s.accepts(r))
+    }
+
+    fn srflx_handle_response(&mut self, idx: usize, message: &StunMessage<'_>) {
+        let req = &mut self.srflx_requests[idx];
+        let base = req.base();
+
+        let Some(addr) = req.handle_response(message) else {
+            return;
+        };
+
+        let mut c = match Candidate::server_reflexive(addr, base) {
+            Ok(c) => c,
+            Err(e) => {
+                debug!("Bad server reflexive candidate: {}", e);
+                return;
+            }
+        };
+        self.inherit_from_host(base, &mut c);
+
+        // When not behind a NAT, the candidate is redundant with the host candidate.
+        if self.add_local_candidate(c) {
+            // unwrap is ok since we just added it.
+            let c = self.local_candidates.last().unwrap().clone();
+            self.emit_event(IceAgentEvent::LocalCandidate(c));
+        }
+    }
+
+    /// Wrap a datagram sent from a relayed candidate for sending via the TURN server.
+    ///
+    /// Datagrams from other sources are returned as is.
+    pub fn relay_transmit(&mut self, t: Transmit) -> Transmit {
+        if self.turn_clients.is_empty() {
+            return t;
+        }
+
+        let source = t.source;
+        if let Some(client) = self
+            .turn_clients
+            .iter_mut()
+            .find(|c| c.relayed() == Some(source))
+        {
+            client.wrap(t)
+        } else {
+            t
+        }
+    }
+
+    /// Tells whether the datagram is traffic from one of our TURN servers.
+    pub fn accepts_relayed(&self, r: &Receive) -> bool {
+        self.turn_clients.iter().any(|t| t.accepts(r))
+    }
+
+    /// Unwrap traffic relayed by a TURN server.
+    ///
+    /// Returns `None` if the datagram was TURN control traffic, or could not be unwrapped.
+    /// Datagrams not from a TURN server are returned as is.
+    pub fn unwrap_relayed<'a>(&mut self, now: Instant, r: Receive<'a>) -> Option<Receive<'a>> {
+        let Some(client) = self.turn_clients.iter_mut().find(|t| {
+            r.proto == Protocol::Udp && t.server_addr() == r.source && t.local() == r.destination
+        }) else {
+            if matches!(r.contents, DatagramRecv::ChannelData(_)) {
+                trace!("Drop ChannelData not from a TURN server");
+                return None;
+            }
+            return Some(r);
+        };
+
+        let unwrapped = client.handle_receive(now, r);
+        self.handle_turn_events();
+
+        unwrapped
+    }
+
+    fn handle_turn_events(&mut self) {
+        let mut events = vec![];
+        for t in &mut self.turn_clients {
+            while let Some(e) = t.poll_event() {
+                events.push((t.local(), t.server_addr(), t.relayed(), e));
+            }
+        }
+
+        for (local, server, relayed, e) in events {
+            match e {
+                TurnEvent::Allocated { relayed, mapped } => {
+                    let mut c = match Candidate::relayed(relayed, mapped) {
+                        Ok(c) => c,
+                        Err(e) => {
+                            warn!("Bad relayed candidate: {}", e);
+                            continue;
+                        }
+                    };
+                    self.inherit_from_host(local, &mut c);
+                    if self.add_local_candidate(c) {
+                        // unwrap is ok since we just added it.
+                        let c = self.local_candidates.last().unwrap().clone();
+                        self.emit_event(IceAgentEvent::LocalCandidate(c));
+                    }
+                }
+                TurnEvent::Failed => {
+                    warn!(
+                        "TURN allocation failed from: {} via server: {}",
+                        local, server
+                    );
+                    // The relayed candidate is gone with the allocation.
+                    if let Some(relayed) = relayed {
+                        self.discard_relayed_candidate(relayed);
+                    }
+                }
+            }
+        }
+
+        self.turn_clients.retain(|t| !t.is_failed());
     }
 
     fn emit_event(&mut self, event: IceAgentEvent) {
@@ -985,6 +1688,7 @@ impl IceAgent {
     fn stun_server_handle_message(
         &mut self,
         now: Instant,
+        proto: Protocol,
         source: SocketAddr,
         destination: SocketAddr,
         message: StunMessage,
@@ -994,6 +1698,8 @@ impl IceAgent {
             // this should be guarded in the parsing
             .expect("STUN request prio");
         let use_candidate = message.use_candidate();
+        let nomination = message.nomination();
+        let network_cost = message.network_cost().map(|(_, cost)| cost);
 
         if use_candidate {
             trace!("Binding request sent USE-CANDIDATE");
@@ -1008,11 +1714,14 @@ impl IceAgent {
         // credentials, we extract all relevant bits of information so it can be owned.
         let req = StunRequest {
             now,
+            proto,
             source,
             destination,
             trans_id,
             prio,
             use_candidate,
+            nomination,
+            network_cost,
             remote_ufrag: remote_ufrag.into(),
         };
 
@@ -1068,6 +1777,28 @@ impl IceAgent {
 
         self.stats.bind_request_recv += 1;
 
+        let found_local = self.local_candidates.iter().enumerate().find(|(_, v)| {
+            // The local candidate will be
+            // either a host candidate (for cases where the request was not received
+            // through a relay) or a relayed candidate (for cases where it is
+            // received through a relay).  The local candidate can never be a
+            // server-reflexive candidate.
+            matches!(v.kind(), CandidateKind::Host | CandidateKind::Relayed)
+                && v.addr() == req.destination
+                && v.proto() == req.proto
+        });
+
+        // An authenticated request can still arrive where we have no candidate, like
+        // over TCP to an address that only has a UDP host candidate.
+        let Some((local_idx, _)) = found_local else {
+            trace!(
+                "STUN request dropped, no local candidate for: {} over {}",
+                req.destination,
+                req.proto
+            );
+            return;
+        };
+
         // If the source transport address of the request does not match any
         // existing remote candidates, it represents a new peer-reflexive remote
         // candidate.
@@ -1075,7 +1806,7 @@ impl IceAgent {
             .remote_candidates
             .iter()
             .enumerate()
-            .find(|(_, c)| !c.discarded() && c.addr() == req.source);
+            .find(|(_, c)| !c.discarded() && c.addr() == req.source && c.proto() == req.proto);
 
         let remote_idx = if let Some((idx, _)) = found_in_remote {
             trace!("Remote candidate for STUN request found");
@@ -1088,7 +1819,15 @@ impl IceAgent {
             //     foundations of all other remote candidates.  If any subsequent
             //     candidate exchanges contain this peer-reflexive candidate, it will
             //     signal the actual foundation for the candidate.
-            let c = Candidate::peer_reflexive(
+            // A remote TCP candidate has the opposite tcptype of the local candidate
+            // it connected to.
+            let local = &self.local_candidates[local_idx];
+            let tcptype = local.tcptype().map(|t| t.opposite());
+            let component_id = local.component_id();
+
+            let mut c = Candidate::peer_reflexive(
+                req.proto,
+                tcptype,
                 req.source,
                 req.source,
                 req.prio,
@@ -1101,6 +1840,10 @@ impl IceAgent {
                 self.local_credentials.ufrag.clone(),
             );
 
+            // The request arrived on the socket of one component, and the remote
+            // candidate is for the same component.
+            c.set_component_id(component_id);
+
             info!(
                 "Created peer reflexive remote candidate from STUN request: {:?}",
                 c
@@ -1113,25 +1856,10 @@ impl IceAgent {
             self.remote_candidates.len() - 1
         };
 
-        let local_idx = self
-            .local_candidates
-            .iter()
-            .enumerate()
-            .find(|(_, v)| {
-                // The local candidate will be
-                // either a host candidate (for cases where the request was not received
-                // through a relay) or a relayed candidate (for cases where it is
-                // received through a relay).  The local candidate can never be a
-                // server-reflexive candidate.
-                matches!(v.kind(), CandidateKind::Host | CandidateKind::Relayed)
-                    && v.addr() == req.destination
-            })
-            // Receiving traffic for an IP address that neither is a HOST nor RELAY is a configuration
-            // fault. We need to be aware of the interfaces that the ice agent is connected to.
-            .expect(
-                "STUN request for socket that is neither a host nor a relay candidate. This is a config error.",
-            )
-            .0;
+        if let Some(cost) = req.network_cost {
+            // GOOG-NETWORK-INFO tells us about the network of the remote candidate.
+            self.remote_candidates[remote_idx].set_remote_network_cost(cost);
+        }
 
         let maybe_pair = self
             .candidate_pairs
@@ -1156,7 +1884,7 @@ impl IceAgent {
             // *  Its state is set to Waiting. (this is the default)
             // *  The pair is inserted into the checklist based on its priority.
             // *  The pair is enqueued into the triggered-check queue.
-            let pair = CandidatePair::new(local_idx, remote_idx, prio);
+            let pair = CandidatePair::new(local_idx, remote_idx, prio, &self.config);
 
             debug!("Created new pair for STUN request: {:?}", pair);
 
@@ -1177,6 +1905,7 @@ impl IceAgent {
         let remote_addr = remote.addr();
 
         pair.increase_remote_binding_requests(req.now);
+        pair.record_remote_request();
 
         if !self.controlling && !pair.is_nominated() && req.use_candidate {
             // We need to answer a nomination request with a binding request
@@ -1186,9 +1915,16 @@ impl IceAgent {
             pair.nominate(self.ice_lite);
         }
 
+        if !self.controlling && req.use_candidate {
+            if let Some(nomination) = req.nomination {
+                // The latest renomination wins, see evaluate_nomination.
+                pair.record_remote_nomination(nomination);
+            }
+        }
+
         if self.controlling && pair.state() == CheckState::Succeeded {
             // See if we can nominate something now.
-            self.evaluate_nomination();
+            self.evaluate_nomination(req.now);
         }
 
         let (_, password) = self.stun_credentials(true);
@@ -1208,6 +1944,7 @@ impl IceAgent {
         buf.truncate(n);
 
         let trans = Transmit {
+            proto: req.proto,
             source: local_addr,
             destination: remote_addr,
             contents: buf.into(),
@@ -1218,15 +1955,20 @@ impl IceAgent {
 
     fn stun_client_binding_request(&mut self, now: Instant, pair_idx: usize) {
         let (username, password) = self.stun_credentials(false);
+        let policy = self.nomination_policy();
 
         let pair = &mut self.candidate_pairs[pair_idx];
         let local = pair.local_candidate(&self.local_candidates);
         let remote = pair.remote_candidate(&self.remote_candidates);
         let prio = local.prio_prflx();
-        // Only the controlling side sends USE-CANDIDATE.
-        let use_candidate = self.controlling && pair.is_nominated();
+        // Only the controlling side sends USE-CANDIDATE. With aggressive nomination,
+        // that is in every check.
+        let use_candidate =
+            self.controlling && (pair.is_nominated() || policy == NominationPolicy::Aggressive);
+        let nomination = (use_candidate && pair.nomination() > 0).then_some(pair.nomination());
+        let network_cost = local.network_cost().map(|cost| (local.network_id(), cost));
 
-        let trans_id = pair.new_attempt(now);
+        let trans_id = pair.new_attempt(now, &self.config);
 
         self.stats.bind_request_sent += 1;
 
@@ -1237,6 +1979,8 @@ impl IceAgent {
             self.control_tie_breaker,
             prio,
             use_candidate,
+            nomination,
+            network_cost,
         );
 
         debug!(
@@ -1254,6 +1998,7 @@ impl IceAgent {
         buf.truncate(n);
 
         let trans = Transmit {
+            proto: local.proto(),
             source: local.base(),
             destination: remote.addr(),
             contents: buf.into(),
@@ -1292,11 +2037,13 @@ impl IceAgent {
             // This should be caught in the parsing.
             .expect("Mapped address in STUN response");
 
+        let proto = pair.local_candidate(&self.local_candidates).proto();
+
         let found_in_local = self
             .local_candidates
             .iter()
             .enumerate()
-            .find(|(_, c)| c.addr() == mapped_address);
+            .find(|(_, c)| c.addr() == mapped_address && c.proto() == proto);
 
         let (pair, valid_idx) = if let Some((valid_idx, _)) = found_in_local {
             // Note, the valid_idx might not be the same as the local_idx that we
@@ -1328,13 +2075,20 @@ impl IceAgent {
             let base = local_sent_from.base();
 
             // o  The type is peer reflexive.
-            let candidate = Candidate::peer_reflexive(
+            let mut candidate = Candidate::peer_reflexive(
+                local_sent_from.proto(),
+                local_sent_from.tcptype(),
                 mapped_address,
                 base,
                 prio,
                 None,
                 self.local_credentials.ufrag.clone(),
             );
+            candidate.set_component_id(local_sent_from.component_id());
+            candidate.set_network(
+                local_sent_from.network_type(),
+                local_sent_from.network_cost(),
+            );
 
             debug!(
                 "Created local peer reflexive candidate for mapped address: {}",
@@ -1361,34 +2115,86 @@ impl IceAgent {
         pair.record_binding_response(now, trans_id, valid_idx);
 
         if self.controlling {
-            self.evaluate_nomination();
+            self.evaluate_nomination(now);
         }
 
         // State might change when we get a response.
         self.evaluate_state(now);
     }
 
-    fn evaluate_nomination(&mut self) {
-        let best = if self.controlling {
+    fn evaluate_nomination(&mut self, now: Instant) {
+        for component_id in 1..=self.components {
+            self.evaluate_nomination_for(now, component_id);
+        }
+    }
+
+    fn evaluate_nomination_for(&mut self, now: Instant, component_id: u16) {
+        let idx = component_idx(component_id);
+
+        // A selected pair only lasts for as long as it works.
+        if let Some(id) = self.selected_pair[idx] {
+            let works = self
+                .candidate_pairs
+                .iter()
+                .any(|p| p.id() == id && p.state() == CheckState::Succeeded);
+            if !works {
+                debug!("Selected pair no longer works");
+                self.selected_pair[idx] = None;
+            }
+        }
+
+        let locals = &self.local_candidates;
+        let pairs = self
+            .candidate_pairs
+            .iter()
+            .filter(|p| p.local_candidate(locals).component_id() == component_id);
+
+        let policy = self.nomination_policy();
+
+        let best = if self.controlling && self.selected_pair[idx].is_some() {
+            self.selected_pair[idx]
+        } else if self.controlling && policy == NominationPolicy::Regular {
+            // For regular nomination, we stick with the nominated pair for as long as
+            // it is there.
+            let current =
+                self.nominated_send[idx].filter(|id| pairs.clone().any(|p| p.id() == *id));
+
+            current.or_else(|| {
+                let best = pairs
+                    .clone()
+                    .filter(|p| p.state() == CheckState::Succeeded)
+                    .max_by_key(|p| p.prio())?;
+
+                // A better pair might still work out.
+                let checking_better = pairs
+                    .clone()
+                    .any(|p| p.state() != CheckState::Succeeded && p.prio() > best.prio());
+
+                // Don't wait forever for it.
+                let first_success = pairs.filter_map(|p| p.succeeded_time()).min()?;
+                let waited = now >= first_success + REGULAR_NOMINATION_WAIT;
+
+                (!checking_better || waited).then_some(best.id())
+            })
+        } else if self.controlling {
             // For controlling agents, we pick the best candidate pair using
             // this strategy.
-            self.candidate_pairs
-                .iter()
+            pairs
                 .filter(|p| p.state() == CheckState::Succeeded)
                 .max_by_key(|p| p.prio())
                 .map(|p| p.id())
         } else {
             // For controlled agents, we pick the best pair from what the controlling
-            // agent has indicated with USE-CANDIDATE stun attribute.
-            self.candidate_pairs
-                .iter()
+            // agent has indicated with USE-CANDIDATE stun attribute. With renomination,
+            // the latest nomination wins over the priority.
+            pairs
                 .filter(|p| p.is_nominated())
-                .max_by_key(|p| p.prio())
+                .max_by_key(|p| (p.nomination(), p.prio()))
                 .map(|p| p.id())
         };
 
         if let Some(best) = best {
-            if let Some(current_best) = self.nominated_send {
+            if let Some(current_best) = self.nominated_send[idx] {
                 if best == current_best {
                     // The best is also the current best.
                     return;
@@ -1406,7 +2212,11 @@ impl IceAgent {
                 // above logic means this can't fail
                 .unwrap();
 
-            if !pair.is_nominated() && (self.controlling || self.ice_lite) {
+            if self.controlling && policy == NominationPolicy::Renomination {
+                // The remote agent follows the highest nomination value.
+                self.nomination_count += 1;
+                pair.renominate(self.nomination_count);
+            } else if !pair.is_nominated() && (self.controlling || self.ice_lite) {
                 // ice lite progresses pair to success straight away.
                 pair.nominate(self.ice_lite);
             }
@@ -1414,14 +2224,41 @@ impl IceAgent {
             let local = pair.local_candidate(&self.local_candidates);
             let remote = pair.remote_candidate(&self.remote_candidates);
 
-            self.nominated_send = Some(best);
+            // Bind a channel to reduce the per packet overhead of relaying.
+            if local.kind() == CandidateKind::Relayed {
+                let (relayed, peer) = (local.addr(), remote.addr());
+                if let Some(t) = self
+                    .turn_clients
+                    .iter_mut()
+                    .find(|t| t.relayed() == Some(relayed))
+                {
+                    t.ensure_channel(peer);
+                }
+            }
+
+            self.nominated_send[idx] = Some(best);
             self.emit_event(IceAgentEvent::NominatedSend {
+                component_id,
+                proto: local.proto(),
                 source: local.base(),
                 destination: remote.addr(),
             })
         }
     }
 
+    /// Give a candidate gathered from a local host candidate the same component and network.
+    fn inherit_from_host(&self, base: SocketAddr, c: &mut Candidate) {
+        let maybe_host = self
+            .local_candidates
+            .iter()
+            .find(|h| h.kind() == CandidateKind::Host && h.base() == base);
+
+        if let Some(host) = maybe_host {
+            c.set_component_id(host.component_id());
+            c.set_network(host.network_type(), host.network_cost());
+        }
+    }
+
     fn set_connection_state(&mut self, state: IceConnectionState, reason: &'static str) {
         if self.state != state {
             info!("State change ({}): {:?} -> {:?}", reason, self.state, state);
@@ -1433,17 +2270,23 @@ impl IceAgent {
     fn evaluate_state(&mut self, now: Instant) {
         use IceConnectionState::*;
 
-        let mut any_nomination = false;
+        let mut nominated_components = [false; 2];
         let mut any_still_possible = false;
 
         for p in &self.candidate_pairs {
             if p.is_nominated() {
-                any_nomination = true;
-            } else if p.is_still_possible(now) {
+                let component_id = p.local_candidate(&self.local_candidates).component_id();
+                nominated_components[component_idx(component_id)] = true;
+            } else if p.is_still_possible(now, &self.config) {
                 any_still_possible = true;
             }
         }
 
+        // Without rtcp-mux, both RTP and RTCP must have a nomination.
+        let any_nomination = nominated_components[..usize::from(self.components)]
+            .iter()
+            .all(|n| *n);
+
         // As a special case, before the ice agent has received any add_remote_candidate() or
         // discovered a peer reflexive via a STUN message, the agent is still viable. This is
         // also the case for ice_restart.
@@ -1455,18 +2298,16 @@ impl IceAgent {
             New => {
                 self.set_connection_state(Checking, "new connection");
             }
-            Checking | Disconnected => {
+            Checking | Disconnected | Failed => {
                 if any_nomination {
                     if self.ice_lite {
                         self.set_connection_state(Completed, "got nomination in ice lite");
-                        return;
-                    }
-                    if any_still_possible {
+                    } else if any_still_possible {
                         self.set_connection_state(Connected, "got nomination, still trying others");
                     } else {
                         self.set_connection_state(Completed, "got nomination, no others to try");
                     }
-                } else if !any_still_possible {
+                } else if !any_still_possible && self.state == Checking {
                     self.set_connection_state(Disconnected, "no possible pairs");
                 }
             }
@@ -1489,6 +2330,17 @@ impl IceAgent {
                 }
             }
         }
+
+        match self.state {
+            Disconnected => {
+                let disconnected_at = *self.disconnected_at.get_or_insert(now);
+                if now >= disconnected_at + self.config.failed_timeout() {
+                    self.set_connection_state(Failed, "disconnected for too long");
+                }
+            }
+            Failed => {}
+            _ => self.disconnected_at = None,
+        }
     }
 
     pub(crate) fn remote_credentials(&self) -> Option<&IceCreds> {
@@ -1496,9 +2348,61 @@ impl IceAgent {
     }
 }
 
+/// Index into per component arrays, with component 1 for RTP and 2 for RTCP.
+fn component_idx(component_id: u16) -> usize {
+    usize::from(component_id.clamp(1, 2) - 1)
+}
+
+/// Generate a random mDNS hostname, a UUID v4 like libWebRTC.
+///
+/// https://datatracker.ietf.org/doc/html/draft-ietf-mmusic-mdns-ice-candidates#section-3.1.1
+fn mdns_hostname() -> String {
+    let v = random::<u128>();
+    // Version 4 and RFC 4122 variant.
+    let v = v & !(0xf << 76) | (0x4 << 76);
+    let v = v & !(0x3 << 62) | (0x2 << 62);
+    let h = format!("{v:032x}");
+    format!(
+        "{}-{}-{}-{}-{}.local",
+        &h[0..8],
+        &h[8..12],
+        &h[12..16],
+        &h[16..20],
+        &h[20..32]
+    )
+}
+
+/// Tells whether a local and remote candidate can form a pair.
+///
+/// https://www.rfc-editor.org/rfc/rfc6544#section-6.2
+fn can_pair(local: &Candidate, remote: &Candidate) -> bool {
+    if local.addr().is_ipv4() != remote.addr().is_ipv4()
+        || local.proto() != remote.proto()
+        || local.component_id() != remote.component_id()
+    {
+        return false;
+    }
+
+    if local.proto() == Protocol::Udp {
+        return true;
+    }
+
+    use TcpType::*;
+    match (local.tcptype(), remote.tcptype()) {
+        (Some(Active), Some(Passive)) => true,
+        (Some(So), Some(So)) => true,
+        // We can't connect to an active candidate, since it only advertises the discard
+        // port. Pairs for passive local candidates are formed on incoming binding requests,
+        // when the remote becomes known as a peer reflexive candidate.
+        (Some(Passive), Some(Active)) => remote.kind() == CandidateKind::PeerReflexive,
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ice::NetworkType;
     use std::net::SocketAddr;
     use std::sync::Once;
 
@@ -1548,6 +2452,30 @@ mod test {
         assert_eq!(v, vec![65534, 65535, 65533, 65532]);
     }
 
+    #[test]
+    fn local_preference_network_type() {
+        let mut agent = IceAgent::new();
+
+        let cellular = Candidate::host(ipv4_1())
+            .unwrap()
+            .with_network_type(NetworkType::Cellular);
+        let wifi = Candidate::host(ipv4_2())
+            .unwrap()
+            .with_network_type(NetworkType::Wifi);
+        agent.add_local_candidate(cellular);
+        agent.add_local_candidate(wifi);
+
+        let v: Vec<_> = agent
+            .local_candidates
+            .iter()
+            .map(|c| c.local_preference())
+            .collect();
+
+        // Wi-Fi ranks above cellular, even when added after.
+        assert_eq!(v, vec![65534 - 3 * 4096, 65534]);
+        assert!(agent.local_candidates[1].prio() > agent.local_candidates[0].prio());
+    }
+
     #[test]
     fn discard_adding_redundant() {
         let mut agent = IceAgent::new();
@@ -1609,6 +2537,96 @@ mod test {
         assert_eq!(agent.pair_indexes(), [(0, 1), (0, 0), (1, 1), (1, 0)]);
     }
 
+    #[test]
+    fn form_pairs_per_component() {
+        let mut agent = IceAgent::new();
+
+        let rtcp = |addr: SocketAddr| {
+            let addr = SocketAddr::new(addr.ip(), addr.port() + 1);
+            Candidate::host(addr).unwrap().with_component_id(2).unwrap()
+        };
+
+        // No RTCP component with rtcp-mux.
+        assert!(!agent.add_local_candidate(rtcp(ipv4_1())));
+
+        agent.set_rtcp_mux(false);
+
+        // local 0, 1
+        agent.add_local_candidate(Candidate::host(ipv4_1()).unwrap());
+        agent.add_local_candidate(rtcp(ipv4_1()));
+        // remote 0, 1
+        agent.add_remote_candidate(rtcp(ipv4_3()));
+        agent.add_remote_candidate(Candidate::host(ipv4_3()).unwrap());
+
+        assert_eq!(agent.pair_indexes(), [(0, 1), (1, 0)]);
+        assert_eq!(agent.local_candidates[0].local_preference(), 65534);
+        assert_eq!(agent.local_candidates[1].local_preference(), 65534);
+
+        // Negotiating rtcp-mux discards the RTCP component.
+        agent.set_rtcp_mux(true);
+        assert_eq!(agent.pair_indexes(), [(0, 1)]);
+        assert!(agent.local_candidates[1].discarded());
+        assert!(agent.remote_candidates[0].discarded());
+    }
+
+    #[test]
+    fn resolve_unresolved_remote_candidate() {
+        let mut agent = IceAgent::new();
+        agent.add_local_candidate(Candidate::host(ipv4_1()).unwrap());
+
+        let hostname = "2c3f05f6-8a7f-4d8e-9f2c-0c7e5a5b7e21.local";
+        let mut c = Candidate::parsed(
+            "1".into(),
+            1,
+            Protocol::Udp,
+            2130706431,
+            "0.0.0.0:5000".parse().unwrap(),
+            CandidateKind::Host,
+            None,
+            None,
+            None,
+        );
+        c.set_hostname(hostname.into());
+        assert!(c.is_unresolved());
+
+        agent.add_remote_candidate(c);
+        assert!(agent.pair_indexes().is_empty());
+
+        let ev = agent.poll_event();
+        assert!(matches!(ev, Some(IceAgentEvent::UnresolvedCandidate(_))));
+
+        assert!(!agent.resolve_candidate("other.local", ipv4_3().ip()));
+        assert!(agent.resolve_candidate(hostname, ipv4_3().ip()));
+        assert_eq!(agent.pair_indexes(), [(0, 0)]);
+        assert_eq!(agent.remote_candidates[0].addr(), ipv4_3());
+
+        // Already resolved.
+        assert!(!agent.resolve_candidate(hostname, ipv4_3().ip()));
+    }
+
+    #[test]
+    fn mdns_host_candidates() {
+        let mut agent = IceAgent::new();
+        agent.set_mdns_host_candidates(true);
+
+        agent.add_local_candidate(Candidate::host(ipv4_1()).unwrap());
+        agent.add_local_candidate(Candidate::host_tcp(ipv4_1(), TcpType::Passive).unwrap());
+        agent.add_local_candidate(Candidate::host(ipv4_2()).unwrap());
+
+        let names: Vec<_> = agent.local_mdns_names().collect();
+        assert_eq!(names.len(), 2);
+        assert!(names[0].0.ends_with(".local"));
+        assert_eq!(names[0].1, ipv4_1().ip());
+        assert_eq!(names[1].1, ipv4_2().ip());
+        assert_ne!(names[0].0, names[1].0);
+
+        // The same IP shares the hostname, and the IP is not signaled.
+        let c0 = &agent.local_candidates[0];
+        let c1 = &agent.local_candidates[1];
+        assert_eq!(c0.hostname(), c1.hostname());
+        assert!(!c0.to_string().contains(&ipv4_1().ip().to_string()));
+    }
+
     #[test]
     fn form_pairs_skip_redundant() {
         let mut agent = IceAgent::new();
@@ -1663,6 +2681,8 @@ mod test {
 
         // This is just prepping the test, this would have been discovered in a STUN packet.
         let c = Candidate::peer_reflexive(
+            Protocol::Udp,
+            None,
             ipv4_3(),
             ipv4_3(),
             123,
@@ -1701,7 +2721,105 @@ mod test {
         agent.handle_timeout(now1);
         let now2 = agent.poll_timeout().unwrap();
 
-        assert!(now2 - now1 == TIMING_ADVANCE);
+        assert!(now2 - now1 == IceConfig::default().timing_advance());
+    }
+
+    #[test]
+    fn poll_time_configured_timing_advance() {
+        let mut agent = IceAgent::new();
+        let ta = Duration::from_millis(200);
+        agent.set_config(IceConfig::new().set_timing_advance(ta));
+        agent.add_local_candidate(Candidate::host(ipv4_1()).unwrap());
+        agent.add_remote_candidate(Candidate::host(ipv4_3()).unwrap());
+
+        let now1 = Instant::now();
+        agent.handle_timeout(now1);
+        let now2 = agent.poll_timeout().unwrap();
+
+        assert!(now2 - now1 == ta);
+    }
+
+    #[test]
+    fn gather_server_reflexive() {
+        use crate::io::{Attribute, Class, Method};
+
+        let mut agent = IceAgent::new();
+        let server: SocketAddr = "5.5.5.5:3478".parse().unwrap();
+        agent.add_stun_server(server);
+        agent.add_local_candidate(Candidate::host(ipv4_1()).unwrap());
+        // Other IP family does not use the server.
+        agent.add_local_candidate(Candidate::host(ipv6_1()).unwrap());
+
+        let now = Instant::now();
+        agent.handle_timeout(now);
+
+        let t = agent.poll_transmit().unwrap();
+        assert_eq!(t.source, ipv4_1());
+        assert_eq!(t.destination, server);
+        assert!(agent.poll_transmit().is_none());
+
+        let trans_id = TransId::from_slice(&t.contents[8..20]);
+        let response = StunMessage::new(
+            Method::Binding,
+            Class::Success,
+            trans_id,
+            vec![Attribute::XorMappedAddress(ipv4_2())],
+        );
+        let mut buf = vec![0; 100];
+        let n = response.to_bytes_with_key(&[], &mut buf).unwrap();
+
+        let r = Receive::new(Protocol::Udp, server, ipv4_1(), &buf[..n]).unwrap();
+        assert!(agent.accepts_srflx_response(&r));
+        agent.handle_receive(now, r);
+
+        let srflx = loop {
+            match agent.poll_event() {
+                Some(IceAgentEvent::LocalCandidate(c)) => break c,
+                Some(_) => continue,
+                None => panic!("No LocalCandidate event"),
+            }
+        };
+        assert_eq!(srflx.kind(), CandidateKind::ServerReflexive);
+        assert_eq!(srflx.addr(), ipv4_2());
+        assert_eq!(srflx.base(), ipv4_1());
+    }
+
+    #[test]
+    fn renomination_needs_remote_support() {
+        let mut agent = IceAgent::new();
+        assert_eq!(agent.nomination_policy(), NominationPolicy::Continuous);
+        assert!(!agent.local_renomination());
+
+        agent.set_config(IceConfig::new().set_nomination_policy(NominationPolicy::Renomination));
+        assert!(agent.local_renomination());
+        assert_eq!(agent.nomination_policy(), NominationPolicy::Continuous);
+
+        agent.set_remote_renomination(true);
+        assert_eq!(agent.nomination_policy(), NominationPolicy::Renomination);
+    }
+
+    #[test]
+    fn drop_request_without_local_candidate() {
+        let mut agent = IceAgent::new();
+        agent.add_local_candidate(Candidate::host(ipv4_1()).unwrap());
+        let remote_creds = IceCreds::new();
+        agent.set_remote_credentials(remote_creds.clone());
+
+        let local_creds = agent.local_credentials().clone();
+        let username = format!("{}:{}", local_creds.ufrag, remote_creds.ufrag);
+        let binding =
+            StunMessage::binding_request(&username, TransId::new(), true, 0, 1, false, None, None);
+        let mut buf = vec![0; 200];
+        let n = binding
+            .to_bytes_with_key(local_creds.pass.as_bytes(), &mut buf)
+            .unwrap();
+
+        // TCP to an address that only has a UDP host candidate.
+        let r = Receive::new(Protocol::Tcp, ipv4_3(), ipv4_1(), &buf[..n]).unwrap();
+        agent.handle_receive(Instant::now(), r);
+
+        assert_eq!(agent.stats().bind_request_recv, 1);
+        assert!(agent.poll_transmit().is_none());
     }
 
     #[test]
@@ -3,6 +3,8 @@ use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::net::{IpAddr, SocketAddr};
 
+use crate::io::Protocol;
+
 use super::IceError;
 
 /// ICE candidates are network addresses used to connect to a peer.
@@ -32,7 +34,12 @@ pub struct Candidate {
     component_id: u16, // 1 for RTP, 2 for RTCP
 
     /// Protocol for the candidate.
-    proto: String, // "udp" or "tcp"
+    proto: Protocol, // "udp" or "tcp"
+
+    /// Type of TCP candidate.
+    ///
+    /// Only set for TCP candidates.
+    tcptype: Option<TcpType>, // active/passive/so
 
     /// Priority.
     ///
@@ -41,8 +48,16 @@ pub struct Candidate {
     prio: Option<u32>, // 1-10 digits
 
     /// The actual address to use. This might be a host address, server reflex, relay etc.
+    ///
+    /// For remote candidates with an unresolved hostname, the IP is unspecified.
     addr: SocketAddr, // ip/port
 
+    /// Hostname instead of an IP address, typically an mDNS `.local` name.
+    ///
+    /// For remote candidates, this is what the remote peer signaled. For local host candidates,
+    /// this is a generated name that is signaled instead of the IP address.
+    hostname: Option<String>,
+
     /// The base on the local host.
     ///
     /// "Base" refers to the address an agent sends from for a
@@ -73,6 +88,13 @@ pub struct Candidate {
     /// that are the same type.
     local_preference: Option<u32>,
 
+    /// Type of network interface, for local candidates.
+    network_type: NetworkType,
+
+    /// Network cost. For local candidates, this overrides the cost of the network type.
+    /// For remote candidates, it is what the remote peer sent in GOOG-NETWORK-INFO.
+    network_cost: Option<u16>,
+
     /// If we discarded this candidate (for example due to being redundant
     /// against another candidate).
     discarded: bool,
@@ -81,6 +103,15 @@ pub struct Candidate {
 impl fmt::Debug for Candidate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Candidate({}={}", self.kind, self.addr)?;
+        if let Some(hostname) = &self.hostname {
+            write!(f, " hostname={hostname}")?;
+        }
+        if self.proto != Protocol::Udp {
+            write!(f, " {}", self.proto)?;
+        }
+        if let Some(tcptype) = self.tcptype {
+            write!(f, " tcptype={tcptype}")?;
+        }
         if let Some(base) = self.base {
             if base != self.addr {
                 write!(f, " base={base}")?;
@@ -90,6 +121,12 @@ impl fmt::Debug for Candidate {
             write!(f, " raddr={raddr}")?;
         }
         write!(f, " prio={}", self.prio())?;
+        if self.network_type != NetworkType::Unknown {
+            write!(f, " network={:?}", self.network_type)?;
+        }
+        if let Some(cost) = self.network_cost() {
+            write!(f, " cost={cost}")?;
+        }
         if self.discarded {
             write!(f, " discarded")?;
         }
@@ -102,7 +139,7 @@ impl Candidate {
     fn new(
         foundation: Option<String>,
         component_id: u16,
-        proto: String,
+        proto: Protocol,
         prio: Option<u32>,
         addr: SocketAddr,
         base: Option<SocketAddr>,
@@ -114,13 +151,17 @@ impl Candidate {
             foundation,
             component_id,
             proto,
+            tcptype: None,
             prio,
             addr,
+            hostname: None,
             base,
             kind,
             raddr,
             ufrag,
             local_preference: None,
+            network_type: NetworkType::Unknown,
+            network_cost: None,
             discarded: false,
         }
     }
@@ -130,14 +171,15 @@ impl Candidate {
     pub fn parsed(
         foundation: String,
         component_id: u16,
-        proto: String,
+        proto: Protocol,
         prio: u32,
         addr: SocketAddr,
         kind: CandidateKind,
         raddr: Option<SocketAddr>,
+        tcptype: Option<TcpType>,
         ufrag: Option<String>,
     ) -> Self {
-        Candidate::new(
+        let mut c = Candidate::new(
             Some(foundation),
             component_id,
             proto,
@@ -147,7 +189,9 @@ impl Candidate {
             kind,
             raddr,
             ufrag,
-        )
+        );
+        c.tcptype = tcptype;
+        c
     }
 
     /// Creates a host ICE candidate.
@@ -161,7 +205,7 @@ impl Candidate {
         Ok(Candidate::new(
             None,
             1, // only RTP
-            "udp".into(),
+            Protocol::Udp,
             None,
             addr,
             Some(addr),
@@ -171,29 +215,124 @@ impl Candidate {
         ))
     }
 
+    /// Creates a TCP host ICE candidate.
+    ///
+    /// TCP candidates are described in [RFC 6544][1]. A passive candidate is a socket
+    /// listening for incoming connections. An active candidate is the local end of outgoing
+    /// connections, and is signaled with the discard port 9. For simultaneous-open (so)
+    /// candidates both ends connect.
+    ///
+    /// Datagrams for TCP candidates have [`Protocol::Tcp`] and must be framed as described
+    /// in [RFC 4571][2], see [`TcpFramer`][crate::net::TcpFramer]. It is up to the user to
+    /// map the destination of an outgoing [`Transmit`][crate::net::Transmit] to a TCP stream,
+    /// for active candidates by opening a connection.
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc6544
+    /// [2]: https://www.rfc-editor.org/rfc/rfc4571
+    pub fn host_tcp(addr: SocketAddr, tcptype: TcpType) -> Result<Self, IceError> {
+        if !is_valid_ip(addr.ip()) {
+            return Err(IceError::BadCandidate(format!("invalid ip {}", addr.ip())));
+        }
+
+        let mut c = Candidate::new(
+            None,
+            1, // only RTP
+            Protocol::Tcp,
+            None,
+            addr,
+            Some(addr),
+            CandidateKind::Host,
+            None,
+            None,
+        );
+        c.tcptype = Some(tcptype);
+
+        Ok(c)
+    }
+
+    /// Creates a server reflexive ICE candidate.
+    ///
+    /// Server reflexive candidates are NAT:ed addresses discovered via a STUN server.
+    /// `addr` is the discovered address. `base` is the local host candidate the binding
+    /// request was sent from.
+    ///
+    /// Candidates for the STUN servers in
+    /// [`RtcConfig::add_stun_server()`][crate::RtcConfig::add_stun_server] are
+    /// gathered by str0m. This constructor is for addresses discovered by other means.
+    pub fn server_reflexive(addr: SocketAddr, base: SocketAddr) -> Result<Self, IceError> {
+        if !is_valid_ip(addr.ip()) {
+            return Err(IceError::BadCandidate(format!("invalid ip {}", addr.ip())));
+        }
+
+        Ok(Candidate::new(
+            None,
+            1, // only RTP
+            Protocol::Udp,
+            None,
+            addr,
+            Some(base),
+            CandidateKind::ServerReflexive,
+            Some(base),
+            None,
+        ))
+    }
+
+    /// Creates a relayed ICE candidate.
+    ///
+    /// Relayed candidates are addresses allocated on a TURN server. `addr` is the
+    /// relayed address on the server. `raddr` is the related address, typically
+    /// the server reflexive address the TURN server saw our allocation request coming from.
+    ///
+    /// Relayed candidates obtained from the TURN servers in
+    /// [`RtcConfig::add_turn_server()`][crate::RtcConfig::add_turn_server] are
+    /// created by str0m. This constructor is for relays obtained by other means,
+    /// in which case wrapping the traffic sent from `addr` is up to the user.
+    pub fn relayed(addr: SocketAddr, raddr: Option<SocketAddr>) -> Result<Self, IceError> {
+        if !is_valid_ip(addr.ip()) {
+            return Err(IceError::BadCandidate(format!("invalid ip {}", addr.ip())));
+        }
+
+        Ok(Candidate::new(
+            None,
+            1, // only RTP
+            Protocol::Udp,
+            None,
+            addr,
+            // The base of a relayed candidate is the candidate itself.
+            Some(addr),
+            CandidateKind::Relayed,
+            raddr,
+            None,
+        ))
+    }
+
     /// Creates a peer reflexive ICE candidate.
     ///
     /// Peer reflexive candidates are NAT:ed addresses discovered via STUN
     /// binding responses. `addr` is the discovered address. `base` is the local
     /// (host) address inside the NAT we used to get this response.
     pub(crate) fn peer_reflexive(
+        proto: Protocol,
+        tcptype: Option<TcpType>,
         addr: SocketAddr,
         base: SocketAddr,
         prio: u32,
         found: Option<String>,
         ufrag: String,
     ) -> Self {
-        Candidate::new(
+        let mut c = Candidate::new(
             found,
             1, // only RTP
-            "udp".into(),
+            proto,
             Some(prio),
             addr,
             Some(base),
             CandidateKind::PeerReflexive,
             None,
             Some(ufrag),
-        )
+        );
+        c.tcptype = tcptype;
+        c
     }
 
     #[cfg(test)]
@@ -201,7 +340,7 @@ impl Candidate {
         Candidate::new(
             None,
             1, // only RTP
-            "udp".into(),
+            Protocol::Udp,
             None,
             addr,
             Some(base),
@@ -266,15 +405,23 @@ impl Candidate {
         // The RECOMMENDED values for type preferences are 126 for host
         // candidates, 110 for peer-reflexive candidates, 100 for server-
         // reflexive candidates, and 0 for relayed candidates.
-        let type_preference = if as_prflx {
-            110
+        //
+        // Like libWebRTC, we prefer UDP over TCP by lowering the type preference
+        // for TCP candidates (except relayed).
+        let is_tcp = self.proto == Protocol::Tcp;
+        let kind = if as_prflx {
+            CandidateKind::PeerReflexive
         } else {
-            match self.kind {
-                CandidateKind::Host => 126,
-                CandidateKind::PeerReflexive => 110,
-                CandidateKind::ServerReflexive => 100,
-                CandidateKind::Relayed => 0,
-            }
+            self.kind
+        };
+        let type_preference = match (kind, is_tcp) {
+            (CandidateKind::Host, false) => 126,
+            (CandidateKind::PeerReflexive, false) => 110,
+            (CandidateKind::ServerReflexive, false) => 100,
+            (CandidateKind::Host, true) => 90,
+            (CandidateKind::PeerReflexive, true) => 80,
+            (CandidateKind::ServerReflexive, true) => 65,
+            (CandidateKind::Relayed, _) => 0,
         };
 
         // The recommended formula combines a preference for the candidate type
@@ -300,19 +447,155 @@ impl Candidate {
             .unwrap_or_else(|| if self.addr.is_ipv6() { 65_535 } else { 65_534 })
     }
 
-    pub(crate) fn component_id(&self) -> u16 {
+    /// Returns the component id of the ICE candidate.
+    ///
+    /// This is 1 for RTP, and 2 for RTCP when RTP and RTCP are not multiplexed on the
+    /// same port. See [`RtcpMuxPolicy`][crate::RtcpMuxPolicy].
+    pub fn component_id(&self) -> u16 {
         self.component_id
     }
 
+    /// Make this a candidate for another component than RTP.
+    ///
+    /// Candidates are created for component 1 (RTP). When RTP and RTCP are not
+    /// multiplexed, the RTCP socket is added as a candidate for component 2.
+    ///
+    /// ```
+    /// # use str0m::Candidate;
+    /// let a = "1.2.3.4:5001".parse().unwrap();
+    /// let c = Candidate::host(a).unwrap().with_component_id(2).unwrap();
+    ///
+    /// assert_eq!(c.component_id(), 2);
+    /// ```
+    pub fn with_component_id(mut self, component_id: u16) -> Result<Self, IceError> {
+        // https://www.rfc-editor.org/rfc/rfc8445#section-5.1.2.1
+        // The component ID MUST be an integer between 1 and 256 inclusive.
+        if !(1..=256).contains(&component_id) {
+            return Err(IceError::BadCandidate(format!(
+                "invalid component id {component_id}"
+            )));
+        }
+        self.component_id = component_id;
+        Ok(self)
+    }
+
+    pub(crate) fn set_component_id(&mut self, component_id: u16) {
+        self.component_id = component_id;
+    }
+
+    /// Tag this candidate with the type of network interface it is on.
+    ///
+    /// Candidates on costly networks, like cellular, get a lower priority than
+    /// other candidates of the same kind. The network cost is also sent to the remote
+    /// peer in connectivity checks.
+    ///
+    /// Server reflexive and relayed candidates gathered by str0m inherit the network
+    /// type of their host candidate.
+    ///
+    /// ```
+    /// # use str0m::{Candidate, NetworkType};
+    /// let a = "1.2.3.4:5000".parse().unwrap();
+    /// let c = Candidate::host(a).unwrap().with_network_type(NetworkType::Cellular);
+    ///
+    /// assert_eq!(c.network_type(), NetworkType::Cellular);
+    /// assert_eq!(c.network_cost(), Some(900));
+    /// ```
+    pub fn with_network_type(mut self, network_type: NetworkType) -> Self {
+        self.network_type = network_type;
+        self
+    }
+
+    /// Set the network cost of this candidate, instead of the cost of its network type.
+    ///
+    /// A higher cost means a less desirable network. For reference, the costs of the
+    /// network types are 0 for wired, 10 for Wi-Fi, 50 for VPN and 900 for cellular.
+    pub fn with_network_cost(mut self, cost: u16) -> Self {
+        self.network_cost = Some(cost);
+        self
+    }
+
+    /// Returns the type of network interface of the candidate.
+    ///
+    /// This is only known for local candidates.
+    pub fn network_type(&self) -> NetworkType {
+        self.network_type
+    }
+
+    /// Returns the network cost of the candidate.
+    ///
+    /// For local candidates, this is the cost set with
+    /// [`Candidate::with_network_cost()`] or the cost of the network type.
+    /// For remote candidates, this is the cost the remote peer sent in its
+    /// connectivity checks, if any.
+    pub fn network_cost(&self) -> Option<u16> {
+        self.network_cost.or_else(|| self.network_type.cost())
+    }
+
+    /// An identifier for the network, sent together with the network cost.
+    pub(crate) fn network_id(&self) -> u16 {
+        let mut hasher = DefaultHasher::new();
+        self.base().ip().hash(&mut hasher);
+        hasher.finish() as u16
+    }
+
+    pub(crate) fn set_network(&mut self, network_type: NetworkType, network_cost: Option<u16>) {
+        self.network_type = network_type;
+        self.network_cost = network_cost;
+    }
+
+    pub(crate) fn set_remote_network_cost(&mut self, cost: u16) {
+        self.network_cost = Some(cost);
+    }
+
+    /// How much lower the local preference is due to the network cost. 0-3.
+    pub(crate) fn network_penalty(&self) -> u32 {
+        let cost = self.network_cost().unwrap_or(0) as u32;
+        (cost / 250).min(3)
+    }
+
     /// Returns the address for the specified ICE candidate.
     pub fn addr(&self) -> SocketAddr {
         self.addr
     }
 
-    /// Returns a reference to the String containing the transport protocol of
-    /// the ICE candidate. For example tcp/udp/..
-    pub fn proto(&self) -> &String {
-        &self.proto
+    /// Returns the hostname of the ICE candidate, if it has one.
+    ///
+    /// Remote candidates can be signaled with a hostname instead of an IP address. Browsers
+    /// do this to hide the host IP behind a random [mDNS][1] `.local` name. Such candidates
+    /// must be resolved with [`Rtc::resolve_candidate()`][crate::Rtc::resolve_candidate]
+    /// before they can be used.
+    ///
+    /// [1]: https://datatracker.ietf.org/doc/html/draft-ietf-mmusic-mdns-ice-candidates
+    pub fn hostname(&self) -> Option<&str> {
+        self.hostname.as_deref()
+    }
+
+    /// Tells if this is a candidate with a hostname that is not resolved to an IP address.
+    pub fn is_unresolved(&self) -> bool {
+        self.hostname.is_some() && self.addr.ip().is_unspecified()
+    }
+
+    pub(crate) fn set_hostname(&mut self, hostname: String) {
+        self.hostname = Some(hostname);
+    }
+
+    /// This candidate with the hostname resolved to `ip`.
+    pub(crate) fn resolved(mut self, ip: IpAddr) -> Result<Candidate, IceError> {
+        if !is_valid_ip(ip) {
+            return Err(IceError::BadCandidate(format!("invalid ip {ip}")));
+        }
+        self.addr = SocketAddr::new(ip, self.addr.port());
+        Ok(self)
+    }
+
+    /// Returns the transport protocol of the ICE candidate.
+    pub fn proto(&self) -> Protocol {
+        self.proto
+    }
+
+    /// Returns the type of TCP candidate. Only set for TCP candidates.
+    pub fn tcptype(&self) -> Option<TcpType> {
+        self.tcptype
     }
 
     pub(crate) fn base(&self) -> SocketAddr {
@@ -361,6 +644,75 @@ pub enum CandidateKind {
     Relayed,
 }
 
+/// Type of network interface a local candidate is on.
+///
+/// See [`Candidate::with_network_type()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum NetworkType {
+    /// The network type is not known.
+    #[default]
+    Unknown,
+    /// Wired, like ethernet.
+    Wired,
+    /// Wi-Fi.
+    Wifi,
+    /// Mobile data.
+    Cellular,
+    /// Virtual private network.
+    Vpn,
+}
+
+impl NetworkType {
+    /// The network cost, using the same values as libWebRTC.
+    fn cost(&self) -> Option<u16> {
+        match self {
+            NetworkType::Unknown => None,
+            NetworkType::Wired => Some(0),
+            NetworkType::Wifi => Some(10),
+            NetworkType::Vpn => Some(50),
+            NetworkType::Cellular => Some(900),
+        }
+    }
+}
+
+/// Type of TCP candidate.
+///
+/// See [RFC 6544][1].
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc6544#section-4.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TcpType {
+    /// Attempts outgoing connections, but doesn't accept incoming.
+    Active,
+    /// Accepts incoming connections, but doesn't attempt outgoing.
+    Passive,
+    /// Simultaneous-open, both attempts and accepts connections.
+    So,
+}
+
+impl TcpType {
+    /// The tcptype a remote candidate has when connecting to a local candidate of this type.
+    pub(crate) fn opposite(&self) -> TcpType {
+        match self {
+            TcpType::Active => TcpType::Passive,
+            TcpType::Passive => TcpType::Active,
+            TcpType::So => TcpType::So,
+        }
+    }
+}
+
+impl fmt::Display for TcpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let x = match self {
+            TcpType::Active => "active",
+            TcpType::Passive => "passive",
+            TcpType::So => "so",
+        };
+        write!(f, "{x}")
+    }
+}
+
 impl fmt::Display for CandidateKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let x = match self {
@@ -385,6 +737,18 @@ fn is_valid_ip(ip: IpAddr) -> bool {
 // TODO: maybe a bit strange this is used for SDP serializing?
 impl fmt::Display for Candidate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // https://www.rfc-editor.org/rfc/rfc6544#section-4.5
+        // Active candidates use the discard port, 9.
+        let port = if self.tcptype == Some(TcpType::Active) {
+            9
+        } else {
+            self.addr.port()
+        };
+        // A hostname hides the IP address.
+        let host = match &self.hostname {
+            Some(hostname) => hostname.clone(),
+            None => self.addr.ip().to_string(),
+        };
         write!(
             f,
             "a=candidate:{} {} {} {} {} {} typ {}",
@@ -392,13 +756,16 @@ impl fmt::Display for Candidate {
             self.component_id,
             self.proto,
             self.prio(),
-            self.addr.ip(),
-            self.addr.port(),
+            host,
+            port,
             self.kind
         )?;
         if let Some((raddr, rport)) = self.raddr.as_ref().map(|r| (r.ip(), r.port())) {
             write!(f, " raddr {raddr} rport {rport}")?;
         }
+        if let Some(tcptype) = self.tcptype {
+            write!(f, " tcptype {tcptype}")?;
+        }
         if let Some(ufrag) = &self.ufrag {
             write!(f, " ufrag {ufrag}")?;
         }
@@ -0,0 +1,372 @@
+use std::time::Duration;
+
+use crate::io::{STUN_INITIAL_RTO_MILLIS, STUN_MAX_RETRANS, STUN_MAX_RTO_MILLIS};
+
+/// Timing, pacing and nomination parameters of the ICE agent.
+///
+/// The defaults follow libWebRTC and are fine for most cases. On lossy links, like
+/// mobile networks, it might be better to wait longer before giving up on a candidate pair.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use str0m::{IceConfig, RtcConfig};
+/// let ice_config = IceConfig::new()
+///     .set_max_retransmits(12)
+///     .set_max_rto(Duration::from_secs(5));
+///
+/// let rtc_config = RtcConfig::new()
+///     .set_ice_config(ice_config);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IceConfig {
+    timing_advance: Duration,
+    initial_rto: Duration,
+    max_rto: Duration,
+    max_retransmits: usize,
+    keepalive_interval: Duration,
+    consent_interval: Duration,
+    consent_timeout: Duration,
+    ice_lite_timeout: Duration,
+    failed_timeout: Duration,
+    nomination_policy: NominationPolicy,
+}
+
+/// How the controlling ICE agent nominates the candidate pair to use.
+///
+/// The policy only matters when str0m is the controlling agent. As the controlled
+/// agent, str0m follows whatever the remote peer nominates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum NominationPolicy {
+    /// Nominate the best working pair, and nominate again when a better pair works.
+    ///
+    /// The remote agent uses the nominated pair with the highest priority, which means
+    /// both sides move to a better pair when its check succeeds later, like a host pair
+    /// that works out after a relay pair.
+    #[default]
+    Continuous,
+
+    /// Nominate once, when the best working pair is known.
+    ///
+    /// The nomination waits for higher priority pairs that are still being checked,
+    /// but not for longer than a short while after the first pair worked. The
+    /// nominated pair is kept until it fails.
+    ///
+    /// See [RFC 8445][1].
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc8445#section-8.1.1
+    Regular,
+
+    /// Nominate every pair with every connectivity check.
+    ///
+    /// Both agents use the highest priority pair that works. This connects fast, but
+    /// the choice can't be changed afterwards.
+    ///
+    /// See [RFC 5245][1].
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc5245#section-8.1.1.2
+    Aggressive,
+
+    /// Nominate as soon as a pair works, and renominate when a better pair works.
+    ///
+    /// Each nomination carries an increasing `GOOG-NOMINATION` value, which lets the
+    /// remote agent follow the latest nomination, also to a pair with lower priority.
+    /// This is what libWebRTC calls renomination.
+    ///
+    /// The SDP has `a=ice-options:renomination`. When the remote peer doesn't signal it
+    /// too, the nomination is [`NominationPolicy::Continuous`].
+    ///
+    /// See [draft-thatcher-ice-renomination][1].
+    ///
+    /// [1]: https://datatracker.ietf.org/doc/html/draft-thatcher-ice-renomination-01
+    Renomination,
+}
+
+impl IceConfig {
+    /// Creates a new config with default values.
+    pub fn new() -> Self {
+        IceConfig::default()
+    }
+
+    /// Set the timing advance (Ta), which paces the connectivity checks.
+    ///
+    /// No more than one check is sent per Ta.
+    ///
+    /// Defaults to 50 milliseconds.
+    pub fn set_timing_advance(mut self, ta: Duration) -> Self {
+        self.timing_advance = ta;
+        self
+    }
+
+    /// The timing advance (Ta).
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::IceConfig;
+    /// assert_eq!(IceConfig::new().timing_advance(), Duration::from_millis(50));
+    /// ```
+    pub fn timing_advance(&self) -> Duration {
+        self.timing_advance
+    }
+
+    /// Set the initial retransmission timeout (RTO) for binding requests.
+    ///
+    /// The RTO doubles for each retransmit until it reaches [`IceConfig::max_rto()`].
+    ///
+    /// Defaults to 250 milliseconds.
+    pub fn set_initial_rto(mut self, rto: Duration) -> Self {
+        self.initial_rto = rto;
+        self
+    }
+
+    /// The initial retransmission timeout (RTO) for binding requests.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::IceConfig;
+    /// assert_eq!(IceConfig::new().initial_rto(), Duration::from_millis(250));
+    /// ```
+    pub fn initial_rto(&self) -> Duration {
+        self.initial_rto
+    }
+
+    /// Set the upper bound for the retransmission timeout (RTO) for binding requests.
+    ///
+    /// When a pair has been unanswered for half this time, the checks are sent more often.
+    ///
+    /// Defaults to 3 seconds.
+    pub fn set_max_rto(mut self, rto: Duration) -> Self {
+        self.max_rto = rto;
+        self
+    }
+
+    /// The upper bound for the retransmission timeout (RTO) for binding requests.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::IceConfig;
+    /// assert_eq!(IceConfig::new().max_rto(), Duration::from_secs(3));
+    /// ```
+    pub fn max_rto(&self) -> Duration {
+        self.max_rto
+    }
+
+    /// Set the number of unanswered binding requests after which a candidate pair is
+    /// considered failed.
+    ///
+    /// When there are no candidate pairs left, the connection state goes to
+    /// [`IceConnectionState::Disconnected`][crate::IceConnectionState::Disconnected].
+    /// This is also how many times a request to a STUN server is sent before giving up.
+    ///
+    /// Defaults to 9.
+    pub fn set_max_retransmits(mut self, count: usize) -> Self {
+        self.max_retransmits = count;
+        self
+    }
+
+    /// The number of unanswered binding requests after which a candidate pair is failed.
+    ///
+    /// ```
+    /// # use str0m::IceConfig;
+    /// assert_eq!(IceConfig::new().max_retransmits(), 9);
+    /// ```
+    pub fn max_retransmits(&self) -> usize {
+        self.max_retransmits
+    }
+
+    /// Set the longest time between binding requests on a candidate pair that works.
+    ///
+    /// This keeps NAT bindings open. The nominated pair is instead checked at the
+    /// [`IceConfig::consent_interval()`].
+    ///
+    /// Defaults to 3 seconds.
+    pub fn set_keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = interval;
+        self
+    }
+
+    /// The longest time between binding requests on a candidate pair that works.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::IceConfig;
+    /// assert_eq!(IceConfig::new().keepalive_interval(), Duration::from_secs(3));
+    /// ```
+    pub fn keepalive_interval(&self) -> Duration {
+        self.keepalive_interval
+    }
+
+    /// Set the basic interval between consent freshness checks.
+    ///
+    /// Once a pair is nominated, the remote peer is periodically asked whether it still
+    /// wants our traffic ([RFC 7675][1]). The actual interval is randomized between
+    /// 0.8 and 1.2 times this value.
+    ///
+    /// Defaults to 5 seconds.
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7675
+    pub fn set_consent_interval(mut self, interval: Duration) -> Self {
+        self.consent_interval = interval;
+        self
+    }
+
+    /// The basic interval between consent freshness checks.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::IceConfig;
+    /// assert_eq!(IceConfig::new().consent_interval(), Duration::from_secs(5));
+    /// ```
+    pub fn consent_interval(&self) -> Duration {
+        self.consent_interval
+    }
+
+    /// Set the time without a consent freshness response after which consent to send
+    /// expires, and [`Event::IceConsentExpired`][crate::Event::IceConsentExpired] is emitted.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn set_consent_timeout(mut self, timeout: Duration) -> Self {
+        self.consent_timeout = timeout;
+        self
+    }
+
+    /// The time without a consent freshness response after which consent expires.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::IceConfig;
+    /// assert_eq!(IceConfig::new().consent_timeout(), Duration::from_secs(30));
+    /// ```
+    pub fn consent_timeout(&self) -> Duration {
+        self.consent_timeout
+    }
+
+    /// Set the time without binding requests from the remote peer after which an ice lite
+    /// agent considers a candidate pair failed.
+    ///
+    /// An ice lite agent doesn't send binding requests itself, so this is the only way it
+    /// can tell the remote peer is gone.
+    ///
+    /// Defaults to 15 seconds.
+    pub fn set_ice_lite_timeout(mut self, timeout: Duration) -> Self {
+        self.ice_lite_timeout = timeout;
+        self
+    }
+
+    /// The time without binding requests after which an ice lite agent fails a pair.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::IceConfig;
+    /// assert_eq!(IceConfig::new().ice_lite_timeout(), Duration::from_secs(15));
+    /// ```
+    pub fn ice_lite_timeout(&self) -> Duration {
+        self.ice_lite_timeout
+    }
+
+    /// Set how long the connection stays [`Disconnected`][d] before it goes to
+    /// [`Failed`][f].
+    ///
+    /// Like a disconnected connection, a failed one can come back if a pair starts
+    /// working again, for instance after adding more remote candidates.
+    ///
+    /// Defaults to 30 seconds.
+    ///
+    /// [d]: crate::IceConnectionState::Disconnected
+    /// [f]: crate::IceConnectionState::Failed
+    pub fn set_failed_timeout(mut self, timeout: Duration) -> Self {
+        self.failed_timeout = timeout;
+        self
+    }
+
+    /// How long the connection stays disconnected before it goes to failed.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::IceConfig;
+    /// assert_eq!(IceConfig::new().failed_timeout(), Duration::from_secs(30));
+    /// ```
+    pub fn failed_timeout(&self) -> Duration {
+        self.failed_timeout
+    }
+
+    /// Set how the controlling agent nominates the candidate pair to use.
+    ///
+    /// Defaults to [`NominationPolicy::Continuous`].
+    pub fn set_nomination_policy(mut self, policy: NominationPolicy) -> Self {
+        self.nomination_policy = policy;
+        self
+    }
+
+    /// How the controlling agent nominates the candidate pair to use.
+    ///
+    /// ```
+    /// # use str0m::{IceConfig, NominationPolicy};
+    /// assert_eq!(IceConfig::new().nomination_policy(), NominationPolicy::Continuous);
+    /// ```
+    pub fn nomination_policy(&self) -> NominationPolicy {
+        self.nomination_policy
+    }
+
+    /// Calculate the send delay given how many times we tried.
+    ///
+    /// Same algorithm as `stun_resend_delay`, but with the configured bounds.
+    pub(crate) fn resend_delay(&self, send_count: usize) -> Duration {
+        if send_count == 0 {
+            return Duration::ZERO;
+        }
+
+        let retrans = (send_count - 1).min(self.max_retransmits).min(31) as u32;
+
+        let rto = self
+            .initial_rto
+            .checked_mul(1 << retrans)
+            .unwrap_or(self.max_rto);
+
+        rto.min(self.max_rto)
+    }
+}
+
+impl Default for IceConfig {
+    fn default() -> Self {
+        IceConfig {
+            timing_advance: Duration::from_millis(50),
+            initial_rto: Duration::from_millis(STUN_INITIAL_RTO_MILLIS),
+            max_rto: Duration::from_millis(STUN_MAX_RTO_MILLIS),
+            max_retransmits: STUN_MAX_RETRANS,
+            keepalive_interval: Duration::from_millis(STUN_MAX_RTO_MILLIS),
+            consent_interval: Duration::from_secs(5),
+            consent_timeout: Duration::from_secs(30),
+            ice_lite_timeout: Duration::from_secs(15),
+            failed_timeout: Duration::from_secs(30),
+            nomination_policy: NominationPolicy::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::io::stun_resend_delay;
+
+    #[test]
+    fn default_resend_delay_same_as_stun() {
+        let config = IceConfig::default();
+        for i in 0..20 {
+            assert_eq!(config.resend_delay(i), stun_resend_delay(i));
+        }
+    }
+
+    #[test]
+    fn resend_delay_bounds() {
+        let config = IceConfig::new()
+            .set_initial_rto(Duration::from_millis(100))
+            .set_max_rto(Duration::from_secs(1));
+
+        assert_eq!(config.resend_delay(1), Duration::from_millis(100));
+        assert_eq!(config.resend_delay(2), Duration::from_millis(200));
+        assert_eq!(config.resend_delay(4), Duration::from_millis(800));
+        assert_eq!(config.resend_delay(5), Duration::from_secs(1));
+        assert_eq!(config.resend_delay(100), Duration::from_secs(1));
+    }
+}
@@ -7,10 +7,19 @@ mod agent;
 pub(crate) use agent::{IceAgent, IceAgentEvent};
 pub use agent::{IceConnectionState, IceCreds};
 
+mod config;
+pub use config::{IceConfig, NominationPolicy};
+
 mod candidate;
-pub use candidate::{Candidate, CandidateKind};
+pub use candidate::{Candidate, CandidateKind, NetworkType, TcpType};
 
 mod pair;
+pub use pair::CheckState;
+
+mod srflx;
+
+mod turn;
+pub use turn::TurnServer;
 
 /// Errors from the ICE agent.
 #[allow(missing_docs)]
@@ -74,7 +83,7 @@ mod test {
     use std::ops::{Deref, DerefMut};
     use std::time::{Duration, Instant};
 
-    use crate::io::Receive;
+    use crate::io::{Protocol, Receive};
     use tracing::Span;
 
     pub fn sock(s: impl Into<String>) -> SocketAddr {
@@ -148,6 +157,10 @@ mod test {
 
         a1.set_controlling(true);
         a2.set_controlling(false);
+        // One side disconnects long before the other, and should not go to failed.
+        let config = IceConfig::new().set_failed_timeout(Duration::from_secs(60));
+        a1.set_config(config);
+        a2.set_config(config);
 
         loop {
             if a1.state().is_connected() && a2.state().is_connected() {
@@ -185,7 +198,7 @@ mod test {
         assert_eq!(
             a1.stats(),
             IceAgentStats {
-                bind_request_sent: 11,
+                bind_request_sent: 13,
                 bind_success_recv: 2,
                 bind_request_recv: 11,
                 discovered_recv_count: 1,
@@ -205,6 +218,102 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn host_host_failed() {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = host("1.1.1.1:9999"); // 9999 is just dropped by propagate
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = host("2.2.2.2:1000");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+        let config = IceConfig::new().set_failed_timeout(Duration::from_secs(5));
+        a1.set_config(config);
+        a2.set_config(config);
+
+        loop {
+            if a1.state().is_failed() && a2.state().is_failed() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        for a in [&a1, &a2] {
+            let state_at = |state| {
+                a.events
+                    .iter()
+                    .find(|(_, e)| *e == IceAgentEvent::IceConnectionStateChange(state))
+                    .map(|(d, _)| *d)
+                    .unwrap()
+            };
+            let disconnected = state_at(IceConnectionState::Disconnected);
+            let failed = state_at(IceConnectionState::Failed);
+
+            let since = failed - disconnected;
+            assert!(since >= Duration::from_secs(5));
+            assert!(since < Duration::from_secs(5) + Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    pub fn host_host_consent_expired() {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = host("1.1.1.1:1000");
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = host("2.2.2.2:1000");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+        let config = IceConfig::new().set_consent_timeout(Duration::from_secs(10));
+        a1.set_config(config);
+        a2.set_config(config);
+
+        loop {
+            if a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        // Run for a while so both sides have consent checks answered.
+        loop {
+            if a1.time - a1.start_time > Duration::from_secs(15) {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        let dropped = a1.time.max(a2.time) - a1.start_time;
+        a1.drop_sent_packets = true;
+
+        loop {
+            if !a1.state().is_connected() && !a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        for a in [&a1, &a2] {
+            let (d, _) = a
+                .events
+                .iter()
+                .find(|(_, e)| matches!(e, IceAgentEvent::ConsentExpired { .. }))
+                .expect("consent to expire");
+
+            // Well before the ordinary pair timeouts would kick in.
+            assert!(*d < dropped + Duration::from_secs(12));
+        }
+    }
+
     #[test]
     pub fn host_host() {
         let mut a1 = TestAgent::new(info_span!("L"));
@@ -249,6 +358,287 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn host_host_network_cost() {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = host("1.1.1.1:1000");
+        a1.add_local_candidate(c1.clone().with_network_type(NetworkType::Cellular));
+        a2.add_remote_candidate(c1);
+        let c2 = host("2.2.2.2:1000");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+
+        loop {
+            if a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        // The remote side learns the cost from GOOG-NETWORK-INFO.
+        let stats = a2.pair_stats();
+        assert_eq!(stats[0].remote.network_cost(), Some(900));
+
+        // Untagged candidates don't send any.
+        let stats = a1.pair_stats();
+        assert_eq!(stats[0].remote.network_cost(), None);
+    }
+
+    #[test]
+    pub fn host_host_tcp() {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = Candidate::host_tcp(sock("1.1.1.1:1000"), TcpType::Active).unwrap();
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = Candidate::host_tcp(sock("2.2.2.2:1000"), TcpType::Passive).unwrap();
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+
+        loop {
+            if a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        for a in [&a1, &a2] {
+            let nominated = a.events.iter().find_map(|(_, e)| match e {
+                IceAgentEvent::NominatedSend { proto, .. } => Some(*proto),
+                _ => None,
+            });
+            assert_eq!(nominated, Some(Protocol::Tcp));
+        }
+    }
+
+    #[test]
+    pub fn host_host_rtcp_component() {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+        a1.set_rtcp_mux(false);
+        a2.set_rtcp_mux(false);
+
+        for (port, component_id) in [(1000, 1), (1001, 2)] {
+            let c1 = host(format!("1.1.1.1:{port}"))
+                .with_component_id(component_id)
+                .unwrap();
+            a1.add_local_candidate(c1.clone());
+            a2.add_remote_candidate(c1);
+            let c2 = host(format!("2.2.2.2:{port}"))
+                .with_component_id(component_id)
+                .unwrap();
+            a2.add_local_candidate(c2.clone());
+            a1.add_remote_candidate(c2);
+        }
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+
+        loop {
+            if a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        for a in [&a1, &a2] {
+            let mut nominated: Vec<_> = a
+                .events
+                .iter()
+                .filter_map(|(_, e)| match e {
+                    IceAgentEvent::NominatedSend {
+                        component_id,
+                        source,
+                        destination,
+                        ..
+                    } => Some((*component_id, source.port(), destination.port())),
+                    _ => None,
+                })
+                .collect();
+            nominated.sort();
+            assert_eq!(nominated, vec![(1, 1000, 1000), (2, 1001, 1001)]);
+        }
+    }
+
+    fn two_pairs(policy: NominationPolicy) -> (TestAgent, TestAgent) {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        for c1 in [host("1.1.1.1:1000"), host("1.1.1.1:1001")] {
+            a1.add_local_candidate(c1.clone());
+            a2.add_remote_candidate(c1);
+        }
+        let c2 = host("2.2.2.2:1000");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+        let config = IceConfig::new().set_nomination_policy(policy);
+        a1.set_config(config);
+        a2.set_config(config);
+        a1.set_remote_renomination(true);
+        a2.set_remote_renomination(true);
+
+        loop {
+            let all_succeeded = [&a1, &a2].iter().all(|a| {
+                let stats = a.pair_stats();
+                stats.len() == 2 && stats.iter().all(|p| p.state == CheckState::Succeeded)
+            });
+            if all_succeeded && a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        (a1, a2)
+    }
+
+    fn last_nominated_port(a: &TestAgent, local: bool) -> Option<u16> {
+        a.events.iter().rev().find_map(|(_, e)| match e {
+            IceAgentEvent::NominatedSend {
+                source,
+                destination,
+                ..
+            } => Some(if local { source } else { destination }.port()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    pub fn regular_nomination_once() {
+        let (a1, a2) = two_pairs(NominationPolicy::Regular);
+
+        assert_eq!(a1.stats().nomination_send_count, 1);
+        // The best pair is the one with the first local candidate.
+        assert_eq!(last_nominated_port(&a1, true), Some(1000));
+        assert_eq!(last_nominated_port(&a2, false), Some(1000));
+    }
+
+    fn late_better_pair(policy: NominationPolicy) -> (TestAgent, TestAgent) {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = host("1.1.1.1:1000");
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = host("2.2.2.2:1000");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+        let config = IceConfig::new().set_nomination_policy(policy);
+        a1.set_config(config);
+        a2.set_config(config);
+
+        loop {
+            if a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        // IPv6 host candidates have a higher priority than the connected IPv4 pair.
+        let c1 = host("[::1]:1000");
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = host("[::2]:1000");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+
+        loop {
+            let all_succeeded = a1.pair_stats().len() == 2
+                && a1
+                    .pair_stats()
+                    .iter()
+                    .all(|p| p.state == CheckState::Succeeded);
+            if all_succeeded {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        for _ in 0..10 {
+            progress(&mut a1, &mut a2);
+        }
+
+        (a1, a2)
+    }
+
+    fn last_nominated_ipv6(a: &TestAgent, local: bool) -> Option<bool> {
+        a.events.iter().rev().find_map(|(_, e)| match e {
+            IceAgentEvent::NominatedSend {
+                source,
+                destination,
+                ..
+            } => Some(if local { source } else { destination }.is_ipv6()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    pub fn continuous_nomination_upgrades() {
+        let (a1, a2) = late_better_pair(NominationPolicy::default());
+
+        // Both sides move to the better pair that worked later.
+        assert_eq!(a1.stats().nomination_send_count, 2);
+        assert_eq!(last_nominated_ipv6(&a1, true), Some(true));
+        assert_eq!(last_nominated_ipv6(&a2, false), Some(true));
+    }
+
+    #[test]
+    pub fn regular_nomination_keeps_pair() {
+        let (a1, a2) = late_better_pair(NominationPolicy::Regular);
+
+        // The nominated pair is kept, despite the better pair.
+        assert_eq!(a1.stats().nomination_send_count, 1);
+        assert_eq!(last_nominated_ipv6(&a1, true), Some(false));
+        assert_eq!(last_nominated_ipv6(&a2, false), Some(false));
+    }
+
+    #[test]
+    pub fn aggressive_nomination() {
+        let (a1, a2) = two_pairs(NominationPolicy::Aggressive);
+
+        // Every check nominates, and both sides end up on the best pair.
+        assert!(a2.pair_stats().iter().all(|p| p.nominated));
+        assert_eq!(last_nominated_port(&a1, true), Some(1000));
+        assert_eq!(last_nominated_port(&a2, false), Some(1000));
+    }
+
+    #[test]
+    pub fn select_pair_renomination() {
+        let (mut a1, mut a2) = two_pairs(NominationPolicy::Renomination);
+
+        assert_eq!(last_nominated_port(&a1, true), Some(1000));
+        assert_eq!(last_nominated_port(&a2, false), Some(1000));
+
+        // The controlled side can't select.
+        let stats = a2.pair_stats();
+        assert!(!a2.select_pair(&stats[0].local, &stats[0].remote));
+
+        let other = a1
+            .pair_stats()
+            .into_iter()
+            .find(|p| !p.selected)
+            .expect("pair that isn't selected");
+        assert_eq!(other.local.addr().port(), 1001);
+        assert!(a1.select_pair(&other.local, &other.remote));
+
+        for _ in 0..10 {
+            progress(&mut a1, &mut a2);
+        }
+
+        // Both sides follow the selection, despite the lower priority.
+        assert_eq!(last_nominated_port(&a1, true), Some(1001));
+        assert_eq!(last_nominated_port(&a2, false), Some(1001));
+    }
+
     #[test]
     pub fn ice_lite_no_connection() {
         let mut a1 = TestAgent::new(info_span!("L"));
@@ -2,14 +2,13 @@ use std::collections::VecDeque;
 use std::fmt;
 use std::time::{Duration, Instant};
 
-use crate::io::{stun_resend_delay, STUN_MAX_RETRANS};
-use crate::io::{Id, TransId, STUN_MAX_RTO_MILLIS};
-use crate::Candidate;
+use rand::random;
 
-const MIN_TIMEOUT: Duration = Duration::from_millis(STUN_MAX_RTO_MILLIS);
+use crate::io::{Id, TransId};
+use crate::stats::IceCandidatePairStats;
+use crate::Candidate;
 
-// When running ice-lite we need a cutoff when we consider the remote definitely gone.
-const RECENT_BINDING_REQUEST: Duration = Duration::from_secs(15);
+use super::IceConfig;
 
 #[derive(Default)]
 /// A pair of candidates, local and remote, in the ice agent.
@@ -35,7 +34,7 @@ pub struct CandidatePair {
 
     /// Record of the latest STUN messages we've tried using this pair.
     ///
-    /// This list will never grow beyond STUN_MAX_RETRANS + 1
+    /// This list will never grow beyond max_retransmits + 1
     binding_attempts: VecDeque<BindingAttempt>,
 
     /// The next time we are to do a binding attempt, cached, since we
@@ -50,8 +49,39 @@ pub struct CandidatePair {
 
     /// State of nomination for this candidate pair.
     nomination_state: NominationState,
+
+    /// GOOG-NOMINATION value. When controlling, the value we send. When controlled,
+    /// the highest value the remote sent. 0 means none.
+    nomination: u32,
+
+    /// When this pair first got a successful binding response.
+    succeeded_time: Option<Instant>,
+
+    /// Last time we got a binding response on this pair. For the nominated pair,
+    /// this is what keeps our consent to send fresh.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc7675
+    consent_recv: Option<Instant>,
+
+    /// Randomized delay until the next consent check, picked for every attempt.
+    consent_delay: Duration,
+
+    /// Counters for the pair stats.
+    requests_sent: u64,
+    responses_recv: u64,
+    requests_recv: u64,
+    responses_sent: u64,
+    bytes_sent: u64,
+    bytes_recv: u64,
+
+    /// Round trip time of the latest answered binding request.
+    rtt: Option<Duration>,
+
+    /// Sum of all round trip times, for calculating averages.
+    total_rtt: Duration,
 }
 
+/// State of connectivity checks for a candidate pair.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum CheckState {
     /// A check has not been sent for this pair.
@@ -116,12 +146,12 @@ impl Default for PairId {
 }
 
 impl CandidatePair {
-    pub fn new(local_idx: usize, remote_idx: usize, prio: u64) -> Self {
+    pub fn new(local_idx: usize, remote_idx: usize, prio: u64, config: &IceConfig) -> Self {
         CandidatePair {
             local_idx,
             remote_idx,
             prio,
-            binding_attempts: VecDeque::with_capacity(STUN_MAX_RETRANS + 1),
+            binding_attempts: VecDeque::with_capacity(config.max_retransmits() + 1),
             ..Default::default()
         }
     }
@@ -181,11 +211,12 @@ impl CandidatePair {
         self.remote_binding_request_time
     }
 
-    pub fn has_recent_remote_binding_request(&self, now: Instant) -> bool {
+    /// When running ice-lite we need a cutoff when we consider the remote definitely gone.
+    pub fn has_recent_remote_binding_request(&self, now: Instant, config: &IceConfig) -> bool {
         let Some(t) = self.remote_binding_request_time else {
             return false;
         };
-        now - t < RECENT_BINDING_REQUEST
+        now - t < config.ice_lite_timeout()
     }
 
     pub fn is_nominated(&self) -> bool {
@@ -203,13 +234,51 @@ impl CandidatePair {
         }
     }
 
+    /// Nominates this pair again, with a higher GOOG-NOMINATION value than before.
+    ///
+    /// Unlike [`CandidatePair::nominate()`], this is fine for a pair that is already
+    /// nominated.
+    pub fn renominate(&mut self, nomination: u32) {
+        self.nomination = nomination;
+        self.nomination_state = NominationState::Nominated;
+        self.cached_next_attempt_time = None;
+        debug!("Renominated pair: {:?}", self);
+    }
+
+    /// The GOOG-NOMINATION value, 0 if there is none.
+    pub fn nomination(&self) -> u32 {
+        self.nomination
+    }
+
+    /// Records a GOOG-NOMINATION value from the remote (controlling) agent.
+    pub fn record_remote_nomination(&mut self, nomination: u32) {
+        self.nomination = self.nomination.max(nomination);
+    }
+
+    /// When this pair first got a successful binding response.
+    pub fn succeeded_time(&self) -> Option<Instant> {
+        self.succeeded_time
+    }
+
+    /// Tells if this pair is the successfully nominated pair, which is checked
+    /// for consent freshness rather than by the ordinary retransmits.
+    pub fn is_consent_checked(&self) -> bool {
+        self.nomination_state == NominationState::Success && self.consent_recv.is_some()
+    }
+
     /// Records a new binding request attempt.
     ///
     /// Returns the transaction id to use in the STUN message.
-    pub fn new_attempt(&mut self, now: Instant) -> TransId {
+    pub fn new_attempt(&mut self, now: Instant, config: &IceConfig) -> TransId {
         // calculate a new time
         self.cached_next_attempt_time = None;
 
+        // To prevent synchronization of consent checks, each interval MUST be randomized
+        // from a uniform distribution in the range of 0.8 and 1.2 times the basic period.
+        self.consent_delay = config
+            .consent_interval()
+            .mul_f64(0.8 + 0.4 * random::<f64>());
+
         if matches!(self.nomination_state, NominationState::Nominated) {
             debug!("Nominated attempt STUN binding: {:?}", self);
             self.nomination_state = NominationState::Attempt;
@@ -223,9 +292,10 @@ impl CandidatePair {
         };
 
         self.binding_attempts.push_back(attempt);
+        self.requests_sent += 1;
 
-        // Never keep more than STUN_MAX_RETRANS attempts.
-        while self.binding_attempts.len() > STUN_MAX_RETRANS {
+        // Never keep more than max_retransmits attempts.
+        while self.binding_attempts.len() > config.max_retransmits() {
             self.binding_attempts.pop_front();
         }
 
@@ -265,6 +335,12 @@ impl CandidatePair {
             .expect("Binding request attempt");
 
         attempt.respone_recv = Some(now);
+        self.consent_recv = Some(now);
+
+        let rtt = now - attempt.request_sent;
+        self.rtt = Some(rtt);
+        self.total_rtt += rtt;
+        self.responses_recv += 1;
 
         if attempt.nominated && self.nomination_state == NominationState::Attempt {
             self.nomination_state = NominationState::Success;
@@ -278,6 +354,7 @@ impl CandidatePair {
                 CheckState::Succeeded
             );
             self.state = CheckState::Succeeded;
+            self.succeeded_time = Some(now);
         }
 
         trace!("Recorded binding response: {:?}", self);
@@ -307,7 +384,7 @@ impl CandidatePair {
     /// When we should do the next retry.
     ///
     /// Returns `None` if we are not to attempt this pair anymore.
-    pub fn next_binding_attempt(&mut self, now: Instant) -> Instant {
+    pub fn next_binding_attempt(&mut self, now: Instant, config: &IceConfig) -> Instant {
         if let Some(cached) = self.cached_next_attempt_time {
             return cached;
         }
@@ -315,24 +392,31 @@ impl CandidatePair {
         let next = if matches!(self.nomination_state, NominationState::Nominated) {
             // Cheating a bit to make the nomination "skip the queue".
             now.checked_sub(Duration::from_secs(60)).unwrap()
+        } else if self.is_consent_checked() && self.unanswered().is_none() {
+            // Consent checks on the nominated pair have their own interval, which is
+            // not capped by the keepalive interval.
+            // unwrap is fine since there has been a response to some attempt.
+            let next = self.last_attempt_time().unwrap() + self.consent_delay;
+            self.cached_next_attempt_time = Some(next);
+            return next;
         } else if let Some(last) = self.last_attempt_time() {
-            // When we have unanswered for longer than STUN_MAX_RTO_MILLIS / 2, start
+            // When we have unanswered for longer than max_rto / 2, start
             // checking more often.
             let unanswered_count = self
                 .unanswered()
-                .filter(|(_, since)| now - *since > Duration::from_millis(STUN_MAX_RTO_MILLIS) / 2)
+                .filter(|(_, since)| now - *since > config.max_rto() / 2)
                 .map(|(count, _)| count);
 
             let send_count = unanswered_count.unwrap_or(self.binding_attempts.len());
 
-            last + stun_resend_delay(send_count)
+            last + config.resend_delay(send_count)
         } else {
             // No previous attempt, do next retry straight away.
             now
         };
 
         // At least do a check at this time.
-        let min = now + MIN_TIMEOUT;
+        let min = now + config.keepalive_interval();
 
         let at_least = next.min(min);
 
@@ -342,24 +426,83 @@ impl CandidatePair {
         at_least
     }
 
+    /// When consent expires, unless there is a new binding response before then.
+    ///
+    /// Returns `None` if this pair is not checked for consent freshness.
+    pub fn consent_expires(&self, config: &IceConfig) -> Option<Instant> {
+        if !self.is_consent_checked() {
+            return None;
+        }
+        self.consent_recv.map(|t| t + config.consent_timeout())
+    }
+
     /// Tells if this candidate pair is still possible to use for connectivity.
     ///
+    /// The nominated pair is possible until consent expires, the consent timeout after
+    /// the last binding response.
+    ///
     /// Returns `false` if the candidate has failed.
-    pub fn is_still_possible(&self, now: Instant) -> bool {
+    pub fn is_still_possible(&self, now: Instant, config: &IceConfig) -> bool {
+        if let Some(expires) = self.consent_expires(config) {
+            return now < expires;
+        }
+
         let attempts = self.binding_attempts.len();
         let unanswered = self.unanswered().map(|b| b.0).unwrap_or(0);
 
-        if attempts < STUN_MAX_RETRANS || unanswered < STUN_MAX_RETRANS {
+        let max_retransmits = config.max_retransmits();
+
+        if attempts < max_retransmits || unanswered < max_retransmits {
             true
         } else {
             // check to see if we are still waiting for the last attempt
             // this unwrap is fine because unanswered count > 0
             let last = self.last_attempt_time().unwrap();
-            let cutoff = last + stun_resend_delay(STUN_MAX_RETRANS);
+            let cutoff = last + config.resend_delay(max_retransmits);
             now < cutoff
         }
     }
 
+    /// Records a binding request from the remote peer, that we replied to.
+    pub fn record_remote_request(&mut self) {
+        self.requests_recv += 1;
+        self.responses_sent += 1;
+    }
+
+    /// Records data (not connectivity checks) sent over this pair.
+    pub fn record_bytes_sent(&mut self, bytes: usize) {
+        self.bytes_sent += bytes as u64;
+    }
+
+    /// Records data (not connectivity checks) received over this pair.
+    pub fn record_bytes_recv(&mut self, bytes: usize) {
+        self.bytes_recv += bytes as u64;
+    }
+
+    /// Stats for this pair.
+    pub fn stats(
+        &self,
+        local_candidates: &[Candidate],
+        remote_candidates: &[Candidate],
+        selected: bool,
+    ) -> IceCandidatePairStats {
+        IceCandidatePairStats {
+            local: self.local_candidate(local_candidates).clone(),
+            remote: self.remote_candidate(remote_candidates).clone(),
+            state: self.state,
+            nominated: self.is_nominated(),
+            selected,
+            rtt: self.rtt,
+            total_rtt: self.total_rtt,
+            requests_sent: self.requests_sent,
+            responses_received: self.responses_recv,
+            requests_received: self.requests_recv,
+            responses_sent: self.responses_sent,
+            bytes_sent: self.bytes_sent,
+            bytes_received: self.bytes_recv,
+        }
+    }
+
     pub(crate) fn copy_remote_binding_requests(&mut self, other: &CandidatePair) {
         self.remote_binding_requests = other.remote_binding_requests;
         self.remote_binding_request_time = other.remote_binding_request_time;
@@ -402,7 +545,7 @@ impl fmt::Debug for CandidatePair {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "CandidatePair({}-{} prio={} state={:?} attempts={} unanswered={} remote={} last={:?} nom={:?})",
+            "CandidatePair({}-{} prio={} state={:?} attempts={} unanswered={} remote={} last={:?} nom={:?} consent={:?})",
             self.local_idx,
             self.remote_idx,
             self.prio,
@@ -411,7 +554,8 @@ impl fmt::Debug for CandidatePair {
             self.unanswered().map(|b| b.0).unwrap_or(0),
             self.remote_binding_requests,
             self.remote_binding_request_time,
-            self.nomination_state
+            self.nomination_state,
+            self.consent_recv,
         )
     }
 }
@@ -0,0 +1,240 @@
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use crate::io::{Attribute, Class, Method, StunMessage, TransId};
+use crate::io::{DatagramRecv, Protocol, Receive, Transmit};
+
+use super::IceConfig;
+
+/// A binding request to a STUN server to discover the server reflexive address
+/// of a local host candidate.
+///
+/// https://www.rfc-editor.org/rfc/rfc8445#section-5.1.1.2
+#[derive(Debug)]
+pub(crate) struct SrflxRequest {
+    /// The STUN server.
+    server: SocketAddr,
+
+    /// Base of the host candidate the request is sent from.
+    base: SocketAddr,
+
+    /// Transaction id, which is the same for all retransmits.
+    trans_id: TransId,
+
+    /// Time of the last send.
+    sent: Option<Instant>,
+
+    /// Number of times the request was sent.
+    send_count: usize,
+
+    /// Set when we got a response, or gave up.
+    done: bool,
+
+    /// Request ready to be polled.
+    transmit: Option<Transmit>,
+}
+
+impl SrflxRequest {
+    pub fn new(server: SocketAddr, base: SocketAddr) -> Self {
+        SrflxRequest {
+            server,
+            base,
+            trans_id: TransId::new(),
+            sent: None,
+            send_count: 0,
+            done: false,
+            transmit: None,
+        }
+    }
+
+    pub fn base(&self) -> SocketAddr {
+        self.base
+    }
+
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Tells if the incoming datagram is the response to this request.
+    pub fn accepts(&self, r: &Receive) -> bool {
+        let DatagramRecv::Stun(message) = &r.contents else {
+            return false;
+        };
+        self.accepts_message(r.proto, r.source, r.destination, message)
+    }
+
+    pub fn accepts_message(
+        &self,
+        proto: Protocol,
+        source: SocketAddr,
+        destination: SocketAddr,
+        message: &StunMessage,
+    ) -> bool {
+        !self.done
+            && proto == Protocol::Udp
+            && source == self.server
+            && destination == self.base
+            && message.method() == Method::Binding
+            && message.is_response()
+            && message.trans_id() == self.trans_id
+    }
+
+    /// Handle the response to this request.
+    ///
+    /// Returns the server reflexive address on success.
+    pub fn handle_response(&mut self, message: &StunMessage) -> Option<SocketAddr> {
+        self.done = true;
+
+        if !message.is_success() {
+            debug!(
+                "STUN server {} error response: {:?}",
+                self.server,
+                message.error_code()
+            );
+            return None;
+        }
+
+        let mapped = message.mapped_address();
+        debug!(
+            "STUN server {} mapped {} to {:?}",
+            self.server, self.base, mapped
+        );
+
+        mapped
+    }
+
+    pub fn handle_timeout(&mut self, now: Instant, config: &IceConfig) {
+        if self.done {
+            return;
+        }
+
+        if let Some(sent) = self.sent {
+            if now < sent + config.resend_delay(self.send_count) {
+                return;
+            }
+        }
+
+        if self.send_count >= config.max_retransmits() {
+            debug!("STUN server {} timed out from {}", self.server, self.base);
+            self.done = true;
+            return;
+        }
+
+        self.sent = Some(now);
+        self.send_count += 1;
+
+        let message = StunMessage::new(
+            Method::Binding,
+            Class::Request,
+            self.trans_id,
+            vec![Attribute::FingerprintMark],
+        );
+
+        trace!(
+            "Send STUN request: {} -> {} {:?}",
+            self.base,
+            self.server,
+            message
+        );
+
+        let mut buf = vec![0_u8; 64];
+        let n = message
+            .to_bytes_with_key(&[], &mut buf)
+            .expect("IO error writing STUN request");
+        buf.truncate(n);
+
+        self.transmit = Some(Transmit {
+            proto: Protocol::Udp,
+            source: self.base,
+            destination: self.server,
+            contents: buf.into(),
+        });
+    }
+
+    pub fn poll_timeout(&self, last_now: Instant, config: &IceConfig) -> Option<Instant> {
+        if self.done {
+            return None;
+        }
+
+        Some(match self.sent {
+            Some(sent) => sent + config.resend_delay(self.send_count),
+            None => last_now,
+        })
+    }
+
+    pub fn poll_transmit(&mut self) -> Option<Transmit> {
+        self.transmit.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn server() -> SocketAddr {
+        "5.5.5.5:3478".parse().unwrap()
+    }
+
+    fn base() -> SocketAddr {
+        "1.1.1.1:1000".parse().unwrap()
+    }
+
+    #[test]
+    fn binding_success() {
+        let now = Instant::now();
+        let mut req = SrflxRequest::new(server(), base());
+        req.handle_timeout(now, &IceConfig::default());
+
+        let t = req.poll_transmit().unwrap();
+        assert_eq!(t.source, base());
+        assert_eq!(t.destination, server());
+
+        // Requests to STUN servers have no integrity, and can't be parsed.
+        let trans_id = TransId::from_slice(&t.contents[8..20]);
+        let mapped: SocketAddr = "9.9.9.9:1234".parse().unwrap();
+        let response = StunMessage::new(
+            Method::Binding,
+            Class::Success,
+            trans_id,
+            vec![Attribute::XorMappedAddress(mapped)],
+        );
+
+        let mut buf = vec![0; 100];
+        let n = response.to_bytes_with_key(&[], &mut buf).unwrap();
+
+        let r = Receive::new(Protocol::Udp, server(), base(), &buf[..n]).unwrap();
+        assert!(req.accepts(&r));
+
+        let DatagramRecv::Stun(message) = r.contents else {
+            panic!("Not STUN");
+        };
+        assert_eq!(req.handle_response(&message), Some(mapped));
+        assert!(req.is_done());
+        assert_eq!(req.poll_timeout(now, &IceConfig::default()), None);
+    }
+
+    #[test]
+    fn binding_retransmit_and_time_out() {
+        for max_retransmits in [9, 3] {
+            let config = IceConfig::new().set_max_retransmits(max_retransmits);
+            let mut now = Instant::now();
+            let mut req = SrflxRequest::new(server(), base());
+
+            let mut sent = 0;
+            while let Some(next) = req.poll_timeout(now, &config) {
+                now = next;
+                req.handle_timeout(now, &config);
+                if req.poll_transmit().is_some() {
+                    sent += 1;
+                }
+            }
+
+            assert!(req.is_done());
+            assert_eq!(sent, max_retransmits);
+        }
+    }
+}
@@ -0,0 +1,1080 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use md5::{Digest, Md5};
+
+use crate::io::{stun_resend_delay, Attribute, Class, Method, StunMessage, TransId};
+use crate::io::{DatagramRecv, Protocol, Receive, Transmit, DATAGRAM_MTU};
+
+/// The lifetime we ask for when allocating and refreshing (seconds).
+///
+/// The server is free to pick another value, which we then respect.
+const ALLOCATION_LIFETIME: u32 = 600;
+
+/// Permissions are always valid for 5 minutes.
+///
+/// https://www.rfc-editor.org/rfc/rfc8656#section-9
+const PERMISSION_LIFETIME: Duration = Duration::from_secs(300);
+
+/// Channel bindings are valid for 10 minutes.
+///
+/// https://www.rfc-editor.org/rfc/rfc8656#section-12
+const CHANNEL_LIFETIME: Duration = Duration::from_secs(600);
+
+/// How long before expiry we refresh allocations, permissions and channels.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Number of times we send a request before giving up on the transaction.
+///
+/// This corresponds to Rc in https://www.rfc-editor.org/rfc/rfc8489#section-6.2.1
+const TURN_MAX_RETRANS: usize = 7;
+
+/// REQUESTED-TRANSPORT value for UDP (IANA protocol number).
+const PROTO_UDP: u8 = 17;
+
+/// Valid range of channel numbers.
+const CHANNEL_MIN: u16 = 0x4000;
+const CHANNEL_MAX: u16 = 0x4fff;
+
+/// A TURN server used to obtain relayed ICE candidates.
+///
+/// str0m makes one allocation per local host candidate of the same IP family
+/// as the server. The resulting relayed candidates are reported via
+/// [`Event::LocalCandidate`][crate::Event::LocalCandidate].
+///
+/// ```
+/// # use str0m::{Rtc, TurnServer};
+/// let server = TurnServer::new("1.2.3.4:3478".parse().unwrap(), "user", "secret");
+///
+/// let rtc = Rtc::builder()
+///     .add_turn_server(server)
+///     .build();
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct TurnServer {
+    addr: SocketAddr,
+    username: String,
+    password: String,
+}
+
+impl TurnServer {
+    /// Creates a new TURN server configuration using long-term credentials.
+    pub fn new(addr: SocketAddr, username: impl Into<String>, password: impl Into<String>) -> Self {
+        TurnServer {
+            addr,
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    /// The address of the TURN server.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The username of the long-term credentials.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+}
+
+/// Client side of one TURN allocation (RFC 8656).
+///
+/// The client is Sans I/O. It talks to the server from the `local` socket, which is the
+/// base of a host candidate.
+#[derive(Debug)]
+pub(crate) struct TurnClient {
+    server: TurnServer,
+
+    /// The local socket we talk to the server from.
+    local: SocketAddr,
+
+    /// Current state of the allocation.
+    state: AllocationState,
+
+    /// Last time handle_timeout run.
+    last_now: Option<Instant>,
+
+    /// Realm provided by the server in a 401 response.
+    realm: Option<String>,
+
+    /// Nonce provided by the server in a 401 or 438 response.
+    nonce: Option<String>,
+
+    /// Key for long-term credentials. MD5(username ":" realm ":" password).
+    key: Option<[u8; 16]>,
+
+    /// The relayed address on the server, once allocated.
+    relayed: Option<SocketAddr>,
+
+    /// Our address as seen by the server. Used as related address of the
+    /// relayed candidate.
+    mapped: Option<SocketAddr>,
+
+    /// When the allocation expires unless refreshed.
+    expires: Option<Instant>,
+
+    /// Permissions installed (or to install) for peer IP addresses.
+    permissions: Vec<Permission>,
+
+    /// Channels bound (or to bind) to peer addresses.
+    channels: Vec<ChannelBinding>,
+
+    /// Channel number for the next binding. Numbers are never reused, not
+    /// even those of failed bindings.
+    next_channel: u16,
+
+    /// Outstanding requests to the server.
+    transactions: Vec<Transaction>,
+
+    /// Requests ready to be polled.
+    transmit: VecDeque<Transmit>,
+
+    /// Events ready to be polled.
+    events: VecDeque<TurnEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AllocationState {
+    /// No request sent yet.
+    New,
+    /// Allocate request is in flight.
+    Allocating,
+    /// We have a relayed address.
+    Allocated,
+    /// The allocation failed or was lost. The client is inert.
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Allocate,
+    Refresh,
+    CreatePermission(IpAddr),
+    ChannelBind(u16, SocketAddr),
+}
+
+#[derive(Debug)]
+struct Transaction {
+    trans_id: TransId,
+    request: Request,
+    /// Time of the last send.
+    sent: Instant,
+    /// Number of times this request was sent.
+    send_count: usize,
+    /// Whether this request is a retry after an authentication challenge.
+    retried: bool,
+}
+
+#[derive(Debug)]
+struct Permission {
+    ip: IpAddr,
+    expires: Option<Instant>,
+    in_flight: bool,
+}
+
+#[derive(Debug)]
+struct ChannelBinding {
+    number: u16,
+    peer: SocketAddr,
+    expires: Option<Instant>,
+    in_flight: bool,
+}
+
+/// Events from a [`TurnClient`].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum TurnEvent {
+    /// The allocation succeeded.
+    Allocated {
+        relayed: SocketAddr,
+        mapped: Option<SocketAddr>,
+    },
+    /// The allocation failed, or an existing allocation was lost.
+    Failed,
+}
+
+impl TurnClient {
+    pub fn new(server: TurnServer, local: SocketAddr) -> Self {
+        TurnClient {
+            server,
+            local,
+            state: AllocationState::New,
+            last_now: None,
+            realm: None,
+            nonce: None,
+            key: None,
+            relayed: None,
+            mapped: None,
+            expires: None,
+            permissions: vec![],
+            channels: vec![],
+            next_channel: CHANNEL_MIN,
+            transactions: vec![],
+            transmit: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn server_addr(&self) -> SocketAddr {
+        self.server.addr
+    }
+
+    pub fn local(&self) -> SocketAddr {
+        self.local
+    }
+
+    /// The relayed address, if allocated.
+    pub fn relayed(&self) -> Option<SocketAddr> {
+        self.relayed
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.state == AllocationState::Failed
+    }
+
+    /// Ensure there is a permission for the peer IP.
+    ///
+    /// The permission is installed on the next `handle_timeout`.
+    pub fn ensure_permission(&mut self, ip: IpAddr) {
+        if self.permissions.iter().any(|p| p.ip == ip) {
+            return;
+        }
+        debug!("TURN add permission for: {}", ip);
+        self.permissions.push(Permission {
+            ip,
+            expires: None,
+            in_flight: false,
+        });
+    }
+
+    /// Ensure there is a channel bound for the peer.
+    ///
+    /// Channels save the 36 bytes overhead of a Send/Data indication per packet.
+    pub fn ensure_channel(&mut self, peer: SocketAddr) {
+        if self.channels.iter().any(|c| c.peer == peer) {
+            return;
+        }
+
+        let number = self.next_channel;
+        if number > CHANNEL_MAX {
+            debug!("TURN no more channels available for: {}", peer);
+            return;
+        }
+        self.next_channel += 1;
+
+        debug!("TURN add channel {:04x} for: {}", number, peer);
+        self.channels.push(ChannelBinding {
+            number,
+            peer,
+            expires: None,
+            in_flight: false,
+        });
+    }
+
+    /// Tells if the incoming datagram is TURN traffic for this allocation.
+    pub fn accepts(&self, r: &Receive) -> bool {
+        if r.proto != Protocol::Udp || r.source != self.server.addr || r.destination != self.local {
+            return false;
+        }
+
+        match &r.contents {
+            DatagramRecv::Stun(v) => {
+                if v.is_data_indication() {
+                    v.xor_peer_address()
+                        .map(|p| self.permissions.iter().any(|x| x.ip == p.ip()))
+                        .unwrap_or(false)
+                } else {
+                    let trans_id = v.trans_id();
+                    self.transactions.iter().any(|t| t.trans_id == trans_id)
+                }
+            }
+            DatagramRecv::ChannelData(v) => {
+                let number = (v[0] as u16) << 8 | v[1] as u16;
+                self.channels.iter().any(|c| c.number == number)
+            }
+            _ => false,
+        }
+    }
+
+    /// Handle incoming traffic from the TURN server.
+    ///
+    /// Returns the unwrapped relayed data, if any.
+    pub fn handle_receive<'a>(&mut self, now: Instant, r: Receive<'a>) -> Option<Receive<'a>> {
+        match r.contents {
+            DatagramRecv::Stun(message) => {
+                if message.is_data_indication() {
+                    let (Some(peer), Some(data)) = (message.xor_peer_address(), message.data())
+                    else {
+                        debug!("TURN Data indication without peer or data");
+                        return None;
+                    };
+                    return self.relayed_receive(peer, data);
+                }
+
+                if message.is_response() {
+                    self.handle_response(now, &message);
+                } else {
+                    trace!("TURN ignore STUN message: {:?}", message);
+                }
+
+                None
+            }
+            DatagramRecv::ChannelData(buf) => {
+                let number = (buf[0] as u16) << 8 | buf[1] as u16;
+                let len = (buf[2] as usize) << 8 | buf[3] as usize;
+
+                if 4 + len > buf.len() {
+                    debug!(
+                        "TURN ChannelData length mismatch: {} > {}",
+                        len,
+                        buf.len() - 4
+                    );
+                    return None;
+                }
+
+                let Some(channel) = self.channels.iter().find(|c| c.number == number) else {
+                    debug!("TURN ChannelData for unknown channel: {:04x}", number);
+                    return None;
+                };
+
+                self.relayed_receive(channel.peer, &buf[4..(4 + len)])
+            }
+            _ => {
+                trace!("TURN drop unexpected datagram: {:?}", r.contents);
+                None
+            }
+        }
+    }
+
+    fn relayed_receive<'a>(&self, peer: SocketAddr, data: &'a [u8]) -> Option<Receive<'a>> {
+        let relayed = self.relayed?;
+
+        match DatagramRecv::try_from(data) {
+            Ok(contents) => Some(Receive {
+                proto: Protocol::Udp,
+                source: peer,
+                destination: relayed,
+                contents,
+            }),
+            Err(e) => {
+                debug!("TURN failed to parse relayed data: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// Wrap a datagram sent from the relayed address for sending to the TURN server.
+    pub fn wrap(&mut self, t: Transmit) -> Transmit {
+        let channel = self
+            .channels
+            .iter()
+            .find(|c| c.peer == t.destination && c.expires.is_some());
+
+        let contents = if let Some(channel) = channel {
+            // https://www.rfc-editor.org/rfc/rfc8656#section-12.4
+            // Over UDP, the padding is not required.
+            let mut buf = Vec::with_capacity(4 + t.contents.len());
+            buf.extend_from_slice(&channel.number.to_be_bytes());
+            buf.extend_from_slice(&(t.contents.len() as u16).to_be_bytes());
+            buf.extend_from_slice(&t.contents);
+            buf
+        } else {
+            let send = StunMessage::new(
+                Method::Send,
+                Class::Indication,
+                TransId::new(),
+                vec![
+                    Attribute::XorPeerAddress(t.destination),
+                    Attribute::Data(&t.contents),
+                    Attribute::FingerprintMark,
+                ],
+            );
+
+            // 36 bytes of header and attributes, plus padding and fingerprint.
+            let mut buf = vec![0_u8; t.contents.len() + 64];
+            let n = send
+                .to_bytes_with_key(&[], &mut buf)
+                .expect("IO error writing TURN Send indication");
+            buf.truncate(n);
+            buf
+        };
+
+        Transmit {
+            proto: Protocol::Udp,
+            source: self.local,
+            destination: self.server.addr,
+            contents: contents.into(),
+        }
+    }
+
+    pub fn handle_timeout(&mut self, now: Instant) {
+        self.last_now = Some(now);
+
+        if self.state == AllocationState::New {
+            info!("TURN allocate from {} via {}", self.local, self.server.addr);
+            self.state = AllocationState::Allocating;
+            self.send_request(now, Request::Allocate, false);
+            return;
+        }
+
+        self.handle_retransmits(now);
+
+        if self.state != AllocationState::Allocated {
+            return;
+        }
+
+        // unwrap is ok because Allocated always sets an expiry.
+        let expires = self.expires.unwrap();
+
+        if now >= expires {
+            warn!("TURN allocation expired: {:?}", self.relayed);
+            self.fail();
+            return;
+        }
+
+        let has_request = |r: Request, ts: &[Transaction]| ts.iter().any(|t| t.request == r);
+
+        if now + REFRESH_MARGIN >= expires && !has_request(Request::Refresh, &self.transactions) {
+            debug!("TURN refresh allocation: {:?}", self.relayed);
+            self.send_request(now, Request::Refresh, false);
+        }
+
+        let mut to_send = vec![];
+
+        for p in &mut self.permissions {
+            let due = p.expires.map(|e| now + REFRESH_MARGIN >= e).unwrap_or(true);
+            if due && !p.in_flight {
+                p.in_flight = true;
+                to_send.push(Request::CreatePermission(p.ip));
+            }
+        }
+
+        for c in &mut self.channels {
+            let due = c.expires.map(|e| now + REFRESH_MARGIN >= e).unwrap_or(true);
+            if due && !c.in_flight {
+                c.in_flight = true;
+                to_send.push(Request::ChannelBind(c.number, c.peer));
+            }
+        }
+
+        for request in to_send {
+            self.send_request(now, request, false);
+        }
+    }
+
+    fn handle_retransmits(&mut self, now: Instant) {
+        let mut timed_out = vec![];
+
+        let mut i = 0;
+        while i < self.transactions.len() {
+            let t = &self.transactions[i];
+            if now < t.sent + stun_resend_delay(t.send_count) {
+                i += 1;
+                continue;
+            }
+
+            if t.send_count >= TURN_MAX_RETRANS {
+                let t = self.transactions.remove(i);
+                debug!("TURN request timed out: {:?}", t.request);
+                timed_out.push(t.request);
+                continue;
+            }
+
+            let t = &mut self.transactions[i];
+            t.sent = now;
+            t.send_count += 1;
+            let (trans_id, request) = (t.trans_id, t.request);
+
+            trace!("TURN resend request: {:?}", request);
+            self.transmit_request(trans_id, request);
+
+            i += 1;
+        }
+
+        for request in timed_out {
+            match request {
+                // An allocation that is refreshed in time is not lost. Refreshing will be
+                // attempted again until the allocation expires.
+                Request::Refresh => {}
+                // Retry on next timeout.
+                Request::CreatePermission(ip) => {
+                    if let Some(p) = self.permissions.iter_mut().find(|p| p.ip == ip) {
+                        p.in_flight = false;
+                    }
+                }
+                Request::ChannelBind(number, _) => {
+                    if let Some(c) = self.channels.iter_mut().find(|c| c.number == number) {
+                        c.in_flight = false;
+                    }
+                }
+                Request::Allocate => self.request_failed(request),
+            }
+        }
+    }
+
+    pub fn poll_timeout(&self, last_now: Instant) -> Option<Instant> {
+        match self.state {
+            AllocationState::New => return Some(last_now),
+            AllocationState::Failed => return None,
+            _ => {}
+        }
+
+        let retransmit = self
+            .transactions
+            .iter()
+            .map(|t| t.sent + stun_resend_delay(t.send_count))
+            .min();
+
+        let refresh = if self.state == AllocationState::Allocated {
+            let pending_permission = self
+                .permissions
+                .iter()
+                .any(|p| p.expires.is_none() && !p.in_flight);
+            let pending_channel = self
+                .channels
+                .iter()
+                .any(|c| c.expires.is_none() && !c.in_flight);
+
+            if pending_permission || pending_channel {
+                Some(last_now)
+            } else {
+                let allocation = self.expires.map(|e| e - REFRESH_MARGIN);
+                let permissions = self.permissions.iter().filter_map(|p| p.expires);
+                let channels = self.channels.iter().filter_map(|c| c.expires);
+                permissions
+                    .chain(channels)
+                    .map(|e| e - REFRESH_MARGIN)
+                    .chain(allocation)
+                    .min()
+            }
+        } else {
+            None
+        };
+
+        match (retransmit, refresh) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    pub fn poll_transmit(&mut self) -> Option<Transmit> {
+        self.transmit.pop_front()
+    }
+
+    pub fn poll_event(&mut self) -> Option<TurnEvent> {
+        self.events.pop_front()
+    }
+
+    fn send_request(&mut self, now: Instant, request: Request, retried: bool) {
+        let trans_id = TransId::new();
+
+        self.transactions.push(Transaction {
+            trans_id,
+            request,
+            sent: now,
+            send_count: 1,
+            retried,
+        });
+
+        self.transmit_request(trans_id, request);
+    }
+
+    fn transmit_request(&mut self, trans_id: TransId, request: Request) {
+        let (method, mut attrs) = match request {
+            Request::Allocate => (
+                Method::Allocate,
+                vec![
+                    Attribute::RequestedTransport(PROTO_UDP),
+                    Attribute::Lifetime(ALLOCATION_LIFETIME),
+                ],
+            ),
+            Request::Refresh => (
+                Method::Refresh,
+                vec![Attribute::Lifetime(ALLOCATION_LIFETIME)],
+            ),
+            Request::CreatePermission(ip) => (
+                Method::CreatePermission,
+                // The port is ignored for permissions.
+                vec![Attribute::XorPeerAddress(SocketAddr::new(ip, 0))],
+            ),
+            Request::ChannelBind(number, peer) => (
+                Method::ChannelBind,
+                vec![
+                    Attribute::ChannelNumber(number),
+                    Attribute::XorPeerAddress(peer),
+                ],
+            ),
+        };
+
+        let key =
+            if let (Some(realm), Some(nonce), Some(key)) = (&self.realm, &self.nonce, &self.key) {
+                attrs.push(Attribute::Username(&self.server.username));
+                attrs.push(Attribute::Realm(realm));
+                attrs.push(Attribute::Nonce(nonce));
+                attrs.push(Attribute::MessageIntegrityMark);
+                &key[..]
+            } else {
+                &[]
+            };
+
+        attrs.push(Attribute::FingerprintMark);
+
+        let message = StunMessage::new(method, Class::Request, trans_id, attrs);
+
+        trace!("Send TURN request: {:?}", message);
+
+        let mut buf = vec![0_u8; DATAGRAM_MTU];
+        let n = message
+            .to_bytes_with_key(key, &mut buf)
+            .expect("IO error writing TURN request");
+        buf.truncate(n);
+
+        self.transmit.push_back(Transmit {
+            proto: Protocol::Udp,
+            source: self.local,
+            destination: self.server.addr,
+            contents: buf.into(),
+        });
+    }
+
+    fn handle_response(&mut self, now: Instant, message: &StunMessage) {
+        let trans_id = message.trans_id();
+        let Some(idx) = self
+            .transactions
+            .iter()
+            .position(|t| t.trans_id == trans_id)
+        else {
+            debug!("TURN response for unknown transaction: {:?}", message);
+            return;
+        };
+
+        // Once we have credentials, successful responses must be integrity protected.
+        if let Some(key) = &self.key {
+            if message.is_success() && !message.check_integrity_with_key(key) {
+                debug!("TURN response failed integrity check: {:?}", message);
+                return;
+            }
+        }
+
+        let t = self.transactions.remove(idx);
+
+        if message.is_success() {
+            self.handle_success(now, t.request, message);
+        } else {
+            self.handle_error(now, t, message);
+        }
+    }
+
+    fn handle_success(&mut self, now: Instant, request: Request, message: &StunMessage) {
+        debug!("TURN {:?} success", request);
+
+        match request {
+            Request::Allocate => {
+                let Some(relayed) = message.xor_relayed_address() else {
+                    warn!("TURN allocate response without relayed address");
+                    self.fail();
+                    return;
+                };
+                let mapped = message.mapped_address();
+                let lifetime = message.lifetime().unwrap_or(ALLOCATION_LIFETIME);
+
+                info!("TURN allocated {} (mapped: {:?})", relayed, mapped);
+
+                self.state = AllocationState::Allocated;
+                self.relayed = Some(relayed);
+                self.mapped = mapped;
+                self.expires = Some(now + Duration::from_secs(lifetime as u64));
+                self.events
+                    .push_back(TurnEvent::Allocated { relayed, mapped });
+            }
+            Request::Refresh => {
+                let lifetime = message.lifetime().unwrap_or(ALLOCATION_LIFETIME);
+                self.expires = Some(now + Duration::from_secs(lifetime as u64));
+            }
+            Request::CreatePermission(ip) => {
+                if let Some(p) = self.permissions.iter_mut().find(|p| p.ip == ip) {
+                    p.expires = Some(now + PERMISSION_LIFETIME);
+                    p.in_flight = false;
+                }
+            }
+            Request::ChannelBind(number, peer) => {
+                if let Some(c) = self.channels.iter_mut().find(|c| c.number == number) {
+                    c.expires = Some(now + CHANNEL_LIFETIME);
+                    c.in_flight = false;
+                }
+                // A channel binding also installs or refreshes a permission.
+                if let Some(p) = self.permissions.iter_mut().find(|p| p.ip == peer.ip()) {
+                    p.expires = Some(now + PERMISSION_LIFETIME);
+                }
+            }
+        }
+    }
+
+    fn handle_error(&mut self, now: Instant, t: Transaction, message: &StunMessage) {
+        let code = message.error_code();
+
+        // 401 (Unauthorized) is the challenge for the first request.
+        // 438 (Stale Nonce) means we need to retry with a new nonce.
+        let is_challenge = matches!(code, Some((401, _)) | Some((438, _)));
+
+        if is_challenge && !t.retried {
+            if let (Some(realm), Some(nonce)) = (message.realm(), message.nonce()) {
+                debug!("TURN {:?} challenged ({:?}), retry", t.request, code);
+                self.key = Some(long_term_key(
+                    &self.server.username,
+                    realm,
+                    &self.server.password,
+                ));
+                self.realm = Some(realm.into());
+                self.nonce = Some(nonce.into());
+                self.send_request(now, t.request, true);
+                return;
+            }
+        }
+
+        warn!("TURN {:?} failed: {:?}", t.request, code);
+        self.request_failed(t.request);
+    }
+
+    fn request_failed(&mut self, request: Request) {
+        match request {
+            Request::Allocate | Request::Refresh => self.fail(),
+            Request::CreatePermission(ip) => {
+                self.permissions.retain(|p| p.ip != ip);
+            }
+            Request::ChannelBind(number, _) => {
+                self.channels.retain(|c| c.number != number);
+            }
+        }
+    }
+
+    fn fail(&mut self) {
+        if self.state == AllocationState::Failed {
+            return;
+        }
+        self.state = AllocationState::Failed;
+        self.transactions.clear();
+        self.events.push_back(TurnEvent::Failed);
+    }
+}
+
+/// Key for long-term credentials.
+///
+/// https://www.rfc-editor.org/rfc/rfc8489#section-9.2.2
+// TODO: The password should be processed using SASLprep (OpaqueString profile).
+fn long_term_key(username: &str, realm: &str, password: &str) -> [u8; 16] {
+    let mut hasher = Md5::new();
+    hasher.update(username.as_bytes());
+    hasher.update(b":");
+    hasher.update(realm.as_bytes());
+    hasher.update(b":");
+    hasher.update(password.as_bytes());
+    hasher.finalize().into()
+}
+
+impl fmt::Debug for TurnServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TurnServer")
+            .field("addr", &self.addr)
+            .field("username", &self.username)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const REALM: &str = "example.org";
+    const NONCE: &str = "f//499k954d6OL34oL9FSTvy64sA";
+
+    fn server() -> TurnServer {
+        TurnServer::new("5.5.5.5:3478".parse().unwrap(), "user", "secret")
+    }
+
+    fn local() -> SocketAddr {
+        "1.1.1.1:1000".parse().unwrap()
+    }
+
+    fn relayed() -> SocketAddr {
+        "5.5.5.5:50000".parse().unwrap()
+    }
+
+    fn mapped() -> SocketAddr {
+        "9.9.9.9:1000".parse().unwrap()
+    }
+
+    fn peer() -> SocketAddr {
+        "2.2.2.2:2000".parse().unwrap()
+    }
+
+    /// Sends a server response to the client.
+    fn respond(
+        client: &mut TurnClient,
+        now: Instant,
+        method: Method,
+        class: Class,
+        attrs: Vec<Attribute>,
+        key: &[u8],
+    ) {
+        let t = client.poll_transmit().expect("a request");
+        let request = StunMessage::parse(&t.contents).unwrap();
+        assert_eq!(request.method(), method);
+
+        let response = StunMessage::new(method, class, request.trans_id(), attrs);
+        let mut buf = vec![0; 1500];
+        let n = response.to_bytes_with_key(key, &mut buf).unwrap();
+        buf.truncate(n);
+
+        let r = Receive::new(Protocol::Udp, server().addr(), local(), &buf).unwrap();
+        assert!(client.accepts(&r));
+        assert!(client.handle_receive(now, r).is_none());
+    }
+
+    fn allocated_client(now: Instant) -> TurnClient {
+        let mut client = TurnClient::new(server(), local());
+        client.handle_timeout(now);
+
+        respond(
+            &mut client,
+            now,
+            Method::Allocate,
+            Class::Failure,
+            vec![
+                Attribute::ErrorCode(401, "Unauthorized"),
+                Attribute::Realm(REALM),
+                Attribute::Nonce(NONCE),
+                Attribute::FingerprintMark,
+            ],
+            &[],
+        );
+
+        let key = long_term_key("user", REALM, "secret");
+
+        respond(
+            &mut client,
+            now,
+            Method::Allocate,
+            Class::Success,
+            vec![
+                Attribute::XorRelayedAddress(relayed()),
+                Attribute::XorMappedAddress(mapped()),
+                Attribute::Lifetime(600),
+                Attribute::MessageIntegrityMark,
+                Attribute::FingerprintMark,
+            ],
+            &key,
+        );
+
+        client
+    }
+
+    #[test]
+    fn allocate_with_challenge() {
+        let now = Instant::now();
+        let mut client = allocated_client(now);
+
+        assert_eq!(client.relayed(), Some(relayed()));
+        assert_eq!(
+            client.poll_event(),
+            Some(TurnEvent::Allocated {
+                relayed: relayed(),
+                mapped: Some(mapped())
+            })
+        );
+        assert!(client.poll_transmit().is_none());
+    }
+
+    #[test]
+    fn authenticated_request_has_integrity() {
+        let now = Instant::now();
+        let mut client = TurnClient::new(server(), local());
+        client.handle_timeout(now);
+
+        respond(
+            &mut client,
+            now,
+            Method::Allocate,
+            Class::Failure,
+            vec![
+                Attribute::ErrorCode(401, "Unauthorized"),
+                Attribute::Realm(REALM),
+                Attribute::Nonce(NONCE),
+            ],
+            &[],
+        );
+
+        let t = client.poll_transmit().unwrap();
+        let request = StunMessage::parse(&t.contents).unwrap();
+        assert_eq!(request.realm(), Some(REALM));
+        assert_eq!(request.nonce(), Some(NONCE));
+
+        let key = long_term_key("user", REALM, "secret");
+        assert!(request.check_integrity_with_key(&key));
+    }
+
+    #[test]
+    fn allocate_fails_on_second_challenge() {
+        let now = Instant::now();
+        let mut client = TurnClient::new(server(), local());
+        client.handle_timeout(now);
+
+        for _ in 0..2 {
+            respond(
+                &mut client,
+                now,
+                Method::Allocate,
+                Class::Failure,
+                vec![
+                    Attribute::ErrorCode(401, "Unauthorized"),
+                    Attribute::Realm(REALM),
+                    Attribute::Nonce(NONCE),
+                ],
+                &[],
+            );
+        }
+
+        assert!(client.is_failed());
+        assert_eq!(client.poll_event(), Some(TurnEvent::Failed));
+    }
+
+    #[test]
+    fn allocate_retransmit_and_time_out() {
+        let mut now = Instant::now();
+        let mut client = TurnClient::new(server(), local());
+        client.handle_timeout(now);
+
+        let mut sent = 0;
+        while !client.is_failed() {
+            while client.poll_transmit().is_some() {
+                sent += 1;
+            }
+            now = client.poll_timeout(now).unwrap();
+            client.handle_timeout(now);
+        }
+
+        assert_eq!(sent, TURN_MAX_RETRANS);
+    }
+
+    #[test]
+    fn send_indication_and_channel_data() {
+        let now = Instant::now();
+        let mut client = allocated_client(now);
+        let key = long_term_key("user", REALM, "secret");
+
+        let t = Transmit {
+            proto: Protocol::Udp,
+            source: relayed(),
+            destination: peer(),
+            contents: vec![23, 1, 2, 3, 4].into(),
+        };
+
+        // No channel, so this becomes a Send indication.
+        let wrapped = client.wrap(t);
+        assert_eq!(wrapped.source, local());
+        assert_eq!(wrapped.destination, server().addr());
+        let send = StunMessage::parse(&wrapped.contents).unwrap();
+        assert_eq!(send.method(), Method::Send);
+        assert_eq!(send.xor_peer_address(), Some(peer()));
+        assert_eq!(send.data(), Some(&[23, 1, 2, 3, 4][..]));
+
+        client.ensure_channel(peer());
+        client.ensure_permission(peer().ip());
+        client.handle_timeout(now);
+
+        respond(
+            &mut client,
+            now,
+            Method::CreatePermission,
+            Class::Success,
+            vec![Attribute::MessageIntegrityMark, Attribute::FingerprintMark],
+            &key,
+        );
+        respond(
+            &mut client,
+            now,
+            Method::ChannelBind,
+            Class::Success,
+            vec![Attribute::MessageIntegrityMark, Attribute::FingerprintMark],
+            &key,
+        );
+
+        let t = Transmit {
+            proto: Protocol::Udp,
+            source: relayed(),
+            destination: peer(),
+            contents: vec![23, 1, 2, 3, 4].into(),
+        };
+
+        let wrapped = client.wrap(t);
+        assert_eq!(&wrapped.contents[..], &[0x40, 0x00, 0, 5, 23, 1, 2, 3, 4]);
+
+        // Incoming ChannelData is unwrapped to DTLS from the peer.
+        let r = Receive::new(Protocol::Udp, server().addr(), local(), &wrapped.contents).unwrap();
+        assert!(client.accepts(&r));
+        let inner = client.handle_receive(now, r).unwrap();
+        assert_eq!(inner.source, peer());
+        assert_eq!(inner.destination, relayed());
+        assert!(matches!(inner.contents, DatagramRecv::Dtls(_)));
+    }
+
+    #[test]
+    fn failed_channel_number_not_reused() {
+        let now = Instant::now();
+        let mut client = allocated_client(now);
+        let key = long_term_key("user", REALM, "secret");
+
+        let peer2: SocketAddr = "5.5.5.5:5000".parse().unwrap();
+
+        client.ensure_channel(peer());
+        client.handle_timeout(now);
+        respond(
+            &mut client,
+            now,
+            Method::ChannelBind,
+            Class::Success,
+            vec![Attribute::MessageIntegrityMark, Attribute::FingerprintMark],
+            &key,
+        );
+
+        // The last bound channel fails.
+        client.ensure_channel(peer2);
+        client.handle_timeout(now);
+        respond(
+            &mut client,
+            now,
+            Method::ChannelBind,
+            Class::Failure,
+            vec![
+                Attribute::ErrorCode(400, "Bad Request"),
+                Attribute::MessageIntegrityMark,
+                Attribute::FingerprintMark,
+            ],
+            &key,
+        );
+
+        let numbers: Vec<_> = client.channels.iter().map(|c| c.number).collect();
+        assert_eq!(numbers, [0x4000]);
+
+        client.ensure_channel(peer2);
+        let numbers: Vec<_> = client.channels.iter().map(|c| c.number).collect();
+        assert_eq!(numbers, [0x4000, 0x4002]);
+    }
+
+    #[test]
+    fn refresh_before_expiry() {
+        let now = Instant::now();
+        let mut client = allocated_client(now);
+
+        let next = client.poll_timeout(now).unwrap();
+        assert_eq!(next - now, Duration::from_secs(600) - REFRESH_MARGIN);
+
+        client.handle_timeout(next);
+        let t = client.poll_transmit().unwrap();
+        let request = StunMessage::parse(&t.contents).unwrap();
+        assert_eq!(request.method(), Method::Refresh);
+        assert_eq!(request.lifetime(), Some(ALLOCATION_LIFETIME));
+    }
+}
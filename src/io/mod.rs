@@ -11,13 +11,18 @@ use thiserror::Error;
 
 mod stun;
 pub(crate) use stun::stun_resend_delay;
+pub(crate) use stun::{Attribute, Class, Method};
 pub(crate) use stun::{
-    StunError, StunMessage, TransId, STUN_MAX_RETRANS, STUN_MAX_RTO_MILLIS, STUN_TIMEOUT,
+    StunError, StunMessage, TransId, STUN_INITIAL_RTO_MILLIS, STUN_MAX_RETRANS,
+    STUN_MAX_RTO_MILLIS, STUN_TIMEOUT,
 };
 
 mod sha1;
 pub(crate) use self::sha1::Sha1;
 
+mod tcp;
+pub use tcp::TcpFramer;
+
 mod id;
 // this is only exported from this crate to avoid needing
 // a "util" crate or similar.
@@ -47,8 +52,24 @@ pub enum NetError {
     Io(#[from] io::Error),
 }
 
+/// Type of protocol used in [`Transmit`] and [`Receive`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    /// UDP
+    Udp,
+    /// TCP
+    ///
+    /// Datagrams over TCP are framed as described in [RFC 4571][1], see [`TcpFramer`].
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc4571
+    Tcp,
+}
+
 /// An outgoing packet
 pub struct Transmit {
+    /// The protocol the datagram should be sent over.
+    pub proto: Protocol,
+
     /// The source socket this packet should be sent from.
     ///
     /// For ICE it's important to match up outgoing packets with source network interface.
@@ -80,6 +101,9 @@ impl From<DatagramSend> for Vec<u8> {
 #[derive(Debug)]
 /// Received incoming data.
 pub struct Receive<'a> {
+    /// The protocol the datagram was received over.
+    pub proto: Protocol,
+
     /// The socket this received data originated from.
     pub source: SocketAddr,
 
@@ -93,12 +117,14 @@ pub struct Receive<'a> {
 impl<'a> Receive<'a> {
     /// Creates a new instance by trying to parse the contents of `buf`.
     pub fn new(
+        proto: Protocol,
         source: SocketAddr,
         destination: SocketAddr,
         buf: &'a [u8],
     ) -> Result<Self, NetError> {
         let contents = DatagramRecv::try_from(buf)?;
         Ok(Receive {
+            proto,
             source,
             destination,
             contents,
@@ -116,6 +142,8 @@ pub enum DatagramRecv<'a> {
     Rtp(&'a [u8]),
     #[doc(hidden)]
     Rtcp(&'a [u8]),
+    #[doc(hidden)]
+    ChannelData(&'a [u8]),
 }
 
 impl<'a> TryFrom<&'a [u8]> for DatagramRecv<'a> {
@@ -131,6 +159,7 @@ impl<'a> TryFrom<&'a [u8]> for DatagramRecv<'a> {
             MultiplexKind::Dtls => Dtls(value),
             MultiplexKind::Rtp => Rtp(value),
             MultiplexKind::Rtcp => Rtcp(value),
+            MultiplexKind::ChannelData => ChannelData(value),
         })
     }
 }
@@ -141,6 +170,7 @@ pub(crate) enum MultiplexKind {
     Dtls,
     Rtp,
     Rtcp,
+    ChannelData,
 }
 
 impl<'a> TryFrom<&'a [u8]> for MultiplexKind {
@@ -154,6 +184,9 @@ impl<'a> TryFrom<&'a [u8]> for MultiplexKind {
             Ok(MultiplexKind::Stun)
         } else if byte0 >= 20 && byte0 < 64 {
             Ok(MultiplexKind::Dtls)
+        } else if byte0 >= 64 && byte0 < 80 && len >= 4 {
+            // https://www.rfc-editor.org/rfc/rfc7983#section-7
+            Ok(MultiplexKind::ChannelData)
         } else if byte0 >= 128 && byte0 < 192 && len > 2 {
             let byte1 = value[1];
             let payload_type = byte1 & 0x7f;
@@ -183,6 +216,7 @@ impl<'a> TryFrom<&'a Transmit> for Receive<'a> {
 
     fn try_from(t: &'a Transmit) -> Result<Self, Self::Error> {
         Ok(Receive {
+            proto: t.proto,
             source: t.source,
             destination: t.destination,
             contents: DatagramRecv::try_from(&t.contents[..])?,
@@ -193,6 +227,7 @@ impl<'a> TryFrom<&'a Transmit> for Receive<'a> {
 impl fmt::Debug for Transmit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Transmit")
+            .field("proto", &self.proto)
             .field("source", &self.source)
             .field("destination", &self.destination)
             .field("len", &self.contents.len())
@@ -200,6 +235,16 @@ impl fmt::Debug for Transmit {
     }
 }
 
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let x = match self {
+            Protocol::Udp => "udp",
+            Protocol::Tcp => "tcp",
+        };
+        write!(f, "{x}")
+    }
+}
+
 impl Deref for DatagramSend {
     type Target = [u8];
 
@@ -215,6 +260,7 @@ impl fmt::Debug for DatagramRecv<'_> {
             Self::Dtls(v) => write!(f, "Dtls(len: {})", v.len()),
             Self::Rtp(v) => write!(f, "Rtp(len: {})", v.len()),
             Self::Rtcp(v) => write!(f, "Rtcp(len: {})", v.len()),
+            Self::ChannelData(v) => write!(f, "ChannelData(len: {})", v.len()),
         }
     }
     //
@@ -1,13 +1,13 @@
 /// The sha1 hmac checks of SRTP packets is one of the most expensive operations.
 /// The sha-1 crate with feature "asm" seems to be the most performant way of doing it,
 /// however the "asm" feature doesn't work on windows. The Sha1 struct uses openssl for
-/// windows, and the sha-1 crate for unix.
+/// windows, and the sha-1 crate for unix, or for windows without openssl.
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "openssl"))]
 #[derive(Clone)]
 pub struct Sha1(std::sync::Arc<openssl::pkey::PKey<openssl::pkey::Private>>);
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "openssl"))]
 impl Sha1 {
     pub fn hmac(self, payloads: &[&[u8]]) -> [u8; 20] {
         use openssl::hash::MessageDigest;
@@ -25,7 +25,7 @@ impl Sha1 {
     }
 }
 
-#[cfg(windows)]
+#[cfg(all(windows, feature = "openssl"))]
 impl From<&[u8]> for Sha1 {
     fn from(value: &[u8]) -> Self {
         Sha1(std::sync::Arc::new(
@@ -34,11 +34,11 @@ impl From<&[u8]> for Sha1 {
     }
 }
 
-#[cfg(unix)]
+#[cfg(not(all(windows, feature = "openssl")))]
 #[derive(Clone)]
 pub struct Sha1(hmac::Hmac<sha1::Sha1>);
 
-#[cfg(unix)]
+#[cfg(not(all(windows, feature = "openssl")))]
 impl Sha1 {
     pub fn hmac(mut self, payloads: &[&[u8]]) -> [u8; 20] {
         use hmac::Mac;
@@ -51,7 +51,7 @@ impl Sha1 {
     }
 }
 
-#[cfg(unix)]
+#[cfg(not(all(windows, feature = "openssl")))]
 impl From<&[u8]> for Sha1 {
     fn from(value: &[u8]) -> Self {
         use hmac::Hmac;
@@ -56,7 +56,7 @@ impl TransId {
         TransId(t)
     }
 
-    fn from_slice(s: &[u8]) -> Self {
+    pub fn from_slice(s: &[u8]) -> Self {
         let mut t = [0_u8; 12];
         t[..].copy_from_slice(s);
         TransId(t)
@@ -95,22 +95,25 @@ impl<'a> StunMessage<'a> {
         let method = Method::from_typ(typ);
         let trans_id = TransId::from_slice(&buf[8..20]);
 
-        let mut message_integrity_offset = 0;
+        let mut message_integrity_offset = None;
 
         let attrs = Attribute::parse(&buf[20..], trans_id, &mut message_integrity_offset)?;
 
-        // message-integrity only includes the length up until and including
-        // the message-integrity attribute.
-        if message_integrity_offset == 0 {
-            return Err(StunError::Parse("No message integrity in incoming".into()));
-        }
-
-        // length including message integrity attribute
-        let integrity_len = (message_integrity_offset + 4 + 20) as u16;
+        let (integrity, integrity_len) = if let Some(offset) = message_integrity_offset {
+            // message-integrity only includes the length up until and including
+            // the message-integrity attribute.
+            let integrity_len = (offset + 4 + 20) as u16;
 
-        // password as key is called "short-term credentials"
-        // buffer from beginning including header (+20) to where message-integrity starts.
-        let integrity = &buf[0..(message_integrity_offset + 20)];
+            // buffer from beginning including header (+20) to where message-integrity starts.
+            (&buf[0..(offset + 20)], integrity_len)
+        } else if method == Method::Binding && class == Class::Request {
+            // ICE connectivity checks always use short-term credentials.
+            return Err(StunError::Parse("No message integrity in incoming".into()));
+        } else {
+            // Responses from STUN servers, TURN indications and some error
+            // responses (such as 401) have no message integrity.
+            (&[][..], 0)
+        };
 
         if method == Method::Binding && class == Class::Success {
             if attrs.mapped_address().is_none() {
@@ -147,10 +150,38 @@ impl<'a> StunMessage<'a> {
         self.method == Method::Binding && self.class == Class::Success
     }
 
+    pub fn is_data_indication(&self) -> bool {
+        self.method == Method::Data && self.class == Class::Indication
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.class == Class::Success
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
     pub fn trans_id(&self) -> TransId {
         self.trans_id
     }
 
+    /// Creates a new message from parts.
+    ///
+    /// To get message integrity and fingerprint, the attributes must end
+    /// with [`Attribute::MessageIntegrityMark`] and [`Attribute::FingerprintMark`].
+    pub fn new(method: Method, class: Class, trans_id: TransId, attrs: Vec<Attribute<'a>>) -> Self {
+        StunMessage {
+            method,
+            class,
+            trans_id,
+            attrs,
+            integrity: &[],
+            integrity_len: 0,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn binding_request(
         username: &'a str,
         trans_id: TransId,
@@ -158,6 +189,8 @@ impl<'a> StunMessage<'a> {
         control_tie_breaker: u64,
         prio: u32,
         use_candidate: bool,
+        nomination: Option<u32>,
+        network_cost: Option<(u16, u16)>,
     ) -> Self {
         let mut m = StunMessage {
             class: Class::Request,
@@ -180,6 +213,14 @@ impl<'a> StunMessage<'a> {
             m.attrs.push(Attribute::UseCandidate);
         }
 
+        if let Some(v) = nomination {
+            m.attrs.push(Attribute::GoogNomination(v));
+        }
+
+        if let Some((net_id, cost)) = network_cost {
+            m.attrs.push(Attribute::NetworkCost(net_id, cost));
+        }
+
         m.attrs.push(Attribute::MessageIntegrityMark);
         m.attrs.push(Attribute::FingerprintMark);
 
@@ -217,9 +258,57 @@ impl<'a> StunMessage<'a> {
         self.attrs.use_candidate()
     }
 
+    pub fn nomination(&self) -> Option<u32> {
+        self.attrs.nomination()
+    }
+
+    pub fn network_cost(&self) -> Option<(u16, u16)> {
+        self.attrs.network_cost()
+    }
+
+    pub fn error_code(&self) -> Option<(u16, &'a str)> {
+        self.attrs.error_code()
+    }
+
+    pub fn realm(&self) -> Option<&'a str> {
+        self.attrs.realm()
+    }
+
+    pub fn nonce(&self) -> Option<&'a str> {
+        self.attrs.nonce()
+    }
+
+    pub fn lifetime(&self) -> Option<u32> {
+        self.attrs.lifetime()
+    }
+
+    pub fn xor_peer_address(&self) -> Option<SocketAddr> {
+        self.attrs.xor_peer_address()
+    }
+
+    pub fn xor_relayed_address(&self) -> Option<SocketAddr> {
+        self.attrs.xor_relayed_address()
+    }
+
+    pub fn data(&self) -> Option<&'a [u8]> {
+        self.attrs.data()
+    }
+
+    pub fn has_message_integrity(&self) -> bool {
+        self.attrs.message_integrity().is_some()
+    }
+
     pub fn check_integrity(&self, password: &str) -> bool {
+        self.check_integrity_with_key(password.as_bytes())
+    }
+
+    /// Check the message integrity using a raw key.
+    ///
+    /// For short-term credentials (ICE), the key is the password. For long-term
+    /// credentials (TURN), it is `MD5(username ":" realm ":" password)`.
+    pub fn check_integrity_with_key(&self, key: &[u8]) -> bool {
         if let Some(integ) = self.attrs.message_integrity() {
-            let sha1: Sha1 = password.as_bytes().into();
+            let sha1: Sha1 = key.into();
             let comp = sha1.hmac(&[
                 &self.integrity[..2],
                 &[(self.integrity_len >> 8) as u8, self.integrity_len as u8],
@@ -232,36 +321,53 @@ impl<'a> StunMessage<'a> {
     }
 
     pub fn to_bytes(&self, password: &str, buf: &mut [u8]) -> Result<usize, StunError> {
-        self.do_to_bytes(password, buf)
+        self.to_bytes_with_key(password.as_bytes(), buf)
+    }
+
+    /// Serialize the message using a raw key for the message integrity.
+    ///
+    /// See [`StunMessage::check_integrity_with_key`].
+    pub fn to_bytes_with_key(&self, key: &[u8], buf: &mut [u8]) -> Result<usize, StunError> {
+        self.do_to_bytes(key, buf)
             .map_err(|e| StunError::Other(format!("io write: {e:?}")))
     }
 
-    fn do_to_bytes(&self, password: &str, buf: &mut [u8]) -> Result<usize, io::Error> {
+    fn do_to_bytes(&self, key: &[u8], buf: &mut [u8]) -> Result<usize, io::Error> {
         let attr_len = self.attrs.iter().fold(0, |p, a| p + a.padded_len());
         let msg_len = 20 + attr_len;
 
+        let has_fingerprint = self
+            .attrs
+            .iter()
+            .any(|a| matches!(a, Attribute::FingerprintMark));
+
         let mut buf = io::Cursor::new(buf);
 
         let typ = self.class.to_u16() | self.method.to_u16();
         buf.write_all(&typ.to_be_bytes())?;
 
         // -8 for fingerprint
-        buf.write_all(&((attr_len - 8) as u16).to_be_bytes())?;
+        let integrity_len = if has_fingerprint {
+            attr_len - 8
+        } else {
+            attr_len
+        };
+        buf.write_all(&(integrity_len as u16).to_be_bytes())?;
         buf.write_all(MAGIC)?;
         buf.write_all(&self.trans_id.0)?;
 
-        let mut i_off = 0;
-        let mut f_off = 0;
+        let mut i_off = None;
+        let mut f_off = None;
 
         {
             let mut off = 20; // attribute start
             for a in &self.attrs {
                 a.to_bytes(&mut buf, &self.trans_id.0)?;
                 if let Attribute::MessageIntegrityMark = a {
-                    i_off = off;
+                    i_off = Some(off);
                 }
                 if let Attribute::FingerprintMark = a {
-                    f_off = off;
+                    f_off = Some(off);
                 }
                 off += a.padded_len();
             }
@@ -269,15 +375,19 @@ impl<'a> StunMessage<'a> {
 
         let buf = buf.into_inner();
 
-        let sha1: Sha1 = password.as_bytes().into();
-        let hmac = sha1.hmac(&[&buf[0..i_off]]);
-        buf[i_off + 4..(i_off + 4 + 20)].copy_from_slice(&hmac);
+        if let Some(i_off) = i_off {
+            let sha1: Sha1 = key.into();
+            let hmac = sha1.hmac(&[&buf[0..i_off]]);
+            buf[i_off + 4..(i_off + 4 + 20)].copy_from_slice(&hmac);
+        }
 
         // fill in correct length
         buf[2..4].copy_from_slice(&(attr_len as u16).to_be_bytes());
 
-        let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&buf[0..f_off]) ^ 0x5354_554e;
-        buf[f_off + 4..(f_off + 4 + 4)].copy_from_slice(&crc.to_be_bytes());
+        if let Some(f_off) = f_off {
+            let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&buf[0..f_off]) ^ 0x5354_554e;
+            buf[f_off + 4..(f_off + 4 + 4)].copy_from_slice(&crc.to_be_bytes());
+        }
 
         Ok(msg_len)
     }
@@ -286,7 +396,7 @@ impl<'a> StunMessage<'a> {
 const MAGIC: &[u8] = &[0x21, 0x12, 0xA4, 0x42];
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-enum Class {
+pub enum Class {
     Request,
     Indication,
     Success,
@@ -319,8 +429,15 @@ impl Class {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-enum Method {
+pub enum Method {
     Binding,
+    // https://www.rfc-editor.org/rfc/rfc8656#section-17
+    Allocate,
+    Refresh,
+    Send,
+    Data,
+    CreatePermission,
+    ChannelBind,
     Unknown,
 }
 
@@ -329,6 +446,12 @@ impl Method {
         use Method::*;
         match typ & 0b0011_1110_1110_1111 {
             0b0000_0000_0000_0001 => Binding,
+            0b0000_0000_0000_0011 => Allocate,
+            0b0000_0000_0000_0100 => Refresh,
+            0b0000_0000_0000_0110 => Send,
+            0b0000_0000_0000_0111 => Data,
+            0b0000_0000_0000_1000 => CreatePermission,
+            0b0000_0000_0000_1001 => ChannelBind,
             _ => Unknown,
         }
     }
@@ -337,6 +460,12 @@ impl Method {
         use Method::*;
         match self {
             Binding => 0b0000_0000_0000_0001,
+            Allocate => 0b0000_0000_0000_0011,
+            Refresh => 0b0000_0000_0000_0100,
+            Send => 0b0000_0000_0000_0110,
+            Data => 0b0000_0000_0000_0111,
+            CreatePermission => 0b0000_0000_0000_1000,
+            ChannelBind => 0b0000_0000_0000_1001,
             _ => panic!("Unknown method"),
         }
     }
@@ -362,8 +491,17 @@ pub enum Attribute<'a> {
     UseCandidate,        // 0x0025
     IceControlled(u64),  // 0x8029
     IceControlling(u64), // 0x802a
+    // https://www.rfc-editor.org/rfc/rfc8656#section-18
+    ChannelNumber(u16),            // 0x000c
+    Lifetime(u32),                 // 0x000d
+    XorPeerAddress(SocketAddr),    // 0x0012
+    Data(&'a [u8]),                // 0x0013
+    XorRelayedAddress(SocketAddr), // 0x0016
+    RequestedTransport(u8),        // 0x0019
     // https://tools.ietf.org/html/draft-thatcher-ice-network-cost-00
     NetworkCost(u16, u16), // 0xc057
+    // https://datatracker.ietf.org/doc/html/draft-thatcher-ice-renomination-01
+    GoogNomination(u32), // 0xc001
     Unknown(u16),
 }
 
@@ -373,7 +511,16 @@ trait Attributes<'a> {
     fn mapped_address(&self) -> Option<SocketAddr>;
     fn prio(&self) -> Option<u32>;
     fn use_candidate(&self) -> bool;
+    fn nomination(&self) -> Option<u32>;
+    fn network_cost(&self) -> Option<(u16, u16)>;
     fn message_integrity(&self) -> Option<&'a [u8]>;
+    fn error_code(&self) -> Option<(u16, &'a str)>;
+    fn realm(&self) -> Option<&'a str>;
+    fn nonce(&self) -> Option<&'a str>;
+    fn lifetime(&self) -> Option<u32>;
+    fn xor_peer_address(&self) -> Option<SocketAddr>;
+    fn xor_relayed_address(&self) -> Option<SocketAddr>;
+    fn data(&self) -> Option<&'a [u8]>;
 }
 
 impl<'a> Attributes<'a> for Vec<Attribute<'a>> {
@@ -425,6 +572,24 @@ impl<'a> Attributes<'a> for Vec<Attribute<'a>> {
         self.iter().any(|a| matches!(a, Attribute::UseCandidate))
     }
 
+    fn nomination(&self) -> Option<u32> {
+        for a in self {
+            if let Attribute::GoogNomination(v) = a {
+                return Some(*v);
+            }
+        }
+        None
+    }
+
+    fn network_cost(&self) -> Option<(u16, u16)> {
+        for a in self {
+            if let Attribute::NetworkCost(net_id, cost) = a {
+                return Some((*net_id, *cost));
+            }
+        }
+        None
+    }
+
     fn message_integrity(&self) -> Option<&'a [u8]> {
         for a in self {
             if let Attribute::MessageIntegrity(v) = a {
@@ -433,6 +598,69 @@ impl<'a> Attributes<'a> for Vec<Attribute<'a>> {
         }
         None
     }
+
+    fn error_code(&self) -> Option<(u16, &'a str)> {
+        for a in self {
+            if let Attribute::ErrorCode(code, reason) = a {
+                return Some((*code, reason));
+            }
+        }
+        None
+    }
+
+    fn realm(&self) -> Option<&'a str> {
+        for a in self {
+            if let Attribute::Realm(v) = a {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    fn nonce(&self) -> Option<&'a str> {
+        for a in self {
+            if let Attribute::Nonce(v) = a {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    fn lifetime(&self) -> Option<u32> {
+        for a in self {
+            if let Attribute::Lifetime(v) = a {
+                return Some(*v);
+            }
+        }
+        None
+    }
+
+    fn xor_peer_address(&self) -> Option<SocketAddr> {
+        for a in self {
+            if let Attribute::XorPeerAddress(v) = a {
+                return Some(*v);
+            }
+        }
+        None
+    }
+
+    fn xor_relayed_address(&self) -> Option<SocketAddr> {
+        for a in self {
+            if let Attribute::XorRelayedAddress(v) = a {
+                return Some(*v);
+            }
+        }
+        None
+    }
+
+    fn data(&self) -> Option<&'a [u8]> {
+        for a in self {
+            if let Attribute::Data(v) = a {
+                return Some(v);
+            }
+        }
+        None
+    }
 }
 
 use std::{io, str};
@@ -443,16 +671,20 @@ impl<'a> Attribute<'a> {
     fn padded_len(&self) -> usize {
         use Attribute::*;
         4 + match self {
-            Username(v) => {
-                let pad = 4 - (v.as_bytes().len() % 4) % 4;
-                v.len() + pad
-            }
+            Username(v) | Realm(v) | Nonce(v) => padded(v.len()),
+            ErrorCode(_, reason) => padded(4 + reason.len()),
+            Data(v) => padded(v.len()),
+            ChannelNumber(_) => 4,
+            Lifetime(_) => 4,
+            RequestedTransport(_) => 4,
             IceControlled(_) => 8,
             IceControlling(_) => 8,
             MessageIntegrityMark => 20,
             FingerprintMark => 4,
             Priority(_) => 4,
-            XorMappedAddress(v) => {
+            GoogNomination(_) => 4,
+            NetworkCost(_, _) => 4,
+            XorMappedAddress(v) | XorPeerAddress(v) | XorRelayedAddress(v) => {
                 if v.is_ipv4() {
                     8
                 } else {
@@ -468,14 +700,42 @@ impl<'a> Attribute<'a> {
         use Attribute::*;
         match self {
             Username(v) => {
-                vec.write_all(&0x0006_u16.to_be_bytes())?;
-                vec.write_all(&(v.as_bytes().len() as u16).to_be_bytes())?;
-                vec.write_all(v.as_bytes())?;
-                let pad = 4 - (v.as_bytes().len() % 4) % 4;
-                for _ in 0..pad {
+                write_padded(vec, 0x0006, v.as_bytes())?;
+            }
+            Realm(v) => {
+                write_padded(vec, 0x0014, v.as_bytes())?;
+            }
+            Nonce(v) => {
+                write_padded(vec, 0x0015, v.as_bytes())?;
+            }
+            Data(v) => {
+                write_padded(vec, 0x0013, v)?;
+            }
+            ErrorCode(code, reason) => {
+                vec.write_all(&0x0009_u16.to_be_bytes())?;
+                vec.write_all(&((4 + reason.len()) as u16).to_be_bytes())?;
+                vec.write_all(&[0, 0, (code / 100) as u8, (code % 100) as u8])?;
+                vec.write_all(reason.as_bytes())?;
+                for _ in (4 + reason.len())..padded(4 + reason.len()) {
                     vec.write_all(&[0])?;
                 }
             }
+            ChannelNumber(v) => {
+                vec.write_all(&0x000c_u16.to_be_bytes())?;
+                vec.write_all(&4_u16.to_be_bytes())?;
+                vec.write_all(&v.to_be_bytes())?;
+                vec.write_all(&[0, 0])?; // RFFU
+            }
+            Lifetime(v) => {
+                vec.write_all(&0x000d_u16.to_be_bytes())?;
+                vec.write_all(&4_u16.to_be_bytes())?;
+                vec.write_all(&v.to_be_bytes())?;
+            }
+            RequestedTransport(v) => {
+                vec.write_all(&0x0019_u16.to_be_bytes())?;
+                vec.write_all(&4_u16.to_be_bytes())?;
+                vec.write_all(&[*v, 0, 0, 0])?; // protocol + RFFU
+            }
             IceControlled(v) => {
                 vec.write_all(&0x8029_u16.to_be_bytes())?;
                 vec.write_all(&8_u16.to_be_bytes())?;
@@ -502,16 +762,29 @@ impl<'a> Attribute<'a> {
                 vec.write_all(&v.to_be_bytes())?;
             }
             XorMappedAddress(v) => {
-                let mut buf = [0_u8; 20];
-                let len = encode_xor(*v, &mut buf, trans_id);
-                vec.write_all(&0x0020_u16.to_be_bytes())?;
-                vec.write_all(&((len as u16).to_be_bytes()))?;
-                vec.write_all(&buf[0..len])?;
+                write_xor(vec, 0x0020, *v, trans_id)?;
+            }
+            XorPeerAddress(v) => {
+                write_xor(vec, 0x0012, *v, trans_id)?;
+            }
+            XorRelayedAddress(v) => {
+                write_xor(vec, 0x0016, *v, trans_id)?;
             }
             UseCandidate => {
                 vec.write_all(&0x0025_u16.to_be_bytes())?;
                 vec.write_all(&0_u16.to_be_bytes())?;
             }
+            GoogNomination(v) => {
+                vec.write_all(&0xc001_u16.to_be_bytes())?;
+                vec.write_all(&4_u16.to_be_bytes())?;
+                vec.write_all(&v.to_be_bytes())?;
+            }
+            NetworkCost(net_id, cost) => {
+                vec.write_all(&0xc057_u16.to_be_bytes())?;
+                vec.write_all(&4_u16.to_be_bytes())?;
+                vec.write_all(&net_id.to_be_bytes())?;
+                vec.write_all(&cost.to_be_bytes())?;
+            }
             _ => panic!("Can't write bytes for: {self:?}"),
         }
 
@@ -521,7 +794,7 @@ impl<'a> Attribute<'a> {
     fn parse(
         mut buf: &'a [u8],
         trans_id: TransId,
-        msg_integrity_off: &mut usize,
+        msg_integrity_off: &mut Option<usize>,
     ) -> Result<Vec<Attribute<'a>>, StunError> {
         let mut ret = vec![];
         let mut off = 0;
@@ -565,7 +838,7 @@ impl<'a> Attribute<'a> {
                         }
                         // message integrity is up until, but not including the message
                         // integrity attribute.
-                        *msg_integrity_off = off;
+                        *msg_integrity_off = Some(off);
                         ignore_rest = true;
                         ret.push(Attribute::MessageIntegrity(&buf[4..24]));
                     }
@@ -589,12 +862,51 @@ impl<'a> Attribute<'a> {
                         warn!("STUN got UnknownAttributes");
                         ret.push(Attribute::UnknownAttributes);
                     }
+                    0x000c => {
+                        if len != 4 {
+                            return Err(StunError::Parse(
+                                "ChannelNumber that isnt 4 in length".into(),
+                            ));
+                        }
+                        let number = (buf[4] as u16) << 8 | buf[5] as u16;
+                        ret.push(Attribute::ChannelNumber(number));
+                    }
+                    0x000d => {
+                        if len != 4 {
+                            return Err(StunError::Parse("Lifetime that isnt 4 in length".into()));
+                        }
+                        let bytes = [buf[4], buf[5], buf[6], buf[7]];
+                        ret.push(Attribute::Lifetime(u32::from_be_bytes(bytes)));
+                    }
+                    0x0012 => {
+                        ret.push(Attribute::XorPeerAddress(decode_xor(
+                            &buf[4..(4 + len)],
+                            trans_id,
+                        )?));
+                    }
+                    0x0013 => {
+                        ret.push(Attribute::Data(&buf[4..(4 + len)]));
+                    }
                     0x0014 => {
                         ret.push(Attribute::Realm(decode_str(typ, &buf[4..], len)?));
                     }
                     0x0015 => {
                         ret.push(Attribute::Nonce(decode_str(typ, &buf[4..], len)?));
                     }
+                    0x0016 => {
+                        ret.push(Attribute::XorRelayedAddress(decode_xor(
+                            &buf[4..(4 + len)],
+                            trans_id,
+                        )?));
+                    }
+                    0x0019 => {
+                        if len != 4 {
+                            return Err(StunError::Parse(
+                                "RequestedTransport that isnt 4 in length".into(),
+                            ));
+                        }
+                        ret.push(Attribute::RequestedTransport(buf[4]));
+                    }
                     0x0020 => {
                         ret.push(Attribute::XorMappedAddress(decode_xor(
                             &buf[4..],
@@ -647,6 +959,15 @@ impl<'a> Attribute<'a> {
                         bytes.copy_from_slice(&buf[4..(4 + 8)]);
                         ret.push(Attribute::IceControlling(u64::from_be_bytes(bytes)));
                     }
+                    0xc001 => {
+                        if len != 4 {
+                            return Err(StunError::Parse(
+                                "GoogNomination that isnt 4 in length".into(),
+                            ));
+                        }
+                        let bytes = [buf[4], buf[5], buf[6], buf[7]];
+                        ret.push(Attribute::GoogNomination(u32::from_be_bytes(bytes)));
+                    }
                     0xc057 => {
                         if len != 4 {
                             warn!("NetworkCost that isnt 4 in length");
@@ -683,6 +1004,30 @@ fn decode_str(typ: u16, buf: &[u8], len: usize) -> Result<&str, StunError> {
     }
 }
 
+/// Attribute length padded to the next 32 bit boundary.
+fn padded(len: usize) -> usize {
+    len + (4 - (len % 4)) % 4
+}
+
+fn write_padded(vec: &mut dyn Write, typ: u16, v: &[u8]) -> io::Result<()> {
+    vec.write_all(&typ.to_be_bytes())?;
+    vec.write_all(&(v.len() as u16).to_be_bytes())?;
+    vec.write_all(v)?;
+    for _ in v.len()..padded(v.len()) {
+        vec.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+fn write_xor(vec: &mut dyn Write, typ: u16, addr: SocketAddr, trans_id: &[u8]) -> io::Result<()> {
+    let mut buf = [0_u8; 20];
+    let len = encode_xor(addr, &mut buf, trans_id);
+    vec.write_all(&typ.to_be_bytes())?;
+    vec.write_all(&((len as u16).to_be_bytes()))?;
+    vec.write_all(&buf[0..len])?;
+    Ok(())
+}
+
 fn encode_xor(addr: SocketAddr, buf: &mut [u8; 20], trans_id: &[u8]) -> usize {
     let port = addr.port() ^ 0x2112;
     buf[2..4].copy_from_slice(&port.to_be_bytes());
@@ -710,6 +1055,9 @@ fn encode_xor(addr: SocketAddr, buf: &mut [u8; 20], trans_id: &[u8]) -> usize {
 }
 
 fn decode_xor(buf: &[u8], trans_id: TransId) -> Result<SocketAddr, StunError> {
+    if buf.len() < 8 {
+        return Err(StunError::Parse("Too short xor address".into()));
+    }
     let port = (((buf[2] as u16) << 8) | (buf[3] as u16)) ^ 0x2112;
     let ip_buf = &buf[4..];
     let ip = match buf[1] {
@@ -721,6 +1069,9 @@ fn decode_xor(buf: &[u8], trans_id: TransId) -> Result<SocketAddr, StunError> {
             IpAddr::V4(bytes.into())
         }
         2 => {
+            if ip_buf.len() < 16 {
+                return Err(StunError::Parse("Too short xor ipv6 address".into()));
+            }
             let mut bytes = [0_u8; 16];
             for i in 0..4 {
                 bytes[i] = ip_buf[i] ^ MAGIC[i];
@@ -771,6 +1122,17 @@ impl<'a> fmt::Debug for Attribute<'a> {
             Self::UseCandidate => write!(f, "UseCandidate"),
             Self::IceControlled(arg0) => f.debug_tuple("IceControlled").field(arg0).finish(),
             Self::IceControlling(arg0) => f.debug_tuple("IceControlling").field(arg0).finish(),
+            Self::ChannelNumber(arg0) => f.debug_tuple("ChannelNumber").field(arg0).finish(),
+            Self::Lifetime(arg0) => f.debug_tuple("Lifetime").field(arg0).finish(),
+            Self::XorPeerAddress(arg0) => f.debug_tuple("XorPeerAddress").field(arg0).finish(),
+            Self::Data(arg0) => write!(f, "Data(len: {})", arg0.len()),
+            Self::XorRelayedAddress(arg0) => {
+                f.debug_tuple("XorRelayedAddress").field(arg0).finish()
+            }
+            Self::RequestedTransport(arg0) => {
+                f.debug_tuple("RequestedTransport").field(arg0).finish()
+            }
+            Self::GoogNomination(arg0) => f.debug_tuple("GoogNomination").field(arg0).finish(),
             Self::NetworkCost(arg0, arg1) => f
                 .debug_tuple("NetworkCost")
                 .field(arg0)
@@ -802,4 +1164,64 @@ mod test {
         let message = StunMessage::parse(&packet).unwrap();
         assert!(message.check_integrity("xJcE9AQAR7kczUDVOXRUCl"));
     }
+
+    #[test]
+    fn turn_attributes_roundtrip() {
+        let peer: SocketAddr = "[2001:db8::1]:5000".parse().unwrap();
+        let relayed: SocketAddr = "5.5.5.5:50000".parse().unwrap();
+        let key = [42_u8; 16];
+
+        let message = StunMessage::new(
+            Method::Allocate,
+            Class::Success,
+            TransId::new(),
+            vec![
+                Attribute::Realm("example.org"),
+                Attribute::Nonce("abc"),
+                Attribute::Lifetime(600),
+                Attribute::XorPeerAddress(peer),
+                Attribute::XorRelayedAddress(relayed),
+                Attribute::Data(&[1, 2, 3, 4, 5]),
+                Attribute::MessageIntegrityMark,
+                Attribute::FingerprintMark,
+            ],
+        );
+
+        let mut buf = vec![0; 1500];
+        let n = message.to_bytes_with_key(&key, &mut buf).unwrap();
+        let parsed = StunMessage::parse(&buf[..n]).unwrap();
+
+        assert_eq!(parsed.method(), Method::Allocate);
+        assert!(parsed.is_success());
+        assert_eq!(parsed.realm(), Some("example.org"));
+        assert_eq!(parsed.nonce(), Some("abc"));
+        assert_eq!(parsed.lifetime(), Some(600));
+        assert_eq!(parsed.xor_peer_address(), Some(peer));
+        assert_eq!(parsed.xor_relayed_address(), Some(relayed));
+        assert_eq!(parsed.data(), Some(&[1, 2, 3, 4, 5][..]));
+        assert!(parsed.check_integrity_with_key(&key));
+    }
+
+    #[test]
+    fn goog_attributes_roundtrip() {
+        let message = StunMessage::binding_request(
+            "abc:def",
+            TransId::new(),
+            true,
+            1,
+            2,
+            true,
+            Some(7),
+            Some((3, 10)),
+        );
+
+        let mut buf = vec![0; 1500];
+        let n = message.to_bytes("pass", &mut buf).unwrap();
+        let parsed = StunMessage::parse(&buf[..n]).unwrap();
+
+        assert!(parsed.use_candidate());
+        assert_eq!(parsed.nomination(), Some(7));
+        assert_eq!(parsed.network_cost(), Some((3, 10)));
+        assert!(parsed.check_integrity("pass"));
+    }
 }
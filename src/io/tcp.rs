@@ -0,0 +1,111 @@
+use std::io;
+
+use super::NetError;
+
+/// Sans I/O framing of datagrams over a TCP stream.
+///
+/// Each datagram is prefixed with its length as a 16 bit big endian integer,
+/// as described in [RFC 4571][1]. This is the framing used for ICE-TCP.
+///
+/// ```
+/// # use str0m::net::TcpFramer;
+/// let mut stream = vec![];
+/// TcpFramer::frame(&[1, 2, 3], &mut stream).unwrap();
+/// assert_eq!(stream, &[0, 3, 1, 2, 3]);
+///
+/// let mut framer = TcpFramer::new();
+///
+/// // Bytes read from the stream can arrive in any chunk size.
+/// framer.push(&stream[..2]);
+/// assert_eq!(framer.pop(), None);
+///
+/// framer.push(&stream[2..]);
+/// assert_eq!(framer.pop(), Some(vec![1, 2, 3]));
+/// ```
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc4571
+#[derive(Debug, Default)]
+pub struct TcpFramer {
+    buf: Vec<u8>,
+}
+
+impl TcpFramer {
+    /// Creates a new framer for incoming data.
+    pub fn new() -> Self {
+        TcpFramer::default()
+    }
+
+    /// Frame an outgoing datagram by appending it, length prefixed, to `out`.
+    ///
+    /// Fails if the datagram is larger than 65535 bytes.
+    pub fn frame(datagram: &[u8], out: &mut Vec<u8>) -> Result<(), NetError> {
+        let len: u16 = datagram.len().try_into().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Datagram too large for TCP framing",
+            )
+        })?;
+
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(datagram);
+
+        Ok(())
+    }
+
+    /// Push bytes read from the TCP stream.
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Pop the next complete datagram, if any.
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        if self.buf.len() < 2 {
+            return None;
+        }
+
+        let len = u16::from_be_bytes([self.buf[0], self.buf[1]]) as usize;
+
+        if self.buf.len() < 2 + len {
+            return None;
+        }
+
+        let datagram = self.buf[2..(2 + len)].to_vec();
+        self.buf.drain(..(2 + len));
+
+        Some(datagram)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frame_and_pop_several() {
+        let mut stream = vec![];
+        TcpFramer::frame(&[1, 2, 3], &mut stream).unwrap();
+        TcpFramer::frame(&[], &mut stream).unwrap();
+        TcpFramer::frame(&[4; 300], &mut stream).unwrap();
+
+        let mut framer = TcpFramer::new();
+
+        // Byte by byte.
+        let mut popped = vec![];
+        for b in &stream {
+            framer.push(&[*b]);
+            while let Some(d) = framer.pop() {
+                popped.push(d);
+            }
+        }
+
+        assert_eq!(popped, vec![vec![1, 2, 3], vec![], vec![4; 300]]);
+        assert_eq!(framer.pop(), None);
+    }
+
+    #[test]
+    fn frame_too_large() {
+        let mut stream = vec![];
+        assert!(TcpFramer::frame(&[0; 70_000], &mut stream).is_err());
+        assert!(stream.is_empty());
+    }
+}
@@ -114,7 +114,7 @@
 //!
 //! ```no_run
 //! # use str0m::{Rtc, Output, IceConnectionState, Event, Input};
-//! # use str0m::net::Receive;
+//! # use str0m::net::{Protocol, Receive};
 //! # use std::io::ErrorKind;
 //! # use std::net::UdpSocket;
 //! # use std::time::Instant;
@@ -183,6 +183,7 @@
 //!             Input::Receive(
 //!                 Instant::now(),
 //!                 Receive {
+//!                     proto: Protocol::Udp,
 //!                     source,
 //!                     destination: socket.local_addr().unwrap(),
 //!                     contents: buf.as_slice().try_into().unwrap(),
@@ -394,9 +395,13 @@
 //! something str0m cares about.
 //!
 //! TURN is a way of obtaining IP addresses that can be used as fallback
-//! in case direct connections fail. We consider TURN similar to
-//! enumerating local network interfaces – it's a way of obtaining
-//! sockets.
+//! in case direct connections fail. str0m has a Sans I/O TURN client. TURN
+//! servers configured with [`RtcConfig::add_turn_server()`] are used to
+//! allocate relayed candidates from each local host candidate. The TURN
+//! traffic goes over the same sockets as the host candidates, and new
+//! relayed candidates are reported as [`Event::LocalCandidate`]. Likewise,
+//! server reflexive candidates are discovered from the STUN servers
+//! configured with [`RtcConfig::add_stun_server()`].
 //!
 //! All discovered candidates, be they local (NIC) or remote sockets
 //! (TURN), are added to str0m and str0m will perform the task of ICE
@@ -505,7 +510,8 @@ use bwe::Bwe;
 use change::{DirectApi, SdpApi};
 use rtp::RawPacket;
 use std::fmt;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use streams::RtpPacket;
 use streams::StreamPaused;
@@ -513,18 +519,20 @@ use thiserror::Error;
 use util::InstantExt;
 
 mod dtls;
+#[cfg(feature = "openssl")]
 use dtls::DtlsCert;
 use dtls::Fingerprint;
-use dtls::{Dtls, DtlsEvent};
+use dtls::{Dtls, DtlsError, DtlsEvent, DtlsInfo, DtlsProvider, SrtpProfile};
 
 mod ice;
-pub use ice::Candidate;
 use ice::IceAgent;
 use ice::IceAgentEvent;
 use ice::IceCreds;
+pub use ice::{Candidate, NetworkType, TcpType, TurnServer};
 
 mod io;
 use io::DatagramRecv;
+use io::MultiplexKind;
 
 mod packet;
 
@@ -544,8 +552,10 @@ pub mod rtp {
     }
     use self::rtcp::Rtcp;
 
+    pub use crate::rtp_::{DecodeTargetIndication, DependencyDescriptor};
     pub use crate::rtp_::{Extension, ExtensionMap, ExtensionSerializer};
     pub use crate::rtp_::{ExtensionValues, UserExtensionValues};
+    pub use crate::rtp_::{FrameDependencyStructure, FrameDependencyTemplate};
 
     pub use crate::rtp_::{RtpHeader, SeqNo, Ssrc, VideoOrientation};
     pub use crate::streams::{RtpPacket, StreamPaused, StreamRx, StreamTx};
@@ -575,10 +585,13 @@ use sctp::{RtcSctp, SctpEvent};
 
 mod sdp;
 
+mod sdes;
+use sdes::Sdes;
+
 pub mod format;
 use format::CodecConfig;
 
-pub use ice::IceConnectionState;
+pub use ice::{IceConfig, IceConnectionState, NominationPolicy};
 
 pub mod channel;
 use channel::{Channel, ChannelData, ChannelHandler, ChannelId};
@@ -597,13 +610,20 @@ mod session;
 use session::Session;
 
 pub mod stats;
-use stats::{MediaEgressStats, MediaIngressStats, PeerStats, Stats, StatsEvent, StatsSnapshot};
+use stats::{IceStats, MediaEgressStats, MediaIngressStats, PeerStats};
+use stats::{Stats, StatsEvent, StatsSnapshot};
 
 mod streams;
 
 /// Network related types to get socket data in/out of [`Rtc`].
 pub mod net {
-    pub use crate::io::{DatagramRecv, DatagramSend, Receive, Transmit};
+    pub use crate::io::{DatagramRecv, DatagramSend, Protocol, Receive, TcpFramer, Transmit};
+}
+
+/// Pluggable crypto, such as the DTLS implementation.
+pub mod crypto {
+    pub use crate::dtls::{DtlsInfo, DtlsProvider, DtlsSession, KeyingMaterial, SrtpProfile};
+    pub use crate::sdes::SrtpMasterKey;
 }
 
 /// Various error types.
@@ -643,6 +663,12 @@ pub enum RtcError {
     #[error("{0}")]
     Dtls(#[from] error::DtlsError),
 
+    /// No DTLS implementation to build the [`Rtc`] with.
+    ///
+    /// Without the `openssl` feature, [`RtcConfig::set_dtls_provider()`] is required.
+    #[error("No DTLS provider")]
+    NoDtlsProvider,
+
     /// RTP packetization error
     #[error("{0} {1} {2}")]
     Packet(Mid, Pt, error::PacketError),
@@ -708,6 +734,11 @@ pub enum RtcError {
     /// is an incorrect usage pattern of the str0m API.
     #[error("Consecutive calls to write() without poll_output() in between")]
     WriteWithoutPoll,
+
+    /// The SRTP master keys given to [`DirectApi::set_srtp_master_keys()`][change::DirectApi::set_srtp_master_keys]
+    /// can't be used.
+    #[error("SRTP master keys: {0}")]
+    SrtpMasterKeys(String),
 }
 
 /// Instance that does WebRTC. Main struct of the entire library.
@@ -747,9 +778,19 @@ pub struct Rtc {
     chan: ChannelHandler,
     stats: Option<Stats>,
     session: Session,
-    remote_fingerprint: Option<Fingerprint>,
+    media_protection: MediaProtection,
+    sdes: Option<Sdes>,
+    /// With [`MediaProtection::Unencrypted`], whether offer/answer has been done.
+    unencrypted_inited: bool,
+    /// Whether [`Event::Connected`] has been emitted, when not using DTLS.
+    connected_without_dtls: bool,
+    /// Whether to emit [`Event::DtlsConnected`].
+    need_dtls_connected_event: bool,
+    remote_fingerprints: Vec<Fingerprint>,
     remote_addrs: Vec<SocketAddr>,
     send_addr: Option<SendAddr>,
+    rtcp_send_addr: Option<SendAddr>,
+    rtcp_mux: Option<bool>,
     last_now: Instant,
     peer_bytes_rx: u64,
     peer_bytes_tx: u64,
@@ -757,6 +798,7 @@ pub struct Rtc {
 }
 
 struct SendAddr {
+    proto: net::Protocol,
     source: SocketAddr,
     destination: SocketAddr,
 }
@@ -769,12 +811,52 @@ pub enum Event {
     // =================== ICE related events ===================
 
     /// Emitted when we got ICE connection and established DTLS.
+    ///
+    /// With [`MediaProtection::Sdes`], this is when we got ICE connection and
+    /// the SRTP keys are set. With [`MediaProtection::Unencrypted`], it's as soon
+    /// as we got ICE connection.
     Connected,
 
+    /// What was negotiated in the DTLS handshake. Emitted right after [`Event::Connected`].
+    ///
+    /// The same information is available with [`Rtc::dtls_info()`].
+    DtlsConnected(Box<DtlsInfo>),
+
     /// ICE connection state changes tells us whether the [`Rtc`] instance is
     /// connected to the peer or not.
     IceConnectionStateChange(IceConnectionState),
 
+    /// The remote peer stopped answering consent freshness checks on the nominated
+    /// ICE pair (see [RFC 7675][1]).
+    ///
+    /// The [`Rtc`] instance stops sending data until ICE finds another working pair.
+    /// This typically happens when the remote peer goes away without saying goodbye,
+    /// like a closed browser tab. The consent timings are set with
+    /// [`IceConfig::set_consent_interval()`] and [`IceConfig::set_consent_timeout()`].
+    ///
+    /// The value is the ICE component. This is 1 for RTP, and 2 for RTCP when it
+    /// isn't multiplexed with RTP, see [`RtcpMuxPolicy`].
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7675
+    IceConsentExpired(u16),
+
+    /// A new local ICE candidate was gathered. This is either a server reflexive
+    /// candidate from a STUN server configured with [`RtcConfig::add_stun_server()`],
+    /// or a relayed candidate from a TURN server configured with
+    /// [`RtcConfig::add_turn_server()`].
+    ///
+    /// The candidate is already added to this [`Rtc`] instance. It should be
+    /// communicated ("trickled") to the remote peer.
+    LocalCandidate(Candidate),
+
+    /// A remote ICE candidate uses an mDNS hostname (like `<uuid>.local`) instead of an
+    /// IP address, which browsers do to hide the local IP.
+    ///
+    /// The candidate is not used until the hostname is resolved with
+    /// [`Rtc::resolve_candidate()`]. str0m does no name resolution itself; it is up to the
+    /// application to do an mDNS query for [`Candidate::hostname()`], or drop the candidate.
+    UnresolvedCandidate(Candidate),
+
     // =================== Media related events ==================
 
     /// Upon adding new media to the session. The lines are emitted.
@@ -825,6 +907,9 @@ pub enum Event {
     /// Aggregated statistics for each media (mid, rid) in the egress direction
     MediaEgressStats(MediaEgressStats),
 
+    /// Statistics for the ICE candidate pairs.
+    IceStats(IceStats),
+
     /// A new estimate from the bandwidth estimation subsystem.
     EgressBitrateEstimate(Bitrate),
 
@@ -899,9 +984,11 @@ impl Rtc {
     ///
     /// let rtc = Rtc::new();
     /// ```
+    ///
+    /// Requires the `openssl` feature, see [`RtcConfig::try_build()`].
+    #[cfg(feature = "openssl")]
     pub fn new() -> Self {
-        let config = RtcConfig::default();
-        Self::new_from_config(config)
+        RtcConfig::default().build()
     }
 
     /// Creates a config builder that configures an [`Rtc`] instance.
@@ -916,34 +1003,60 @@ impl Rtc {
         RtcConfig::new()
     }
 
-    pub(crate) fn new_from_config(config: RtcConfig) -> Self {
+    pub(crate) fn new_from_config(config: RtcConfig) -> Result<Self, RtcError> {
         let session = Session::new(&config);
 
+        let dtls_provider = config.dtls_provider_or_default()?;
         let mut ice = IceAgent::with_local_credentials(config.local_ice_credentials);
         if config.ice_lite {
             ice.set_ice_lite(config.ice_lite);
         }
-
-        Rtc {
+        for server in &config.stun_servers {
+            ice.add_stun_server(*server);
+        }
+        for server in &config.turn_servers {
+            ice.add_turn_server(server.clone());
+        }
+        ice.set_config(config.ice_config);
+        ice.set_mdns_host_candidates(config.mdns_host_candidates);
+        ice.set_rtcp_mux(config.rtcp_mux_policy == RtcpMuxPolicy::Require);
+
+        let dtls = Dtls::new(
+            &*dtls_provider,
+            &config.srtp_profiles,
+            config.fingerprint_verification,
+            config.dtls_handshake_timeout,
+        )?;
+
+        Ok(Rtc {
             alive: true,
             ice,
-            dtls: Dtls::new(
-                config.dtls_cert.unwrap_or_else(DtlsCert::new),
-                config.fingerprint_verification,
-            )
-            .expect("DTLS to init without problem"),
+            dtls,
             session,
+            media_protection: config.media_protection,
+            sdes: match config.media_protection {
+                MediaProtection::Sdes => Some(Sdes::new(&config.srtp_profiles)),
+                MediaProtection::DtlsSrtp | MediaProtection::Unencrypted => None,
+            },
+            unencrypted_inited: false,
+            connected_without_dtls: false,
+            need_dtls_connected_event: false,
             sctp: RtcSctp::new(),
             chan: ChannelHandler::default(),
             stats: config.stats_interval.map(Stats::new),
-            remote_fingerprint: None,
+            remote_fingerprints: vec![],
             remote_addrs: vec![],
             send_addr: None,
+            rtcp_send_addr: None,
+            rtcp_mux: match config.rtcp_mux_policy {
+                RtcpMuxPolicy::Require => Some(true),
+                RtcpMuxPolicy::Negotiate => None,
+            },
             last_now: already_happened(),
             peer_bytes_rx: 0,
             peer_bytes_tx: 0,
             change_counter: 0,
-        }
+        })
     }
 
     /// Tests if this instance is still working.
@@ -989,9 +1102,10 @@ impl Rtc {
     /// Add a local ICE candidate. Local candidates are socket addresses the `Rtc` instance
     /// use for communicating with the peer.
     ///
-    /// This library has no built-in discovery of local network addresses on the host
-    /// or NATed addresses via a STUN server or TURN server. The user of the library
-    /// is expected to add new local candidates as they are discovered.
+    /// This library has no built-in discovery of local network addresses on the host.
+    /// The user of the library is expected to add new local candidates as they are
+    /// discovered. Server reflexive and relayed candidates are gathered automatically,
+    /// see [`RtcConfig::add_stun_server()`] and [`RtcConfig::add_turn_server()`].
     ///
     /// In WebRTC lingo, the `Rtc` instance is permanently in a mode of [Trickle Ice][1]. It's
     /// however advisable to add at least one local candidate before starting the instance.
@@ -1034,12 +1148,93 @@ impl Rtc {
         self.ice.add_remote_candidate(c);
     }
 
+    /// Resolve the hostname of remote candidates reported as [`Event::UnresolvedCandidate`].
+    ///
+    /// All unresolved candidates with the hostname get the IP address, and the port that was
+    /// signaled in the candidate. Returns `false` if there was no candidate with the hostname.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let ip = "1.2.3.4".parse().unwrap();
+    /// assert!(!rtc.resolve_candidate("2c3f05f6-8a7f-4d8e-9f2c-0c7e5a5b7e21.local", ip));
+    /// ```
+    pub fn resolve_candidate(&mut self, hostname: &str, ip: IpAddr) -> bool {
+        self.ice.resolve_candidate(hostname, ip)
+    }
+
+    /// The mDNS hostnames used by the local host candidates and the IP addresses they
+    /// stand for.
+    ///
+    /// Only used when [`RtcConfig::set_mdns_host_candidates()`] is enabled. The application
+    /// must answer mDNS queries for these names for the remote peer to connect.
+    pub fn local_mdns_names(&self) -> impl Iterator<Item = (&str, IpAddr)> + '_ {
+        self.ice.local_mdns_names()
+    }
+
+    /// What was negotiated in the DTLS handshake.
+    ///
+    /// This is `None` until the handshake is complete, or if the DTLS implementation
+    /// can't tell.
+    ///
+    /// ```no_run
+    /// # use str0m::Rtc;
+    /// let rtc = Rtc::new();
+    ///
+    /// if let Some(info) = rtc.dtls_info() {
+    ///     println!("{} {} {}", info.version, info.cipher, info.srtp_profile);
+    /// }
+    /// ```
+    pub fn dtls_info(&self) -> Option<&DtlsInfo> {
+        self.dtls.info()
+    }
+
     /// Checks if we are connected.
     ///
     /// This tests both if we have ICE connection and DTLS is ready.
     ///
     pub fn is_connected(&self) -> bool {
-        self.ice.state().is_connected() && self.dtls.is_connected()
+        self.ice.state().is_connected() && self.is_srtp_ready()
+    }
+
+    /// Whether DTLS has handshaken, or the SRTP keys are set with SDES.
+    fn is_srtp_ready(&self) -> bool {
+        match self.media_protection {
+            MediaProtection::DtlsSrtp => self.dtls.is_connected(),
+            MediaProtection::Sdes | MediaProtection::Unencrypted => self.session.ready_for_srtp(),
+        }
+    }
+
+    /// Whether the offer/answer has set up DTLS, SDES or unencrypted media.
+    pub(crate) fn is_secure_inited(&self) -> bool {
+        match self.media_protection {
+            MediaProtection::DtlsSrtp => self.dtls.is_inited(),
+            MediaProtection::Sdes => self.sdes.as_ref().map(|s| s.is_negotiated()) == Some(true),
+            MediaProtection::Unencrypted => self.unencrypted_inited,
+        }
+    }
+
+    /// Statistics for the ICE candidate pairs, including which pair is selected for
+    /// sending data.
+    ///
+    /// The same statistics are emitted periodically as [`Event::IceStats`] when
+    /// [`RtcConfig::set_stats_interval()`] is set.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let rtc = Rtc::new();
+    ///
+    /// // No candidates, no pairs.
+    /// let stats = rtc.ice_stats();
+    /// assert!(stats.pairs.is_empty());
+    /// assert!(stats.selected().is_none());
+    /// ```
+    pub fn ice_stats(&self) -> IceStats {
+        IceStats {
+            pairs: self.ice.pair_stats(),
+            timestamp: self.last_now,
+        }
     }
 
     /// Make changes to the Rtc session via SDP.
@@ -1206,17 +1401,49 @@ impl Rtc {
                     }
                 }
                 IceAgentEvent::NominatedSend {
+                    component_id,
+                    proto,
                     source,
                     destination,
                 } => {
                     info!(
-                        "ICE nominated send from: {:?} to: {:?}",
-                        source, destination
+                        "ICE nominated send (component {}) from: {:?} to: {:?} over {}",
+                        component_id, source, destination, proto
                     );
-                    self.send_addr = Some(SendAddr {
+                    let send_addr = Some(SendAddr {
+                        proto,
                         source,
                         destination,
                     });
+                    if component_id == 1 {
+                        self.send_addr = send_addr;
+                    } else {
+                        self.rtcp_send_addr = send_addr;
+                    }
+                }
+                IceAgentEvent::ConsentExpired {
+                    component_id,
+                    proto,
+                    source,
+                    destination,
+                } => {
+                    info!(
+                        "ICE consent expired (component {}) from: {:?} to: {:?} over {}",
+                        component_id, source, destination, proto
+                    );
+                    // Stop sending until the ICE agent nominates a new pair.
+                    if component_id == 1 {
+                        self.send_addr = None;
+                    } else {
+                        self.rtcp_send_addr = None;
+                    }
+                    return Ok(Output::Event(Event::IceConsentExpired(component_id)));
+                }
+                IceAgentEvent::LocalCandidate(c) => {
+                    return Ok(Output::Event(Event::LocalCandidate(c)));
+                }
+                IceAgentEvent::UnresolvedCandidate(c) => {
+                    return Ok(Output::Event(Event::UnresolvedCandidate(c)));
                 }
             }
         }
@@ -1237,17 +1464,19 @@ impl Rtc {
                     let active = self.dtls.is_active().expect("DTLS must be inited by now");
                     self.session.set_keying_material(mat, srtp_profile, active);
                 }
-                DtlsEvent::RemoteFingerprint(v1) => {
+                DtlsEvent::RemoteFingerprint(_) => {
                     debug!("DTLS verify remote fingerprint");
-                    if let Some(v2) = &self.remote_fingerprint {
-                        if v1 != *v2 {
-                            self.disconnect();
-                            return Err(RtcError::RemoteSdp("remote fingerprint no match".into()));
-                        }
-                    } else {
+                    if self.remote_fingerprints.is_empty() {
                         self.disconnect();
                         return Err(RtcError::RemoteSdp("no a=fingerprint before dtls".into()));
                     }
+                    if !self
+                        .dtls
+                        .verify_remote_fingerprint(&self.remote_fingerprints)
+                    {
+                        self.disconnect();
+                        return Err(RtcError::RemoteSdp("remote fingerprint no match".into()));
+                    }
                 }
                 DtlsEvent::Data(v) => {
                     self.sctp.handle_input(self.last_now, &v);
@@ -1256,6 +1485,23 @@ impl Rtc {
         }
 
         if dtls_connected {
+            self.need_dtls_connected_event = true;
+            return Ok(Output::Event(Event::Connected));
+        }
+
+        if self.need_dtls_connected_event {
+            self.need_dtls_connected_event = false;
+            if let Some(info) = self.dtls.info() {
+                return Ok(Output::Event(Event::DtlsConnected(Box::new(info.clone()))));
+            }
+        }
+
+        if self.media_protection != MediaProtection::DtlsSrtp
+            && !self.connected_without_dtls
+            && self.is_connected()
+        {
+            debug!("Connected without DTLS: {:?}", self.media_protection);
+            self.connected_without_dtls = true;
             return Ok(Output::Event(Event::Connected));
         }
 
@@ -1317,6 +1563,7 @@ impl Rtc {
                 StatsEvent::Peer(s) => Output::Event(Event::PeerStats(s)),
                 StatsEvent::MediaIngress(s) => Output::Event(Event::MediaIngressStats(s)),
                 StatsEvent::MediaEgress(s) => Output::Event(Event::MediaEgressStats(s)),
+                StatsEvent::Ice(s) => Output::Event(Event::IceStats(s)),
             });
         }
 
@@ -1326,22 +1573,43 @@ impl Rtc {
 
         if let Some(send) = &self.send_addr {
             // These can only be sent after we got an ICE connection.
-            let datagram = None
+            while let Some(contents) = None
                 .or_else(|| self.dtls.poll_datagram())
-                .or_else(|| self.session.poll_datagram(self.last_now));
+                .or_else(|| self.session.poll_datagram(self.last_now))
+            {
+                // Without rtcp-mux, RTCP is sent over the pair of the RTCP component.
+                let is_rtcp = !self.ice.rtcp_mux()
+                    && matches!(
+                        MultiplexKind::try_from(&contents[..]),
+                        Ok(MultiplexKind::Rtcp)
+                    );
+
+                let (component_id, send) = if is_rtcp {
+                    let Some(rtcp_send) = &self.rtcp_send_addr else {
+                        trace!("Drop RTCP without a nominated RTCP pair");
+                        continue;
+                    };
+                    (2, rtcp_send)
+                } else {
+                    (1, send)
+                };
 
-            if let Some(contents) = datagram {
+                self.ice.record_bytes_sent(component_id, contents.len());
                 let t = net::Transmit {
+                    proto: send.proto,
                     source: send.source,
                     destination: send.destination,
                     contents,
                 };
+                // Datagrams from a relayed candidate go via the TURN server.
+                let t = self.ice.relay_transmit(t);
                 return Ok(Output::Transmit(t));
             }
         }
 
         let time_and_reason = (None, "<not happening>")
             .soonest((self.ice.poll_timeout(), "ice"))
+            .soonest((self.dtls.poll_timeout(), "dtls"))
             .soonest((self.session.poll_timeout(), "session"))
             .soonest((self.sctp.poll_timeout(), "sctp"))
             .soonest((self.chan.poll_timeout(&self.sctp), "chan"))
@@ -1397,15 +1665,25 @@ impl Rtc {
         };
 
         // This should cover Dtls, Rtp and Rtcp
-        if let Some(send_addr) = &self.send_addr {
+        for send_addr in self.send_addr.iter().chain(self.rtcp_send_addr.iter()) {
             // TODO: This assume symmetrical routing, i.e. we are getting
             // the incoming traffic from a remote peer from the same socket address
             // we've nominated for sending via the ICE agent.
-            if r.source == send_addr.destination {
+            if r.proto == send_addr.proto && r.source == send_addr.destination {
                 return true;
             }
         }
 
+        // Traffic from a TURN server for one of our allocations.
+        if self.ice.accepts_relayed(r) {
+            return true;
+        }
+
+        // Responses from STUN servers have no message integrity.
+        if self.ice.accepts_srflx_response(r) {
+            return true;
+        }
+
         // STUN can use the ufrag/password to identify that a message belongs
         // to this Rtc instance.
         if let DatagramRecv::Stun(v) = &r.contents {
@@ -1465,6 +1743,14 @@ impl Rtc {
 
         self.last_now = now;
         self.ice.handle_timeout(now);
+
+        // DTLS can only handshake once we got an ICE connection.
+        if self.send_addr.is_some() {
+            if let Err(e) = self.dtls.handle_timeout(now) {
+                return Err(self.dtls_failed(e));
+            }
+        }
+
         self.sctp.handle_timeout(now);
         self.chan.handle_timeout(now, &mut self.sctp);
         self.session.handle_timeout(now)?;
@@ -1474,6 +1760,7 @@ impl Rtc {
                 let mut snapshot = StatsSnapshot::new(now);
                 snapshot.peer_rx = self.peer_bytes_rx;
                 snapshot.peer_tx = self.peer_bytes_tx;
+                snapshot.ice_pairs = self.ice.pair_stats();
                 self.session.visit_stats(now, &mut snapshot);
                 stats.do_handle_timeout(&mut snapshot);
             }
@@ -1482,6 +1769,17 @@ impl Rtc {
         Ok(())
     }
 
+    fn dtls_failed(&mut self, e: DtlsError) -> RtcError {
+        // There is no recovering from a failed handshake.
+        if matches!(
+            e,
+            DtlsError::HandshakeFailed(_) | DtlsError::HandshakeTimeout(_)
+        ) {
+            self.disconnect();
+        }
+        e.into()
+    }
+
     fn do_handle_receive(&mut self, now: Instant, r: net::Receive) -> Result<(), RtcError> {
         self.init_time(now);
 
@@ -1489,18 +1787,29 @@ impl Rtc {
         self.last_now = now;
         use net::DatagramRecv::*;
 
+        // Unwrap traffic relayed by a TURN server. TURN control traffic is consumed here.
+        let Some(r) = self.ice.unwrap_relayed(now, r) else {
+            return Ok(());
+        };
+
         let bytes_rx = match r.contents {
             // TODO: stun is already parsed (depacketized) here
             Stun(_) => 0,
-            Dtls(v) | Rtp(v) | Rtcp(v) => v.len(),
+            Dtls(v) | Rtp(v) | Rtcp(v) | ChannelData(v) => v.len(),
         };
 
         self.peer_bytes_rx += bytes_rx as u64;
+        self.ice.record_bytes_recv(&r, bytes_rx);
 
         match r.contents {
             Stun(_) => self.ice.handle_receive(now, r),
-            Dtls(_) => self.dtls.handle_receive(r)?,
+            Dtls(_) => {
+                if let Err(e) = self.dtls.handle_receive(now, r) {
+                    return Err(self.dtls_failed(e));
+                }
+            }
             Rtp(_) | Rtcp(_) => self.session.handle_receive(now, r),
+            ChannelData(_) => trace!("Drop ChannelData not from a TURN server"),
         }
 
         Ok(())
@@ -1593,8 +1902,13 @@ impl Rtc {
 #[derive(Debug, Clone)]
 pub struct RtcConfig {
     local_ice_credentials: IceCreds,
+    #[cfg(feature = "openssl")]
     dtls_cert: Option<DtlsCert>,
+    dtls_provider: Option<Arc<dyn DtlsProvider>>,
+    srtp_profiles: Vec<SrtpProfile>,
+    media_protection: MediaProtection,
     fingerprint_verification: bool,
+    dtls_handshake_timeout: Duration,
     ice_lite: bool,
     codec_config: CodecConfig,
     exts: ExtensionMap,
@@ -1607,6 +1921,74 @@ pub struct RtcConfig {
     send_buffer_video: usize,
     rtp_mode: bool,
     enable_raw_packets: bool,
+    stun_servers: Vec<SocketAddr>,
+    turn_servers: Vec<TurnServer>,
+    ice_config: IceConfig,
+    mdns_host_candidates: bool,
+    rtcp_mux_policy: RtcpMuxPolicy,
+}
+
+/// Policy for multiplexing RTP and RTCP on the same port (rtcp-mux).
+///
+/// WebRTC always multiplexes RTP and RTCP, but some legacy SIP gateways and media
+/// servers do not support it. The names follow `RTCRtcpMuxPolicy` of the WebRTC API.
+///
+/// See [`RtcConfig::set_rtcp_mux_policy()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum RtcpMuxPolicy {
+    /// RTP and RTCP are always multiplexed. Only ICE candidates for component 1 (RTP)
+    /// are gathered and used.
+    #[default]
+    Require,
+
+    /// Offer rtcp-mux, but fall back to separate RTP and RTCP components if the remote
+    /// peer does not support it.
+    ///
+    /// The RTCP socket is added with [`Rtc::add_local_candidate()`] using a candidate for
+    /// component 2, see [`Candidate::with_component_id()`]. Until the SDP negotiation
+    /// is done, both components are checked.
+    ///
+    /// The DTLS handshake is only done on the RTP component, and the SRTCP keys are
+    /// derived from that handshake.
+    Negotiate,
+}
+
+/// How media is protected, and how the keys for that are exchanged.
+///
+/// See [`RtcConfig::set_media_protection()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum MediaProtection {
+    /// SRTP keyed with a DTLS handshake ([RFC 5764][1]). This is what WebRTC uses.
+    ///
+    /// The SDP m-lines for audio and video use `UDP/TLS/RTP/SAVPF`.
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc5764
+    #[default]
+    DtlsSrtp,
+
+    /// SRTP keyed with SDES ([RFC 4568][1]), for SIP trunks and gateways that don't do
+    /// DTLS.
+    ///
+    /// The master keys are sent in clear text `a=crypto` lines in the SDP, which means
+    /// the signaling channel must be secure. The SDP m-lines for audio and video use
+    /// `RTP/SAVPF`, or `RTP/SAVP` if the remote peer offers that.
+    ///
+    /// Data channels are not available, since they need DTLS.
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc4568
+    Sdes,
+
+    /// Plain RTP and RTCP without any encryption or authentication.
+    ///
+    /// There is no DTLS handshake, media flows as soon as ICE is connected. This is only
+    /// meant for trusted networks, such as links between servers in a private network.
+    /// The SDP m-lines for audio and video use `RTP/AVPF`. It can also be used with the
+    /// [`DirectApi`][change::DirectApi].
+    ///
+    /// Data channels are not available, since they need DTLS.
+    Unencrypted,
 }
 
 impl RtcConfig {
@@ -1635,6 +2017,7 @@ impl RtcConfig {
     ///     .direct_api()
     ///     .local_dtls_fingerprint();
     /// ```
+    #[cfg(feature = "openssl")]
     pub fn dtls_cert(&self) -> Option<&DtlsCert> {
         self.dtls_cert.as_ref()
     }
@@ -1653,11 +2036,119 @@ impl RtcConfig {
     /// let rtc_config = RtcConfig::default()
     ///     .set_dtls_cert(dtls_cert);
     /// ```
+    #[cfg(feature = "openssl")]
     pub fn set_dtls_cert(mut self, dtls_cert: DtlsCert) -> Self {
         self.dtls_cert = Some(dtls_cert);
         self
     }
 
+    /// Get the configured DTLS implementation, if set.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// assert!(RtcConfig::new().dtls_provider().is_none());
+    /// ```
+    pub fn dtls_provider(&self) -> Option<&Arc<dyn DtlsProvider>> {
+        self.dtls_provider.as_ref()
+    }
+
+    /// Set the DTLS implementation.
+    ///
+    /// Each [`Rtc`] instance gets its own [`DtlsSession`][crate::crypto::DtlsSession]
+    /// from the provider. When set, this takes precedence over
+    /// [`RtcConfig::set_dtls_cert()`].
+    ///
+    /// Defaults to OpenSSL with a new [`DtlsCert`][crate::change::DtlsCert]. Without
+    /// the `openssl` feature, a provider must be set.
+    pub fn set_dtls_provider(mut self, provider: Arc<dyn DtlsProvider>) -> Self {
+        self.dtls_provider = Some(provider);
+        self
+    }
+
+    fn dtls_provider_or_default(&self) -> Result<Arc<dyn DtlsProvider>, RtcError> {
+        if let Some(provider) = &self.dtls_provider {
+            return Ok(provider.clone());
+        }
+
+        #[cfg(feature = "openssl")]
+        {
+            Ok(Arc::new(
+                self.dtls_cert.clone().unwrap_or_else(DtlsCert::new),
+            ))
+        }
+        #[cfg(not(feature = "openssl"))]
+        {
+            Err(RtcError::NoDtlsProvider)
+        }
+    }
+
+    /// How media is protected.
+    ///
+    /// ```
+    /// # use str0m::{RtcConfig, MediaProtection};
+    /// // Defaults to DTLS-SRTP.
+    /// assert_eq!(RtcConfig::default().media_protection(), MediaProtection::DtlsSrtp);
+    /// ```
+    pub fn media_protection(&self) -> MediaProtection {
+        self.media_protection
+    }
+
+    /// Set how media is protected, and how the keys for that are exchanged.
+    ///
+    /// With [`MediaProtection::Sdes`], the [`SdpApi`] offers one `a=crypto` line per
+    /// profile in [`RtcConfig::srtp_profiles()`]. With the [`DirectApi`], the keys are
+    /// set with [`DirectApi::set_srtp_master_keys()`].
+    ///
+    /// [`MediaProtection::Unencrypted`] sends media in the clear and must be enabled on
+    /// both sides.
+    ///
+    /// Defaults to [`MediaProtection::DtlsSrtp`].
+    ///
+    /// ```
+    /// # use str0m::{RtcConfig, MediaProtection};
+    /// let rtc_config = RtcConfig::new()
+    ///     .set_media_protection(MediaProtection::Sdes);
+    /// ```
+    pub fn set_media_protection(mut self, protection: MediaProtection) -> Self {
+        self.media_protection = protection;
+        self
+    }
+
+    /// The SRTP profiles offered in the DTLS handshake, most preferred first.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use str0m::crypto::SrtpProfile;
+    /// let config = RtcConfig::new();
+    /// assert_eq!(
+    ///     config.srtp_profiles(),
+    ///     &[SrtpProfile::AeadAes128Gcm, SrtpProfile::Aes128CmSha1_80]
+    /// );
+    /// ```
+    pub fn srtp_profiles(&self) -> &[SrtpProfile] {
+        &self.srtp_profiles
+    }
+
+    /// Set the SRTP profiles to offer in the DTLS handshake, most preferred first.
+    ///
+    /// Use this to restrict the profiles, or to enable the AES-256 ones. Profiles that
+    /// have no DTLS-SRTP protection profile, such as
+    /// [`SrtpProfile::Aes256CmSha1_80`], are not offered in DTLS. With
+    /// [`MediaProtection::Sdes`], these are the profiles offered in `a=crypto` lines.
+    ///
+    /// Defaults to [`SrtpProfile::DEFAULT`].
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use str0m::crypto::SrtpProfile;
+    /// let config = RtcConfig::new()
+    ///     .set_srtp_profiles(&[SrtpProfile::AeadAes256Gcm, SrtpProfile::AeadAes128Gcm]);
+    /// ```
+    pub fn set_srtp_profiles(mut self, profiles: &[SrtpProfile]) -> Self {
+        self.srtp_profiles = profiles.to_vec();
+        self
+    }
+
     /// Toggle ice lite. Ice lite is a mode for WebRTC servers with public IP address.
     /// An [`Rtc`] instance in ice lite mode will not make STUN binding requests, but only
     /// answer to requests from the remote peer.
@@ -1670,6 +2161,146 @@ impl RtcConfig {
         self
     }
 
+    /// Add a STUN server to discover server reflexive candidates from.
+    ///
+    /// A binding request is sent to the server from every host candidate of the same
+    /// IP family. The discovered candidates are reported as [`Event::LocalCandidate`].
+    ///
+    /// Server reflexive candidates are not used in ice lite mode.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let rtc_config = RtcConfig::default()
+    ///     .add_stun_server("1.2.3.4:3478".parse().unwrap());
+    /// ```
+    pub fn add_stun_server(mut self, server: SocketAddr) -> Self {
+        self.stun_servers.push(server);
+        self
+    }
+
+    /// The configured STUN servers.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// // Defaults to none.
+    /// assert!(RtcConfig::default().stun_servers().is_empty());
+    /// ```
+    pub fn stun_servers(&self) -> &[SocketAddr] {
+        &self.stun_servers
+    }
+
+    /// Add a TURN server to allocate relayed candidates from.
+    ///
+    /// An allocation is made from every host candidate of the same IP family as
+    /// the server. The relayed candidates are reported as [`Event::LocalCandidate`]
+    /// once allocated.
+    ///
+    /// Relayed candidates are not used in ice lite mode.
+    ///
+    /// ```
+    /// # use str0m::{RtcConfig, TurnServer};
+    /// let server = TurnServer::new("1.2.3.4:3478".parse().unwrap(), "user", "secret");
+    ///
+    /// let rtc_config = RtcConfig::default()
+    ///     .add_turn_server(server);
+    /// ```
+    pub fn add_turn_server(mut self, server: TurnServer) -> Self {
+        self.turn_servers.push(server);
+        self
+    }
+
+    /// The configured TURN servers.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// // Defaults to none.
+    /// assert!(RtcConfig::default().turn_servers().is_empty());
+    /// ```
+    pub fn turn_servers(&self) -> &[TurnServer] {
+        &self.turn_servers
+    }
+
+    /// Set the timing and pacing parameters of the ICE agent.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::{IceConfig, RtcConfig};
+    /// // Be more patient on lossy links.
+    /// let ice_config = IceConfig::new()
+    ///     .set_max_retransmits(15)
+    ///     .set_ice_lite_timeout(Duration::from_secs(30));
+    ///
+    /// let rtc_config = RtcConfig::default()
+    ///     .set_ice_config(ice_config);
+    /// ```
+    pub fn set_ice_config(mut self, ice_config: IceConfig) -> Self {
+        self.ice_config = ice_config;
+        self
+    }
+
+    /// The timing and pacing parameters of the ICE agent.
+    ///
+    /// ```
+    /// # use str0m::{IceConfig, RtcConfig};
+    /// // Defaults to the default IceConfig.
+    /// assert_eq!(*RtcConfig::default().ice_config(), IceConfig::default());
+    /// ```
+    pub fn ice_config(&self) -> &IceConfig {
+        &self.ice_config
+    }
+
+    /// Signal local host candidates with mDNS hostnames instead of IP addresses.
+    ///
+    /// The hostnames are random UUIDs ending in `.local`, like browsers do, to not leak
+    /// local IP addresses to the remote peer. The application must answer mDNS queries
+    /// for the names, see [`Rtc::local_mdns_names()`].
+    ///
+    /// Defaults to disabled.
+    pub fn set_mdns_host_candidates(mut self, enabled: bool) -> Self {
+        self.mdns_host_candidates = enabled;
+        self
+    }
+
+    /// Tells whether local host candidates are signaled with mDNS hostnames.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// // Defaults to false.
+    /// assert!(!RtcConfig::default().mdns_host_candidates());
+    /// ```
+    pub fn mdns_host_candidates(&self) -> bool {
+        self.mdns_host_candidates
+    }
+
+    /// Set the policy for multiplexing RTP and RTCP on the same port.
+    ///
+    /// With [`RtcpMuxPolicy::Negotiate`], the SDP has an `a=rtcp` line and candidates for
+    /// component 2 (RTCP). If the remote peer answers without `a=rtcp-mux`, RTCP is sent
+    /// over the nominated pair of component 2.
+    ///
+    /// Defaults to [`RtcpMuxPolicy::Require`].
+    ///
+    /// ```
+    /// # use str0m::{RtcConfig, RtcpMuxPolicy};
+    /// let rtc_config = RtcConfig::new()
+    ///     .set_rtcp_mux_policy(RtcpMuxPolicy::Negotiate);
+    /// ```
+    pub fn set_rtcp_mux_policy(mut self, policy: RtcpMuxPolicy) -> Self {
+        self.rtcp_mux_policy = policy;
+        self
+    }
+
+    /// The policy for multiplexing RTP and RTCP on the same port.
+    ///
+    /// ```
+    /// # use str0m::{RtcConfig, RtcpMuxPolicy};
+    /// // Defaults to Require.
+    /// assert_eq!(RtcConfig::default().rtcp_mux_policy(), RtcpMuxPolicy::Require);
+    /// ```
+    pub fn rtcp_mux_policy(&self) -> RtcpMuxPolicy {
+        self.rtcp_mux_policy
+    }
+
     /// Get fingerprint verification mode.
     ///
     /// ```
@@ -1690,6 +2321,39 @@ impl RtcConfig {
         self
     }
 
+    /// Max time for the DTLS handshake.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::RtcConfig;
+    /// // Defaults to 30 seconds.
+    /// assert_eq!(RtcConfig::default().dtls_handshake_timeout(), Duration::from_secs(30));
+    /// ```
+    pub fn dtls_handshake_timeout(&self) -> Duration {
+        self.dtls_handshake_timeout
+    }
+
+    /// Set the max time for the DTLS handshake.
+    ///
+    /// The time is counted from when ICE is connected and DTLS can start handshaking.
+    /// If the handshake is not complete by then, the [`Rtc`] instance is disconnected and
+    /// [`Rtc::handle_input()`] returns [`DtlsError::HandshakeTimeout`][error::DtlsError::HandshakeTimeout].
+    /// Lost handshake datagrams are resent with a timer that starts at 1 second and
+    /// doubles up to 60 seconds.
+    ///
+    /// Defaults to 30 seconds.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::RtcConfig;
+    /// let rtc_config = RtcConfig::new()
+    ///     .set_dtls_handshake_timeout(Duration::from_secs(10));
+    /// ```
+    pub fn set_dtls_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.dtls_handshake_timeout = timeout;
+        self
+    }
+
     /// Tells whether ice lite is enabled.
     ///
     /// ```
@@ -1749,15 +2413,21 @@ impl RtcConfig {
         self
     }
 
-    // TODO: AV1 depacketizer/packetizer.
-    //
-    // /// Enable AV1 video codec.
-    // ///
-    // /// Enabled by default.
-    // pub fn enable_av1(mut self) -> Self {
-    //     self.codec_config.add_default_av1();
-    //     self
-    // }
+    /// Enable H265 video codec.
+    ///
+    /// Disabled by default.
+    pub fn enable_h265(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_h265(enabled);
+        self
+    }
+
+    /// Enable AV1 video codec.
+    ///
+    /// Enabled by default.
+    pub fn enable_av1(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_av1(enabled);
+        self
+    }
 
     /// Enable VP9 video codec.
     ///
@@ -1808,7 +2478,7 @@ impl RtcConfig {
     ///
     /// None turns off the stats events.
     ///
-    /// This includes [`MediaEgressStats`], [`MediaIngressStats`], [`MediaEgressStats`]
+    /// This includes [`MediaEgressStats`], [`MediaIngressStats`], [`MediaEgressStats`], [`IceStats`]
     pub fn set_stats_interval(mut self, interval: Option<Duration>) -> Self {
         self.stats_interval = interval;
         self
@@ -2015,7 +2685,18 @@ impl RtcConfig {
     }
 
     /// Create a [`Rtc`] from the configuration.
+    ///
+    /// Requires the `openssl` feature, see [`RtcConfig::try_build()`].
+    #[cfg(feature = "openssl")]
     pub fn build(self) -> Rtc {
+        self.try_build().expect("DTLS to init without problem")
+    }
+
+    /// Create a [`Rtc`] from the configuration, or fail if DTLS can't be set up.
+    ///
+    /// Without the `openssl` feature, this fails with [`RtcError::NoDtlsProvider`] unless
+    /// [`RtcConfig::set_dtls_provider()`] is used.
+    pub fn try_build(self) -> Result<Rtc, RtcError> {
         Rtc::new_from_config(self)
     }
 }
@@ -2024,8 +2705,13 @@ impl Default for RtcConfig {
     fn default() -> Self {
         Self {
             local_ice_credentials: IceCreds::new(),
+            #[cfg(feature = "openssl")]
             dtls_cert: None,
+            dtls_provider: None,
+            srtp_profiles: SrtpProfile::DEFAULT.to_vec(),
+            media_protection: MediaProtection::default(),
             fingerprint_verification: true,
+            dtls_handshake_timeout: Duration::from_secs(30),
             ice_lite: false,
             codec_config: CodecConfig::new_with_defaults(),
             exts: ExtensionMap::standard(),
@@ -2037,6 +2723,11 @@ impl Default for RtcConfig {
             send_buffer_video: 1000,
             rtp_mode: false,
             enable_raw_packets: false,
+            stun_servers: vec![],
+            turn_servers: vec![],
+            ice_config: IceConfig::default(),
+            mdns_host_candidates: false,
+            rtcp_mux_policy: RtcpMuxPolicy::default(),
         }
     }
 }
@@ -2045,6 +2736,9 @@ impl PartialEq for Event {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::IceConnectionStateChange(l0), Self::IceConnectionStateChange(r0)) => l0 == r0,
+            (Self::IceConsentExpired(l0), Self::IceConsentExpired(r0)) => l0 == r0,
+            (Self::LocalCandidate(l0), Self::LocalCandidate(r0)) => l0 == r0,
+            (Self::UnresolvedCandidate(l0), Self::UnresolvedCandidate(r0)) => l0 == r0,
             (Self::MediaAdded(m0), Self::MediaAdded(m1)) => m0 == m1,
             (Self::MediaData(m1), Self::MediaData(m2)) => m1 == m2,
             (Self::ChannelOpen(l0, l1), Self::ChannelOpen(r0, r1)) => l0 == r0 && l1 == r1,
@@ -2102,11 +2796,10 @@ pub(crate) use log_stat;
 
 #[cfg(test)]
 mod test {
-    use std::panic::UnwindSafe;
-
     use super::*;
 
     #[test]
+    #[cfg(feature = "openssl")]
     fn rtc_is_send() {
         fn is_send<T: Send>(_t: T) {}
         fn is_sync<T: Sync>(_t: T) {}
@@ -2115,11 +2808,21 @@ mod test {
     }
 
     #[test]
+    #[cfg(feature = "openssl")]
     fn rtc_is_unwind_safe() {
+        use std::panic::UnwindSafe;
+
         fn is_unwind_safe<T: UnwindSafe>(_t: T) {}
         is_unwind_safe(Rtc::new());
     }
 
+    #[test]
+    #[cfg(not(feature = "openssl"))]
+    fn build_without_dtls_provider() {
+        let r = RtcConfig::new().try_build();
+        assert!(matches!(r, Err(RtcError::NoDtlsProvider)));
+    }
+
     #[test]
     fn event_is_reasonably_sized() {
         let n = std::mem::size_of::<Event>();
@@ -2128,6 +2831,13 @@ mod test {
     }
 }
 
+#[cfg(feature = "_internal_test_exports")]
+#[allow(missing_docs)]
+#[doc(hidden)]
+pub mod _internal_test_exports {
+    pub use crate::rtp_::SrtpContext;
+}
+
 #[cfg(fuzzing)]
 #[allow(missing_docs)]
 pub mod fuzz {
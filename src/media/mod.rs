@@ -254,8 +254,8 @@ impl Media {
                         seq_range: dep.seq_range(),
                         contiguous: dep.contiguous,
                         ext_vals: dep.ext_vals().clone(),
-                        codec_extra: dep.codec_extra,
                         last_sender_info: dep.first_sender_info(),
+                        codec_extra: dep.codec_extra,
                         data: dep.data,
                     })
                     .map_err(|e| RtcError::Packet(self.mid, *pt, e)),
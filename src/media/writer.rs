@@ -1,7 +1,7 @@
 use std::time::Instant;
 
 use crate::format::PayloadParams;
-use crate::rtp_::VideoOrientation;
+use crate::rtp_::{DependencyDescriptor, VideoOrientation};
 use crate::session::Session;
 use crate::RtcError;
 
@@ -75,6 +75,15 @@ impl<'a> Writer<'a> {
         self
     }
 
+    /// Add a Dependency Descriptor for the frame.
+    ///
+    /// The start/end of frame flags are set per packet, and the structure is only
+    /// attached to the first packet of the frame.
+    pub fn dependency_descriptor(mut self, dd: DependencyDescriptor) -> Self {
+        self.ext_vals.dependency_descriptor = Some(Box::new(dd));
+        self
+    }
+
     /// Set a user extension value.
     pub fn user_extension_value<T: Send + Sync + 'static>(mut self, val: T) -> Self {
         self.ext_vals.user_values.set(val);
@@ -0,0 +1,491 @@
+use super::{CodecExtra, Depacketizer, PacketError, Packetizer};
+
+/// Size of the aggregation header.
+const AV1_AGGREGATION_HEADER_SIZE: usize = 1;
+
+/// More OBU elements than this use a length field also for the last element.
+const MAX_W_ELEMENTS: usize = 3;
+
+const OBU_SEQUENCE_HEADER: u8 = 1;
+const OBU_TEMPORAL_DELIMITER: u8 = 2;
+const OBU_TILE_LIST: u8 = 8;
+
+/// `obu_has_size_field` bit in the OBU header.
+const OBU_HAS_SIZE_FIELD: u8 = 0x02;
+/// `obu_extension_flag` bit in the OBU header.
+const OBU_EXTENSION_FLAG: u8 = 0x04;
+
+/// A temporal delimiter OBU with a zero size field.
+const TEMPORAL_DELIMITER: [u8; 2] = [OBU_TEMPORAL_DELIMITER << 3 | OBU_HAS_SIZE_FIELD, 0];
+
+/// Packetizes AV1 RTP packets.
+///
+/// The input is a temporal unit in the low overhead bitstream format, i.e. the OBUs
+/// as output by an encoder, each with a size field.
+#[derive(Default, Debug, Copy, Clone)]
+pub struct Av1Packetizer;
+
+/// An RTP packet being filled with OBU elements.
+#[derive(Default)]
+struct Av1Packet<'a> {
+    /// First element is the continuation of an OBU from the previous packet.
+    z: bool,
+    /// Last element continues in the next packet.
+    y: bool,
+    elements: Vec<&'a [u8]>,
+    size: usize,
+}
+
+impl<'a> Av1Packet<'a> {
+    fn push(&mut self, element: &'a [u8]) {
+        self.size += leb128_size(element.len() as u64) + element.len();
+        self.elements.push(element);
+    }
+
+    fn to_payload(&self, n: bool) -> Vec<u8> {
+        let count = self.elements.len();
+        let w = if count <= MAX_W_ELEMENTS { count } else { 0 };
+
+        let mut out = Vec::with_capacity(AV1_AGGREGATION_HEADER_SIZE + self.size);
+
+        out.push((self.z as u8) << 7 | (self.y as u8) << 6 | (w as u8) << 4 | (n as u8) << 3);
+
+        for (i, e) in self.elements.iter().enumerate() {
+            // With W set, the last element has no length field.
+            if w == 0 || i < count - 1 {
+                write_leb128(&mut out, e.len() as u64);
+            }
+            out.extend_from_slice(e);
+        }
+
+        out
+    }
+}
+
+impl Packetizer for Av1Packetizer {
+    fn packetize(&mut self, mtu: usize, payload: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
+        /*
+         * https://aomediacodec.github.io/av1-rtp-spec/#44-av1-aggregation-header
+         *
+         *  0 1 2 3 4 5 6 7
+         * +-+-+-+-+-+-+-+-+
+         * |Z|Y| W |N|-|-|-|
+         * +-+-+-+-+-+-+-+-+
+         *
+         * Z: first OBU element is the continuation of an OBU fragment from the previous packet.
+         * Y: last OBU element will continue in the next packet.
+         * W: number of OBU elements in the packet. 0 means each element has a length field.
+         * N: first packet of a coded video sequence.
+         */
+        if payload.is_empty() || mtu <= AV1_AGGREGATION_HEADER_SIZE + 1 {
+            return Ok(vec![]);
+        }
+
+        let mut obus = vec![];
+        let mut new_sequence = false;
+
+        let mut rest = payload;
+        while !rest.is_empty() {
+            let obu = Obu::parse(rest)?;
+            rest = &rest[obu.len..];
+
+            match obu.obu_type() {
+                // These must not be sent over RTP.
+                OBU_TEMPORAL_DELIMITER | OBU_TILE_LIST => continue,
+                OBU_SEQUENCE_HEADER => new_sequence = true,
+                _ => {}
+            }
+
+            // Each OBU is an element without the size field.
+            let mut element = Vec::with_capacity(obu.header.len() + obu.payload.len());
+            element.extend_from_slice(obu.header);
+            element[0] &= !OBU_HAS_SIZE_FIELD;
+            element.extend_from_slice(obu.payload);
+            obus.push(element);
+        }
+
+        let max_size = mtu - AV1_AGGREGATION_HEADER_SIZE;
+        let mut packets = vec![];
+        let mut packet = Av1Packet::default();
+
+        for obu in &obus {
+            let mut offset = 0;
+
+            while offset < obu.len() {
+                let available = max_size - packet.size;
+                // Room for the length field and at least one byte.
+                let max_element = available.saturating_sub(leb128_size(available as u64));
+
+                if max_element == 0 {
+                    packets.push(packet);
+                    packet = Av1Packet::default();
+                    continue;
+                }
+
+                let len = max_element.min(obu.len() - offset);
+                packet.push(&obu[offset..offset + len]);
+                offset += len;
+
+                if offset < obu.len() {
+                    // The OBU continues in the next packet.
+                    packet.y = true;
+                    packets.push(packet);
+                    packet = Av1Packet {
+                        z: true,
+                        ..Default::default()
+                    };
+                }
+            }
+        }
+
+        if !packet.elements.is_empty() {
+            packets.push(packet);
+        }
+
+        Ok(packets
+            .iter()
+            .enumerate()
+            .map(|(i, p)| p.to_payload(new_sequence && i == 0))
+            .collect())
+    }
+
+    fn is_marker(&mut self, _data: &[u8], _previous: Option<&[u8]>, last: bool) -> bool {
+        last
+    }
+}
+
+/// Depacketizes AV1 RTP packets.
+///
+/// Outputs the temporal unit in the low overhead bitstream format, starting with a
+/// temporal delimiter, and with a size field in every OBU.
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
+pub struct Av1Depacketizer {
+    /// First packet of a coded video sequence.
+    pub n: bool,
+
+    /// An OBU fragmented over several packets, collected so far.
+    fragment: Vec<u8>,
+}
+
+impl Depacketizer for Av1Depacketizer {
+    fn depacketize(
+        &mut self,
+        packet: &[u8],
+        out: &mut Vec<u8>,
+        _codec_extra: &mut CodecExtra,
+    ) -> Result<(), PacketError> {
+        if packet.len() <= AV1_AGGREGATION_HEADER_SIZE {
+            return Err(PacketError::ErrShortPacket);
+        }
+
+        let z = packet[0] & 0x80 != 0;
+        let y = packet[0] & 0x40 != 0;
+        let w = ((packet[0] & 0x30) >> 4) as usize;
+        self.n = packet[0] & 0x08 != 0;
+
+        if !z && out.is_empty() {
+            // Temporal delimiters are not sent, but start every temporal unit.
+            out.extend_from_slice(&TEMPORAL_DELIMITER);
+        }
+
+        if !z && !self.fragment.is_empty() {
+            trace!("Drop unfinished AV1 OBU fragment");
+            self.fragment.clear();
+        }
+
+        let mut rest = &packet[AV1_AGGREGATION_HEADER_SIZE..];
+        let mut index = 0;
+
+        while !rest.is_empty() {
+            let (element, element_end) = if w != 0 && index == w - 1 {
+                // Last element without length field.
+                (rest, rest.len())
+            } else {
+                let (len, n) = read_leb128(rest).ok_or(PacketError::ErrAv1CorruptedPacket)?;
+                let len = len as usize;
+                if len > rest.len() - n {
+                    return Err(PacketError::ErrAv1CorruptedPacket);
+                }
+                (&rest[n..n + len], n + len)
+            };
+            rest = &rest[element_end..];
+
+            let is_continuation = index == 0 && z;
+            let is_fragment = rest.is_empty() && y;
+            index += 1;
+
+            if is_continuation && self.fragment.is_empty() {
+                // The start of this OBU was lost.
+                trace!("Drop AV1 OBU fragment without start");
+                continue;
+            }
+
+            if is_continuation || is_fragment {
+                self.fragment.extend_from_slice(element);
+            }
+
+            if is_fragment {
+                continue;
+            }
+
+            if is_continuation {
+                let obu = std::mem::take(&mut self.fragment);
+                write_obu(&obu, out)?;
+            } else {
+                write_obu(element, out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_partition_head(&self, packet: &[u8]) -> bool {
+        // Not a continuation of an OBU from a previous packet.
+        packet.len() > AV1_AGGREGATION_HEADER_SIZE && packet[0] & 0x80 == 0
+    }
+
+    fn is_partition_tail(&self, marker: bool, _packet: &[u8]) -> bool {
+        marker
+    }
+}
+
+/// One OBU in the low overhead bitstream format.
+struct Obu<'a> {
+    /// OBU header with the optional extension header.
+    header: &'a [u8],
+    payload: &'a [u8],
+    /// Total length, including the size field.
+    len: usize,
+}
+
+impl<'a> Obu<'a> {
+    fn parse(buf: &'a [u8]) -> Result<Self, PacketError> {
+        let first = *buf.first().ok_or(PacketError::ErrShortPacket)?;
+
+        let header_len = if first & OBU_EXTENSION_FLAG != 0 {
+            2
+        } else {
+            1
+        };
+        if buf.len() < header_len {
+            return Err(PacketError::ErrShortPacket);
+        }
+        let (header, rest) = buf.split_at(header_len);
+
+        if first & OBU_HAS_SIZE_FIELD == 0 {
+            // Without size field, the OBU extends to the end.
+            return Ok(Obu {
+                header,
+                payload: rest,
+                len: buf.len(),
+            });
+        }
+
+        let (size, n) = read_leb128(rest).ok_or(PacketError::ErrAv1CorruptedPacket)?;
+        let size = size as usize;
+        if size > rest.len() - n {
+            return Err(PacketError::ErrShortPacket);
+        }
+
+        Ok(Obu {
+            header,
+            payload: &rest[n..n + size],
+            len: header_len + n + size,
+        })
+    }
+
+    fn obu_type(&self) -> u8 {
+        (self.header[0] >> 3) & 0x0f
+    }
+}
+
+/// Write an OBU from an RTP element with a size field.
+fn write_obu(element: &[u8], out: &mut Vec<u8>) -> Result<(), PacketError> {
+    let obu = Obu::parse(element).map_err(|_| PacketError::ErrAv1CorruptedPacket)?;
+
+    if matches!(obu.obu_type(), OBU_TEMPORAL_DELIMITER | OBU_TILE_LIST) {
+        return Ok(());
+    }
+
+    out.push(obu.header[0] | OBU_HAS_SIZE_FIELD);
+    out.extend_from_slice(&obu.header[1..]);
+    write_leb128(out, obu.payload.len() as u64);
+    out.extend_from_slice(obu.payload);
+
+    Ok(())
+}
+
+/// Reads a leb128 value, returning the value and the number of bytes read.
+fn read_leb128(buf: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0;
+    for (i, b) in buf.iter().take(8).enumerate() {
+        value |= ((b & 0x7f) as u64) << (i * 7);
+        if b & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let b = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(b);
+            break;
+        }
+        out.push(b | 0x80);
+    }
+}
+
+fn leb128_size(value: u64) -> usize {
+    let mut size = 1;
+    let mut value = value >> 7;
+    while value > 0 {
+        size += 1;
+        value >>= 7;
+    }
+    size
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// An OBU with size field.
+    fn obu(obu_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut v = vec![obu_type << 3 | OBU_HAS_SIZE_FIELD];
+        write_leb128(&mut v, payload.len() as u64);
+        v.extend_from_slice(payload);
+        v
+    }
+
+    fn depacketize(packets: &[Vec<u8>]) -> Result<Vec<u8>, PacketError> {
+        let mut depack = Av1Depacketizer::default();
+        let mut out = vec![];
+        let mut extra = CodecExtra::None;
+        for p in packets {
+            depack.depacketize(p, &mut out, &mut extra)?;
+        }
+        Ok(out)
+    }
+
+    #[test]
+    fn leb128() {
+        for v in [0, 1, 127, 128, 300, 16_383, 16_384, 1 << 40] {
+            let mut buf = vec![];
+            write_leb128(&mut buf, v);
+            assert_eq!(buf.len(), leb128_size(v));
+            assert_eq!(read_leb128(&buf), Some((v, buf.len())));
+        }
+        assert_eq!(read_leb128(&[0x80]), None);
+    }
+
+    #[test]
+    fn aggregate_small_obus() {
+        let mut tu = obu(OBU_TEMPORAL_DELIMITER, &[]);
+        tu.extend(obu(OBU_SEQUENCE_HEADER, &[1, 2, 3]));
+        tu.extend(obu(6, &[4, 5, 6, 7]));
+
+        let packets = Av1Packetizer.packetize(1200, &tu).unwrap();
+        assert_eq!(
+            packets,
+            vec![vec![
+                // Z=0 Y=0 W=2 N=1
+                0b0010_1000,
+                // Sequence header, with length field, without size field.
+                4,
+                OBU_SEQUENCE_HEADER << 3,
+                1,
+                2,
+                3,
+                // Frame OBU, last element without length field.
+                6 << 3,
+                4,
+                5,
+                6,
+                7,
+            ]]
+        );
+
+        // The depacketized temporal unit starts with a temporal delimiter.
+        assert_eq!(depacketize(&packets).unwrap(), tu);
+    }
+
+    #[test]
+    fn fragment_large_obu() {
+        let payload: Vec<u8> = (0..3000).map(|i| i as u8).collect();
+        let mut tu = obu(OBU_TEMPORAL_DELIMITER, &[]);
+        tu.extend(obu(6, &payload));
+
+        let packets = Av1Packetizer.packetize(1000, &tu).unwrap();
+        assert_eq!(packets.len(), 4);
+        assert!(packets.iter().all(|p| p.len() <= 1000));
+
+        // Z and Y tell the OBU continues between packets.
+        assert_eq!(packets[0][0] & 0xc0, 0b0100_0000);
+        assert_eq!(packets[1][0] & 0xc0, 0b1100_0000);
+        assert_eq!(packets[3][0] & 0xc0, 0b1000_0000);
+
+        let depack = Av1Depacketizer::default();
+        assert!(depack.is_partition_head(&packets[0]));
+        assert!(!depack.is_partition_head(&packets[1]));
+
+        assert_eq!(depacketize(&packets).unwrap(), tu);
+    }
+
+    #[test]
+    fn many_obus_use_w_0() {
+        let mut tu = vec![];
+        for i in 0..5 {
+            tu.extend(obu(6, &[i; 10]));
+        }
+
+        let packets = Av1Packetizer.packetize(1200, &tu).unwrap();
+        assert_eq!(packets.len(), 1);
+        // W=0, N=0
+        assert_eq!(packets[0][0], 0);
+
+        let mut expected = TEMPORAL_DELIMITER.to_vec();
+        expected.extend(tu);
+        assert_eq!(depacketize(&packets).unwrap(), expected);
+    }
+
+    #[test]
+    fn obu_extension_and_no_size_field() {
+        // Extension header, and no size field in the last OBU from the encoder.
+        let tu = [6 << 3 | OBU_EXTENSION_FLAG, 0b0010_1000, 9, 9, 9];
+
+        let packets = Av1Packetizer.packetize(1200, &tu).unwrap();
+        assert_eq!(
+            packets,
+            vec![vec![0b0001_0000, 6 << 3 | 0x04, 0x28, 9, 9, 9]]
+        );
+
+        let mut expected = TEMPORAL_DELIMITER.to_vec();
+        expected.extend([6 << 3 | 0x06, 0x28, 3, 9, 9, 9]);
+        assert_eq!(depacketize(&packets).unwrap(), expected);
+    }
+
+    #[test]
+    fn drop_fragment_without_start() {
+        let payload = vec![7; 2000];
+        let packets = Av1Packetizer.packetize(1000, &obu(6, &payload)).unwrap();
+
+        // Lose the first packet.
+        let out = depacketize(&packets[1..]).unwrap();
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn corrupt_packets() {
+        assert_eq!(depacketize(&[vec![0]]), Err(PacketError::ErrShortPacket));
+
+        // Length field longer than the packet.
+        assert_eq!(
+            depacketize(&[vec![0, 10, 6 << 3]]),
+            Err(PacketError::ErrAv1CorruptedPacket)
+        );
+    }
+}
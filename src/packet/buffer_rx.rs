@@ -188,9 +188,10 @@ impl DepacketizingBuffer {
             return None;
         }
 
-        let (can_emit, contiguous_codec) = match dep.codec_extra {
+        let (can_emit, contiguous_codec) = match &dep.codec_extra {
             CodecExtra::None => (true, contiguous_seq),
-            CodecExtra::Vp8(next) => self.vp8_contiguity.check(&next, contiguous_seq),
+            CodecExtra::Vp8(next) => self.vp8_contiguity.check(next, contiguous_seq),
+            CodecExtra::Vp9(_) | CodecExtra::H264(_) => (true, contiguous_seq),
         };
 
         dep.contiguous = contiguous_codec;
@@ -210,7 +211,7 @@ impl DepacketizingBuffer {
             return None;
         }
 
-        self.last_emitted = Some((last, dep.codec_extra));
+        self.last_emitted = Some((last, dep.codec_extra.clone()));
 
         Some(Ok(dep))
     }
@@ -1,6 +1,6 @@
 #![allow(clippy::all)]
 
-use super::{CodecExtra, Depacketizer, MediaKind, PacketError, Packetizer};
+use super::{BitRead, CodecExtra, Depacketizer, MediaKind, PacketError, Packetizer};
 
 /// Packetizes H264 RTP packets.
 #[derive(Default, Debug, Clone)]
@@ -12,6 +12,8 @@ pub struct H264Packetizer {
 pub const STAPA_NALU_TYPE: u8 = 24;
 pub const FUA_NALU_TYPE: u8 = 28;
 pub const FUB_NALU_TYPE: u8 = 29;
+pub const SLICE_NALU_TYPE: u8 = 1;
+pub const IDR_NALU_TYPE: u8 = 5;
 pub const SPS_NALU_TYPE: u8 = 7;
 pub const PPS_NALU_TYPE: u8 = 8;
 pub const AUD_NALU_TYPE: u8 = 9;
@@ -31,7 +33,7 @@ pub const OUTPUT_STAP_AHEADER: u8 = 0x78;
 pub static ANNEXB_NALUSTART_CODE: &[u8] = &[0x00, 0x00, 0x00, 0x01];
 
 impl H264Packetizer {
-    fn next_ind(nalu: &[u8], start: usize) -> (isize, isize) {
+    pub(super) fn next_ind(nalu: &[u8], start: usize) -> (isize, isize) {
         let mut zero_count = 0;
 
         for (i, &b) in nalu[start..].iter().enumerate() {
@@ -193,6 +195,91 @@ impl Packetizer for H264Packetizer {
     }
 }
 
+/// H264 information describing the depacketized data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct H264CodecExtra {
+    /// True if the sample contains an IDR slice, i.e. it is a keyframe.
+    pub has_idr: bool,
+    /// True if the sample contains a sequence parameter set (SPS).
+    pub has_sps: bool,
+    /// True if the sample contains a picture parameter set (PPS).
+    pub has_pps: bool,
+    /// The NAL unit types in the sample, bit `n` is set for NAL unit type `n`.
+    pub nalu_types: u32,
+    /// The slice types in the sample, bit `n` is set for `slice_type % 5 == n`.
+    ///
+    /// * 0 - P
+    /// * 1 - B
+    /// * 2 - I
+    /// * 3 - SP
+    /// * 4 - SI
+    pub slice_types: u8,
+}
+
+impl H264CodecExtra {
+    /// Tells if the sample contains a NAL unit of the given type.
+    ///
+    /// ```
+    /// # use str0m::format::H264CodecExtra;
+    /// let extra = H264CodecExtra {
+    ///     nalu_types: 1 << 5,
+    ///     ..Default::default()
+    /// };
+    /// assert!(extra.has_nalu_type(5));
+    /// assert!(!extra.has_nalu_type(1));
+    /// ```
+    pub fn has_nalu_type(&self, nalu_type: u8) -> bool {
+        nalu_type < 32 && self.nalu_types & (1 << nalu_type) != 0
+    }
+
+    /// Records a NAL unit, `rbsp` is the data after the NAL unit header.
+    fn add_nalu(&mut self, nalu_type: u8, rbsp: &[u8]) {
+        self.nalu_types |= 1 << nalu_type;
+
+        match nalu_type {
+            IDR_NALU_TYPE => self.has_idr = true,
+            SPS_NALU_TYPE => self.has_sps = true,
+            PPS_NALU_TYPE => self.has_pps = true,
+            _ => {}
+        }
+
+        if nalu_type == SLICE_NALU_TYPE || nalu_type == IDR_NALU_TYPE {
+            // The slice header starts with first_mb_in_slice and slice_type. Emulation
+            // prevention bytes are ignored, they can only occur this early for a
+            // first_mb_in_slice above 65534.
+            let mut reader = (rbsp, 0);
+            if let Some(slice_type) = read_ue(&mut reader).and_then(|_| read_ue(&mut reader)) {
+                self.slice_types |= 1 << (slice_type % 5);
+            }
+        }
+    }
+}
+
+/// Reads an unsigned Exp-Golomb code, `ue(v)` in the H264 spec.
+fn read_ue(reader: &mut dyn BitRead) -> Option<u32> {
+    let mut zeros = 0;
+    loop {
+        if reader.remaining() == 0 || zeros > 31 {
+            return None;
+        }
+        if reader.get_bit() == 1 {
+            break;
+        }
+        zeros += 1;
+    }
+
+    if reader.remaining() < zeros {
+        return None;
+    }
+
+    let mut v = 0_u32;
+    for _ in 0..zeros {
+        v = v << 1 | reader.get_bit() as u32;
+    }
+
+    Some((1 << zeros) - 1 + v)
+}
+
 /// Depacketizes H264 RTP packets.
 #[derive(PartialEq, Eq, Debug, Default, Clone)]
 pub struct H264Depacketizer {
@@ -206,12 +293,20 @@ impl Depacketizer for H264Depacketizer {
         &mut self,
         packet: &[u8],
         out: &mut Vec<u8>,
-        _: &mut CodecExtra,
+        extra: &mut CodecExtra,
     ) -> Result<(), PacketError> {
         if packet.len() == 0 {
             return Err(PacketError::ErrShortPacket);
         }
 
+        // All packets of the sample add to the same extra.
+        if !matches!(extra, CodecExtra::H264(_)) {
+            *extra = CodecExtra::H264(H264CodecExtra::default());
+        }
+        let CodecExtra::H264(extra) = extra else {
+            unreachable!()
+        };
+
         // NALU Types
         // https://tools.ietf.org/html/rfc6184#section-5.4
         let b0 = packet[0];
@@ -225,6 +320,7 @@ impl Depacketizer for H264Depacketizer {
                     out.extend_from_slice(ANNEXB_NALUSTART_CODE);
                 }
                 out.extend_from_slice(packet);
+                extra.add_nalu(nalu_type, &packet[1..]);
                 Ok(())
             }
             STAPA_NALU_TYPE => {
@@ -246,7 +342,11 @@ impl Depacketizer for H264Depacketizer {
                     } else {
                         out.extend_from_slice(ANNEXB_NALUSTART_CODE);
                     }
-                    out.extend_from_slice(&packet[curr_offset..curr_offset + nalu_size]);
+                    let nalu = &packet[curr_offset..curr_offset + nalu_size];
+                    out.extend_from_slice(nalu);
+                    if let Some(b) = nalu.first() {
+                        extra.add_nalu(b & NALU_TYPE_BITMASK, &nalu[1..]);
+                    }
                     curr_offset += nalu_size;
                 }
 
@@ -266,6 +366,10 @@ impl Depacketizer for H264Depacketizer {
                 }
 
                 let b1 = packet[1];
+                if b1 & FU_START_BITMASK != 0 {
+                    extra.add_nalu(b1 & NALU_TYPE_BITMASK, &packet[FUA_HEADER_SIZE..]);
+                }
+
                 if b1 & FU_END_BITMASK != 0 {
                     let nalu_ref_idc = b0 & NALU_REF_IDC_BITMASK;
                     let fragmented_nalu_type = b1 & NALU_TYPE_BITMASK;
@@ -513,6 +617,46 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_h264_codec_extra() -> Result<(), PacketError> {
+        let mut pck = H264Depacketizer::default();
+        let mut out = vec![];
+        let mut extra = CodecExtra::None;
+
+        // STAP-A with SPS and PPS, then an IDR slice (I, slice_type 7) in two FU-A.
+        let stap_a = &[0x78, 0x00, 0x02, 0x67, 0x42, 0x00, 0x02, 0x68, 0xce];
+        let fu_a_start = &[0x7c, 0x85, 0x88, 0x84];
+        let fu_a_end = &[0x7c, 0x45, 0x21, 0x00];
+
+        pck.depacketize(stap_a, &mut out, &mut extra)?;
+        pck.depacketize(fu_a_start, &mut out, &mut extra)?;
+        pck.depacketize(fu_a_end, &mut out, &mut extra)?;
+
+        let CodecExtra::H264(e) = extra else {
+            panic!("Expected H264 codec extra");
+        };
+        assert!(e.has_idr);
+        assert!(e.has_sps);
+        assert!(e.has_pps);
+        assert_eq!(e.nalu_types, 1 << 5 | 1 << 7 | 1 << 8);
+        assert!(e.has_nalu_type(5));
+        assert_eq!(e.slice_types, 1 << 2);
+
+        // A non-IDR P slice (slice_type 5).
+        let mut extra = CodecExtra::None;
+        pck.depacketize(&[0x41, 0x98, 0x12], &mut out, &mut extra)?;
+
+        let CodecExtra::H264(e) = extra else {
+            panic!("Expected H264 codec extra");
+        };
+        assert!(!e.has_idr);
+        assert!(!e.has_sps);
+        assert_eq!(e.nalu_types, 1 << 1);
+        assert_eq!(e.slice_types, 1 << 0);
+
+        Ok(())
+    }
+
     #[test]
     fn test_h264_partition_head_checker_is_partition_head() -> Result<(), PacketError> {
         let h264 = H264Depacketizer::default();
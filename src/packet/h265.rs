@@ -1,7 +1,8 @@
 #![allow(clippy::all)]
 #![allow(unused)]
 
-use super::{CodecExtra, Depacketizer, PacketError};
+use super::h264::{H264Packetizer, ANNEXB_NALUSTART_CODE};
+use super::{CodecExtra, Depacketizer, PacketError, Packetizer};
 
 ///
 /// Network Abstraction Unit Header implementation
@@ -14,6 +15,15 @@ const H265NALU_AGGREGATION_PACKET_TYPE: u8 = 48;
 const H265NALU_FRAGMENTATION_UNIT_TYPE: u8 = 49;
 /// https://datatracker.ietf.org/doc/html/rfc7798#section-4.4.4
 const H265NALU_PACI_PACKET_TYPE: u8 = 50;
+/// Access unit delimiter, dropped when packetizing.
+const H265NALU_AUD_TYPE: u8 = 35;
+/// Filler data, dropped when packetizing.
+const H265NALU_FILLER_TYPE: u8 = 38;
+
+/// Payload header followed by the FU header.
+const H265FU_HEADER_SIZE: usize = 3;
+/// Each NAL unit in an aggregation packet is preceded by its size.
+const H265AP_NALU_LENGTH_SIZE: usize = 2;
 
 /// H265NALUHeader is a H265 NAL Unit Header
 /// https://datatracker.ietf.org/doc/html/rfc7798#section-1.1.4
@@ -710,6 +720,157 @@ impl Default for H265Payload {
     }
 }
 
+/// Packetizes H265 RTP packets.
+///
+/// NAL units that fit are aggregated into Aggregation Packets (typically VPS, SPS and PPS
+/// together with the first slice). Larger ones are split into Fragmentation Units.
+/// DONL is never sent, i.e. `sprop-max-don-diff` is 0.
+#[derive(Default, Debug, Clone)]
+pub struct H265Packetizer;
+
+impl H265Packetizer {
+    /// Splits an Annex B byte stream into NAL units.
+    fn split_annex_b(payload: &[u8]) -> Vec<&[u8]> {
+        let (mut next_ind_start, mut next_ind_len) = H264Packetizer::next_ind(payload, 0);
+        if next_ind_start == -1 {
+            return vec![payload];
+        }
+
+        let mut nalus = vec![];
+        while next_ind_start != -1 {
+            let prev_start = (next_ind_start + next_ind_len) as usize;
+            let (next_ind_start2, next_ind_len2) = H264Packetizer::next_ind(payload, prev_start);
+            next_ind_start = next_ind_start2;
+            next_ind_len = next_ind_len2;
+            if next_ind_start != -1 {
+                nalus.push(&payload[prev_start..next_ind_start as usize]);
+            } else {
+                // Until end of stream, no end indicator found
+                nalus.push(&payload[prev_start..]);
+            }
+        }
+
+        nalus
+    }
+
+    /// Emits the pending NAL units as a single NAL unit packet or an aggregation packet.
+    fn emit_aggregated(nalus: &mut Vec<&[u8]>, payloads: &mut Vec<Vec<u8>>) {
+        if nalus.len() <= 1 {
+            payloads.extend(nalus.drain(..).map(|n| n.to_vec()));
+            return;
+        }
+
+        // The F bit is set if any aggregated unit has it, LayerId and TID are the lowest.
+        let headers = nalus.iter().map(|n| H265NALUHeader::new(n[0], n[1]));
+        let f = headers.clone().any(|h| h.f());
+        let layer_id = headers.clone().map(|h| h.layer_id()).min().unwrap_or(0);
+        let tid = headers.map(|h| h.tid()).min().unwrap_or(0);
+
+        let header = (f as u16) << 15
+            | (H265NALU_AGGREGATION_PACKET_TYPE as u16) << 9
+            | (layer_id as u16) << 3
+            | tid as u16;
+
+        let size = nalus
+            .iter()
+            .map(|n| H265AP_NALU_LENGTH_SIZE + n.len())
+            .sum::<usize>();
+        let mut out = Vec::with_capacity(H265NALU_HEADER_SIZE + size);
+        out.extend(header.to_be_bytes());
+        for nalu in nalus.drain(..) {
+            out.extend((nalu.len() as u16).to_be_bytes());
+            out.extend_from_slice(nalu);
+        }
+
+        payloads.push(out);
+    }
+
+    /// Splits a NAL unit larger than the MTU into fragmentation units.
+    fn emit_fragmented(nalu: &[u8], mtu: usize, payloads: &mut Vec<Vec<u8>>) {
+        if mtu <= H265FU_HEADER_SIZE {
+            return;
+        }
+
+        let header = H265NALUHeader::new(nalu[0], nalu[1]);
+
+        // The payload header keeps F, LayerId and TID of the fragmented NAL unit.
+        let payload_header =
+            (header.0 & !(0b0111_1110 << 8)) | (H265NALU_FRAGMENTATION_UNIT_TYPE as u16) << 9;
+
+        // The NAL unit header is not included in the FU payload, the type is in the FU header.
+        let data = &nalu[H265NALU_HEADER_SIZE..];
+        let fragments = data.chunks(mtu - H265FU_HEADER_SIZE);
+        let count = fragments.len();
+
+        for (i, fragment) in fragments.enumerate() {
+            // +---------------+
+            // |0|1|2|3|4|5|6|7|
+            // +-+-+-+-+-+-+-+-+
+            // |S|E|  FuType   |
+            // +---------------+
+            let mut fu_header = header.nalu_type();
+            if i == 0 {
+                fu_header |= 1 << 7;
+            }
+            if i == count - 1 {
+                fu_header |= 1 << 6;
+            }
+
+            let mut out = Vec::with_capacity(H265FU_HEADER_SIZE + fragment.len());
+            out.extend(payload_header.to_be_bytes());
+            out.push(fu_header);
+            out.extend_from_slice(fragment);
+            payloads.push(out);
+        }
+    }
+}
+
+impl Packetizer for H265Packetizer {
+    /// Payload fragments a H265 access unit in Annex B format across one or more byte arrays.
+    fn packetize(&mut self, mtu: usize, payload: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
+        if payload.is_empty() || mtu == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut payloads = vec![];
+
+        let mut pending: Vec<&[u8]> = vec![];
+        let mut pending_size = H265NALU_HEADER_SIZE;
+
+        for nalu in Self::split_annex_b(payload) {
+            if nalu.len() <= H265NALU_HEADER_SIZE {
+                continue;
+            }
+
+            let nalu_type = H265NALUHeader::new(nalu[0], nalu[1]).nalu_type();
+            if nalu_type == H265NALU_AUD_TYPE || nalu_type == H265NALU_FILLER_TYPE {
+                continue;
+            }
+
+            let size = H265AP_NALU_LENGTH_SIZE + nalu.len();
+            if pending_size + size > mtu {
+                Self::emit_aggregated(&mut pending, &mut payloads);
+                pending_size = H265NALU_HEADER_SIZE;
+            }
+
+            if nalu.len() > mtu {
+                Self::emit_fragmented(nalu, mtu, &mut payloads);
+            } else {
+                pending.push(nalu);
+                pending_size += size;
+            }
+        }
+
+        Self::emit_aggregated(&mut pending, &mut payloads);
+
+        Ok(payloads)
+    }
+
+    fn is_marker(&mut self, _data: &[u8], _previous: Option<&[u8]>, last: bool) -> bool {
+        last
+    }
+}
+
 /// Depacketizes H265 RTP packets.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct H265Depacketizer {
@@ -741,7 +902,7 @@ impl Depacketizer for H265Depacketizer {
         &mut self,
         packet: &[u8],
         out: &mut Vec<u8>,
-        _: &mut CodecExtra,
+        extra: &mut CodecExtra,
     ) -> Result<(), PacketError> {
         if packet.len() <= H265NALU_HEADER_SIZE {
             return Err(PacketError::ErrShortPacket);
@@ -752,10 +913,28 @@ impl Depacketizer for H265Depacketizer {
             return Err(PacketError::ErrH265CorruptedPacket);
         }
 
+        // The output is an Annex B byte stream, same as the packetizer input.
         if header.is_paci_packet() {
             let mut decoded = H265PACIPacket::default();
             decoded.depacketize(packet)?;
 
+            // The PACI payload is the packet the PACI header was added to, with the
+            // payload header rebuilt from the copied F bit and type.
+            let inner_header = H265NALUHeader(
+                (decoded.a() as u16) << 15
+                    | (decoded.ctype() as u16) << 9
+                    | (header.0 & 0b0000_0001_1111_1111),
+            );
+            let mut inner = inner_header.0.to_be_bytes().to_vec();
+            inner.extend_from_slice(&decoded.payload);
+
+            if inner_header.is_aggregation_packet() || inner_header.is_fragmentation_unit() {
+                self.depacketize(&inner, out, extra)?;
+            } else {
+                out.extend_from_slice(ANNEXB_NALUSTART_CODE);
+                out.extend_from_slice(&inner);
+            }
+
             self.payload = H265Payload::H265PACIPacket(decoded);
         } else if header.is_fragmentation_unit() {
             let mut decoded = H265FragmentationUnitPacket::default();
@@ -763,6 +942,15 @@ impl Depacketizer for H265Depacketizer {
 
             decoded.depacketize(packet)?;
 
+            if decoded.fu_header.s() {
+                // The fragmented NAL unit header is the payload header with the FU type.
+                let nalu_header = (decoded.payload_header.0 & !(0b0111_1110 << 8))
+                    | (decoded.fu_header.fu_type() as u16) << 9;
+                out.extend_from_slice(ANNEXB_NALUSTART_CODE);
+                out.extend(nalu_header.to_be_bytes());
+            }
+            out.extend_from_slice(&decoded.payload);
+
             self.payload = H265Payload::H265FragmentationUnitPacket(decoded);
         } else if header.is_aggregation_packet() {
             let mut decoded = H265AggregationPacket::default();
@@ -770,6 +958,13 @@ impl Depacketizer for H265Depacketizer {
 
             decoded.depacketize(packet)?;
 
+            let first = decoded.first_unit.iter().map(|u| &u.nal_unit);
+            let others = decoded.other_units.iter().map(|u| &u.nal_unit);
+            for nalu in first.chain(others) {
+                out.extend_from_slice(ANNEXB_NALUSTART_CODE);
+                out.extend_from_slice(nalu);
+            }
+
             self.payload = H265Payload::H265AggregationPacket(decoded);
         } else {
             let mut decoded = H265SingleNALUnitPacket::default();
@@ -777,18 +972,28 @@ impl Depacketizer for H265Depacketizer {
 
             decoded.depacketize(packet)?;
 
+            out.extend_from_slice(ANNEXB_NALUSTART_CODE);
+            out.extend_from_slice(&packet[..H265NALU_HEADER_SIZE]);
+            out.extend_from_slice(&decoded.payload);
+
             self.payload = H265Payload::H265SingleNALUnitPacket(decoded);
         }
 
-        out.extend_from_slice(packet);
-
         Ok(())
     }
 
     /// is_partition_head checks if this is the head of a packetized nalu stream.
-    fn is_partition_head(&self, _payload: &[u8]) -> bool {
-        //TODO:
-        true
+    fn is_partition_head(&self, packet: &[u8]) -> bool {
+        if packet.len() <= H265NALU_HEADER_SIZE {
+            return false;
+        }
+
+        let header = H265NALUHeader::new(packet[0], packet[1]);
+        if header.is_fragmentation_unit() {
+            H265FragmentationUnitHeader(packet[2]).s()
+        } else {
+            true
+        }
     }
 
     fn is_partition_tail(&self, marker: bool, _payload: &[u8]) -> bool {
@@ -1697,4 +1902,93 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_h265_packetizer_aggregation() -> Result<()> {
+        let mut pck = H265Packetizer::default();
+
+        // AUD, VPS, SPS, PPS and an IDR slice with a mix of 3 and 4 byte start codes.
+        let au = b"\x00\x00\x00\x01\x46\x01\x10\
+            \x00\x00\x00\x01\x40\x01\x0c\x01\
+            \x00\x00\x01\x42\x01\x01\x01\x60\
+            \x00\x00\x00\x01\x44\x01\xc0\xf2\
+            \x00\x00\x01\x26\x01\xaf\x06\xb8\x63";
+
+        let payloads = pck.packetize(1200, au)?;
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(
+            payloads[0],
+            b"\x60\x01\
+              \x00\x04\x40\x01\x0c\x01\
+              \x00\x05\x42\x01\x01\x01\x60\
+              \x00\x04\x44\x01\xc0\xf2\
+              \x00\x06\x26\x01\xaf\x06\xb8\x63"
+        );
+
+        let mut depack = H265Depacketizer::default();
+        let mut out = vec![];
+        depack.depacketize(&payloads[0], &mut out, &mut CodecExtra::None)?;
+        assert_eq!(
+            out,
+            b"\x00\x00\x00\x01\x40\x01\x0c\x01\
+              \x00\x00\x00\x01\x42\x01\x01\x01\x60\
+              \x00\x00\x00\x01\x44\x01\xc0\xf2\
+              \x00\x00\x00\x01\x26\x01\xaf\x06\xb8\x63"
+        );
+        let H265Payload::H265AggregationPacket(ap) = depack.payload() else {
+            panic!("Expected aggregation packet");
+        };
+        assert_eq!(
+            ap.first_unit().unwrap().nal_unit(),
+            [0x40, 0x01, 0x0c, 0x01]
+        );
+        assert_eq!(ap.other_units().len(), 3);
+
+        // No room to aggregate, each NAL unit on its own.
+        let payloads = pck.packetize(14, au)?;
+        assert_eq!(payloads.len(), 4);
+        assert_eq!(payloads[3], [0x26, 0x01, 0xaf, 0x06, 0xb8, 0x63]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_h265_packetizer_fragmentation() -> Result<()> {
+        let mut pck = H265Packetizer::default();
+
+        // Single NAL unit without start code, TID 1.
+        let mut nalu = vec![0x02, 0x01];
+        nalu.extend((0..20).map(|i| i as u8));
+
+        let payloads = pck.packetize(100, &nalu)?;
+        assert_eq!(payloads, vec![nalu.clone()]);
+
+        let payloads = pck.packetize(10, &nalu)?;
+        assert_eq!(payloads.len(), 3);
+
+        let mut data = vec![];
+        let mut out = vec![];
+        for (i, p) in payloads.iter().enumerate() {
+            assert!(p.len() <= 10);
+
+            let mut depack = H265Depacketizer::default();
+            assert_eq!(depack.is_partition_head(p), i == 0);
+            depack.depacketize(p, &mut out, &mut CodecExtra::None)?;
+            let H265Payload::H265FragmentationUnitPacket(fu) = depack.payload() else {
+                panic!("Expected fragmentation unit");
+            };
+            assert_eq!(fu.payload_header().tid(), 1);
+            assert_eq!(fu.fu_header().fu_type(), 1);
+            assert_eq!(fu.fu_header().s(), i == 0);
+            assert_eq!(fu.fu_header().e(), i == payloads.len() - 1);
+            data.extend(fu.payload());
+        }
+        assert_eq!(data, &nalu[2..]);
+        assert_eq!(out[..4], *ANNEXB_NALUSTART_CODE);
+        assert_eq!(out[4..], nalu);
+
+        assert!(pck.packetize(3, &nalu)?.is_empty());
+
+        Ok(())
+    }
 }
@@ -8,17 +8,21 @@ use thiserror::Error;
 use crate::format::Codec;
 use crate::sdp::MediaType;
 
+mod av1;
+use av1::{Av1Depacketizer, Av1Packetizer};
+
 mod g7xx;
 use g7xx::{G711Packetizer, G722Packetizer};
 
 mod h264;
+pub use h264::H264CodecExtra;
 use h264::{H264Depacketizer, H264Packetizer};
 
 mod h264_profile;
 pub(crate) use h264_profile::H264ProfileLevel;
 
 mod h265;
-use h265::H265Depacketizer;
+use h265::{H265Depacketizer, H265Packetizer};
 
 mod opus;
 use opus::{OpusDepacketizer, OpusPacketizer};
@@ -28,6 +32,7 @@ pub use vp8::Vp8CodecExtra;
 use vp8::{Vp8Depacketizer, Vp8Packetizer};
 
 mod vp9;
+pub use vp9::{Vp9CodecExtra, Vp9PictureGroupPicture, Vp9ScalabilityStructure};
 use vp9::{Vp9Depacketizer, Vp9Packetizer};
 
 mod null;
@@ -79,12 +84,16 @@ pub(crate) trait Packetizer: fmt::Debug {
 ///
 /// Contains additional codec specific information which are deemed useful for
 /// managing and repackaging the sample
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum CodecExtra {
     /// No extra information available
     None,
     /// Codec extra parameters for VP8.
     Vp8(Vp8CodecExtra),
+    /// Codec extra parameters for VP9.
+    Vp9(Vp9CodecExtra),
+    /// Codec extra parameters for H264.
+    H264(H264CodecExtra),
 }
 
 /// Depacketizes an RTP payload.
@@ -124,6 +133,8 @@ pub enum PacketError {
     ErrH265CorruptedPacket,
     #[error("H265 invalid packet type")]
     ErrInvalidH265PacketType,
+    #[error("AV1 corrupted packet")]
+    ErrAv1CorruptedPacket,
     #[error("H264 StapA size larger than buffer: {0} > {1}")]
     StapASizeLargerThanBuffer(usize, usize),
     #[error("H264 NALU type is not handled: {0}")]
@@ -133,6 +144,7 @@ pub enum PacketError {
 /// Helper to replace Bytes. Provides get_u8 and get_u16 over some buffer of bytes.
 pub(crate) trait BitRead {
     fn remaining(&self) -> usize;
+    fn get_bit(&mut self) -> u8;
     fn get_u8(&mut self) -> u8;
     fn get_u16(&mut self) -> u16;
 }
@@ -143,6 +155,19 @@ impl BitRead for (&[u8], usize) {
         (self.0.len() * 8).saturating_sub(self.1)
     }
 
+    #[inline(always)]
+    fn get_bit(&mut self) -> u8 {
+        if self.remaining() == 0 {
+            panic!("Too few bits left");
+        }
+
+        let offs = self.1 / 8;
+        let shift = 7 - (self.1 % 8) as u32;
+        self.1 += 1;
+
+        (self.0[offs] >> shift) & 1
+    }
+
     #[inline(always)]
     fn get_u8(&mut self) -> u8 {
         if self.remaining() == 0 {
@@ -170,10 +195,11 @@ impl BitRead for (&[u8], usize) {
 
 #[derive(Debug)]
 pub(crate) enum CodecPacketizer {
+    Av1(Av1Packetizer),
     G711(G711Packetizer),
     G722(G722Packetizer),
     H264(H264Packetizer),
-    // H265() TODO
+    H265(H265Packetizer),
     Opus(OpusPacketizer),
     Vp8(Vp8Packetizer),
     Vp9(Vp9Packetizer),
@@ -183,6 +209,7 @@ pub(crate) enum CodecPacketizer {
 
 #[derive(Debug)]
 pub(crate) enum CodecDepacketizer {
+    Av1(Av1Depacketizer),
     H264(H264Depacketizer),
     H265(H265Depacketizer),
     Opus(OpusDepacketizer),
@@ -197,10 +224,10 @@ impl From<Codec> for CodecPacketizer {
         match c {
             Codec::Opus => CodecPacketizer::Opus(OpusPacketizer),
             Codec::H264 => CodecPacketizer::H264(H264Packetizer::default()),
-            Codec::H265 => unimplemented!("Missing packetizer for H265"),
+            Codec::H265 => CodecPacketizer::H265(H265Packetizer),
             Codec::Vp8 => CodecPacketizer::Vp8(Vp8Packetizer::default()),
             Codec::Vp9 => CodecPacketizer::Vp9(Vp9Packetizer::default()),
-            Codec::Av1 => unimplemented!("Missing packetizer for AV1"),
+            Codec::Av1 => CodecPacketizer::Av1(Av1Packetizer),
             Codec::Null => CodecPacketizer::Null(NullPacketizer),
             Codec::Rtx => panic!("Cant instantiate packetizer for RTX codec"),
             Codec::Unknown => panic!("Cant instantiate packetizer for unknown codec"),
@@ -217,7 +244,7 @@ impl From<Codec> for CodecDepacketizer {
             Codec::H265 => CodecDepacketizer::H265(H265Depacketizer::default()),
             Codec::Vp8 => CodecDepacketizer::Vp8(Vp8Depacketizer::default()),
             Codec::Vp9 => CodecDepacketizer::Vp9(Vp9Depacketizer::default()),
-            Codec::Av1 => unimplemented!("Missing depacketizer for AV1"),
+            Codec::Av1 => CodecDepacketizer::Av1(Av1Depacketizer::default()),
             Codec::Null => CodecDepacketizer::Null(NullDepacketizer),
             Codec::Rtx => panic!("Cant instantiate depacketizer for RTX codec"),
             Codec::Unknown => panic!("Cant instantiate depacketizer for unknown codec"),
@@ -230,9 +257,11 @@ impl Packetizer for CodecPacketizer {
     fn packetize(&mut self, mtu: usize, b: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
         use CodecPacketizer::*;
         match self {
+            Av1(v) => v.packetize(mtu, b),
             G711(v) => v.packetize(mtu, b),
             G722(v) => v.packetize(mtu, b),
             H264(v) => v.packetize(mtu, b),
+            H265(v) => v.packetize(mtu, b),
             Opus(v) => v.packetize(mtu, b),
             Vp8(v) => v.packetize(mtu, b),
             Vp9(v) => v.packetize(mtu, b),
@@ -243,10 +272,12 @@ impl Packetizer for CodecPacketizer {
 
     fn is_marker(&mut self, data: &[u8], previous: Option<&[u8]>, last: bool) -> bool {
         match self {
+            CodecPacketizer::Av1(v) => v.is_marker(data, previous, last),
             CodecPacketizer::G711(v) => v.is_marker(data, previous, last),
             CodecPacketizer::G722(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Opus(v) => v.is_marker(data, previous, last),
             CodecPacketizer::H264(v) => v.is_marker(data, previous, last),
+            CodecPacketizer::H265(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Vp8(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Vp9(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Null(v) => v.is_marker(data, previous, last),
@@ -264,6 +295,7 @@ impl Depacketizer for CodecDepacketizer {
     ) -> Result<(), PacketError> {
         use CodecDepacketizer::*;
         match self {
+            Av1(v) => v.depacketize(packet, out, extra),
             H264(v) => v.depacketize(packet, out, extra),
             H265(v) => v.depacketize(packet, out, extra),
             Opus(v) => v.depacketize(packet, out, extra),
@@ -277,6 +309,7 @@ impl Depacketizer for CodecDepacketizer {
     fn is_partition_head(&self, packet: &[u8]) -> bool {
         use CodecDepacketizer::*;
         match self {
+            Av1(v) => v.is_partition_head(packet),
             H264(v) => v.is_partition_head(packet),
             H265(v) => v.is_partition_head(packet),
             Opus(v) => v.is_partition_head(packet),
@@ -290,6 +323,7 @@ impl Depacketizer for CodecDepacketizer {
     fn is_partition_tail(&self, marker: bool, packet: &[u8]) -> bool {
         use CodecDepacketizer::*;
         match self {
+            Av1(v) => v.is_partition_tail(marker, packet),
             H264(v) => v.is_partition_tail(marker, packet),
             H265(v) => v.is_partition_tail(marker, packet),
             Opus(v) => v.is_partition_tail(marker, packet),
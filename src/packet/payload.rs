@@ -64,13 +64,20 @@ impl Payloader {
             // TODO: delegate to self.pack to decide whether this packet is nackable.
             let nackable = !is_audio;
 
+            let mut ext_vals = ext_vals.clone();
+            if let Some(dd) = &mut ext_vals.dependency_descriptor {
+                dd.start_of_frame = first;
+                dd.end_of_frame = last;
+                dd.attached_structure &= first;
+            }
+
             stream.write_rtp(
                 pt,
                 seq_no,
                 rtp_time.rebase(self.clock_rate.into()).numer() as u32,
                 wallclock,
                 marker,
-                ext_vals.clone(),
+                ext_vals,
                 nackable,
                 data,
             );
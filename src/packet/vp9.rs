@@ -13,6 +13,62 @@ const MAX_VP9REF_PICS: usize = 3;
 /// InitialPictureIDFn is a function that returns random initial picture ID.
 pub type InitialPictureIDFn = Arc<dyn (Fn() -> u16) + Send + Sync + UnwindSafe + RefUnwindSafe>;
 
+/// Vp9 information describing the depacketized data.
+///
+/// A picture with several spatial layers is depacketized as one sample, the layer
+/// fields are then those of the last (highest) spatial layer in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vp9CodecExtra {
+    /// True if the picture is predicted from earlier pictures, false for a keyframe
+    /// (or an upper spatial layer only predicted from lower layers).
+    pub inter_picture_predicted: bool,
+    /// True if the stream is in flexible mode, and the references are in `pdiff`.
+    pub flexible: bool,
+    /// 7 or 15 bit picture id, if present.
+    pub picture_id: Option<u16>,
+    /// Index of the vp9 temporal layer, if present.
+    pub temporal_layer_index: Option<u8>,
+    /// Index of the vp9 spatial layer, if present.
+    pub spatial_layer_index: Option<u8>,
+    /// True if this frame and subsequent ones on this temporal layer only depend on
+    /// lower temporal layers, i.e. it's possible to switch up to this layer.
+    pub switching_up_point: bool,
+    /// True if the frame depends on the lower spatial layer of the same picture.
+    pub inter_layer_dependency: bool,
+    /// True if the frame is not used as reference by the upper spatial layers.
+    pub not_upper_layer_reference: bool,
+    /// Picture id of layer 0 frames, in non-flexible mode if present.
+    pub tl0_picture_id: Option<u8>,
+    /// Differences to the picture id of the reference pictures, in flexible mode.
+    pub pdiff: [Option<u8>; MAX_VP9REF_PICS],
+    /// The scalability structure, typically sent with keyframes.
+    pub scalability_structure: Option<Arc<Vp9ScalabilityStructure>>,
+}
+
+/// Vp9 scalability structure (SS), describing the spatial layers of the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vp9ScalabilityStructure {
+    /// Number of spatial layers.
+    pub spatial_layers: u8,
+    /// Width and height of each spatial layer, if present.
+    ///
+    /// One entry per spatial layer.
+    pub resolutions: Option<Vec<(u16, u16)>>,
+    /// The pictures of the picture group (PG), if the PG description is present.
+    pub picture_group: Option<Vec<Vp9PictureGroupPicture>>,
+}
+
+/// A picture in the picture group (PG) description of a [`Vp9ScalabilityStructure`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vp9PictureGroupPicture {
+    /// Index of the temporal layer of the picture.
+    pub temporal_layer_index: u8,
+    /// True if it's possible to switch up to the temporal layer at this picture.
+    pub switching_up_point: bool,
+    /// Differences to the picture id of the reference pictures.
+    pub pdiff: Vec<u8>,
+}
+
 /// Packetizes VP9 RTP packets.
 #[derive(Default, Clone)]
 pub struct Vp9Packetizer {
@@ -200,7 +256,7 @@ impl Depacketizer for Vp9Depacketizer {
         &mut self,
         packet: &[u8],
         out: &mut Vec<u8>,
-        _: &mut CodecExtra,
+        extra: &mut CodecExtra,
     ) -> Result<(), PacketError> {
         if packet.is_empty() {
             return Err(PacketError::ErrShortPacket);
@@ -238,6 +294,53 @@ impl Depacketizer for Vp9Depacketizer {
 
         out.extend_from_slice(&packet[payload_index..]);
 
+        let mut pdiff = [None; MAX_VP9REF_PICS];
+        if self.f && self.p {
+            for (d, v) in pdiff.iter_mut().zip(&self.pdiff) {
+                *d = Some(*v);
+            }
+        }
+
+        // The SS is only in one of the packets, keep it for the rest of the sample.
+        let scalability_structure = if self.v {
+            let resolutions = self
+                .y
+                .then(|| self.width.iter().copied().zip(self.height.iter().copied()))
+                .map(|r| r.collect());
+            let picture_group = self.g.then(|| {
+                (0..self.ng as usize)
+                    .map(|i| Vp9PictureGroupPicture {
+                        temporal_layer_index: self.pgtid[i],
+                        switching_up_point: self.pgu[i],
+                        pdiff: self.pgpdiff[i].clone(),
+                    })
+                    .collect()
+            });
+            Some(Arc::new(Vp9ScalabilityStructure {
+                spatial_layers: self.ns + 1,
+                resolutions,
+                picture_group,
+            }))
+        } else if let CodecExtra::Vp9(v) = extra {
+            v.scalability_structure.take()
+        } else {
+            None
+        };
+
+        *extra = CodecExtra::Vp9(Vp9CodecExtra {
+            inter_picture_predicted: self.p,
+            flexible: self.f,
+            picture_id: self.i.then_some(self.picture_id),
+            temporal_layer_index: self.l.then_some(self.tid),
+            spatial_layer_index: self.l.then_some(self.sid),
+            switching_up_point: self.l && self.u,
+            inter_layer_dependency: self.l && self.d,
+            not_upper_layer_reference: self.z,
+            tl0_picture_id: (self.l && !self.f).then_some(self.tl0picidx),
+            pdiff,
+            scalability_structure,
+        });
+
         Ok(())
     }
 
@@ -365,6 +468,8 @@ impl Vp9Depacketizer {
         reader: &mut dyn BitRead,
         mut payload_index: usize,
     ) -> Result<usize, PacketError> {
+        self.pdiff.clear();
+
         let mut b = 1u8;
         while (b & 0x1) != 0 {
             if reader.remaining() == 0 {
@@ -374,7 +479,7 @@ impl Vp9Depacketizer {
             payload_index += 1;
 
             self.pdiff.push(b >> 1);
-            if self.pdiff.len() >= MAX_VP9REF_PICS {
+            if (b & 0x1) != 0 && self.pdiff.len() >= MAX_VP9REF_PICS {
                 return Err(PacketError::ErrTooManyPDiff);
             }
         }
@@ -421,6 +526,16 @@ impl Vp9Depacketizer {
         let ns = (self.ns + 1) as usize;
         self.ng = 0;
 
+        if ns > MAX_SPATIAL_LAYERS as usize {
+            return Err(PacketError::ErrTooManySpatialLayers);
+        }
+
+        self.width.clear();
+        self.height.clear();
+        self.pgtid.clear();
+        self.pgu.clear();
+        self.pgpdiff.clear();
+
         if self.y {
             if reader.remaining() < 4 * ns {
                 return Err(PacketError::ErrShortPacket);
@@ -712,6 +827,112 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_vp9_codec_extra() -> Result<(), PacketError> {
+        let mut p = Vp9Depacketizer::default();
+        let mut payload = Vec::new();
+        let mut extra = CodecExtra::None;
+
+        // Keyframe start with SS, picture id 0x0102, T:0 S:0, tl0picidx 5.
+        let first = &[
+            0xAA,
+            0x81,
+            0x02,
+            0x00,
+            0x05,
+            (1 << 5) | (1 << 4), // NS:1 Y:1 G:0
+            (640 >> 8) as u8,
+            (640 & 0xff) as u8,
+            (360 >> 8) as u8,
+            (360 & 0xff) as u8,
+            (1280 >> 8) as u8,
+            (1280 & 0xff) as u8,
+            (720 >> 8) as u8,
+            (720 & 0xff) as u8,
+            0xAA,
+        ];
+        // Same picture, S:1 with inter-layer dependency, end of frame.
+        let second = &[0xA4, 0x81, 0x02, 0x03, 0x05, 0xBB];
+
+        p.depacketize(first, &mut payload, &mut extra)?;
+        p.depacketize(second, &mut payload, &mut extra)?;
+        assert_eq!(payload, &[0xAA, 0xBB]);
+
+        let CodecExtra::Vp9(e) = extra else {
+            panic!("Expected Vp9 codec extra");
+        };
+        assert!(!e.inter_picture_predicted);
+        assert!(!e.flexible);
+        assert_eq!(e.picture_id, Some(0x0102));
+        assert_eq!(e.temporal_layer_index, Some(0));
+        assert_eq!(e.spatial_layer_index, Some(1));
+        assert!(e.inter_layer_dependency);
+        assert_eq!(e.tl0_picture_id, Some(5));
+        assert_eq!(e.pdiff, [None; 3]);
+
+        let ss = e.scalability_structure.expect("SS from the first packet");
+        assert_eq!(ss.spatial_layers, 2);
+        assert_eq!(ss.resolutions, Some(vec![(640, 360), (1280, 720)]));
+        assert_eq!(ss.picture_group, None);
+
+        // The picture group description.
+        let mut extra = CodecExtra::None;
+        p.depacketize(
+            &[
+                0x8A,
+                0x01,
+                (1 << 3), // NS:0 Y:0 G:1
+                2,
+                (1 << 4),            // T:0 U:1 R:0 -
+                (2 << 5) | (1 << 2), // T:2 U:0 R:1 -
+                33,
+                0xAA,
+            ],
+            &mut payload,
+            &mut extra,
+        )?;
+        let CodecExtra::Vp9(e) = extra else {
+            panic!("Expected Vp9 codec extra");
+        };
+        let ss = e.scalability_structure.expect("SS with PG");
+        assert_eq!(ss.spatial_layers, 1);
+        assert_eq!(ss.resolutions, None);
+        assert_eq!(
+            ss.picture_group,
+            Some(vec![
+                Vp9PictureGroupPicture {
+                    temporal_layer_index: 0,
+                    switching_up_point: true,
+                    pdiff: vec![],
+                },
+                Vp9PictureGroupPicture {
+                    temporal_layer_index: 2,
+                    switching_up_point: false,
+                    pdiff: vec![33],
+                },
+            ])
+        );
+
+        // Flexible mode references don't accumulate between packets.
+        for _ in 0..3 {
+            let mut extra = CodecExtra::None;
+            p.depacketize(
+                &[0xD0, 0x02, 0x03, 0x05, 0x06, 0xAA],
+                &mut payload,
+                &mut extra,
+            )?;
+            let CodecExtra::Vp9(e) = extra else {
+                panic!("Expected Vp9 codec extra");
+            };
+            assert!(e.flexible);
+            assert_eq!(e.tl0_picture_id, None);
+            assert_eq!(e.pdiff, [Some(1), Some(2), Some(3)]);
+            assert_eq!(e.scalability_structure, None);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_vp9_packetizer_payload() -> Result<(), PacketError> {
         let mut r0 = 8692;
@@ -0,0 +1,749 @@
+//! Dependency Descriptor RTP header extension, see the [AV1 RTP spec][1].
+//!
+//! The descriptor tells which layer a frame belongs to and what frames it depends on,
+//! without looking into the (possibly encrypted) payload. Most packets only reference a
+//! template in a [`FrameDependencyStructure`] that was sent earlier on the stream,
+//! which means parsing is stateful.
+//!
+//! [1]: https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension
+
+use std::sync::Arc;
+
+/// Template ids are 6 bits.
+const MAX_TEMPLATES: usize = 64;
+
+/// Longest value that fits a two-byte header extension element.
+const MAX_SERIALIZED_LEN: usize = 255;
+
+/// Parsed Dependency Descriptor of an RTP packet.
+///
+/// Frame dependency information is resolved against the
+/// [`FrameDependencyStructure`] in effect for the stream, i.e. the values are filled
+/// in from the template even when the packet itself only carries a template id.
+///
+/// When sending, `start_of_frame`, `end_of_frame` and `attached_structure` are set per
+/// packet by str0m. The structure is attached to the first packet of the frame if
+/// `attached_structure` is `true`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyDescriptor {
+    /// This is the first packet of the frame.
+    pub start_of_frame: bool,
+    /// This is the last packet of the frame.
+    pub end_of_frame: bool,
+    /// Id of the frame dependency template, 0-63.
+    pub template_id: u8,
+    /// Frame number, wrapping at 16 bits.
+    pub frame_number: u16,
+    /// Spatial layer of the frame.
+    pub spatial_id: u8,
+    /// Temporal layer of the frame.
+    pub temporal_id: u8,
+    /// How the frame relates to each decode target.
+    pub decode_target_indications: Vec<DecodeTargetIndication>,
+    /// Frame number differences to the frames this frame depends on.
+    pub frame_diffs: Vec<u16>,
+    /// Frame number differences to the previous frame in each chain.
+    pub chain_diffs: Vec<u8>,
+    /// Bit mask of active decode targets, lowest bit is decode target 0.
+    ///
+    /// `None` if not changed by this packet.
+    pub active_decode_targets: Option<u32>,
+    /// The structure the frame is described by.
+    pub structure: Arc<FrameDependencyStructure>,
+    /// Whether the structure is attached to this packet.
+    pub attached_structure: bool,
+}
+
+/// Layers and frame dependency templates of a stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameDependencyStructure {
+    /// Template id of the first template, 0-63.
+    pub template_id_offset: u8,
+    /// Number of decode targets, 1-32.
+    pub decode_target_count: u8,
+    /// Number of chains, 0 up to the number of decode targets.
+    pub chain_count: u8,
+    /// The chain protecting each decode target. Empty if there are no chains.
+    pub decode_target_protected_by_chain: Vec<u8>,
+    /// The templates, ordered by spatial id and then temporal id.
+    pub templates: Vec<FrameDependencyTemplate>,
+    /// Max render resolution (width, height) per spatial layer. Empty if not sent.
+    pub resolutions: Vec<(u16, u16)>,
+}
+
+/// Frame dependencies shared by many frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameDependencyTemplate {
+    /// Spatial layer of the frame.
+    pub spatial_id: u8,
+    /// Temporal layer of the frame.
+    pub temporal_id: u8,
+    /// How the frame relates to each decode target.
+    pub decode_target_indications: Vec<DecodeTargetIndication>,
+    /// Frame number differences to the frames this frame depends on, 1-16.
+    pub frame_diffs: Vec<u16>,
+    /// Frame number differences to the previous frame in each chain, 0-15.
+    pub chain_diffs: Vec<u8>,
+}
+
+/// How a frame relates to a decode target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeTargetIndication {
+    /// The frame is not part of the decode target.
+    NotPresent = 0,
+    /// The frame is part of the decode target, but no later frame depends on it.
+    Discardable = 1,
+    /// Forwarding of the decode target can start at this frame.
+    Switch = 2,
+    /// The frame is part of the decode target.
+    Required = 3,
+}
+
+impl DependencyDescriptor {
+    /// Max render resolution (width, height) of the frame's spatial layer, if known.
+    pub fn resolution(&self) -> Option<(u16, u16)> {
+        self.structure
+            .resolutions
+            .get(self.spatial_id as usize)
+            .copied()
+    }
+
+    /// Parses the descriptor using the last structure seen on the stream.
+    ///
+    /// Returns `None` if the buffer is malformed or it references a structure we don't have.
+    pub(crate) fn parse(
+        buf: &[u8],
+        structure: Option<&Arc<FrameDependencyStructure>>,
+    ) -> Option<Self> {
+        if buf.len() < 3 {
+            return None;
+        }
+
+        let mut r = BitReader::new(buf);
+
+        let start_of_frame = r.bit()?;
+        let end_of_frame = r.bit()?;
+        let template_id = r.bits(6)? as u8;
+        let frame_number = r.bits(16)? as u16;
+
+        let mut attached = None;
+        let mut active_decode_targets = None;
+        let mut custom_dtis = false;
+        let mut custom_fdiffs = false;
+        let mut custom_chains = false;
+
+        if buf.len() > 3 {
+            let structure_present = r.bit()?;
+            let active_present = r.bit()?;
+            custom_dtis = r.bit()?;
+            custom_fdiffs = r.bit()?;
+            custom_chains = r.bit()?;
+
+            if structure_present {
+                let s = FrameDependencyStructure::parse(&mut r)?;
+                active_decode_targets = Some(all_decode_targets(s.decode_target_count));
+                attached = Some(Arc::new(s));
+            }
+
+            if active_present {
+                let s = attached.as_ref().or(structure)?;
+                active_decode_targets = Some(r.bits(s.decode_target_count as usize)?);
+            }
+        }
+
+        let attached_structure = attached.is_some();
+        let structure = attached.or_else(|| structure.cloned())?;
+
+        let template = structure.template(template_id)?;
+
+        let decode_target_indications = if custom_dtis {
+            (0..structure.decode_target_count)
+                .map(|_| r.bits(2).map(DecodeTargetIndication::from))
+                .collect::<Option<Vec<_>>>()?
+        } else {
+            template.decode_target_indications.clone()
+        };
+
+        let frame_diffs = if custom_fdiffs {
+            let mut v = vec![];
+            loop {
+                let size = r.bits(2)? as usize;
+                if size == 0 {
+                    break;
+                }
+                v.push(r.bits(4 * size)? as u16 + 1);
+            }
+            v
+        } else {
+            template.frame_diffs.clone()
+        };
+
+        let chain_diffs = if custom_chains {
+            (0..structure.chain_count)
+                .map(|_| r.bits(8).map(|v| v as u8))
+                .collect::<Option<Vec<_>>>()?
+        } else {
+            template.chain_diffs.clone()
+        };
+
+        Some(DependencyDescriptor {
+            start_of_frame,
+            end_of_frame,
+            template_id,
+            frame_number,
+            spatial_id: template.spatial_id,
+            temporal_id: template.temporal_id,
+            decode_target_indications,
+            frame_diffs,
+            chain_diffs,
+            active_decode_targets,
+            structure,
+            attached_structure,
+        })
+    }
+
+    /// Writes the descriptor, only using the extended fields when they are needed.
+    ///
+    /// Returns `None` if the descriptor doesn't match its structure.
+    pub(crate) fn write_to(&self, buf: &mut [u8]) -> Option<usize> {
+        let s = &*self.structure;
+        let template = s.template(self.template_id)?;
+
+        let custom_dtis = self.decode_target_indications != template.decode_target_indications;
+        let custom_fdiffs = self.frame_diffs != template.frame_diffs;
+        let custom_chains = self.chain_diffs != template.chain_diffs;
+
+        // An attached structure implicitly activates all decode targets.
+        let active_present = match self.active_decode_targets {
+            Some(v) => !self.attached_structure || v != all_decode_targets(s.decode_target_count),
+            None => false,
+        };
+
+        let extended = self.attached_structure
+            || active_present
+            || custom_dtis
+            || custom_fdiffs
+            || custom_chains;
+
+        let len = buf.len().min(MAX_SERIALIZED_LEN);
+        let mut w = BitWriter::new(&mut buf[..len]);
+
+        w.bit(self.start_of_frame)?;
+        w.bit(self.end_of_frame)?;
+        w.bits(self.template_id as u32, 6)?;
+        w.bits(self.frame_number as u32, 16)?;
+
+        if !extended {
+            return w.finish();
+        }
+
+        w.bit(self.attached_structure)?;
+        w.bit(active_present)?;
+        w.bit(custom_dtis)?;
+        w.bit(custom_fdiffs)?;
+        w.bit(custom_chains)?;
+
+        if self.attached_structure {
+            s.write_to(&mut w)?;
+        }
+
+        if active_present {
+            let v = self.active_decode_targets?;
+            w.bits(v, s.decode_target_count as usize)?;
+        }
+
+        if custom_dtis {
+            if self.decode_target_indications.len() != s.decode_target_count as usize {
+                return None;
+            }
+            for dti in &self.decode_target_indications {
+                w.bits(*dti as u32, 2)?;
+            }
+        }
+
+        if custom_fdiffs {
+            for fdiff in &self.frame_diffs {
+                let v = (*fdiff as u32).checked_sub(1)?;
+                let size = (1..=3).find(|size| v < 1 << (4 * size))?;
+                w.bits(size, 2)?;
+                w.bits(v, 4 * size as usize)?;
+            }
+            w.bits(0, 2)?;
+        }
+
+        if custom_chains {
+            if self.chain_diffs.len() != s.chain_count as usize {
+                return None;
+            }
+            for chain_diff in &self.chain_diffs {
+                w.bits(*chain_diff as u32, 8)?;
+            }
+        }
+
+        w.finish()
+    }
+}
+
+impl FrameDependencyStructure {
+    fn template(&self, template_id: u8) -> Option<&FrameDependencyTemplate> {
+        let index = (template_id as usize + MAX_TEMPLATES - self.template_id_offset as usize)
+            % MAX_TEMPLATES;
+        self.templates.get(index)
+    }
+
+    fn parse(r: &mut BitReader) -> Option<Self> {
+        let template_id_offset = r.bits(6)? as u8;
+        let decode_target_count = r.bits(5)? as u8 + 1;
+
+        // template_layers()
+        let mut templates = vec![];
+        let mut spatial_id = 0;
+        let mut temporal_id = 0;
+        loop {
+            if templates.len() == MAX_TEMPLATES {
+                return None;
+            }
+            templates.push(FrameDependencyTemplate {
+                spatial_id,
+                temporal_id,
+                decode_target_indications: vec![],
+                frame_diffs: vec![],
+                chain_diffs: vec![],
+            });
+            match r.bits(2)? {
+                0 => {}
+                1 => temporal_id += 1,
+                2 => {
+                    spatial_id += 1;
+                    temporal_id = 0;
+                }
+                _ => break,
+            }
+        }
+
+        // template_dtis()
+        for t in &mut templates {
+            for _ in 0..decode_target_count {
+                t.decode_target_indications
+                    .push(DecodeTargetIndication::from(r.bits(2)?));
+            }
+        }
+
+        // template_fdiffs()
+        for t in &mut templates {
+            while r.bit()? {
+                t.frame_diffs.push(r.bits(4)? as u16 + 1);
+            }
+        }
+
+        // template_chains()
+        let chain_count = r.ns(decode_target_count as u32 + 1)? as u8;
+        let mut decode_target_protected_by_chain = vec![];
+        if chain_count > 0 {
+            for _ in 0..decode_target_count {
+                decode_target_protected_by_chain.push(r.ns(chain_count as u32)? as u8);
+            }
+            for t in &mut templates {
+                for _ in 0..chain_count {
+                    t.chain_diffs.push(r.bits(4)? as u8);
+                }
+            }
+        }
+
+        // render_resolutions()
+        let mut resolutions = vec![];
+        if r.bit()? {
+            for _ in 0..=spatial_id {
+                let width = r.bits(16)?.saturating_add(1).min(u16::MAX as u32);
+                let height = r.bits(16)?.saturating_add(1).min(u16::MAX as u32);
+                resolutions.push((width as u16, height as u16));
+            }
+        }
+
+        Some(FrameDependencyStructure {
+            template_id_offset,
+            decode_target_count,
+            chain_count,
+            decode_target_protected_by_chain,
+            templates,
+            resolutions,
+        })
+    }
+
+    fn write_to(&self, w: &mut BitWriter) -> Option<()> {
+        let dt_count = self.decode_target_count as usize;
+        let chain_count = self.chain_count as usize;
+
+        if self.template_id_offset as usize >= MAX_TEMPLATES
+            || !(1..=32).contains(&dt_count)
+            || chain_count > dt_count
+            || self.templates.is_empty()
+            || self.templates.len() > MAX_TEMPLATES
+        {
+            return None;
+        }
+
+        w.bits(self.template_id_offset as u32, 6)?;
+        w.bits(dt_count as u32 - 1, 5)?;
+
+        // template_layers(), the templates must be ordered by layer.
+        for pair in self.templates.windows(2) {
+            let (prev, next) = (&pair[0], &pair[1]);
+            let idc = if next.spatial_id == prev.spatial_id {
+                if next.temporal_id == prev.temporal_id {
+                    0
+                } else if next.temporal_id == prev.temporal_id + 1 {
+                    1
+                } else {
+                    return None;
+                }
+            } else if next.spatial_id == prev.spatial_id + 1 && next.temporal_id == 0 {
+                2
+            } else {
+                return None;
+            };
+            w.bits(idc, 2)?;
+        }
+        w.bits(3, 2)?;
+
+        // template_dtis()
+        for t in &self.templates {
+            if t.decode_target_indications.len() != dt_count {
+                return None;
+            }
+            for dti in &t.decode_target_indications {
+                w.bits(*dti as u32, 2)?;
+            }
+        }
+
+        // template_fdiffs()
+        for t in &self.templates {
+            for fdiff in &t.frame_diffs {
+                if !(1..=16).contains(fdiff) {
+                    return None;
+                }
+                w.bit(true)?;
+                w.bits(*fdiff as u32 - 1, 4)?;
+            }
+            w.bit(false)?;
+        }
+
+        // template_chains()
+        w.ns(dt_count as u32 + 1, chain_count as u32)?;
+        if chain_count > 0 {
+            if self.decode_target_protected_by_chain.len() != dt_count {
+                return None;
+            }
+            for chain in &self.decode_target_protected_by_chain {
+                if *chain as usize >= chain_count {
+                    return None;
+                }
+                w.ns(chain_count as u32, *chain as u32)?;
+            }
+            for t in &self.templates {
+                if t.chain_diffs.len() != chain_count || t.chain_diffs.iter().any(|c| *c > 15) {
+                    return None;
+                }
+                for chain_diff in &t.chain_diffs {
+                    w.bits(*chain_diff as u32, 4)?;
+                }
+            }
+        }
+
+        // render_resolutions()
+        w.bit(!self.resolutions.is_empty())?;
+        if !self.resolutions.is_empty() {
+            // Unwrap is OK, templates is not empty.
+            let max_spatial_id = self.templates.last().unwrap().spatial_id as usize;
+            if self.resolutions.len() != max_spatial_id + 1 {
+                return None;
+            }
+            for (width, height) in &self.resolutions {
+                w.bits((*width as u32).checked_sub(1)?, 16)?;
+                w.bits((*height as u32).checked_sub(1)?, 16)?;
+            }
+        }
+
+        Some(())
+    }
+}
+
+impl From<u32> for DecodeTargetIndication {
+    fn from(value: u32) -> Self {
+        match value & 3 {
+            1 => Self::Discardable,
+            2 => Self::Switch,
+            3 => Self::Required,
+            _ => Self::NotPresent,
+        }
+    }
+}
+
+fn all_decode_targets(count: u8) -> u32 {
+    ((1_u64 << count) - 1) as u32
+}
+
+/// Number of bits in a non-symmetric unsigned encoding of a value less than `n`.
+fn ns_bits(n: u32) -> (usize, u32) {
+    let w = 32 - n.leading_zeros() as usize;
+    let m = (1 << w) - n;
+    (w, m)
+}
+
+struct BitReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        BitReader { buf, pos: 0 }
+    }
+
+    fn bit(&mut self) -> Option<bool> {
+        let byte = self.buf.get(self.pos / 8)?;
+        let bit = byte >> (7 - self.pos % 8) & 1;
+        self.pos += 1;
+        Some(bit == 1)
+    }
+
+    /// Reads `n` bits, up to 32, most significant first.
+    fn bits(&mut self, n: usize) -> Option<u32> {
+        let mut v = 0_u32;
+        for _ in 0..n {
+            v = v << 1 | self.bit()? as u32;
+        }
+        Some(v)
+    }
+
+    /// Reads a non-symmetric unsigned value less than `n`.
+    fn ns(&mut self, n: u32) -> Option<u32> {
+        let (w, m) = ns_bits(n);
+        let v = self.bits(w - 1)?;
+        if v < m {
+            return Some(v);
+        }
+        let extra = self.bits(1)?;
+        Some((v << 1) - m + extra)
+    }
+}
+
+struct BitWriter<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        BitWriter { buf, pos: 0 }
+    }
+
+    fn bit(&mut self, bit: bool) -> Option<()> {
+        let byte = self.buf.get_mut(self.pos / 8)?;
+        let mask = 1 << (7 - self.pos % 8);
+        if bit {
+            *byte |= mask;
+        } else {
+            *byte &= !mask;
+        }
+        self.pos += 1;
+        Some(())
+    }
+
+    /// Writes the `n` lowest bits of `v`, most significant first.
+    fn bits(&mut self, v: u32, n: usize) -> Option<()> {
+        for i in (0..n).rev() {
+            self.bit(v >> i & 1 == 1)?;
+        }
+        Some(())
+    }
+
+    /// Writes a non-symmetric unsigned value `v` less than `n`.
+    fn ns(&mut self, n: u32, v: u32) -> Option<()> {
+        let (w, m) = ns_bits(n);
+        if v < m {
+            self.bits(v, w - 1)
+        } else {
+            self.bits((v + m) >> 1, w - 1)?;
+            self.bits((v + m) & 1, 1)
+        }
+    }
+
+    /// Zero pads to a whole byte and returns the number of bytes written.
+    fn finish(mut self) -> Option<usize> {
+        let pad = (8 - self.pos % 8) % 8;
+        self.bits(0, pad)?;
+        Some(self.pos / 8)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use DecodeTargetIndication::*;
+
+    /// One spatial layer, two temporal layers (L1T2).
+    fn l1t2() -> Arc<FrameDependencyStructure> {
+        Arc::new(FrameDependencyStructure {
+            template_id_offset: 60,
+            decode_target_count: 2,
+            chain_count: 1,
+            decode_target_protected_by_chain: vec![0, 0],
+            templates: vec![
+                FrameDependencyTemplate {
+                    spatial_id: 0,
+                    temporal_id: 0,
+                    decode_target_indications: vec![Switch, Switch],
+                    frame_diffs: vec![],
+                    chain_diffs: vec![0],
+                },
+                FrameDependencyTemplate {
+                    spatial_id: 0,
+                    temporal_id: 0,
+                    decode_target_indications: vec![Switch, Switch],
+                    frame_diffs: vec![2],
+                    chain_diffs: vec![2],
+                },
+                FrameDependencyTemplate {
+                    spatial_id: 0,
+                    temporal_id: 1,
+                    decode_target_indications: vec![NotPresent, Discardable],
+                    frame_diffs: vec![1],
+                    chain_diffs: vec![1],
+                },
+            ],
+            resolutions: vec![(640, 360)],
+        })
+    }
+
+    fn from_template(
+        structure: &Arc<FrameDependencyStructure>,
+        index: usize,
+    ) -> DependencyDescriptor {
+        let t = &structure.templates[index];
+        DependencyDescriptor {
+            start_of_frame: true,
+            end_of_frame: true,
+            template_id: ((structure.template_id_offset as usize + index) % 64) as u8,
+            frame_number: 0xfffe,
+            spatial_id: t.spatial_id,
+            temporal_id: t.temporal_id,
+            decode_target_indications: t.decode_target_indications.clone(),
+            frame_diffs: t.frame_diffs.clone(),
+            chain_diffs: t.chain_diffs.clone(),
+            active_decode_targets: None,
+            structure: structure.clone(),
+            attached_structure: false,
+        }
+    }
+
+    fn roundtrip(
+        dd: &DependencyDescriptor,
+        structure: Option<&Arc<FrameDependencyStructure>>,
+    ) -> (usize, DependencyDescriptor) {
+        let mut buf = [0; 255];
+        let n = dd.write_to(&mut buf).unwrap();
+        (
+            n,
+            DependencyDescriptor::parse(&buf[..n], structure).unwrap(),
+        )
+    }
+
+    #[test]
+    fn attached_structure() {
+        let structure = l1t2();
+        let mut dd = from_template(&structure, 0);
+        dd.attached_structure = true;
+
+        let (n, parsed) = roundtrip(&dd, None);
+        assert!(n > 3);
+
+        // An attached structure activates all decode targets.
+        dd.active_decode_targets = Some(0b11);
+        assert_eq!(parsed, dd);
+        assert_eq!(parsed.resolution(), Some((640, 360)));
+    }
+
+    #[test]
+    fn template_only() {
+        let structure = l1t2();
+        let dd = from_template(&structure, 2);
+
+        let (n, parsed) = roundtrip(&dd, Some(&structure));
+        assert_eq!(n, 3);
+        assert_eq!(parsed, dd);
+        assert_eq!(parsed.template_id, 62);
+        assert_eq!(parsed.temporal_id, 1);
+
+        // Without a structure we can't make sense of it.
+        let mut buf = [0; 255];
+        let n = dd.write_to(&mut buf).unwrap();
+        assert_eq!(DependencyDescriptor::parse(&buf[..n], None), None);
+    }
+
+    #[test]
+    fn custom_values() {
+        let structure = l1t2();
+        let mut dd = from_template(&structure, 1);
+        dd.start_of_frame = false;
+        dd.decode_target_indications = vec![Required, NotPresent];
+        dd.frame_diffs = vec![1, 17, 300, 4096];
+        dd.chain_diffs = vec![200];
+        dd.active_decode_targets = Some(0b01);
+
+        let (_, parsed) = roundtrip(&dd, Some(&structure));
+        assert_eq!(parsed, dd);
+    }
+
+    #[test]
+    fn invalid_values() {
+        let structure = l1t2();
+        let mut buf = [0; 255];
+
+        let mut dd = from_template(&structure, 1);
+        dd.frame_diffs = vec![4097];
+        assert_eq!(dd.write_to(&mut buf), None);
+
+        let mut dd = from_template(&structure, 1);
+        dd.template_id = 10;
+        assert_eq!(dd.write_to(&mut buf), None);
+
+        // Templates out of layer order.
+        let mut s = (*structure).clone();
+        s.templates.swap(1, 2);
+        let mut dd = from_template(&Arc::new(s), 0);
+        dd.attached_structure = true;
+        assert_eq!(dd.write_to(&mut buf), None);
+
+        // Truncated.
+        let dd = from_template(&structure, 0);
+        let n = dd.write_to(&mut buf).unwrap();
+        assert_eq!(
+            DependencyDescriptor::parse(&buf[..n - 1], Some(&structure)),
+            None
+        );
+    }
+
+    #[test]
+    fn non_symmetric() {
+        for n in 1..40 {
+            for v in 0..n {
+                let mut buf = [0; 2];
+                let mut w = BitWriter::new(&mut buf);
+                w.ns(n, v).unwrap();
+                let bits = w.pos;
+
+                let mut r = BitReader::new(&buf);
+                assert_eq!(r.ns(n), Some(v));
+                assert_eq!(r.pos, bits);
+            }
+        }
+
+        // ns(5) uses 2 bits for 0-2 and 3 bits for 3-4.
+        let mut buf = [0; 1];
+        let mut w = BitWriter::new(&mut buf);
+        w.ns(5, 3).unwrap();
+        assert_eq!(w.pos, 3);
+        assert_eq!(buf[0], 0b1100_0000);
+    }
+}
@@ -9,6 +9,7 @@ use std::panic::UnwindSafe;
 use std::str::from_utf8;
 use std::sync::Arc;
 
+use super::dependency_descriptor::DependencyDescriptor;
 use super::mtime::MediaTime;
 use super::{Mid, Rid};
 
@@ -52,6 +53,11 @@ pub enum Extension {
     FrameMarking,
     /// <http://www.webrtc.org/experiments/rtp-hdrext/color-space>
     ColorSpace,
+    /// <https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension>
+    ///
+    /// Layer and frame dependencies for SVC forwarding. This is not in the standard
+    /// mapping, it must be set with [`ExtensionMap::set()`].
+    DependencyDescriptor,
 
     /// Not recognized URI, but it could still be user parseable.
     #[doc(hidden)]
@@ -152,6 +158,10 @@ const EXT_URI: &[(Extension, &str)] = &[
         Extension::ColorSpace,
         "http://www.webrtc.org/experiments/rtp-hdrext/color-space",
     ),
+    (
+        Extension::DependencyDescriptor,
+        "https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension",
+    ),
 ];
 
 impl Extension {
@@ -247,6 +257,7 @@ impl Extension {
                 | VideoTiming
                 | FrameMarking
                 | ColorSpace
+                | DependencyDescriptor
         )
     }
 }
@@ -373,62 +384,53 @@ impl ExtensionMap {
         x
     }
 
-    // https://tools.ietf.org/html/rfc5285
-    pub(crate) fn parse(&self, mut buf: &[u8], ext_vals: &mut ExtensionValues) {
-        loop {
-            if buf.is_empty() {
-                return;
-            }
-
-            if buf[0] == 0 {
-                // padding
-                buf = &buf[1..];
-                continue;
-            }
-
-            let id = buf[0] >> 4;
-            let len = (buf[0] & 0xf) as usize + 1;
-            buf = &buf[1..];
-
-            if id == 15 {
-                // If the ID value 15 is
-                // encountered, its length field should be ignored, processing of the
-                // entire extension should terminate at that point, and only the
-                // extension elements present prior to the element with ID 15
-                // considered.
-                return;
-            }
-
-            if buf.len() < len {
-                trace!("Not enough type ext len: {} < {}", buf.len(), len);
-                return;
-            }
-
-            let ext_buf = &buf[..len];
+    // https://tools.ietf.org/html/rfc8285
+    pub(crate) fn parse(&self, buf: &[u8], two_byte: bool, ext_vals: &mut ExtensionValues) {
+        for (id, ext_buf) in ext_elements(buf, two_byte) {
             if let Some(ext) = self.lookup(id) {
                 ext.parse_value(ext_buf, ext_vals);
             }
-
-            buf = &buf[len..];
         }
     }
 
-    pub(crate) fn write_to(&self, ext_buf: &mut [u8], ev: &ExtensionValues) -> usize {
+    /// Writes the values using the one-byte or two-byte header form.
+    ///
+    /// Values too long for the form are skipped. Returns the written length and
+    /// whether any value was skipped.
+    pub(crate) fn write_to(
+        &self,
+        ext_buf: &mut [u8],
+        ev: &ExtensionValues,
+        two_byte: bool,
+    ) -> (usize, bool) {
         let orig_len = ext_buf.len();
         let mut b = ext_buf;
+        let header_len = if two_byte { 2 } else { 1 };
+        let max_len = if two_byte { 255 } else { 16 };
+        let mut skipped = false;
 
         for (idx, x) in self.0.iter().enumerate() {
             if let Some(v) = x {
-                if let Some(n) = v.ext.write_to(&mut b[1..], ev) {
-                    assert!(n <= 16);
-                    assert!(n > 0);
-                    b[0] = (idx as u8 + 1) << 4 | (n as u8 - 1);
-                    b = &mut b[1 + n..];
+                if let Some(n) = v.ext.write_to(&mut b[header_len..], ev) {
+                    if n > max_len {
+                        trace!("Skip too long extension value ({}): {:?}", n, v.ext);
+                        skipped = true;
+                        continue;
+                    }
+                    let id = idx as u8 + 1;
+                    if two_byte {
+                        b[0] = id;
+                        b[1] = n as u8;
+                    } else {
+                        assert!(n > 0);
+                        b[0] = id << 4 | (n as u8 - 1);
+                    }
+                    b = &mut b[header_len + n..];
                 }
             }
         }
 
-        orig_len - b.len()
+        (orig_len - b.len(), skipped)
     }
 
     pub(crate) fn remap(&mut self, remote_exts: &[(u8, &Extension)]) {
@@ -481,6 +483,56 @@ impl ExtensionMap {
     }
 }
 
+/// The (id, value) elements in the one-byte or two-byte header extension form.
+pub(crate) fn ext_elements(mut buf: &[u8], two_byte: bool) -> impl Iterator<Item = (u8, &[u8])> {
+    std::iter::from_fn(move || loop {
+        if buf.is_empty() {
+            return None;
+        }
+
+        if buf[0] == 0 {
+            // padding
+            buf = &buf[1..];
+            continue;
+        }
+
+        let (id, len) = if two_byte {
+            if buf.len() < 2 {
+                trace!("Not enough two-byte ext header");
+                return None;
+            }
+            let id_len = (buf[0], buf[1] as usize);
+            buf = &buf[2..];
+            id_len
+        } else {
+            let id = buf[0] >> 4;
+            let len = (buf[0] & 0xf) as usize + 1;
+            buf = &buf[1..];
+
+            if id == 15 {
+                // If the ID value 15 is
+                // encountered, its length field should be ignored, processing of the
+                // entire extension should terminate at that point, and only the
+                // extension elements present prior to the element with ID 15
+                // considered.
+                return None;
+            }
+
+            (id, len)
+        };
+
+        if buf.len() < len {
+            trace!("Not enough type ext len: {} < {}", buf.len(), len);
+            return None;
+        }
+
+        let (ext_buf, rest) = buf.split_at(len);
+        buf = rest;
+
+        return Some((id, ext_buf));
+    })
+}
+
 const FIXED_POINT_6_18: i64 = 262_144; // 2 ^ 18
 
 impl Extension {
@@ -570,6 +622,10 @@ impl Extension {
                 // TODO HDR color space
                 None
             }
+            DependencyDescriptor => {
+                let v = ev.dependency_descriptor.as_ref()?;
+                v.write_to(buf)
+            }
             UnknownUri(_, serializer) => {
                 let n = serializer.write_to(buf, ev);
 
@@ -676,6 +732,9 @@ impl Extension {
             ColorSpace => {
                 // TODO HDR color space
             }
+            DependencyDescriptor => {
+                // Needs the structure from earlier packets, parsed per stream.
+            }
             UnknownUri(_, serializer) => {
                 let success = serializer.parse_value(buf, ev);
                 if !success {
@@ -729,6 +788,11 @@ pub struct ExtensionValues {
     #[doc(hidden)]
     pub frame_mark: Option<u32>,
 
+    /// Layer and frame dependencies of the frame, for SVC forwarding decisions.
+    ///
+    /// Requires [`Extension::DependencyDescriptor`] to be mapped.
+    pub dependency_descriptor: Option<Box<DependencyDescriptor>>,
+
     /// User values for [`ExtensionSerializer`] to parse into and write from.
     pub user_values: UserExtensionValues,
 }
@@ -880,6 +944,13 @@ impl fmt::Debug for ExtensionValues {
         if let Some(t) = &self.frame_mark {
             write!(f, " frame_mark: {t}")?;
         }
+        if let Some(t) = &self.dependency_descriptor {
+            write!(
+                f,
+                " dependency_descriptor: (frame: {} spatial: {} temporal: {})",
+                t.frame_number, t.spatial_id, t.temporal_id
+            )?;
+        }
 
         write!(f, " }}")?;
         Ok(())
@@ -917,6 +988,7 @@ impl fmt::Display for Extension {
                 RtpMid => "mid",
                 FrameMarking => "frame-marking07",
                 ColorSpace => "color-space",
+                DependencyDescriptor => "dependency-descriptor",
                 UnknownUri(uri, _) => uri,
             }
         )
@@ -980,6 +1052,7 @@ impl PartialEq for Extension {
             (Extension::RtpMid, Extension::RtpMid) => true,
             (Extension::FrameMarking, Extension::FrameMarking) => true,
             (Extension::ColorSpace, Extension::ColorSpace) => true,
+            (Extension::DependencyDescriptor, Extension::DependencyDescriptor) => true,
             (Extension::UnknownUri(uri1, _), Extension::UnknownUri(uri2, _)) => uri1 == uri2,
             _ => false,
         }
@@ -1002,10 +1075,10 @@ mod test {
         };
 
         let mut buf = vec![0_u8; 8];
-        exts.write_to(&mut buf[..], &ev);
+        exts.write_to(&mut buf[..], &ev, false);
 
         let mut ev2 = ExtensionValues::default();
-        exts.parse(&buf, &mut ev2);
+        exts.parse(&buf, false, &mut ev2);
 
         assert_eq!(ev.abs_send_time, ev2.abs_send_time);
     }
@@ -1021,10 +1094,10 @@ mod test {
         };
 
         let mut buf = vec![0_u8; 8];
-        exts.write_to(&mut buf[..], &ev);
+        exts.write_to(&mut buf[..], &ev, false);
 
         let mut ev2 = ExtensionValues::default();
-        exts.parse(&buf, &mut ev2);
+        exts.parse(&buf, false, &mut ev2);
 
         assert_eq!(ev.play_delay_min, ev2.play_delay_min);
         assert_eq!(ev.play_delay_max, ev2.play_delay_max);
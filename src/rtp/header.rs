@@ -1,6 +1,6 @@
 #![allow(clippy::unusual_byte_groupings)]
 
-use super::ext::{ExtensionMap, ExtensionValues};
+use super::ext::{ext_elements, ExtensionMap, ExtensionValues};
 use super::{Pt, SeqNo, Ssrc, MAX_BLANK_PADDING_PAYLOAD_SIZE};
 
 /// Parsed header from an RTP packet.
@@ -33,7 +33,12 @@ pub struct RtpHeader {
 }
 
 impl RtpHeader {
-    pub(crate) fn write_to(&self, buf: &mut [u8], exts: &ExtensionMap) -> usize {
+    pub(crate) fn write_to(
+        &self,
+        buf: &mut [u8],
+        exts: &ExtensionMap,
+        two_byte_allowed: bool,
+    ) -> usize {
         buf[0] = 0b10_0_0_0000
             | if self.has_padding { 1 << 5 } else { 0 }
             | if self.has_extension { 1 << 4 } else { 0 };
@@ -45,10 +50,16 @@ impl RtpHeader {
         buf[4..8].copy_from_slice(&self.timestamp.to_be_bytes());
         buf[8..12].copy_from_slice(&self.ssrc.to_be_bytes());
 
-        buf[12..14].copy_from_slice(&0xbede_u16.to_be_bytes());
-
         let ext_buf = &mut buf[16..];
-        let mut ext_len = exts.write_to(ext_buf, &self.ext_vals);
+
+        // The one-byte form unless a value is too long for it, see RFC 8285. The
+        // two-byte form is only used if the remote signaled a=extmap-allow-mixed.
+        let (mut ext_len, skipped) = exts.write_to(ext_buf, &self.ext_vals, false);
+        let mut ext_type = 0xbede_u16;
+        if skipped && two_byte_allowed {
+            ext_len = exts.write_to(ext_buf, &self.ext_vals, true).0;
+            ext_type = 0x1000;
+        }
 
         let pad = 4 - ext_len % 4;
         if pad < 4 {
@@ -58,8 +69,9 @@ impl RtpHeader {
             }
         }
 
-        let bede_len = (ext_len / 4) as u16;
-        buf[14..16].copy_from_slice(&bede_len.to_be_bytes());
+        let ext_words = (ext_len / 4) as u16;
+        buf[12..14].copy_from_slice(&ext_type.to_be_bytes());
+        buf[14..16].copy_from_slice(&ext_words.to_be_bytes());
 
         16 + ext_len
     }
@@ -192,9 +204,11 @@ impl RtpHeader {
                 return None;
             }
 
+            // each media has a specific extmap mapping.
             if ext_type == 0xbede {
-                // each media has a specific extmap mapping.
-                exts.parse(&buf[..ext_len], &mut ext);
+                exts.parse(&buf[..ext_len], false, &mut ext);
+            } else if ext_type & 0xfff0 == 0x1000 {
+                exts.parse(&buf[..ext_len], true, &mut ext);
             }
 
             &buf[ext_len..]
@@ -220,6 +234,34 @@ impl RtpHeader {
         Some(ret)
     }
 
+    /// Raw value of the header extension with `id` in an RTP packet.
+    pub(crate) fn ext_value(buf: &[u8], id: u8) -> Option<&[u8]> {
+        let has_extension = buf.first()? & 0b0001_0000 > 0;
+        if !has_extension {
+            return None;
+        }
+
+        let csrc_len = 4 * (buf[0] & 0b0000_1111) as usize;
+        let buf = buf.get(12 + csrc_len..)?;
+        if buf.len() < 4 {
+            return None;
+        }
+
+        let ext_type = u16::from_be_bytes([buf[0], buf[1]]);
+        let ext_len = u16::from_be_bytes([buf[2], buf[3]]) as usize * 4;
+        let ext_buf = buf.get(4..4 + ext_len)?;
+
+        let two_byte = match ext_type {
+            0xbede => false,
+            t if t & 0xfff0 == 0x1000 => true,
+            _ => return None,
+        };
+
+        ext_elements(ext_buf, two_byte)
+            .find(|(i, _)| *i == id)
+            .map(|(_, v)| v)
+    }
+
     /// For RTX the original sequence number is inserted before the RTP payload.
     pub fn read_original_sequence_number(buf: &[u8], seq_no: &mut u16) -> usize {
         if buf.len() < 2 {
@@ -326,9 +368,12 @@ impl Default for RtpHeader {
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+
     use crate::{
         io::DATAGRAM_MAX_PACKET_SIZE,
-        rtp_::{Extension, MediaTime},
+        rtp_::{DecodeTargetIndication, DependencyDescriptor, Extension, MediaTime},
+        rtp_::{FrameDependencyStructure, FrameDependencyTemplate},
     };
 
     use super::*;
@@ -454,7 +499,7 @@ mod test {
                 ..Default::default()
             };
             let mut buf = vec![0; DATAGRAM_MAX_PACKET_SIZE];
-            let n = header.write_to(&mut buf[..], exts);
+            let n = header.write_to(&mut buf[..], exts, false);
             buf.truncate(n);
 
             buf
@@ -576,6 +621,84 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_two_byte_header_extension() {
+        let template = FrameDependencyTemplate {
+            spatial_id: 0,
+            temporal_id: 0,
+            decode_target_indications: vec![DecodeTargetIndication::Required; 4],
+            frame_diffs: vec![1],
+            chain_diffs: vec![],
+        };
+        let structure = Arc::new(FrameDependencyStructure {
+            template_id_offset: 0,
+            decode_target_count: 4,
+            chain_count: 0,
+            decode_target_protected_by_chain: vec![],
+            templates: vec![template.clone(); 6],
+            resolutions: vec![],
+        });
+        let dd = DependencyDescriptor {
+            start_of_frame: true,
+            end_of_frame: true,
+            template_id: 0,
+            frame_number: 1,
+            spatial_id: 0,
+            temporal_id: 0,
+            decode_target_indications: template.decode_target_indications,
+            frame_diffs: template.frame_diffs,
+            chain_diffs: vec![],
+            active_decode_targets: Some(0b1111),
+            structure,
+            attached_structure: true,
+        };
+
+        let mut exts = ExtensionMap::empty();
+        exts.set(3, Extension::AudioLevel);
+        exts.set(5, Extension::DependencyDescriptor);
+
+        let header = RtpHeader {
+            payload_type: 96.into(),
+            ext_vals: ExtensionValues {
+                audio_level: Some(-42),
+                voice_activity: Some(false),
+                dependency_descriptor: Some(Box::new(dd.clone())),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Without a=extmap-allow-mixed the descriptor is skipped.
+        let mut buf = vec![0; DATAGRAM_MAX_PACKET_SIZE];
+        let n = header.write_to(&mut buf[..], &exts, false);
+        buf.truncate(n);
+
+        assert_eq!(&buf[12..14], &[0xbe, 0xde]);
+        assert_eq!(&buf[16..18], &[0x30, 170]);
+        assert_eq!(RtpHeader::ext_value(&buf, 5), None);
+
+        let parsed = RtpHeader::parse(&buf, &exts).unwrap();
+        assert_eq!(parsed.header_len, n);
+        assert_eq!(parsed.ext_vals.audio_level, Some(-42));
+        assert_eq!(parsed.ext_vals.dependency_descriptor, None);
+
+        let mut buf = vec![0; DATAGRAM_MAX_PACKET_SIZE];
+        let n = header.write_to(&mut buf[..], &exts, true);
+        buf.truncate(n);
+
+        // The descriptor is too long for the one-byte form.
+        assert_eq!(&buf[12..14], &[0x10, 0x00]);
+        assert_eq!(&buf[16..19], &[3, 1, 170]);
+
+        let parsed = RtpHeader::parse(&buf, &exts).unwrap();
+        assert_eq!(parsed.header_len, n);
+        assert_eq!(parsed.ext_vals.audio_level, Some(-42));
+
+        let raw = RtpHeader::ext_value(&buf, 5).unwrap();
+        assert!(raw.len() > 16);
+        assert_eq!(DependencyDescriptor::parse(raw, None), Some(dd));
+    }
+
     #[test]
     fn truncate_off_srtp_padding() {
         let truncate = |mut payload| -> Result<Vec<u8>, ()> {
@@ -1,6 +1,5 @@
 use std::io;
 
-use openssl::error::ErrorStack;
 use thiserror::Error;
 
 mod id;
@@ -10,6 +9,10 @@ mod ext;
 pub use ext::{Extension, ExtensionMap, ExtensionSerializer, ExtensionValues};
 pub use ext::{UserExtensionValues, VideoOrientation};
 
+mod dependency_descriptor;
+pub use dependency_descriptor::{DecodeTargetIndication, DependencyDescriptor};
+pub use dependency_descriptor::{FrameDependencyStructure, FrameDependencyTemplate};
+
 mod dir;
 pub use dir::Direction;
 
@@ -21,7 +24,7 @@ pub use header::RtpHeader;
 pub(crate) use header::{extend_u15, extend_u16, extend_u32, extend_u7, extend_u8};
 
 mod srtp;
-pub(crate) use srtp::SrtpContext;
+pub use srtp::SrtpContext;
 pub(crate) use srtp::{SRTCP_OVERHEAD, SRTP_BLOCK_SIZE, SRTP_OVERHEAD};
 
 mod rtcp;
@@ -37,8 +40,9 @@ pub const MAX_BLANK_PADDING_PAYLOAD_SIZE: usize = 224;
 #[derive(Debug, Error)]
 pub enum RtpError {
     /// Some error from OpenSSL layer (used for SRTP).
+    #[cfg(feature = "openssl")]
     #[error("{0}")]
-    OpenSsl(#[from] ErrorStack),
+    OpenSsl(#[from] openssl::error::ErrorStack),
 
     /// Other IO errors.
     #[error("{0}")]
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use openssl::symm::{Cipher, Crypter, Mode};
+use thiserror::Error;
 
 use crate::dtls::KeyingMaterial;
 use crate::dtls::SrtpProfile;
@@ -8,6 +8,22 @@ use crate::io::Sha1;
 
 use super::header::RtpHeader;
 
+#[cfg(feature = "openssl")]
+#[cfg_attr(feature = "rust-crypto", allow(dead_code))]
+mod ossl;
+
+#[cfg(feature = "rust-crypto")]
+mod rust_crypto;
+
+// RustCrypto is used when both backends are enabled.
+#[cfg(all(feature = "openssl", not(feature = "rust-crypto")))]
+use ossl as backend;
+#[cfg(feature = "rust-crypto")]
+use rust_crypto as backend;
+
+#[cfg(not(any(feature = "openssl", feature = "rust-crypto")))]
+compile_error!("SRTP requires either the \"openssl\" or the \"rust-crypto\" feature");
+
 // Common among various profiles(defined in RFC3711 Section 4.3)
 const LABEL_RTP_AES: u8 = 0;
 const LABEL_RTP_AUTHENTICATION_KEY: u8 = 1;
@@ -27,69 +43,93 @@ const LABEL_RTCP_SALT: u8 = 5;
 
 pub const SRTP_BLOCK_SIZE: usize = 16;
 const SRTCP_INDEX_LEN: usize = 4;
-const MAX_TAG_LEN: usize = aead_aes_128_gcm::TAG_LEN;
+// The GCM tag is the same for both key lengths, and longer than the HMAC tag.
+const MAX_TAG_LEN: usize = aead_aes_gcm::TAG_LEN;
 pub const SRTCP_OVERHEAD: usize = MAX_TAG_LEN + SRTCP_INDEX_LEN;
 pub const SRTP_OVERHEAD: usize = MAX_TAG_LEN;
 
+const AES_128_KEY_LEN: usize = 16;
+const AES_256_KEY_LEN: usize = 32;
+
 impl SrtpContext {
     /// Create an SRTP context for the relevant profile using the provided keying material.
     pub fn new(profile: SrtpProfile, mat: &KeyingMaterial, left: bool) -> Self {
-        match profile {
-            SrtpProfile::Aes128CmSha1_80 => {
-                use aes_128_cm_sha1_80::{KEY_LEN, SALT_LEN};
-
-                let key = SrtpKey::<KEY_LEN, SALT_LEN>::new(mat, left);
+        // layout in KeyingMaterial is [key_left, key_right, salt_left, salt_right]
+        let ml = profile.master_key_len();
+        let sl = profile.master_salt_len();
 
-                let (rtp, rtcp) = Derived::aes_128_cm_sha1_80(&key);
+        // Invariant
+        assert!(
+            mat.len() == profile.keying_material_len(),
+            "The KeyingMaterial must be ML * 2 + SL * 2 in length"
+        );
 
-                SrtpContext {
-                    rtp,
-                    rtcp,
-                    srtcp_index: 0,
-                }
-            }
-            SrtpProfile::AeadAes128Gcm => {
-                use aead_aes_128_gcm::{KEY_LEN, SALT_LEN};
+        // offset key, offset salt
+        let (ok, os) = if left { (0, ml * 2) } else { (ml, ml * 2 + sl) };
 
-                let key = SrtpKey::<KEY_LEN, SALT_LEN>::new(mat, left);
+        Self::from_master_key(profile, &mat[ok..(ok + ml)], &mat[os..(os + sl)])
+    }
 
-                let (rtp, rtcp) = Derived::aead_aes_128_gcm(&key);
+    /// Create an SRTP context for the relevant profile from a master key and salt.
+    ///
+    /// This is used when the keys are exchanged with SDES rather than DTLS.
+    pub fn from_master_key(profile: SrtpProfile, key: &[u8], salt: &[u8]) -> Self {
+        use aead_aes_gcm::SALT_LEN as GCM_SALT_LEN;
+        use aes_cm_sha1_80::SALT_LEN as CM_SALT_LEN;
 
-                SrtpContext {
-                    rtp,
-                    rtcp,
-                    srtcp_index: 0,
-                }
+        let (rtp, rtcp) = match profile {
+            SrtpProfile::Aes128CmSha1_80 => {
+                let key = SrtpKey::<AES_128_KEY_LEN, CM_SALT_LEN>::new(key, salt);
+                Derived::aes_cm_sha1_80(profile, &key)
+            }
+            SrtpProfile::Aes256CmSha1_80 => {
+                let key = SrtpKey::<AES_256_KEY_LEN, CM_SALT_LEN>::new(key, salt);
+                Derived::aes_cm_sha1_80(profile, &key)
+            }
+            SrtpProfile::AeadAes128Gcm => {
+                let key = SrtpKey::<AES_128_KEY_LEN, GCM_SALT_LEN>::new(key, salt);
+                Derived::aead_aes_gcm(profile, &key)
+            }
+            SrtpProfile::AeadAes256Gcm => {
+                let key = SrtpKey::<AES_256_KEY_LEN, GCM_SALT_LEN>::new(key, salt);
+                Derived::aead_aes_gcm(profile, &key)
             }
+        };
+
+        SrtpContext {
+            rtp,
+            rtcp,
+            srtcp_index: 0,
         }
     }
 
     #[cfg(test)]
     fn new_aead_aes_128_gcm(
-        rtp_key: [u8; aead_aes_128_gcm::KEY_LEN],
-        rtp_salt: [u8; aead_aes_128_gcm::SALT_LEN],
-        rtcp_key: [u8; aead_aes_128_gcm::KEY_LEN],
-        rtcp_salt: [u8; aead_aes_128_gcm::SALT_LEN],
+        rtp_key: [u8; AES_128_KEY_LEN],
+        rtp_salt: [u8; aead_aes_gcm::SALT_LEN],
+        rtcp_key: [u8; AES_128_KEY_LEN],
+        rtcp_salt: [u8; aead_aes_gcm::SALT_LEN],
         srtcp_index: u32,
     ) -> Self {
-        use aead_aes_128_gcm::*;
-
         Self {
-            rtp: Derived::AeadAes128Gcm {
+            rtp: Derived::AeadAesGcm {
+                profile: SrtpProfile::AeadAes128Gcm,
                 salt: rtp_salt,
-                enc: Encrypter::new(&rtp_key),
-                dec: Decrypter::new(&rtp_key),
+                enc: backend::AesGcm::new(&rtp_key, true),
+                dec: backend::AesGcm::new(&rtp_key, false),
             },
-            rtcp: Derived::AeadAes128Gcm {
+            rtcp: Derived::AeadAesGcm {
+                profile: SrtpProfile::AeadAes128Gcm,
                 salt: rtcp_salt,
-                enc: Encrypter::new(&rtcp_key),
-                dec: Decrypter::new(&rtcp_key),
+                enc: backend::AesGcm::new(&rtcp_key, true),
+                dec: backend::AesGcm::new(&rtcp_key, false),
             },
             srtcp_index,
         }
     }
 }
 
+/// SRTP and SRTCP keys and ciphers for one direction of a session.
 #[derive(Debug)]
 pub struct SrtpContext {
     /// Encryption/decryption derived from srtp_key for RTP.
@@ -101,6 +141,7 @@ pub struct SrtpContext {
 }
 
 impl SrtpContext {
+    /// Encrypt and authenticate an RTP packet.
     pub fn protect_rtp(
         &mut self,
         buf: &[u8],
@@ -138,19 +179,19 @@ impl SrtpContext {
         let input = &buf[hlen..];
 
         match &mut self.rtp {
-            Derived::Aes128CmSha1_80 {
+            Derived::AesCmSha1_80 {
                 hmac, salt, enc, ..
             } => {
                 assert!(
                     input.len() % SRTP_BLOCK_SIZE == 0,
                     "RTP body should be padded to 16 byte block size, {header:?} with body length {} was not", input.len()
                 );
-                use aes_128_cm_sha1_80::{RtpHmac, ToRtpIv, HMAC_TAG_LEN};
+                use aes_cm_sha1_80::{RtpHmac, ToRtpIv, HMAC_TAG_LEN};
 
                 let iv = salt.rtp_iv(*header.ssrc, srtp_index);
 
                 let mut output = vec![0_u8; buf.len() + HMAC_TAG_LEN];
-                enc.encrypt(&iv, input, &mut output[hlen..])
+                enc.apply(&iv, input, &mut output[hlen..])
                     .expect("rtp encrypt");
 
                 output[..hlen].copy_from_slice(&buf[..hlen]);
@@ -160,8 +201,8 @@ impl SrtpContext {
 
                 output
             }
-            Derived::AeadAes128Gcm { salt, enc, .. } => {
-                use aead_aes_128_gcm::{ToRtpIv, TAG_LEN};
+            Derived::AeadAesGcm { salt, enc, .. } => {
+                use aead_aes_gcm::{ToRtpIv, TAG_LEN};
                 let roc = (srtp_index >> 16) as u32;
 
                 let iv = salt.rtp_iv(*header.ssrc, roc, header.sequence_number);
@@ -179,6 +220,7 @@ impl SrtpContext {
         }
     }
 
+    /// Verify and decrypt the payload of an SRTP packet.
     pub fn unprotect_rtp(
         &mut self,
         buf: &[u8],
@@ -186,10 +228,10 @@ impl SrtpContext {
         srtp_index: u64, // same as ext_seq
     ) -> Option<Vec<u8>> {
         match &mut self.rtp {
-            Derived::Aes128CmSha1_80 {
+            Derived::AesCmSha1_80 {
                 hmac, salt, dec, ..
             } => {
-                use aes_128_cm_sha1_80::{RtpHmac, ToRtpIv, HMAC_TAG_LEN};
+                use aes_cm_sha1_80::{RtpHmac, ToRtpIv, HMAC_TAG_LEN};
 
                 if buf.len() < HMAC_TAG_LEN {
                     return None;
@@ -207,15 +249,15 @@ impl SrtpContext {
                 let input = &buf[header.header_len..hmac_start];
                 let mut output = vec![0; input.len()];
 
-                if let Err(e) = dec.decrypt(&iv, input, &mut output) {
+                if let Err(e) = dec.apply(&iv, input, &mut output) {
                     warn!("Failed to decrypt SRTP ({}): {:?}", self.rtp.profile(), e);
                     return None;
                 };
 
                 Some(output)
             }
-            Derived::AeadAes128Gcm { salt, dec, .. } => {
-                use aead_aes_128_gcm::{ToRtpIv, TAG_LEN};
+            Derived::AeadAesGcm { salt, dec, .. } => {
+                use aead_aes_gcm::{ToRtpIv, TAG_LEN};
 
                 if buf.len() < TAG_LEN {
                     return None;
@@ -243,6 +285,7 @@ impl SrtpContext {
         }
     }
 
+    /// Encrypt and authenticate an RTCP packet.
     pub fn protect_rtcp(&mut self, buf: &[u8]) -> Vec<u8> {
         let srtcp_index = self.srtcp_index;
 
@@ -261,10 +304,10 @@ impl SrtpContext {
         }
 
         match &mut self.rtcp {
-            Derived::Aes128CmSha1_80 {
+            Derived::AesCmSha1_80 {
                 hmac, salt, enc, ..
             } => {
-                use aes_128_cm_sha1_80::{RtpHmac, ToRtpIv, HMAC_TAG_LEN};
+                use aes_cm_sha1_80::{RtpHmac, ToRtpIv, HMAC_TAG_LEN};
 
                 let iv = salt.rtp_iv(ssrc, srtcp_index as u64);
 
@@ -273,7 +316,7 @@ impl SrtpContext {
                 let input = &buf[8..];
                 let encout = &mut output[8..(8 + input.len())];
 
-                enc.encrypt(&iv, input, encout).expect("rtcp encrypt");
+                enc.apply(&iv, input, encout).expect("rtcp encrypt");
 
                 let to = &mut output[buf.len()..];
                 to[0..4].copy_from_slice(&e_and_si.to_be_bytes());
@@ -283,8 +326,8 @@ impl SrtpContext {
 
                 output
             }
-            Derived::AeadAes128Gcm { salt, enc, .. } => {
-                use aead_aes_128_gcm::{ToRtpIv, RTCP_AAD_LEN, TAG_LEN};
+            Derived::AeadAesGcm { salt, enc, .. } => {
+                use aead_aes_gcm::{ToRtpIv, RTCP_AAD_LEN, TAG_LEN};
                 let iv = salt.rtcp_iv(ssrc, srtcp_index);
 
                 let mut aad = [0; RTCP_AAD_LEN];
@@ -317,12 +360,14 @@ impl SrtpContext {
     //
     //                  |--------------------------------------|
     //                              encrypted (aes)
+
+    /// Verify and decrypt an SRTCP packet.
     pub fn unprotect_rtcp(&mut self, buf: &[u8]) -> Option<Vec<u8>> {
         match &mut self.rtcp {
-            Derived::Aes128CmSha1_80 {
+            Derived::AesCmSha1_80 {
                 hmac, salt, dec, ..
             } => {
-                use aes_128_cm_sha1_80::{RtpHmac, ToRtpIv, HMAC_TAG_LEN};
+                use aes_cm_sha1_80::{RtpHmac, ToRtpIv, HMAC_TAG_LEN};
 
                 if buf.len() < HMAC_TAG_LEN + SRTCP_INDEX_LEN {
                     return None;
@@ -368,15 +413,15 @@ impl SrtpContext {
                 let mut output = vec![0_u8; input.len() + 8];
                 output[0..8].copy_from_slice(&buf[0..8]);
 
-                if let Err(e) = dec.decrypt(&iv, input, &mut output[8..]) {
+                if let Err(e) = dec.apply(&iv, input, &mut output[8..]) {
                     warn!("Failed to decrypt SRTCP ({}): {:?}", self.rtcp.profile(), e);
                     return None;
                 }
 
                 Some(output)
             }
-            Derived::AeadAes128Gcm { salt, dec, .. } => {
-                use aead_aes_128_gcm::{ToRtpIv, RTCP_AAD_LEN, TAG_LEN};
+            Derived::AeadAesGcm { salt, dec, .. } => {
+                use aead_aes_gcm::{ToRtpIv, RTCP_AAD_LEN, TAG_LEN};
 
                 if buf.len() < SRTCP_INDEX_LEN + TAG_LEN {
                     // Too short
@@ -451,6 +496,52 @@ impl SrtpContext {
     }
 }
 
+/// AES in counter mode, used by `SRTP_AES128_CM_SHA1_80` and `SRTP_AES256_CM_SHA1_80`.
+///
+/// Counter mode is symmetric, the same operation encrypts and decrypts.
+trait AesCtrCipher: Sized {
+    /// The key is either 16 or 32 bytes long.
+    fn new(key: &[u8]) -> Self;
+
+    /// Apply the key stream for `iv` to `input`.
+    ///
+    /// The `output` must be at least as long as `input`.
+    fn apply(&mut self, iv: &[u8; 16], input: &[u8], output: &mut [u8]) -> Result<(), CryptoError>;
+}
+
+/// AES in Galois/Counter mode, used by `AEAD_AES_128_GCM` and `AEAD_AES_256_GCM`.
+trait AesGcmCipher: Sized {
+    /// The key is either 16 or 32 bytes long.
+    fn new(key: &[u8], encrypt: bool) -> Self;
+
+    /// Encrypt `input` and append the tag.
+    ///
+    /// The `output` must be at least `input` + `TAG_LEN` long.
+    fn encrypt(
+        &mut self,
+        iv: &[u8; 12],
+        aad: &[u8],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), CryptoError>;
+
+    /// Verify the tag at the end of `input` and decrypt the rest.
+    ///
+    /// The `aads` are authenticated in order. Returns the number of decrypted bytes.
+    fn decrypt(
+        &mut self,
+        iv: &[u8; 12],
+        aads: &[&[u8]],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, CryptoError>;
+}
+
+/// Failure in the crypto backend.
+#[derive(Debug, Error)]
+#[error("{0}")]
+struct CryptoError(String);
+
 /// SrtpKeys created from DTLS SrtpKeyMaterial.
 #[derive(Debug)]
 struct SrtpKey<const ML: usize, const SL: usize> {
@@ -459,40 +550,28 @@ struct SrtpKey<const ML: usize, const SL: usize> {
 }
 
 impl<const ML: usize, const SL: usize> SrtpKey<ML, SL> {
-    pub fn new(mat: &KeyingMaterial, left: bool) -> Self {
-        // layout in SrtpKeyMaterial is [key_input, key_output, salt_input, salt_output]
-
-        // Invariant
-        assert!(
-            mat.len() == ML * 2 + SL * 2,
-            "The KeyingMaterial provided to SrtpKey::new must be ML * 2 + SL * 2 in length"
-        );
-
-        // offset 0, offset 1
-        let (o0, o1) = if left { (0, 0) } else { (ML, SL) };
-
-        let mut master = [0; ML];
-        let mut salt = [0; SL];
-
-        master[0..ML].copy_from_slice(&mat[o0..(o0 + ML)]);
-        salt[0..SL].copy_from_slice(&mat[(ML + ML + o1)..(ML + ML + o1 + SL)]);
-
-        SrtpKey { master, salt }
+    pub fn new(master: &[u8], salt: &[u8]) -> Self {
+        SrtpKey {
+            master: master.try_into().expect("master key of ML length"),
+            salt: salt.try_into().expect("master salt of SL length"),
+        }
     }
 
     fn derive(&self, label: u8, out: &mut [u8]) {
-        // AEC-CM (128 bits) defined in RFC3711
-        assert!(ML == 16, "Only valid for 128 bit master keys");
-        assert!(SL <= 14, "Only valid for 128 bit master keys");
+        // AES-CM (128 bits) defined in RFC3711, and (256 bits) in RFC6188
+        assert!(
+            ML == AES_128_KEY_LEN || ML == AES_256_KEY_LEN,
+            "Only valid for 128 or 256 bit master keys"
+        );
+        assert!(SL <= 14, "Only valid for salts up to 112 bits");
         let mut i = 0; // index in out
 
         // input layout: [salt[SL] || label, round[2]] (|| is xor 7th byte)
-        let mut input = [0; ML];
+        let mut input = [0; 16];
 
         input[0..SL].copy_from_slice(&self.salt[..]);
         input[7] ^= label;
 
-        let mut buf = [0; 16 + 16]; // output from each AES
         let mut round: u16 = 0; // counter for each AES round
 
         // loop each AES round
@@ -505,17 +584,10 @@ impl<const ML: usize, const SL: usize> SrtpKey<ML, SL> {
             input[14..].copy_from_slice(&round.to_be_bytes()[..]);
 
             // default key derivation function, which uses AES-128 in Counter Mode
-            let mut aes = Crypter::new(Cipher::aes_128_ecb(), Mode::Encrypt, &self.master, None)
-                .expect("AES deriver");
+            let buf = backend::aes_ecb_block(&self.master, &input);
 
-            // Run AES
-            let count = aes.update(&input[..], &mut buf[..]).expect("AES update");
-            let rest = aes.finalize(&mut buf[count..]).expect("AES finalize");
-            assert_eq!(count + rest, 16 + 16); // input len + block size
-
-            // Copy to output. Even if we get 32 bytes of output with AES 128 ECB, we
-            // only use the first 16. That matches the tests in the RFC.
-            for j in buf.iter().take(16) {
+            // Copy to output.
+            for j in buf.iter() {
                 if i == out.len() {
                     break;
                 }
@@ -530,27 +602,30 @@ impl<const ML: usize, const SL: usize> SrtpKey<ML, SL> {
 
 /// Encryption/decryption derived from the SrtpKey.
 enum Derived {
-    Aes128CmSha1_80 {
+    AesCmSha1_80 {
+        profile: SrtpProfile,
         hmac: Sha1,
-        salt: aes_128_cm_sha1_80::RtpSalt,
-        enc: aes_128_cm_sha1_80::Encrypter,
-        dec: aes_128_cm_sha1_80::Decrypter,
+        salt: aes_cm_sha1_80::RtpSalt,
+        enc: backend::AesCtr,
+        dec: backend::AesCtr,
     },
-    AeadAes128Gcm {
-        salt: aead_aes_128_gcm::RtpSalt,
-        enc: aead_aes_128_gcm::Encrypter,
-        dec: aead_aes_128_gcm::Decrypter,
+    AeadAesGcm {
+        profile: SrtpProfile,
+        salt: aead_aes_gcm::RtpSalt,
+        enc: backend::AesGcm,
+        dec: backend::AesGcm,
     },
 }
 
 impl Derived {
-    fn aes_128_cm_sha1_80(
-        srtp_key: &SrtpKey<{ aes_128_cm_sha1_80::KEY_LEN }, { aes_128_cm_sha1_80::SALT_LEN }>,
+    fn aes_cm_sha1_80<const KL: usize>(
+        profile: SrtpProfile,
+        srtp_key: &SrtpKey<KL, { aes_cm_sha1_80::SALT_LEN }>,
     ) -> (Self, Self) {
-        use aes_128_cm_sha1_80::*;
+        use aes_cm_sha1_80::*;
 
         // RTP AES Counter
-        let mut rtp_aes = [0; KEY_LEN];
+        let mut rtp_aes = [0; KL];
         srtp_key.derive(LABEL_RTP_AES, &mut rtp_aes[..]);
 
         // RTP SHA1 HMAC
@@ -565,7 +640,7 @@ impl Derived {
         srtp_key.derive(LABEL_RTP_SALT, &mut rtp_salt[..]);
 
         // RTCP AES Counter
-        let mut rtcp_aes = [0; KEY_LEN];
+        let mut rtcp_aes = [0; KL];
         srtp_key.derive(LABEL_RTCP_AES, &mut rtcp_aes[..]);
 
         // RTCP SHA1 HMAC
@@ -579,30 +654,33 @@ impl Derived {
         let mut rtcp_salt = [0; SALT_LEN];
         srtp_key.derive(LABEL_RTCP_SALT, &mut rtcp_salt[..]);
 
-        let rtp = Derived::Aes128CmSha1_80 {
+        let rtp = Derived::AesCmSha1_80 {
+            profile,
             hmac: rtp_hmac,
             salt: rtp_salt,
-            enc: Encrypter::new(rtp_aes),
-            dec: Decrypter::new(rtp_aes),
+            enc: backend::AesCtr::new(&rtp_aes),
+            dec: backend::AesCtr::new(&rtp_aes),
         };
 
-        let rtcp = Derived::Aes128CmSha1_80 {
+        let rtcp = Derived::AesCmSha1_80 {
+            profile,
             hmac: rtcp_hmac,
             salt: rtcp_salt,
-            enc: Encrypter::new(rtcp_aes),
-            dec: Decrypter::new(rtcp_aes),
+            enc: backend::AesCtr::new(&rtcp_aes),
+            dec: backend::AesCtr::new(&rtcp_aes),
         };
 
         (rtp, rtcp)
     }
 
-    fn aead_aes_128_gcm(
-        srtp_key: &SrtpKey<{ aead_aes_128_gcm::KEY_LEN }, { aead_aes_128_gcm::SALT_LEN }>,
+    fn aead_aes_gcm<const KL: usize>(
+        profile: SrtpProfile,
+        srtp_key: &SrtpKey<KL, { aead_aes_gcm::SALT_LEN }>,
     ) -> (Derived, Derived) {
-        use aead_aes_128_gcm::*;
+        use aead_aes_gcm::*;
 
         // RTP session key
-        let mut rtp_aes = [0; KEY_LEN];
+        let mut rtp_aes = [0; KL];
         srtp_key.derive(LABEL_RTP_AES, &mut rtp_aes[..]);
 
         // RTP session salt
@@ -610,23 +688,25 @@ impl Derived {
         srtp_key.derive(LABEL_RTP_SALT, &mut rtp_salt[..]);
 
         // RTCP session key
-        let mut rtcp_aes = [0; KEY_LEN];
+        let mut rtcp_aes = [0; KL];
         srtp_key.derive(LABEL_RTCP_AES, &mut rtcp_aes[..]);
 
         // RTCP session salt
         let mut rtcp_salt = [0; SALT_LEN];
         srtp_key.derive(LABEL_RTCP_SALT, &mut rtcp_salt[..]);
 
-        let rtp = Derived::AeadAes128Gcm {
+        let rtp = Derived::AeadAesGcm {
+            profile,
             salt: rtp_salt,
-            enc: Encrypter::new(&rtp_aes),
-            dec: Decrypter::new(&rtp_aes),
+            enc: backend::AesGcm::new(&rtp_aes, true),
+            dec: backend::AesGcm::new(&rtp_aes, false),
         };
 
-        let rtcp = Derived::AeadAes128Gcm {
+        let rtcp = Derived::AeadAesGcm {
+            profile,
             salt: rtcp_salt,
-            enc: Encrypter::new(&rtcp_aes),
-            dec: Decrypter::new(&rtcp_aes),
+            enc: backend::AesGcm::new(&rtcp_aes, true),
+            dec: backend::AesGcm::new(&rtcp_aes, false),
         };
 
         (rtp, rtcp)
@@ -634,8 +714,8 @@ impl Derived {
 
     fn profile(&self) -> SrtpProfile {
         match self {
-            Derived::Aes128CmSha1_80 { .. } => SrtpProfile::Aes128CmSha1_80,
-            Derived::AeadAes128Gcm { .. } => SrtpProfile::AeadAes128Gcm,
+            Derived::AesCmSha1_80 { profile, .. } => *profile,
+            Derived::AeadAesGcm { profile, .. } => *profile,
         }
     }
 }
@@ -646,8 +726,8 @@ impl fmt::Debug for Derived {
     }
 }
 
-// Implementation specific to `AES128_CM_SHA1_80`
-mod aes_128_cm_sha1_80 {
+// Implementation specific to `AES128_CM_SHA1_80` and `AES256_CM_SHA1_80` (RFC 6188)
+mod aes_cm_sha1_80 {
     // SRTP_AES128_CM_HMAC_SHA1_80
     //    cipher: AES_128_CM
     //    cipher_key_length: 128
@@ -656,75 +736,17 @@ mod aes_128_cm_sha1_80 {
     //    auth_function: HMAC-SHA1
     //    auth_key_length: 160
     //    auth_tag_length: 80
-    pub(super) const KEY_LEN: usize = 16;
+    //
+    // SRTP_AES256_CM_HMAC_SHA1_80 only differs in the 256 bit cipher key.
     pub(super) const SALT_LEN: usize = 14;
     pub(super) const HMAC_KEY_LEN: usize = 20;
     pub(super) const HMAC_TAG_LEN: usize = 10;
 
-    use std::fmt;
-
-    use openssl::cipher;
-    use openssl::cipher_ctx::CipherCtx;
-    use openssl::error::ErrorStack;
-
     use crate::io::Sha1;
 
-    type AesKey = [u8; 16];
     pub(super) type RtpSalt = [u8; 14];
     type RtpIv = [u8; 16];
 
-    pub(super) struct Encrypter {
-        ctx: CipherCtx,
-    }
-
-    impl Encrypter {
-        pub(super) fn new(key: AesKey) -> Self {
-            let t = cipher::Cipher::aes_128_ctr();
-            let mut ctx = CipherCtx::new().expect("a reusable cipher context");
-            ctx.encrypt_init(Some(t), Some(&key[..]), None)
-                .expect("enc init");
-            Encrypter { ctx }
-        }
-
-        pub(super) fn encrypt(
-            &mut self,
-            iv: &RtpIv,
-            input: &[u8],
-            output: &mut [u8],
-        ) -> Result<(), ErrorStack> {
-            self.ctx.encrypt_init(None, None, Some(iv))?;
-            let count = self.ctx.cipher_update(input, Some(output))?;
-            self.ctx.cipher_final(&mut output[count..])?;
-            Ok(())
-        }
-    }
-
-    pub(super) struct Decrypter {
-        ctx: CipherCtx,
-    }
-
-    impl Decrypter {
-        pub(super) fn new(key: AesKey) -> Self {
-            let t = cipher::Cipher::aes_128_ctr();
-            let mut ctx = CipherCtx::new().expect("a reusable cipher context");
-            ctx.decrypt_init(Some(t), Some(&key[..]), None)
-                .expect("enc init");
-            Decrypter { ctx }
-        }
-
-        pub(super) fn decrypt(
-            &mut self,
-            iv: &RtpIv,
-            input: &[u8],
-            output: &mut [u8],
-        ) -> Result<(), ErrorStack> {
-            self.ctx.decrypt_init(None, None, Some(iv))?;
-            let count = self.ctx.cipher_update(input, Some(output))?;
-            self.ctx.cipher_final(&mut output[count..])?;
-            Ok(())
-        }
-    }
-
     pub(super) trait RtpHmac {
         fn rtp_hmac(&self, buf: &mut [u8], srtp_index: u64, hmac_start: usize);
         fn rtp_verify(&self, buf: &[u8], srtp_index: u64, cmp: &[u8]) -> bool;
@@ -793,142 +815,31 @@ mod aes_128_cm_sha1_80 {
             iv
         }
     }
-
-    impl fmt::Debug for Encrypter {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            f.debug_struct("Encrypter").finish()
-        }
-    }
-
-    impl fmt::Debug for Decrypter {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            f.debug_struct("Decrypter").finish()
-        }
-    }
 }
 
-// Implementation specific to `AEAD_AES_128_GCM`
-mod aead_aes_128_gcm {
+// Implementation specific to `AEAD_AES_128_GCM` and `AEAD_AES_256_GCM`
+mod aead_aes_gcm {
     // +--------------------------------+------------------------------+
     // | Parameter                      | Value                        |
     // +--------------------------------+------------------------------+
-    // | Master key length              | 128 bits                     |
+    // | Master key length              | 128 or 256 bits              |
     // | Master salt length             | 96 bits                      |
     // | Key Derivation Function        | AES_CM PRF(RFC3711)          |
     // | Maximum key lifetime (SRTP)    | 2^48 packets                 |
     // | Maximum key lifetime (SRTCP)   | 2^31 packets                 |
-    // | Cipher (for SRTP and SRTCP)    | AEAD_AES_128_GCM             |
+    // | Cipher (for SRTP and SRTCP)    | AEAD_AES_128/256_GCM         |
     // | AEAD authentication tag length | 128 bits                     |
     // +--------------------------------+------------------------------+
 
-    use openssl::cipher;
-    use openssl::cipher_ctx::CipherCtx;
-    use openssl::error::ErrorStack;
-
-    pub(super) const KEY_LEN: usize = 16;
     pub(super) const SALT_LEN: usize = 12;
     pub(super) const RTCP_AAD_LEN: usize = 12;
     pub(super) const TAG_LEN: usize = 16;
-    const IV_LEN: usize = 12;
+    #[cfg(feature = "openssl")]
+    pub(super) const IV_LEN: usize = 12;
 
-    type EncryptionKey = [u8; KEY_LEN];
-    type DecryptionKey = [u8; KEY_LEN];
     pub(super) type RtpSalt = [u8; SALT_LEN];
     type RtpIv = [u8; SALT_LEN];
 
-    pub(super) struct Encrypter {
-        ctx: CipherCtx,
-    }
-
-    impl Encrypter {
-        pub(super) fn new(key: &EncryptionKey) -> Self {
-            let t = cipher::Cipher::aes_128_gcm();
-            let mut ctx = CipherCtx::new().expect("a reusable cipher context");
-            ctx.encrypt_init(Some(t), Some(key), None)
-                .expect("enc init");
-            ctx.set_iv_length(IV_LEN).expect("IV length");
-            ctx.set_padding(false);
-
-            Self { ctx }
-        }
-
-        pub(super) fn encrypt(
-            &mut self,
-            iv: &[u8; IV_LEN],
-            aad: &[u8],
-            input: &[u8],
-            output: &mut [u8],
-        ) -> Result<(), ErrorStack> {
-            assert!(
-                aad.len() >= 12,
-                "Associated data length MUST be at least 12 octets"
-            );
-
-            // Set the IV
-            self.ctx.encrypt_init(None, None, Some(iv))?;
-
-            // Add the additional authenticated data, omitting the output argument informs
-            // OpenSSL that we are providing AAD.
-            let aad_c = self.ctx.cipher_update(aad, None)?;
-            // TODO: This should maybe be an error
-            assert!(aad_c == aad.len());
-
-            let count = self.ctx.cipher_update(input, Some(output))?;
-            let final_count = self.ctx.cipher_final(&mut output[count..])?;
-
-            // Get the authentication tag and append it to the output
-            let tag_offset = count + final_count;
-            self.ctx
-                .tag(&mut output[tag_offset..tag_offset + TAG_LEN])?;
-
-            Ok(())
-        }
-    }
-
-    pub(super) struct Decrypter {
-        ctx: CipherCtx,
-    }
-
-    impl Decrypter {
-        pub(super) fn new(key: &DecryptionKey) -> Self {
-            let t = cipher::Cipher::aes_128_gcm();
-            let mut ctx = CipherCtx::new().expect("a reusable cipher context");
-            ctx.decrypt_init(Some(t), Some(key), None)
-                .expect("dec init");
-
-            Self { ctx }
-        }
-
-        pub(super) fn decrypt(
-            &mut self,
-            iv: &[u8; IV_LEN],
-            aads: &[&[u8]],
-            input: &[u8],
-            output: &mut [u8],
-        ) -> Result<usize, ErrorStack> {
-            // This needs to be converted to an error maybe
-            assert!(input.len() >= TAG_LEN);
-
-            let (cipher_text, tag) = input.split_at(input.len() - TAG_LEN);
-            self.ctx.decrypt_init(None, None, Some(iv))?;
-
-            // Add the additional authenticated data, omitting the output argument informs
-            // OpenSSL that we are providing AAD.
-            // With this the authentication tag will be verified.
-            for aad in aads {
-                self.ctx.cipher_update(aad, None)?;
-            }
-
-            self.ctx.set_tag(tag)?;
-
-            let count = self.ctx.cipher_update(cipher_text, Some(output))?;
-
-            let final_count = self.ctx.cipher_final(&mut output[count..])?;
-
-            Ok(count + final_count)
-        }
-    }
-
     pub(super) trait ToRtpIv {
         fn rtp_iv(&self, ssrc: u32, roc: u32, seq: u16) -> RtpIv;
         fn rtcp_iv(&self, ssrc: u32, srtp_index: u32) -> RtpIv;
@@ -1038,8 +949,99 @@ mod test {
         );
     }
 
+    #[test]
+    fn derive_key_aes_256() {
+        // https://www.rfc-editor.org/rfc/rfc6188#section-7.1
+        //
+        // AES_256_CM_PRF Test Vectors.
+
+        let master = [
+            0xf0, 0xf0, 0x49, 0x14, 0xb5, 0x13, 0xf2, 0x76, //
+            0x3a, 0x1b, 0x1f, 0xa1, 0x30, 0xf1, 0x0e, 0x29, //
+            0x98, 0xf6, 0xf6, 0xe4, 0x3e, 0x43, 0x09, 0xd1, //
+            0xe6, 0x22, 0xa0, 0xe3, 0x32, 0xb9, 0xf1, 0xb6,
+        ];
+
+        let salt = [
+            0x3b, 0x04, 0x80, 0x3d, 0xe5, 0x1e, 0xe7, //
+            0xc9, 0x64, 0x23, 0xab, 0x5b, 0x78, 0xd2,
+        ];
+
+        let sk = SrtpKey { master, salt };
+
+        // aes crypto key
+        let mut out = [0_u8; 32];
+        sk.derive(0, &mut out[..]);
+
+        assert_eq!(
+            out,
+            [
+                0x5b, 0xa1, 0x06, 0x4e, 0x30, 0xec, 0x51, 0x61, //
+                0x3c, 0xad, 0x92, 0x6c, 0x5a, 0x28, 0xef, 0x73, //
+                0x1e, 0xc7, 0xfb, 0x39, 0x7f, 0x70, 0xa9, 0x60, //
+                0x65, 0x3c, 0xaf, 0x06, 0x55, 0x4c, 0xd8, 0xc4
+            ]
+        );
+
+        // hmac
+        let mut out = [0_u8; 20];
+        sk.derive(1, &mut out[..]);
+
+        assert_eq!(
+            out,
+            [
+                0xfd, 0x9c, 0x32, 0xd3, 0x9e, 0xd5, 0xfb, 0xb5, //
+                0xa9, 0xdc, 0x96, 0xb3, 0x08, 0x18, 0x45, 0x4d, //
+                0x13, 0x13, 0xdc, 0x05
+            ]
+        );
+
+        // salt
+        let mut out = [0_u8; 14];
+        sk.derive(2, &mut out[..]);
+
+        assert_eq!(
+            out,
+            [
+                0xfa, 0x31, 0x79, 0x16, 0x85, 0xca, 0x44, //
+                0x4a, 0x9e, 0x07, 0xc6, 0xc6, 0x4e, 0x93
+            ]
+        );
+    }
+
+    #[test]
+    fn roundtrip_all_profiles() {
+        use crate::rtp_::ExtensionMap;
+
+        let mut rtp = vec![
+            0x80, 0x60, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78,
+        ];
+        rtp.extend((0..96).map(|i| i as u8));
+        let header = RtpHeader::parse(&rtp, &ExtensionMap::empty()).expect("header to parse");
+
+        // Sender report with no report blocks.
+        let mut rtcp = vec![0x80, 0xc8, 0x00, 0x06, 0x12, 0x34, 0x56, 0x78];
+        rtcp.extend((0..20).map(|i| i as u8));
+
+        for profile in SrtpProfile::ALL {
+            let mat = KeyingMaterial::new(&vec![7; profile.keying_material_len()]);
+            let mut ctx_tx = SrtpContext::new(*profile, &mat, true);
+            let mut ctx_rx = SrtpContext::new(*profile, &mat, true);
+
+            let protected = ctx_tx.protect_rtp(&rtp, &header, 1);
+            assert!(protected.len() <= rtp.len() + SRTP_OVERHEAD);
+            let unprotected = ctx_rx.unprotect_rtp(&protected, &header, 1);
+            assert_eq!(unprotected.as_deref(), Some(&rtp[12..]), "{}", profile);
+
+            let protected = ctx_tx.protect_rtcp(&rtcp);
+            assert!(protected.len() <= rtcp.len() + SRTCP_OVERHEAD);
+            let unprotected = ctx_rx.unprotect_rtcp(&protected);
+            assert_eq!(unprotected.as_deref(), Some(&rtcp[..]), "{}", profile);
+        }
+    }
+
     mod test_aes128_cm_sha1_80 {
-        use super::aes_128_cm_sha1_80::*;
+        use super::aes_cm_sha1_80::*;
         use super::*;
 
         const MAT: [u8; 60] = [
@@ -1094,7 +1096,7 @@ mod test {
 
         use super::*;
 
-        use super::aead_aes_128_gcm::*;
+        use super::aead_aes_gcm::*;
 
         mod rfc7714 {
             // Test vectors from RFC7714
@@ -0,0 +1,148 @@
+//! SRTP ciphers using OpenSSL.
+
+use openssl::cipher;
+use openssl::cipher_ctx::CipherCtx;
+use openssl::error::ErrorStack;
+use openssl::symm::{Cipher, Crypter, Mode};
+
+use super::aead_aes_gcm::{IV_LEN, TAG_LEN};
+use super::{AesCtrCipher, AesGcmCipher, CryptoError};
+use super::{AES_128_KEY_LEN, AES_256_KEY_LEN};
+
+pub(super) struct AesCtr {
+    ctx: CipherCtx,
+}
+
+impl AesCtrCipher for AesCtr {
+    fn new(key: &[u8]) -> Self {
+        let t = match key.len() {
+            AES_128_KEY_LEN => cipher::Cipher::aes_128_ctr(),
+            AES_256_KEY_LEN => cipher::Cipher::aes_256_ctr(),
+            n => panic!("Bad AES key length: {}", n),
+        };
+        let mut ctx = CipherCtx::new().expect("a reusable cipher context");
+        ctx.encrypt_init(Some(t), Some(key), None)
+            .expect("enc init");
+        AesCtr { ctx }
+    }
+
+    fn apply(&mut self, iv: &[u8; 16], input: &[u8], output: &mut [u8]) -> Result<(), CryptoError> {
+        self.ctx.encrypt_init(None, None, Some(iv))?;
+        let count = self.ctx.cipher_update(input, Some(output))?;
+        self.ctx.cipher_final(&mut output[count..])?;
+        Ok(())
+    }
+}
+
+pub(super) struct AesGcm {
+    ctx: CipherCtx,
+    encrypt: bool,
+}
+
+impl AesGcmCipher for AesGcm {
+    fn new(key: &[u8], encrypt: bool) -> Self {
+        let t = match key.len() {
+            AES_128_KEY_LEN => cipher::Cipher::aes_128_gcm(),
+            AES_256_KEY_LEN => cipher::Cipher::aes_256_gcm(),
+            n => panic!("Bad AES key length: {}", n),
+        };
+        let mut ctx = CipherCtx::new().expect("a reusable cipher context");
+        if encrypt {
+            ctx.encrypt_init(Some(t), Some(key), None)
+                .expect("enc init");
+            ctx.set_iv_length(IV_LEN).expect("IV length");
+            ctx.set_padding(false);
+        } else {
+            ctx.decrypt_init(Some(t), Some(key), None)
+                .expect("dec init");
+        }
+
+        Self { ctx, encrypt }
+    }
+
+    fn encrypt(
+        &mut self,
+        iv: &[u8; 12],
+        aad: &[u8],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), CryptoError> {
+        assert!(self.encrypt, "encrypt with a decrypting context");
+        assert!(
+            aad.len() >= 12,
+            "Associated data length MUST be at least 12 octets"
+        );
+
+        // Set the IV
+        self.ctx.encrypt_init(None, None, Some(iv))?;
+
+        // Add the additional authenticated data, omitting the output argument informs
+        // OpenSSL that we are providing AAD.
+        let aad_c = self.ctx.cipher_update(aad, None)?;
+        // TODO: This should maybe be an error
+        assert!(aad_c == aad.len());
+
+        let count = self.ctx.cipher_update(input, Some(output))?;
+        let final_count = self.ctx.cipher_final(&mut output[count..])?;
+
+        // Get the authentication tag and append it to the output
+        let tag_offset = count + final_count;
+        self.ctx
+            .tag(&mut output[tag_offset..tag_offset + TAG_LEN])?;
+
+        Ok(())
+    }
+
+    fn decrypt(
+        &mut self,
+        iv: &[u8; 12],
+        aads: &[&[u8]],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, CryptoError> {
+        assert!(!self.encrypt, "decrypt with an encrypting context");
+
+        let (cipher_text, tag) = input.split_at(input.len() - TAG_LEN);
+        self.ctx.decrypt_init(None, None, Some(iv))?;
+
+        // Add the additional authenticated data, omitting the output argument informs
+        // OpenSSL that we are providing AAD.
+        // With this the authentication tag will be verified.
+        for aad in aads {
+            self.ctx.cipher_update(aad, None)?;
+        }
+
+        self.ctx.set_tag(tag)?;
+
+        let count = self.ctx.cipher_update(cipher_text, Some(output))?;
+
+        let final_count = self.ctx.cipher_final(&mut output[count..])?;
+
+        Ok(count + final_count)
+    }
+}
+
+pub(super) fn aes_ecb_block(key: &[u8], input: &[u8; 16]) -> [u8; 16] {
+    let t = match key.len() {
+        AES_128_KEY_LEN => Cipher::aes_128_ecb(),
+        AES_256_KEY_LEN => Cipher::aes_256_ecb(),
+        n => panic!("Bad AES key length: {}", n),
+    };
+    let mut aes = Crypter::new(t, Mode::Encrypt, key, None).expect("AES deriver");
+
+    // Even if we get 32 bytes of output with AES ECB, we only use the first 16.
+    let mut buf = [0; 16 + 16];
+    let count = aes.update(&input[..], &mut buf[..]).expect("AES update");
+    let rest = aes.finalize(&mut buf[count..]).expect("AES finalize");
+    assert_eq!(count + rest, 16 + 16); // input len + block size
+
+    let mut out = [0; 16];
+    out.copy_from_slice(&buf[..16]);
+    out
+}
+
+impl From<ErrorStack> for CryptoError {
+    fn from(value: ErrorStack) -> Self {
+        CryptoError(value.to_string())
+    }
+}
@@ -0,0 +1,195 @@
+//! SRTP ciphers using RustCrypto.
+
+use aes::cipher::consts::U16;
+use aes::cipher::{BlockCipher, BlockEncrypt, BlockSizeUser, InnerIvInit, KeyInit};
+use aes::cipher::{StreamCipher, StreamCipherCoreWrapper};
+use aes_gcm::{AeadInPlace, Nonce, Tag};
+use ctr::flavors::Ctr128BE;
+
+use super::aead_aes_gcm::TAG_LEN;
+use super::{AesCtrCipher, AesGcmCipher, CryptoError};
+use super::{AES_128_KEY_LEN, AES_256_KEY_LEN};
+
+/// The key schedule is done once, the counter is set up per packet.
+#[allow(clippy::large_enum_variant)]
+pub(super) enum AesCtr {
+    Aes128(aes::Aes128),
+    Aes256(aes::Aes256),
+}
+
+impl AesCtrCipher for AesCtr {
+    fn new(key: &[u8]) -> Self {
+        match key.len() {
+            AES_128_KEY_LEN => AesCtr::Aes128(aes::Aes128::new(key.into())),
+            AES_256_KEY_LEN => AesCtr::Aes256(aes::Aes256::new(key.into())),
+            n => panic!("Bad AES key length: {}", n),
+        }
+    }
+
+    fn apply(&mut self, iv: &[u8; 16], input: &[u8], output: &mut [u8]) -> Result<(), CryptoError> {
+        match self {
+            AesCtr::Aes128(aes) => apply_ctr(aes.clone(), iv, input, output),
+            AesCtr::Aes256(aes) => apply_ctr(aes.clone(), iv, input, output),
+        }
+    }
+}
+
+fn apply_ctr<C>(aes: C, iv: &[u8; 16], input: &[u8], output: &mut [u8]) -> Result<(), CryptoError>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16>,
+{
+    let core = ctr::CtrCore::<_, Ctr128BE>::inner_iv_init(aes, iv.into());
+    let mut ctr = StreamCipherCoreWrapper::from_core(core);
+    ctr.apply_keystream_b2b(input, &mut output[..input.len()])
+        .map_err(|e| CryptoError(e.to_string()))
+}
+
+#[allow(clippy::large_enum_variant)]
+pub(super) enum AesGcm {
+    Aes128(aes_gcm::Aes128Gcm),
+    Aes256(aes_gcm::Aes256Gcm),
+}
+
+impl AesGcmCipher for AesGcm {
+    fn new(key: &[u8], _encrypt: bool) -> Self {
+        match key.len() {
+            AES_128_KEY_LEN => AesGcm::Aes128(aes_gcm::Aes128Gcm::new(key.into())),
+            AES_256_KEY_LEN => AesGcm::Aes256(aes_gcm::Aes256Gcm::new(key.into())),
+            n => panic!("Bad AES key length: {}", n),
+        }
+    }
+
+    fn encrypt(
+        &mut self,
+        iv: &[u8; 12],
+        aad: &[u8],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), CryptoError> {
+        let (text, rest) = output.split_at_mut(input.len());
+        text.copy_from_slice(input);
+
+        let nonce = Nonce::from_slice(iv);
+        let tag = match self {
+            AesGcm::Aes128(aead) => aead.encrypt_in_place_detached(nonce, aad, text),
+            AesGcm::Aes256(aead) => aead.encrypt_in_place_detached(nonce, aad, text),
+        }
+        .map_err(|e| CryptoError(e.to_string()))?;
+
+        rest[..TAG_LEN].copy_from_slice(&tag);
+
+        Ok(())
+    }
+
+    fn decrypt(
+        &mut self,
+        iv: &[u8; 12],
+        aads: &[&[u8]],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, CryptoError> {
+        let (cipher_text, tag) = input.split_at(input.len() - TAG_LEN);
+
+        // The AAD must be contiguous. It's only in several parts for unencrypted SRTCP.
+        let joined;
+        let aad = if aads.len() == 1 {
+            aads[0]
+        } else {
+            joined = aads.concat();
+            &joined[..]
+        };
+
+        let text = &mut output[..cipher_text.len()];
+        text.copy_from_slice(cipher_text);
+
+        let (nonce, tag) = (Nonce::from_slice(iv), Tag::from_slice(tag));
+        match self {
+            AesGcm::Aes128(aead) => aead.decrypt_in_place_detached(nonce, aad, text, tag),
+            AesGcm::Aes256(aead) => aead.decrypt_in_place_detached(nonce, aad, text, tag),
+        }
+        .map_err(|e| CryptoError(e.to_string()))?;
+
+        Ok(cipher_text.len())
+    }
+}
+
+pub(super) fn aes_ecb_block(key: &[u8], input: &[u8; 16]) -> [u8; 16] {
+    let mut block = (*input).into();
+
+    match key.len() {
+        AES_128_KEY_LEN => aes::Aes128::new(key.into()).encrypt_block(&mut block),
+        AES_256_KEY_LEN => aes::Aes256::new(key.into()).encrypt_block(&mut block),
+        n => panic!("Bad AES key length: {}", n),
+    }
+
+    block.into()
+}
+
+#[cfg(all(test, feature = "openssl"))]
+mod test {
+    use super::super::ossl;
+    use super::*;
+
+    fn keys() -> [Vec<u8>; 2] {
+        [(0..16).collect(), (0..32).collect()]
+    }
+
+    fn input() -> Vec<u8> {
+        (0..1000).map(|i| (i * 7) as u8).collect()
+    }
+
+    #[test]
+    fn same_as_openssl_ctr() {
+        let iv = [0x42; 16];
+        let input = input();
+
+        for key in keys() {
+            let mut out1 = vec![0; input.len()];
+            let mut out2 = vec![0; input.len()];
+            ossl::AesCtr::new(&key)
+                .apply(&iv, &input, &mut out1)
+                .unwrap();
+            AesCtr::new(&key).apply(&iv, &input, &mut out2).unwrap();
+
+            assert_eq!(out1, out2);
+        }
+    }
+
+    #[test]
+    fn same_as_openssl_gcm() {
+        let iv = [0x42; 12];
+        let aad = [0x17; 12];
+        let input = input();
+
+        for key in keys() {
+            let mut out1 = vec![0; input.len() + TAG_LEN];
+            let mut out2 = vec![0; input.len() + TAG_LEN];
+            ossl::AesGcm::new(&key, true)
+                .encrypt(&iv, &aad, &input, &mut out1)
+                .unwrap();
+            AesGcm::new(&key, true)
+                .encrypt(&iv, &aad, &input, &mut out2)
+                .unwrap();
+            assert_eq!(out1, out2);
+
+            // Decrypt what the other backend encrypted.
+            let mut dec = vec![0; input.len()];
+            let n = AesGcm::new(&key, false)
+                .decrypt(&iv, &[&aad[..6], &aad[6..]], &out1, &mut dec)
+                .unwrap();
+            assert_eq!(n, input.len());
+            assert_eq!(dec, input);
+        }
+    }
+
+    #[test]
+    fn same_as_openssl_ecb() {
+        let block = [0x42; 16];
+        for key in keys() {
+            assert_eq!(
+                ossl::aes_ecb_block(&key, &block),
+                aes_ecb_block(&key, &block)
+            );
+        }
+    }
+}
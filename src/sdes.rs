@@ -0,0 +1,317 @@
+//! SDES keying of SRTP, see [RFC 4568][1].
+//!
+//! The master keys are sent in the clear in `a=crypto` SDP attributes, one per direction.
+//! This relies on the signaling being protected by other means.
+//!
+//! [1]: https://www.rfc-editor.org/rfc/rfc4568
+
+use std::fmt;
+
+use rand::random;
+
+use crate::dtls::SrtpProfile;
+use crate::sdp::Proto;
+
+/// SRTP master key and master salt for one direction of a session.
+///
+/// With SDES ([RFC 4568][1]) each side picks its own master key to protect what it
+/// sends. The keys are exchanged in `a=crypto` SDP attributes, or set directly with
+/// [`DirectApi::set_srtp_master_keys()`][crate::change::DirectApi::set_srtp_master_keys].
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc4568
+#[derive(Clone, PartialEq, Eq)]
+pub struct SrtpMasterKey {
+    profile: SrtpProfile,
+    key: Vec<u8>,
+    salt: Vec<u8>,
+}
+
+impl SrtpMasterKey {
+    /// Creates a master key from its parts.
+    ///
+    /// Returns `None` if the lengths don't match [`SrtpProfile::master_key_len()`]
+    /// and [`SrtpProfile::master_salt_len()`].
+    ///
+    /// ```
+    /// # use str0m::crypto::{SrtpMasterKey, SrtpProfile};
+    /// let key = SrtpMasterKey::new(SrtpProfile::Aes128CmSha1_80, &[1; 16], &[2; 14]);
+    /// assert!(key.is_some());
+    ///
+    /// let key = SrtpMasterKey::new(SrtpProfile::Aes128CmSha1_80, &[1; 32], &[2; 14]);
+    /// assert!(key.is_none());
+    /// ```
+    pub fn new(profile: SrtpProfile, key: &[u8], salt: &[u8]) -> Option<Self> {
+        if key.len() != profile.master_key_len() || salt.len() != profile.master_salt_len() {
+            return None;
+        }
+
+        Some(SrtpMasterKey {
+            profile,
+            key: key.to_vec(),
+            salt: salt.to_vec(),
+        })
+    }
+
+    /// Creates a new random master key.
+    pub fn random(profile: SrtpProfile) -> Self {
+        let key: Vec<u8> = (0..profile.master_key_len()).map(|_| random()).collect();
+        let salt: Vec<u8> = (0..profile.master_salt_len()).map(|_| random()).collect();
+
+        SrtpMasterKey { profile, key, salt }
+    }
+
+    /// The SRTP profile this key is for.
+    pub fn profile(&self) -> SrtpProfile {
+        self.profile
+    }
+
+    /// The master key.
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// The master salt.
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    /// Parses the `inline:` key parameter of an `a=crypto` line, which is the base64
+    /// of the key and salt concatenated.
+    pub(crate) fn from_inline(profile: SrtpProfile, inline: &str) -> Option<Self> {
+        let bytes = base64::decode(inline).ok()?;
+        if bytes.len() != profile.master_key_len() + profile.master_salt_len() {
+            return None;
+        }
+        let (key, salt) = bytes.split_at(profile.master_key_len());
+        SrtpMasterKey::new(profile, key, salt)
+    }
+
+    /// Formats the key as the `inline:` key parameter of an `a=crypto` line.
+    pub(crate) fn to_inline(&self) -> String {
+        let mut bytes = self.key.clone();
+        bytes.extend_from_slice(&self.salt);
+        base64::encode(bytes)
+    }
+}
+
+impl fmt::Debug for SrtpMasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't leak the key in logs.
+        f.debug_struct("SrtpMasterKey")
+            .field("profile", &self.profile)
+            .finish()
+    }
+}
+
+/// An `a=crypto` line in the SDP.
+///
+/// `a=crypto:<tag> <crypto-suite> inline:<key||salt>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdesCrypto {
+    /// Identifies the line in the offer. The answer uses the same tag.
+    pub tag: u32,
+    pub key: SrtpMasterKey,
+}
+
+/// The (local, remote) keys that changed, `None` for a direction keeping its key.
+pub(crate) type ChangedKeys = (Option<SrtpMasterKey>, Option<SrtpMasterKey>);
+
+/// The SDES state of an [`Rtc`][crate::Rtc].
+pub(crate) struct Sdes {
+    /// Profiles to offer, most preferred first.
+    profiles: Vec<SrtpProfile>,
+
+    /// m-line protocol for audio and video.
+    ///
+    /// `RTP/SAVPF`, unless the remote peer offers `RTP/SAVP`.
+    proto: Proto,
+
+    /// Our keys as offered or answered.
+    local: Vec<SdesCrypto>,
+
+    /// The local and remote keys in use.
+    active: Option<(SrtpMasterKey, SrtpMasterKey)>,
+}
+
+impl Sdes {
+    pub fn new(profiles: &[SrtpProfile]) -> Self {
+        Sdes {
+            profiles: profiles.to_vec(),
+            proto: Proto::RtpSavpf,
+            local: vec![],
+            active: None,
+        }
+    }
+
+    pub fn proto(&self) -> Proto {
+        self.proto
+    }
+
+    pub fn local(&self) -> &[SdesCrypto] {
+        &self.local
+    }
+
+    pub fn is_negotiated(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Keys to put in an offer.
+    ///
+    /// Once negotiated, we keep offering the key in use.
+    pub fn create_offer(&mut self) {
+        if self.local.is_empty() {
+            self.local = self
+                .profiles
+                .iter()
+                .enumerate()
+                .map(|(i, p)| SdesCrypto {
+                    tag: i as u32 + 1,
+                    key: SrtpMasterKey::random(*p),
+                })
+                .collect();
+        }
+    }
+
+    /// Pick one of the offered keys and make a local key to answer with.
+    ///
+    /// Returns the keys that changed, if any.
+    pub fn accept_offer(
+        &mut self,
+        proto: Proto,
+        offered: &[SdesCrypto],
+    ) -> Result<Option<ChangedKeys>, String> {
+        // The first offered key we support, the offer is in the remote's preference order.
+        let remote = offered
+            .iter()
+            .find(|c| self.profiles.contains(&c.key.profile()))
+            .ok_or_else(|| "No supported a=crypto in offer".to_string())?;
+
+        // Keep our key if the profile is the same, a re-offer with a new remote key
+        // must not restart what we send.
+        let local_key = match &self.active {
+            Some((l, _)) if l.profile() == remote.key.profile() => l.clone(),
+            _ => SrtpMasterKey::random(remote.key.profile()),
+        };
+
+        if proto.is_sdes() {
+            self.proto = proto;
+        }
+
+        self.local = vec![SdesCrypto {
+            tag: remote.tag,
+            key: local_key.clone(),
+        }];
+
+        Ok(self.activate(local_key, remote.key.clone()))
+    }
+
+    /// Find the answered key matching one we offered.
+    ///
+    /// Returns the keys that changed, if any.
+    pub fn accept_answer(
+        &mut self,
+        answered: &[SdesCrypto],
+    ) -> Result<Option<ChangedKeys>, String> {
+        let (local, remote) = answered
+            .iter()
+            .find_map(|a| {
+                self.local
+                    .iter()
+                    .find(|l| l.tag == a.tag && l.key.profile() == a.key.profile())
+                    .map(|l| (l.clone(), a.key.clone()))
+            })
+            .ok_or_else(|| "No a=crypto in answer matching the offer".to_string())?;
+
+        self.local = vec![local.clone()];
+
+        Ok(self.activate(local.key, remote))
+    }
+
+    /// Use keys that were exchanged outside of SDP.
+    ///
+    /// Returns the keys that changed, if any.
+    pub fn set_keys(&mut self, local: SrtpMasterKey, remote: SrtpMasterKey) -> Option<ChangedKeys> {
+        self.activate(local, remote)
+    }
+
+    fn activate(&mut self, local: SrtpMasterKey, remote: SrtpMasterKey) -> Option<ChangedKeys> {
+        let (old_local, old_remote) = match &self.active {
+            Some((l, r)) => (Some(l), Some(r)),
+            None => (None, None),
+        };
+
+        // Recreating an SRTP context with the same key would reuse SRTCP indexes.
+        let changed_local = (old_local != Some(&local)).then(|| local.clone());
+        let changed_remote = (old_remote != Some(&remote)).then(|| remote.clone());
+
+        if changed_local.is_none() && changed_remote.is_none() {
+            return None;
+        }
+
+        self.active = Some((local, remote));
+        Some((changed_local, changed_remote))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inline_roundtrip() {
+        for profile in SrtpProfile::ALL {
+            let key = SrtpMasterKey::random(*profile);
+            let inline = key.to_inline();
+            assert_eq!(SrtpMasterKey::from_inline(*profile, &inline), Some(key));
+        }
+    }
+
+    #[test]
+    fn inline_rfc_4568() {
+        // 30 bytes, AES_CM_128_HMAC_SHA1_80 from RFC 4568.
+        let inline = "PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR";
+
+        let key = SrtpMasterKey::from_inline(SrtpProfile::Aes128CmSha1_80, inline).unwrap();
+        assert_eq!(key.key().len(), 16);
+        assert_eq!(key.salt().len(), 14);
+
+        assert!(SrtpMasterKey::from_inline(SrtpProfile::AeadAes128Gcm, inline).is_none());
+        assert!(SrtpMasterKey::from_inline(SrtpProfile::Aes128CmSha1_80, "#").is_none());
+    }
+
+    #[test]
+    fn negotiate() {
+        let mut offerer = Sdes::new(&[SrtpProfile::AeadAes128Gcm, SrtpProfile::Aes128CmSha1_80]);
+        let mut answerer = Sdes::new(&[SrtpProfile::Aes128CmSha1_80]);
+
+        offerer.create_offer();
+        assert_eq!(offerer.local().len(), 2);
+
+        let offer = offerer.local().to_vec();
+        let (a_local, a_remote) = answerer
+            .accept_offer(Proto::RtpSavpf, &offer)
+            .unwrap()
+            .unwrap();
+        let (a_local, a_remote) = (a_local.unwrap(), a_remote.unwrap());
+        assert_eq!(a_local.profile(), SrtpProfile::Aes128CmSha1_80);
+        assert_eq!(answerer.local()[0].tag, 2);
+
+        let answer = answerer.local().to_vec();
+        let (o_local, o_remote) = offerer.accept_answer(&answer).unwrap().unwrap();
+        assert_eq!(o_local, Some(a_remote));
+        assert_eq!(o_remote, Some(a_local.clone()));
+
+        // A repeated answer doesn't change the keys.
+        assert_eq!(offerer.accept_answer(&answer).unwrap(), None);
+
+        // A re-offer with a new key only changes the remote key.
+        let new_key = SrtpMasterKey::random(SrtpProfile::Aes128CmSha1_80);
+        let reoffer = [SdesCrypto {
+            tag: 1,
+            key: new_key.clone(),
+        }];
+        let changed = answerer.accept_offer(Proto::RtpSavpf, &reoffer).unwrap();
+        assert_eq!(changed, Some((None, Some(new_key))));
+        assert_eq!(answerer.local()[0].key, a_local);
+    }
+}
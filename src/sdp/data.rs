@@ -3,6 +3,7 @@
 use combine::EasyParser;
 use std::collections::HashSet;
 use std::fmt::{self};
+use std::net::{IpAddr, SocketAddr};
 use std::num::ParseFloatError;
 use std::ops::Deref;
 use std::str::FromStr;
@@ -14,6 +15,7 @@ use crate::format::FormatParams;
 use crate::format::PayloadParams;
 use crate::ice::{Candidate, IceCreds};
 use crate::rtp_::{Direction, Extension, Mid, Pt, Rid, SessionId, Ssrc};
+use crate::sdes::SdesCrypto;
 use crate::VERSION;
 
 use super::parser::sdp_parser;
@@ -42,11 +44,31 @@ impl Sdp {
         }
     }
 
+    /// The fingerprints of the session, or else of the first m-line having any.
+    ///
+    /// There can be several, using different hash functions.
     #[doc(hidden)]
-    pub fn fingerprint(&self) -> Option<Fingerprint> {
-        self.session
-            .fingerprint()
-            .or_else(|| self.media_lines.iter().find_map(|m| m.fingerprint()))
+    pub fn fingerprints(&self) -> Vec<Fingerprint> {
+        let session = self.session.fingerprints();
+        if !session.is_empty() {
+            return session;
+        }
+        self.media_lines
+            .iter()
+            .map(|m| m.fingerprints())
+            .find(|f| !f.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// The SDES keys of the first audio/video m-line. With BUNDLE, these are used
+    /// for all media.
+    #[doc(hidden)]
+    pub fn crypto(&self) -> (Proto, Vec<SdesCrypto>) {
+        self.media_lines
+            .iter()
+            .find(|m| m.typ.is_media() && !m.disabled)
+            .map(|m| (m.proto, m.crypto()))
+            .unwrap_or_default()
     }
 
     #[doc(hidden)]
@@ -56,6 +78,25 @@ impl Sdp {
             .or_else(|| self.media_lines.iter().find_map(|m| m.ice_creds()))
     }
 
+    /// Tells if a=ice-options has the option, at session level or in any m-line.
+    #[doc(hidden)]
+    pub fn has_ice_option(&self, option: &str) -> bool {
+        let session = self.session.attrs.iter().filter_map(|a| match a {
+            SessionAttribute::IceOptions(v) => Some(v),
+            _ => None,
+        });
+        let media = self.media_lines.iter().flat_map(|m| {
+            m.attrs.iter().filter_map(|a| match a {
+                MediaAttribute::IceOptions(v) => Some(v),
+                _ => None,
+            })
+        });
+
+        session
+            .chain(media)
+            .any(|v| v.split_whitespace().any(|o| o == option))
+    }
+
     #[doc(hidden)]
     pub fn ice_candidates(&self) -> impl Iterator<Item = &Candidate> {
         let mut candidates: HashSet<&Candidate> = HashSet::new();
@@ -78,6 +119,21 @@ impl Sdp {
             .or_else(|| self.media_lines.iter().find_map(|m| m.setup()))
     }
 
+    /// Whether all enabled audio/video m-lines have a=rtcp-mux. Data channels don't
+    /// have RTCP, so they don't count.
+    #[doc(hidden)]
+    pub fn rtcp_mux(&self) -> bool {
+        self.media_lines
+            .iter()
+            .filter(|m| m.typ.is_media() && !m.disabled)
+            .all(|m| m.rtcp_mux())
+    }
+
+    #[doc(hidden)]
+    pub fn rtcp_addr(&self) -> Option<SocketAddr> {
+        self.media_lines.iter().find_map(|m| m.rtcp_addr())
+    }
+
     fn do_assert_consistency(&self) -> Option<String> {
         // TODO: SDP assertions we need to make:
         // 1. Ensure that every m-line has the same PT configuration for a codec. I.e. if FIR is enabled
@@ -164,13 +220,17 @@ impl Session {
         })
     }
 
-    pub fn fingerprint(&self) -> Option<Fingerprint> {
-        for a in &self.attrs {
-            if let SessionAttribute::Fingerprint(v) = a {
-                return Some(v.clone());
-            }
-        }
-        None
+    pub fn fingerprints(&self) -> Vec<Fingerprint> {
+        self.attrs
+            .iter()
+            .filter_map(|a| {
+                if let SessionAttribute::Fingerprint(v) = a {
+                    Some(v.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
     }
 
     pub fn ice_lite(&self) -> bool {
@@ -179,6 +239,12 @@ impl Session {
             .any(|a| matches!(a, SessionAttribute::IceLite))
     }
 
+    pub fn extmap_allow_mixed(&self) -> bool {
+        self.attrs
+            .iter()
+            .any(|a| matches!(a, SessionAttribute::ExtmapAllowMixed))
+    }
+
     pub fn ice_candidates(&self) -> impl Iterator<Item = &Candidate> {
         self.attrs.iter().filter_map(|a| {
             if let SessionAttribute::Candidate(v) = a {
@@ -208,6 +274,7 @@ pub enum SessionAttribute {
         stream_ids: Vec<String>,
     },
     IceLite,
+    ExtmapAllowMixed,
     IceUfrag(String),
     IcePwd(String),
     IceOptions(String),
@@ -229,7 +296,8 @@ pub struct MediaLine {
     pub typ: MediaType,
     pub disabled: bool,
     pub proto: Proto,
-    pub pts: Vec<Pt>, // payload types 96 97 125 107 from the m= line
+    pub pts: Vec<Pt>,               // payload types 96 97 125 107 from the m= line
+    pub connection: Option<IpAddr>, // from the c= line
     pub bw: Option<Bandwidth>,
     pub attrs: Vec<MediaAttribute>,
 }
@@ -401,14 +469,14 @@ impl MediaLine {
 
         let dir_count = self.attrs.iter().filter(|a| is_dir(a)).count();
 
-        if self.proto == Proto::Srtp && dir_count != 1 {
+        if self.proto.is_rtp() && dir_count != 1 {
             return Some(format!(
                 "Expected exactly one of a=sendrecv, a=sendonly, a=recvonly, a=inactive for mid: {}",
                 self.mid()
             ));
         }
 
-        if self.proto == Proto::Srtp && self.pts.is_empty() {
+        if self.proto.is_rtp() && self.pts.is_empty() {
             return Some(format!("Expected at least one PT for mid: {}", self.mid()));
         }
 
@@ -472,13 +540,30 @@ impl MediaLine {
             pass: pass.to_string(),
         })
     }
-    pub fn fingerprint(&self) -> Option<Fingerprint> {
-        for a in &self.attrs {
-            if let MediaAttribute::Fingerprint(v) = a {
-                return Some(v.clone());
-            }
-        }
-        None
+    pub fn fingerprints(&self) -> Vec<Fingerprint> {
+        self.attrs
+            .iter()
+            .filter_map(|a| {
+                if let MediaAttribute::Fingerprint(v) = a {
+                    Some(v.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn crypto(&self) -> Vec<SdesCrypto> {
+        self.attrs
+            .iter()
+            .filter_map(|a| {
+                if let MediaAttribute::Crypto(v) = a {
+                    Some(v.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
     }
 
     /// This hoovers the ice candidates from all m-lines, lots of dupes.
@@ -501,6 +586,37 @@ impl MediaLine {
             .any(|a| matches!(a, MediaAttribute::EndOfCandidates))
     }
 
+    pub fn rtcp_mux(&self) -> bool {
+        self.attrs
+            .iter()
+            .any(|a| matches!(a, MediaAttribute::RtcpMux))
+    }
+
+    /// The address in a=rtcp. Without an address, the one in c= is used.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc3605#section-2.1
+    /// rtcp-attribute =  "a=rtcp:" port  [nettype space addrtype space
+    ///                       connection-address] CRLF
+    pub fn rtcp_addr(&self) -> Option<SocketAddr> {
+        let v = self.attrs.iter().find_map(|a| {
+            if let MediaAttribute::Rtcp(v) = a {
+                Some(v)
+            } else {
+                None
+            }
+        })?;
+
+        let mut parts = v.split_whitespace();
+        let port = parts.next()?.parse::<u16>().ok()?;
+        // Skip nettype and addrtype.
+        let ip = match parts.nth(2) {
+            Some(v) => v.parse::<IpAddr>().ok()?,
+            None => self.connection?,
+        };
+
+        Some(SocketAddr::new(ip, port))
+    }
+
     pub fn extmaps(&self) -> Vec<(u8, &Extension)> {
         let mut ret = vec![];
 
@@ -722,6 +838,12 @@ pub enum Proto {
     #[default]
     Srtp,
     Sctp,
+    /// SRTP keyed with SDES.
+    RtpSavpf,
+    /// SRTP keyed with SDES, without RTCP feedback. Legacy SIP.
+    RtpSavp,
+    /// Unencrypted RTP.
+    RtpAvpf,
 }
 
 impl Proto {
@@ -729,8 +851,21 @@ impl Proto {
         match self {
             Proto::Srtp => "UDP/TLS/RTP/SAVPF",
             Proto::Sctp => "UDP/DTLS/SCTP",
+            Proto::RtpSavpf => "RTP/SAVPF",
+            Proto::RtpSavp => "RTP/SAVP",
+            Proto::RtpAvpf => "RTP/AVPF",
         }
     }
+
+    /// Whether this is an audio/video protocol.
+    pub fn is_rtp(&self) -> bool {
+        !matches!(self, Proto::Sctp)
+    }
+
+    /// Whether the SRTP keys are exchanged with SDES.
+    pub fn is_sdes(&self) -> bool {
+        matches!(self, Proto::RtpSavpf | Proto::RtpSavp)
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -798,7 +933,9 @@ pub enum MediaAttribute {
     IceOptions(String),
     Fingerprint(Fingerprint),
     Setup(Setup), // active, passive, actpass, holdconn
-    Mid(Mid),     // 0, 1, 2
+    // a=crypto:1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR
+    Crypto(SdesCrypto),
+    Mid(Mid), // 0, 1, 2
     SctpPort(u16),
     MaxMessageSize(usize),
     // a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level
@@ -897,9 +1034,24 @@ pub enum FormatParam {
     /// * 64 00 1f - 6400=high (H)                  1f=level 3.1
     ProfileLevelId(u32),
 
-    /// VP9 profile id
+    /// VP9 and H265 profile id
     ProfileId(u32),
 
+    /// H265 level id
+    LevelId(u8),
+
+    /// H265 tier flag
+    TierFlag(u8),
+
+    /// AV1 profile
+    Profile(u8),
+
+    /// AV1 level index
+    LevelIdx(u8),
+
+    /// AV1 tier
+    Tier(u8),
+
     /// RTX (resend) codecs, which PT it concerns.
     Apt(Pt),
 
@@ -945,6 +1097,46 @@ impl FormatParam {
                     Unknown
                 }
             }
+            "level-id" => {
+                if let Ok(v) = v.parse() {
+                    LevelId(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
+            "tier-flag" => {
+                if let Ok(v) = v.parse() {
+                    TierFlag(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
+            "profile" => {
+                if let Ok(v) = v.parse() {
+                    Profile(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
+            "level-idx" => {
+                if let Ok(v) = v.parse() {
+                    LevelIdx(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
+            "tier" => {
+                if let Ok(v) = v.parse() {
+                    Tier(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
             "apt" => {
                 if let Ok(v) = v.parse::<u8>() {
                     Apt(v.into())
@@ -970,6 +1162,11 @@ impl fmt::Display for FormatParam {
             PacketizationMode(v) => write!(f, "packetization-mode={}", *v),
             ProfileLevelId(v) => write!(f, "profile-level-id={:06x}", *v),
             ProfileId(v) => write!(f, "profile-id={}", *v),
+            LevelId(v) => write!(f, "level-id={}", *v),
+            TierFlag(v) => write!(f, "tier-flag={}", *v),
+            Profile(v) => write!(f, "profile={}", *v),
+            LevelIdx(v) => write!(f, "level-idx={}", *v),
+            Tier(v) => write!(f, "tier={}", *v),
             Apt(v) => write!(f, "apt={v}"),
             Unknown => Ok(()),
         }
@@ -1162,6 +1359,7 @@ impl fmt::Display for SessionAttribute {
                 )?;
             }
             IceLite => write!(f, "a=ice-lite\r\n")?,
+            ExtmapAllowMixed => write!(f, "a=extmap-allow-mixed\r\n")?,
             IceUfrag(v) => write!(f, "a=ice-ufrag:{v}\r\n")?,
             IcePwd(v) => write!(f, "a=ice-pwd:{v}\r\n")?,
             IceOptions(v) => write!(f, "a=ice-options:{v}\r\n")?,
@@ -1243,6 +1441,13 @@ impl fmt::Display for MediaAttribute {
                 )?;
             }
             Setup(v) => write!(f, "a=setup:{}\r\n", v.setup_line())?,
+            Crypto(v) => write!(
+                f,
+                "a=crypto:{} {} inline:{}\r\n",
+                v.tag,
+                v.key.profile().sdes_name(),
+                v.key.to_inline()
+            )?,
             Mid(v) => write!(f, "a=mid:{v}\r\n")?,
             SctpPort(v) => write!(f, "a=sctp-port:{v}\r\n")?,
             MaxMessageSize(v) => write!(f, "a=max-message-size:{v}\r\n")?,
@@ -1403,6 +1608,64 @@ mod test {
         assert_eq!(f.to_string(), "minptime=10;useinbandfec=1");
     }
 
+    #[test]
+    fn fmtp_av1() {
+        let f = FormatParams::parse_line("level-idx=5;profile=0;tier=0");
+        assert_eq!(f.profile, Some(0));
+        assert_eq!(f.level_idx, Some(5));
+        assert_eq!(f.tier, Some(0));
+        assert_eq!(f.to_string(), "level-idx=5;profile=0;tier=0");
+    }
+
+    #[test]
+    fn fmtp_h265() {
+        let f = FormatParams::parse_line("level-id=93;profile-id=1;tier-flag=0;tx-mode=SRST");
+        assert_eq!(f.profile_id, Some(1));
+        assert_eq!(f.level_id, Some(93));
+        assert_eq!(f.tier_flag, Some(0));
+        assert_eq!(f.to_string(), "level-id=93;profile-id=1;tier-flag=0");
+    }
+
+    #[test]
+    fn rtcp_addr() {
+        let mut line = MediaLine {
+            typ: MediaType::Audio,
+            disabled: false,
+            proto: Proto::Srtp,
+            pts: vec![],
+            connection: None,
+            bw: None,
+            attrs: vec![MediaAttribute::Rtcp("9 IN IP4 0.0.0.0".into())],
+        };
+        assert_eq!(line.rtcp_addr(), Some("0.0.0.0:9".parse().unwrap()));
+        assert!(!line.rtcp_mux());
+
+        // Without c= address.
+        line.attrs = vec![MediaAttribute::Rtcp("53020".into())];
+        assert_eq!(line.rtcp_addr(), None);
+
+        // Port only uses the c= address.
+        line.connection = Some("198.51.100.1".parse().unwrap());
+        assert_eq!(
+            line.rtcp_addr(),
+            Some("198.51.100.1:53020".parse().unwrap())
+        );
+
+        line.attrs = vec![
+            MediaAttribute::Rtcp("53020 IN IP6 2001:2345:6789:ABCD:EF01:2345:6789:ABCD".into()),
+            MediaAttribute::RtcpMux,
+        ];
+        assert_eq!(
+            line.rtcp_addr(),
+            Some(
+                "[2001:2345:6789:ABCD:EF01:2345:6789:ABCD]:53020"
+                    .parse()
+                    .unwrap()
+            )
+        );
+        assert!(line.rtcp_mux());
+    }
+
     #[test]
     fn parse_error() {
         let input = "v=0\r\n\
@@ -1466,6 +1729,7 @@ mod test {
                     113.into(),
                     126.into(),
                 ],
+                connection: None,
                 bw: None,
                 attrs: vec![
                         MediaAttribute::Rtcp("9 IN IP4 0.0.0.0".into()),
@@ -10,7 +10,7 @@ pub(crate) use data::{FormatParam, Sdp, Session, SessionAttribute, Setup};
 pub(crate) use data::{MediaAttribute, MediaLine, MediaType, Msid, Proto};
 pub(crate) use data::{Simulcast, SimulcastGroups};
 
-#[cfg(test)]
+#[cfg(all(test, feature = "openssl"))]
 pub(crate) use data::RtpMap;
 
 mod parser;
@@ -1,4 +1,4 @@
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use {
     combine::error::*,
     combine::parser::char::*,
@@ -8,9 +8,11 @@ use {
     combine::{ParseError, Parser, Stream},
 };
 
-use crate::dtls::Fingerprint;
-use crate::ice::{Candidate, CandidateKind};
+use crate::dtls::{Fingerprint, SrtpProfile};
+use crate::ice::{Candidate, CandidateKind, TcpType};
+use crate::io::Protocol;
 use crate::rtp_::{Direction, Extension, Mid, Pt, SessionId, Ssrc};
+use crate::sdes::{SdesCrypto, SrtpMasterKey};
 
 use super::data::*;
 
@@ -144,6 +146,10 @@ where
     // a=ice-lite
     let ice_lite = attribute_line_flag("ice-lite").map(|_| SessionAttribute::IceLite);
 
+    // a=extmap-allow-mixed
+    let allow_mixed =
+        attribute_line_flag("extmap-allow-mixed").map(|_| SessionAttribute::ExtmapAllowMixed);
+
     // a=ice-ufrag:IdNYTNL1fjvjyEzL
     let ice_ufrag = attribute_line("ice-ufrag", any_value()).map(SessionAttribute::IceUfrag);
 
@@ -190,6 +196,7 @@ where
         attempt(group),
         attempt(msid_semantic),
         attempt(ice_lite),
+        attempt(allow_mixed),
         attempt(ice_ufrag),
         attempt(ice_pwd),
         attempt(ice_opt),
@@ -225,6 +232,31 @@ where
         })
     };
 
+    // Either an IP address, or a hostname (typically mDNS .local) to be resolved later.
+    let host = || {
+        not_sp::<Input>().map(|s| match s.parse::<IpAddr>() {
+            Ok(ip) => (ip, None),
+            Err(_) => (IpAddr::V4(Ipv4Addr::UNSPECIFIED), Some(s)),
+        })
+    };
+
+    // Candidates with other transports, like "ssltcp", fail here. In an SDP they are
+    // then parsed as unused attribute lines, which skips them.
+    let proto = not_sp().and_then(|s| match s.to_lowercase().as_str() {
+        "udp" => Ok(Protocol::Udp),
+        "tcp" => Ok(Protocol::Tcp),
+        _ => Err(StreamErrorFor::<Input>::message_static_message(
+            "unknown candidate protocol",
+        )),
+    });
+
+    // https://www.rfc-editor.org/rfc/rfc6544#section-4.5
+    let tcptype = choice((
+        attempt(string("active").map(|_| TcpType::Active)),
+        attempt(string("passive").map(|_| TcpType::Passive)),
+        string("so").map(|_| TcpType::So),
+    ));
+
     let kind = choice((
         string("host").map(|_| CandidateKind::Host),
         string("prflx").map(|_| CandidateKind::PeerReflexive),
@@ -242,14 +274,14 @@ where
                     .map_err(StreamErrorFor::<Input>::message_format)
             }),
             token(' '),
-            not_sp(),
+            proto,
             token(' '),
             not_sp().and_then(|s| {
                 s.parse::<u32>()
                     .map_err(StreamErrorFor::<Input>::message_format)
             }),
             token(' '),
-            ip_addr(),
+            host(),
             token(' '),
             port(),
             string(" typ "),
@@ -260,12 +292,30 @@ where
                 string(" rport "),
                 port(),
             )),
+            optional((attempt(string(" tcptype ")), tcptype)),
             optional((attempt(string(" ufrag ")), not_sp())),
         ),
     )
     .map(
-        |(found, _, comp_id, _, proto, _, prio, _, addr, _, port, _, kind, raddr, ufrag)| {
-            Candidate::parsed(
+        |(
+            found,
+            _,
+            comp_id,
+            _,
+            proto,
+            _,
+            prio,
+            _,
+            (addr, hostname),
+            _,
+            port,
+            _,
+            kind,
+            raddr,
+            tcptype,
+            ufrag,
+        )| {
+            let mut c = Candidate::parsed(
                 found,
                 comp_id,
                 proto,
@@ -273,8 +323,13 @@ where
                 SocketAddr::from((addr, port)),
                 kind,
                 raddr.map(|(_, addr, _, port)| SocketAddr::from((addr, port))),
+                tcptype.map(|(_, t)| t),
                 ufrag.map(|(_, u)| u),
-            )
+            );
+            if let Some(hostname) = hostname {
+                c.set_hostname(hostname);
+            }
+            c
         },
     )
 }
@@ -312,12 +367,16 @@ where
         optional(bandwidth_line()),             // b=AS:2500
         many::<Vec<_>, _, _>(media_attribute_line()),
     )
-        .and_then(|((typ, port, proto, pts), _, bw, attrs)| {
+        .and_then(|((typ, port, proto, pts), c, bw, attrs)| {
+            // Skip nettype and addrtype. Multicast addresses can have a /ttl suffix.
+            let connection =
+                c.and_then(|c| c.split_whitespace().nth(2)?.split('/').next()?.parse().ok());
             let m = MediaLine {
                 typ,
                 disabled: port == "0",
                 proto,
                 pts,
+                connection,
                 bw,
                 attrs,
             };
@@ -355,6 +414,9 @@ where
 
     let proto_line = choice((
         attempt(string("UDP/TLS/RTP/SAVPF").map(|_| Proto::Srtp)),
+        attempt(string("RTP/SAVPF").map(|_| Proto::RtpSavpf)),
+        attempt(string("RTP/SAVP").map(|_| Proto::RtpSavp)),
+        attempt(string("RTP/AVPF").map(|_| Proto::RtpAvpf)),
         attempt(string("DTLS/SCTP").map(|_| Proto::Sctp)),
         attempt(string("UDP/DTLS/SCTP").map(|_| Proto::Sctp)),
     ));
@@ -426,6 +488,38 @@ where
     // a=setup:actpass
     let setup = attribute_line("setup", setup_val).map(MediaAttribute::Setup);
 
+    // a=crypto:1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^20
+    // a=crypto:<tag> <crypto-suite> inline:<key||salt>[|lifetime]
+    // Lines with MKI or session parameters are not supported, and end up as Unused.
+    let crypto = attribute_line(
+        "crypto",
+        (
+            many1::<String, _, _>(digit()).and_then(|s| {
+                s.parse::<u32>()
+                    .map_err(StreamErrorFor::<Input>::message_format)
+            }),
+            token(' '),
+            not_sp::<Input>(),
+            string(" inline:"),
+            many1::<String, _, _>(satisfy(|c: char| {
+                c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '='
+            })),
+            // Lifetime is ignored, we don't rekey.
+            optional((
+                token('|'),
+                many1::<String, _, _>(satisfy(|c: char| c == '^' || c.is_ascii_digit())),
+            )),
+        ),
+    )
+    .and_then(|(tag, _, suite, _, inline, _)| {
+        let key = SrtpProfile::from_sdes_name(&suite)
+            .and_then(|p| SrtpMasterKey::from_inline(p, &inline))
+            .ok_or_else(|| {
+                StreamErrorFor::<Input>::message_static_message("unsupported a=crypto")
+            })?;
+        Ok::<_, StreamErrorFor<Input>>(MediaAttribute::Crypto(SdesCrypto { tag, key }))
+    });
+
     // a=mid:0
     let mid = attribute_line("mid", any_value())
         .map(|m| Mid::from(m.as_str()))
@@ -694,8 +788,8 @@ where
         attempt(ice_ufrag),
         attempt(ice_pwd),
         attempt(ice_opt),
-        attempt(finger),
-        attempt(setup),
+        // Grouped, since choice() takes at most 26 parsers.
+        choice((attempt(finger), attempt(setup), attempt(crypto))),
         attempt(mid),
         attempt(sctp_port),
         attempt(max_message_size),
@@ -843,6 +937,9 @@ mod test {
     fn session_attribute_line_simple() {
         let x = session_attribute_line().parse("a=ice-lite");
         assert_eq!(x, Ok((SessionAttribute::IceLite, "")));
+
+        let x = session_attribute_line().parse("a=extmap-allow-mixed");
+        assert_eq!(x, Ok((SessionAttribute::ExtmapAllowMixed, "")));
     }
 
     #[test]
@@ -928,6 +1025,43 @@ mod test {
         assert_eq!("a=simulcast:send 4 recv 2;3\r\n", x.0.to_string());
     }
 
+    #[test]
+    fn media_attribute_line_crypto() {
+        let x = media_attribute_line()
+            .parse("a=crypto:1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^20")
+            .unwrap();
+        let MediaAttribute::Crypto(crypto) = &x.0 else {
+            panic!("Not a=crypto: {:?}", x.0);
+        };
+        assert_eq!(crypto.tag, 1);
+        assert_eq!(crypto.key.profile(), SrtpProfile::Aes128CmSha1_80);
+        // The lifetime is not kept.
+        assert_eq!(
+            "a=crypto:1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR\r\n",
+            x.0.to_string()
+        );
+
+        // Unknown suites are not understood.
+        let x = media_attribute_line()
+            .parse("a=crypto:1 F8_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR")
+            .unwrap();
+        assert!(matches!(x.0, MediaAttribute::Unused(_)));
+    }
+
+    #[test]
+    fn media_line_sdes() {
+        let m = media_line().parse("m=video 9 RTP/SAVPF 96\r\n").unwrap();
+        assert_eq!((m.0).2, Proto::RtpSavpf);
+        let m = media_line().parse("m=audio 9 RTP/SAVP 0\r\n").unwrap();
+        assert_eq!((m.0).2, Proto::RtpSavp);
+    }
+
+    #[test]
+    fn media_line_unencrypted() {
+        let m = media_line().parse("m=audio 9 RTP/AVPF 111\r\n").unwrap();
+        assert_eq!((m.0).2, Proto::RtpAvpf);
+    }
+
     #[test]
     fn media_line_simple() {
         let m = media_line().parse("m=audio 9 UDP/TLS/RTP/SAVPF 10\r\n");
@@ -1155,6 +1289,63 @@ mod test {
         assert_eq!(c.ufrag(), Some("abc"));
     }
 
+    #[test]
+    fn parse_candidate_tcptype() {
+        let a =
+            "a=candidate:1 1 TCP 1518280447 192.168.1.2 9 typ host tcptype active ufrag abc\r\n";
+
+        let (c, _) = candidate().parse(a).unwrap();
+        assert_eq!(c.proto(), Protocol::Tcp);
+        assert_eq!(c.tcptype(), Some(TcpType::Active));
+        assert_eq!(c.ufrag(), Some("abc"));
+
+        let a = "a=candidate:1 1 tcp 1518280447 192.168.1.2 443 typ host tcptype passive\r\n";
+        let (c, _) = candidate().parse(a).unwrap();
+        assert_eq!(c.tcptype(), Some(TcpType::Passive));
+        assert_eq!(c.addr().port(), 443);
+    }
+
+    #[test]
+    fn parse_unknown_candidate_protocol() {
+        let sdp = "v=0\r\n\
+        o=- 5058682828002148772 3 IN IP4 0.0.0.0\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        a=ice-ufrag:S5hk\r\n\
+        a=ice-pwd:0zV/Yu3y8aDzbHgqWhnVQhqP\r\n\
+        a=fingerprint:sha-256 8C:64:ED:03:76:D0:3D:B4:C1:5C:14:7E:F9:55:21:61:5E:9F:F3:B2:9E:C8:8D:5E:AF:23:47:E0:C3:A6:3F:0B\r\n\
+        m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=setup:actpass\r\n\
+        a=mid:0\r\n\
+        a=sctp-port:5000\r\n\
+        a=candidate:1 1 udp 2113929471 203.0.113.100 10100 typ host\r\n\
+        a=candidate:2 1 ssltcp 2113929470 203.0.113.100 443 typ host\r\n\
+        ";
+
+        let (sdp, _) = sdp_parser().parse(sdp).unwrap();
+        let cands: Vec<_> = sdp.media_lines[0]
+            .attrs
+            .iter()
+            .filter(|a| matches!(a, MediaAttribute::Candidate(_)))
+            .collect();
+        assert_eq!(cands.len(), 1);
+    }
+
+    #[test]
+    fn parse_candidate_mdns() {
+        let a = "a=candidate:1 1 udp 2122262783 2c3f05f6-8a7f-4d8e-9f2c-0c7e5a5b7e21.local \
+            54400 typ host\r\n";
+
+        let (c, _) = candidate().parse(a).unwrap();
+        assert!(c.is_unresolved());
+        assert_eq!(
+            c.hostname(),
+            Some("2c3f05f6-8a7f-4d8e-9f2c-0c7e5a5b7e21.local")
+        );
+        assert_eq!(c.addr().port(), 54400);
+    }
+
     #[test]
     fn parse_firefox_missing_setup_on_mid1() {
         let sdp = "v=0\r\n\
@@ -1204,6 +1395,27 @@ mod test {
         println!("{:?}", parsed);
         parsed.expect("to parse ok");
     }
+
+    #[test]
+    fn parse_rtcp_port_only() {
+        let sdp = "v=0\r\n\
+        o=- 0 0 IN IP4 198.51.100.1\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        m=audio 53018 RTP/AVPF 0\r\n\
+        c=IN IP4 198.51.100.1\r\n\
+        a=rtcp:53020\r\n\
+        a=sendrecv\r\n\
+        a=mid:0\r\n\
+        a=rtpmap:0 PCMU/8000\r\n\
+        ";
+
+        let (sdp, _) = sdp_parser().parse(sdp).unwrap();
+        assert_eq!(
+            sdp.media_lines[0].rtcp_addr(),
+            Some("198.51.100.1:53020".parse().unwrap())
+        );
+    }
 }
 
 // Safari addTransceiver('audio', {direction: 'sendonly'}))
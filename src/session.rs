@@ -17,13 +17,14 @@ use crate::rtp_::Pt;
 use crate::rtp_::SeqNo;
 use crate::rtp_::SRTCP_OVERHEAD;
 use crate::rtp_::{extend_u16, RtpHeader, SessionId, TwccRecvRegister, TwccSendRegister};
-use crate::rtp_::{Bitrate, ExtensionMap, Mid, Rtcp, RtcpFb};
+use crate::rtp_::{Bitrate, Extension, ExtensionMap, Mid, Rtcp, RtcpFb};
 use crate::rtp_::{SrtpContext, Ssrc};
+use crate::sdes::SrtpMasterKey;
 use crate::stats::StatsSnapshot;
 use crate::streams::{RtpPacket, Streams};
 use crate::util::{already_happened, not_happening, Soonest};
 use crate::Event;
-use crate::{RtcConfig, RtcError};
+use crate::{MediaProtection, RtcConfig, RtcError};
 
 /// Minimum time we delay between sending nacks. This should be
 /// set high enough to not cause additional problems in very bad
@@ -68,6 +69,10 @@ pub(crate) struct Session {
 
     srtp_rx: Option<SrtpContext>,
     srtp_tx: Option<SrtpContext>,
+
+    /// Plain RTP/RTCP without SRTP, see [`MediaProtection::Unencrypted`].
+    unencrypted: bool,
+
     last_nack: Instant,
     last_twcc: Instant,
     twcc: u64,
@@ -89,6 +94,10 @@ pub(crate) struct Session {
 
     pub ice_lite: bool,
 
+    /// Whether the remote signaled a=extmap-allow-mixed, i.e. accepts two-byte
+    /// header extensions.
+    pub remote_extmap_allow_mixed: bool,
+
     /// Whether we are running in RTP-mode.
     pub rtp_mode: bool,
 
@@ -140,6 +149,7 @@ impl Session {
 
             srtp_rx: None,
             srtp_tx: None,
+            unencrypted: config.media_protection == MediaProtection::Unencrypted,
             last_nack: already_happened(),
             last_twcc: already_happened(),
             twcc: 0,
@@ -151,6 +161,7 @@ impl Session {
             poll_packet_buf: vec![0; 2000],
             pending_packet: None,
             ice_lite: config.ice_lite,
+            remote_extmap_allow_mixed: false,
             rtp_mode: config.rtp_mode,
             feedback_tx: VecDeque::new(),
             feedback_rx: VecDeque::new(),
@@ -199,6 +210,36 @@ impl Session {
         self.srtp_tx = Some(SrtpContext::new(srtp_profile, &mat, left));
     }
 
+    /// Set the SRTP keys exchanged with SDES. The local key protects what we send.
+    ///
+    /// A direction without a new key keeps its context, which means the SRTCP index
+    /// of what we send keeps increasing when only the remote key changes.
+    pub fn set_srtp_master_keys(
+        &mut self,
+        local: Option<&SrtpMasterKey>,
+        remote: Option<&SrtpMasterKey>,
+    ) {
+        if let Some(remote) = remote {
+            self.srtp_rx = Some(SrtpContext::from_master_key(
+                remote.profile(),
+                remote.key(),
+                remote.salt(),
+            ));
+        }
+        if let Some(local) = local {
+            self.srtp_tx = Some(SrtpContext::from_master_key(
+                local.profile(),
+                local.key(),
+                local.salt(),
+            ));
+        }
+    }
+
+    #[cfg(all(test, feature = "openssl"))]
+    pub(crate) fn srtp_tx(&mut self) -> Option<&mut SrtpContext> {
+        self.srtp_tx.as_mut()
+    }
+
     pub fn handle_timeout(&mut self, now: Instant) -> Result<(), RtcError> {
         // Payload any waiting samples
         self.do_payload(now)?;
@@ -369,13 +410,10 @@ impl Session {
             return;
         };
 
-        let srtp = match self.srtp_rx.as_mut() {
-            Some(v) => v,
-            None => {
-                trace!("Rejecting SRTP while missing SrtpContext");
-                return;
-            }
-        };
+        if !self.unencrypted && self.srtp_rx.is_none() {
+            trace!("Rejecting SRTP while missing SrtpContext");
+            return;
+        }
 
         // Both of these unwraps are fine because mid_and_ssrc_for_header guarantees it.
         let media = self.medias.iter_mut().find(|m| m.mid() == mid).unwrap();
@@ -399,7 +437,12 @@ impl Session {
         // Either way we get a seq_no_outer which is used to decrypt the SRTP.
         let receipt_outer = stream.update(now, &header, clock_rate, is_repair);
 
-        let mut data = match srtp.unprotect_rtp(buf, &header, *receipt_outer.seq_no) {
+        let unprotected = match self.srtp_rx.as_mut() {
+            Some(srtp) => srtp.unprotect_rtp(buf, &header, *receipt_outer.seq_no),
+            None => buf.get(header.header_len..).map(|b| b.to_vec()),
+        };
+
+        let mut data = match unprotected {
             Some(v) => v,
             None => {
                 trace!("Failed to unprotect SRTP");
@@ -440,6 +483,13 @@ impl Session {
             receipt_outer
         };
 
+        // The Dependency Descriptor typically references a structure from earlier packets.
+        let dd_id = self.exts.id_of(Extension::DependencyDescriptor);
+        if let Some(dd) = dd_id.and_then(|id| RtpHeader::ext_value(buf, id)) {
+            header.ext_vals.dependency_descriptor =
+                stream.parse_dependency_descriptor(dd).map(Box::new);
+        }
+
         let Some(packet) = stream.handle_rtp(now, header, data, receipt.seq_no, receipt.time)
         else {
             return;
@@ -464,8 +514,12 @@ impl Session {
     }
 
     fn handle_rtcp(&mut self, now: Instant, buf: &[u8]) -> Option<()> {
-        let srtp: &mut SrtpContext = self.srtp_rx.as_mut()?;
-        let unprotected = srtp.unprotect_rtcp(buf)?;
+        let unprotected = if self.unencrypted {
+            buf.to_vec()
+        } else {
+            let srtp: &mut SrtpContext = self.srtp_rx.as_mut()?;
+            srtp.unprotect_rtcp(buf)?
+        };
 
         Rtcp::read_packet(&unprotected, &mut self.feedback_rx);
         let mut need_configure_pacer = false;
@@ -579,8 +633,8 @@ impl Session {
         None
     }
 
-    fn ready_for_srtp(&self) -> bool {
-        self.srtp_rx.is_some() && self.srtp_tx.is_some()
+    pub fn ready_for_srtp(&self) -> bool {
+        self.unencrypted || self.srtp_rx.is_some() && self.srtp_tx.is_some()
     }
 
     pub fn poll_datagram(&mut self, now: Instant) -> Option<net::DatagramSend> {
@@ -631,6 +685,10 @@ impl Session {
 
         data.truncate(len);
 
+        if self.unencrypted {
+            return Some(data.into());
+        }
+
         let srtp = self.srtp_tx.as_mut()?;
         let protected = srtp.protect_rtcp(&data);
 
@@ -643,7 +701,9 @@ impl Session {
     }
 
     fn poll_packet(&mut self, now: Instant) -> Option<DatagramSend> {
-        let srtp_tx = self.srtp_tx.as_mut()?;
+        if !self.ready_for_srtp() {
+            return None;
+        }
 
         // Figure out which, if any, queue to poll
         let mid = self.pacer.poll_queue()?;
@@ -661,7 +721,8 @@ impl Session {
 
         let params = &self.codec_config;
         let exts = media.remote_extmap();
-        let receipt = stream.poll_packet(now, exts, &mut self.twcc, params, buf)?;
+        let two_byte_ext = self.remote_extmap_allow_mixed;
+        let receipt = stream.poll_packet(now, exts, two_byte_ext, &mut self.twcc, params, buf)?;
 
         let PacketReceipt {
             header,
@@ -685,7 +746,10 @@ impl Session {
             raw_packets.push_back(Box::new(RawPacket::RtpTx(header.clone(), buf.clone())));
         }
 
-        let protected = srtp_tx.protect_rtp(buf, &header, *seq_no);
+        let protected = match self.srtp_tx.as_mut() {
+            Some(srtp_tx) => srtp_tx.protect_rtp(buf, &header, *seq_no),
+            None => buf.clone(),
+        };
 
         self.twcc_tx_register
             .register_seq(twcc_seq.into(), now, payload_size);
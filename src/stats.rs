@@ -6,7 +6,9 @@ use std::{
 };
 
 use crate::rtp_::{Mid, Rid};
-use crate::Bitrate;
+use crate::{Bitrate, Candidate};
+
+pub use crate::ice::CheckState;
 
 pub(crate) struct Stats {
     last_now: Instant,
@@ -24,6 +26,7 @@ pub(crate) struct StatsSnapshot {
     pub ingress: HashMap<(Mid, Option<Rid>), MediaIngressStats>,
     pub egress: HashMap<(Mid, Option<Rid>), MediaEgressStats>,
     pub bwe_tx: Option<Bitrate>,
+    pub ice_pairs: Vec<IceCandidatePairStats>,
     timestamp: Instant,
 }
 
@@ -39,6 +42,7 @@ impl StatsSnapshot {
             ingress: HashMap::new(),
             egress: HashMap::new(),
             bwe_tx: None,
+            ice_pairs: vec![],
             timestamp,
         }
     }
@@ -51,6 +55,7 @@ pub(crate) enum StatsEvent {
     Peer(PeerStats),
     MediaEgress(MediaEgressStats),
     MediaIngress(MediaIngressStats),
+    Ice(IceStats),
 }
 
 /// Peer statistics in [`Event::PeerStats`][crate::Event::PeerStats].
@@ -76,6 +81,60 @@ pub struct PeerStats {
     pub ingress_loss_fraction: Option<f32>,
 }
 
+/// ICE statistics in [`Event::IceStats`][crate::Event::IceStats].
+///
+/// Can also be obtained at any time with [`Rtc::ice_stats()`][crate::Rtc::ice_stats].
+#[derive(Debug, Clone)]
+pub struct IceStats {
+    /// All current candidate pairs. Failed pairs are removed.
+    pub pairs: Vec<IceCandidatePairStats>,
+    /// Timestamp when this event was generated.
+    pub timestamp: Instant,
+}
+
+impl IceStats {
+    /// The pair currently selected for sending data, if any.
+    pub fn selected(&self) -> Option<&IceCandidatePairStats> {
+        self.pairs.iter().find(|p| p.selected)
+    }
+}
+
+/// Statistics for an ICE candidate pair in [`IceStats`].
+///
+/// Spec equivalent of [`RTCIceCandidatePairStats`][1].
+///
+/// [1]: https://www.w3.org/TR/webrtc-stats/#candidatepair-dict*
+#[derive(Debug, Clone)]
+pub struct IceCandidatePairStats {
+    /// The local candidate of the pair.
+    pub local: Candidate,
+    /// The remote candidate of the pair.
+    pub remote: Candidate,
+    /// State of the connectivity checks.
+    pub state: CheckState,
+    /// Whether the pair is nominated.
+    pub nominated: bool,
+    /// Whether this is the pair used for sending data.
+    pub selected: bool,
+    /// Round trip time of the latest answered binding request.
+    pub rtt: Option<Duration>,
+    /// Sum of all binding request round trip times. Divide by `responses_received`
+    /// for the average.
+    pub total_rtt: Duration,
+    /// Number of binding requests sent.
+    pub requests_sent: u64,
+    /// Number of binding responses received.
+    pub responses_received: u64,
+    /// Number of binding requests received.
+    pub requests_received: u64,
+    /// Number of binding responses sent.
+    pub responses_sent: u64,
+    /// Total data bytes sent, not counting connectivity checks.
+    pub bytes_sent: u64,
+    /// Total data bytes received, not counting connectivity checks.
+    pub bytes_received: u64,
+}
+
 /// Outgoing media statistics in [`Event::MediaEgressStats`][crate::Event::MediaEgressStats].
 ///
 /// note: when simulcast is disabled, `rid` is `None`
@@ -238,6 +297,11 @@ impl Stats {
             self.events.push_back(StatsEvent::MediaEgress(event));
         }
 
+        self.events.push_back(StatsEvent::Ice(IceStats {
+            pairs: std::mem::take(&mut snapshot.ice_pairs),
+            timestamp: snapshot.timestamp,
+        }));
+
         self.last_now = snapshot.timestamp;
     }
 
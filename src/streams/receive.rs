@@ -1,8 +1,10 @@
 use std::collections::VecDeque;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use crate::media::KeyframeRequestKind;
 use crate::rtp_::{extend_u32, DlrrItem, ExtendedReport, Fir, FirEntry, MediaTime};
+use crate::rtp_::{DependencyDescriptor, FrameDependencyStructure};
 use crate::rtp_::{Mid, Pli, Pt, ReceiverReport};
 use crate::rtp_::{ReportBlock, ReportList, Rid, Rrtr, Rtcp, RtcpFb, RtpHeader, SenderInfo, SeqNo};
 use crate::rtp_::{SdesType, Ssrc};
@@ -81,6 +83,11 @@ pub struct StreamRx {
     /// Sequence number of the next FIR.
     fir_seq_no: u8,
 
+    /// Last received Dependency Descriptor structure.
+    ///
+    /// Most descriptors only reference templates in this structure.
+    dd_structure: Option<Arc<FrameDependencyStructure>>,
+
     /// Last time we produced regular feedback RR.
     last_receiver_report: Instant,
 
@@ -145,6 +152,7 @@ impl StreamRx {
             last_time: None,
             pending_request_keyframe: None,
             fir_seq_no: 0,
+            dd_structure: None,
             last_receiver_report: already_happened(),
             stats: StreamRxStats::default(),
             check_paused_at: None,
@@ -391,6 +399,21 @@ impl StreamRx {
         Some(packet)
     }
 
+    /// Parses a Dependency Descriptor, which typically references the last structure
+    /// received on the stream.
+    pub(crate) fn parse_dependency_descriptor(
+        &mut self,
+        buf: &[u8],
+    ) -> Option<DependencyDescriptor> {
+        let dd = DependencyDescriptor::parse(buf, self.dd_structure.as_ref())?;
+
+        if dd.attached_structure {
+            self.dd_structure = Some(dd.structure.clone());
+        }
+
+        Some(dd)
+    }
+
     pub(crate) fn un_rtx(&self, header: &mut RtpHeader, data: &mut Vec<u8>, pt: Pt) {
         let mut orig_seq_no_16 = 0;
 
@@ -308,6 +308,7 @@ impl StreamTx {
         &mut self,
         now: Instant,
         exts: &ExtensionMap,
+        two_byte_ext: bool,
         twcc: &mut u64,
         params: &[PayloadParams],
         buf: &mut Vec<u8>,
@@ -399,7 +400,7 @@ impl StreamTx {
 
         buf.resize(DATAGRAM_MAX_PACKET_SIZE, 0);
 
-        let header_len = header.write_to(buf, exts);
+        let header_len = header.write_to(buf, exts, two_byte_ext);
         assert!(header_len % 4 == 0, "RTP header must be multiple of 4");
         header.header_len = header_len;
 
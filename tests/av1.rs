@@ -0,0 +1,98 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::format::Codec;
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+/// An OBU with size field.
+fn obu(obu_type: u8, payload: &[u8]) -> Vec<u8> {
+    assert!(payload.len() < 16_384);
+    let mut v = vec![obu_type << 3 | 0x02];
+    if payload.len() < 128 {
+        v.push(payload.len() as u8);
+    } else {
+        v.push(payload.len() as u8 | 0x80);
+        v.push((payload.len() >> 7) as u8);
+    }
+    v.extend_from_slice(payload);
+    v
+}
+
+#[test]
+pub fn av1_frames() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = *l
+        .codec_config()
+        .find(|p| p.spec().codec == Codec::Av1)
+        .unwrap();
+    let pt = params.pt();
+    assert_eq!(params.spec().format.profile, Some(0));
+
+    // Temporal delimiter, sequence header and a frame that needs several packets.
+    let mut frame = obu(2, &[]);
+    frame.extend(obu(1, &[0x0a; 12]));
+    frame.extend(obu(6, &[0x42; 3000]));
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, frame.clone())?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let data: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert!(data.len() > 200, "Not enough MediaData: {}", data.len());
+    assert!(data.iter().all(|d| d.params.spec().codec == Codec::Av1));
+    assert!(data.iter().all(|d| d.data == frame));
+
+    Ok(())
+}
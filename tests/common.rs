@@ -1,3 +1,4 @@
+#![cfg(feature = "openssl")]
 #![allow(unused)]
 use std::io::Cursor;
 use std::net::Ipv4Addr;
@@ -81,6 +82,7 @@ pub fn progress(l: &mut TestRtc, r: &mut TestRtc) -> Result<(), RtcError> {
                 let input = Input::Receive(
                     f.last,
                     Receive {
+                        proto: v.proto,
                         source: v.source,
                         destination: v.destination,
                         contents: (&*data).try_into()?,
@@ -121,6 +123,7 @@ pub fn progress_with_loss(l: &mut TestRtc, r: &mut TestRtc, loss: f32) -> Result
                 let input = Input::Receive(
                     f.last,
                     Receive {
+                        proto: v.proto,
                         source: v.source,
                         destination: v.destination,
                         contents: (&*data).try_into()?,
@@ -1,3 +1,5 @@
+#![cfg(feature = "openssl")]
+
 use std::net::Ipv4Addr;
 use std::time::Duration;
 
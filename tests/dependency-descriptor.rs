@@ -0,0 +1,166 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use str0m::format::Codec;
+use str0m::media::{Direction, MediaKind};
+use str0m::rtp::{DecodeTargetIndication, DependencyDescriptor, Extension};
+use str0m::rtp::{FrameDependencyStructure, FrameDependencyTemplate};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+/// One spatial and two temporal layers.
+fn l1t2() -> Arc<FrameDependencyStructure> {
+    use DecodeTargetIndication::*;
+
+    let template = |temporal_id, dtis: [DecodeTargetIndication; 2], fdiff, chain_diff| {
+        FrameDependencyTemplate {
+            spatial_id: 0,
+            temporal_id,
+            decode_target_indications: dtis.to_vec(),
+            frame_diffs: fdiff,
+            chain_diffs: vec![chain_diff],
+        }
+    };
+
+    Arc::new(FrameDependencyStructure {
+        template_id_offset: 0,
+        decode_target_count: 2,
+        chain_count: 1,
+        decode_target_protected_by_chain: vec![0, 0],
+        templates: vec![
+            template(0, [Switch, Switch], vec![], 0),
+            template(0, [Switch, Switch], vec![2], 2),
+            template(1, [NotPresent, Discardable], vec![1], 1),
+        ],
+        resolutions: vec![(1280, 720)],
+    })
+}
+
+fn descriptor(
+    structure: &Arc<FrameDependencyStructure>,
+    frame_number: u16,
+) -> DependencyDescriptor {
+    let template_id = match frame_number {
+        0 => 0,
+        n if n % 2 == 0 => 1,
+        _ => 2,
+    };
+    let t = &structure.templates[template_id as usize];
+
+    DependencyDescriptor {
+        start_of_frame: true,
+        end_of_frame: true,
+        template_id,
+        frame_number,
+        spatial_id: t.spatial_id,
+        temporal_id: t.temporal_id,
+        decode_target_indications: t.decode_target_indications.clone(),
+        frame_diffs: t.frame_diffs.clone(),
+        chain_diffs: t.chain_diffs.clone(),
+        active_decode_targets: None,
+        structure: structure.clone(),
+        attached_structure: frame_number == 0,
+    }
+}
+
+#[test]
+pub fn dependency_descriptor() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = Rtc::builder()
+        .set_extension(8, Extension::DependencyDescriptor)
+        .build();
+    let rtc_r = Rtc::builder()
+        .set_extension(8, Extension::DependencyDescriptor)
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l
+        .codec_config()
+        .find(|p| p.spec().codec == Codec::Av1)
+        .unwrap()
+        .pt();
+
+    // Temporal delimiter and a frame that needs several packets.
+    let mut frame = vec![0x12, 0x00, 0x32, 0xb8, 0x17];
+    frame.extend([0x42; 3000]);
+
+    let structure = l1t2();
+    let mut frame_number = 0;
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .dependency_descriptor(descriptor(&structure, frame_number))
+            .write(pt, wallclock, time, frame.clone())?;
+        frame_number += 1;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let data: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert!(data.len() > 200, "Not enough MediaData: {}", data.len());
+
+    for (i, d) in data.iter().enumerate() {
+        let dd = d
+            .ext_vals
+            .dependency_descriptor
+            .as_deref()
+            .expect("dependency descriptor");
+
+        // The sample carries the extension values of its last packet.
+        let mut expected = descriptor(&structure, i as u16);
+        expected.start_of_frame = false;
+        expected.attached_structure = false;
+
+        assert_eq!(dd, &expected);
+        assert_eq!(dd.resolution(), Some((1280, 720)));
+    }
+
+    Ok(())
+}
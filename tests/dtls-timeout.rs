@@ -0,0 +1,132 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::error::DtlsError;
+use str0m::media::{Direction, MediaKind};
+use str0m::net::Receive;
+use str0m::{Candidate, Event, Input, Output, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, TestRtc};
+
+fn is_dtls(data: &[u8]) -> bool {
+    matches!(data.first(), Some(20..=63))
+}
+
+/// Like `common::progress`, but lets `drop` throw away outgoing datagrams.
+fn progress_dropping(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    drop: &mut dyn FnMut(&[u8]) -> bool,
+) -> Result<(), RtcError> {
+    let (f, t) = if l.last < r.last { (l, r) } else { (r, l) };
+
+    loop {
+        f.span
+            .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+        match f.span.in_scope(|| f.rtc.poll_output())? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                if drop(&v.contents) {
+                    continue;
+                }
+                let input = Input::Receive(
+                    f.last,
+                    Receive {
+                        proto: v.proto,
+                        source: v.source,
+                        destination: v.destination,
+                        contents: (&*v.contents).try_into()?,
+                    },
+                );
+                t.span.in_scope(|| t.rtc.handle_input(input))?;
+            }
+            Output::Event(v) => {
+                f.events.push((f.last, v));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn setup(timeout: Duration) -> (TestRtc, TestRtc) {
+    let rtc_l = Rtc::builder().set_dtls_handshake_timeout(timeout).build();
+    let rtc_r = Rtc::builder().set_dtls_handshake_timeout(timeout).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into()).unwrap();
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into()).unwrap();
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    });
+
+    (l, r)
+}
+
+#[test]
+pub fn dtls_handshake_timeout() {
+    init_log();
+
+    let (mut l, mut r) = setup(Duration::from_secs(5));
+
+    let err = loop {
+        if let Err(e) = progress_dropping(&mut l, &mut r, &mut is_dtls) {
+            break e;
+        }
+        assert!(
+            l.duration() < Duration::from_secs(10),
+            "No handshake timeout"
+        );
+    };
+
+    assert!(matches!(
+        err,
+        RtcError::Dtls(DtlsError::HandshakeTimeout(d)) if d == Duration::from_secs(5)
+    ));
+    assert!(!l.is_alive() || !r.is_alive());
+    assert!(l.duration() >= Duration::from_secs(5));
+    assert!(!l.events.iter().any(|(_, e)| *e == Event::Connected));
+}
+
+#[test]
+pub fn dtls_retransmit_lost_flight() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = setup(Duration::from_secs(30));
+
+    let mut dropped = false;
+    let mut drop_first = |data: &[u8]| {
+        if dropped || !is_dtls(data) {
+            return false;
+        }
+        dropped = true;
+        true
+    };
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress_dropping(&mut l, &mut r, &mut drop_first)?;
+        assert!(l.duration() < Duration::from_secs(10), "No DTLS retransmit");
+    }
+
+    // The handshake waited for the retransmission timer.
+    assert!(l.duration() >= Duration::from_secs(1));
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+
+use sha2::Digest;
+use str0m::change::{DtlsCert, Fingerprint, SdpOffer};
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+fn fingerprint_line(hash_func: &str, bytes: Vec<u8>) -> String {
+    let f = Fingerprint {
+        hash_func: hash_func.into(),
+        bytes,
+    };
+    format!("a=fingerprint:{}\r\n", f.to_string())
+}
+
+/// Replace the a=fingerprint line in the offer from L with the lines made from its certificate.
+fn negotiate(lines: impl Fn(&[u8]) -> String) -> Result<(TestRtc, TestRtc), RtcError> {
+    let cert = DtlsCert::new();
+    let der = cert.certificate_der().unwrap();
+
+    let rtc_l = Rtc::builder().set_dtls_cert(cert).build();
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let replacement = lines(&der);
+
+    let offer = offer
+        .to_sdp_string()
+        .split_inclusive("\r\n")
+        .map(|l| {
+            if l.starts_with("a=fingerprint:") {
+                replacement.as_str()
+            } else {
+                l
+            }
+        })
+        .collect::<String>();
+    let offer = SdpOffer::from_sdp_string(&offer).unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        if l.duration().as_secs() > 5 {
+            panic!("Failed to connect");
+        }
+    }
+
+    Ok((l, r))
+}
+
+#[test]
+pub fn fingerprint_sha1() -> Result<(), RtcError> {
+    init_log();
+
+    negotiate(|der| fingerprint_line("sha-1", sha1::Sha1::digest(der).to_vec()))?;
+
+    Ok(())
+}
+
+#[test]
+pub fn fingerprint_several_one_matching() -> Result<(), RtcError> {
+    init_log();
+
+    negotiate(|der| {
+        fingerprint_line("sha-256", vec![0; 32])
+            + &fingerprint_line("SHA-512", sha2::Sha512::digest(der).to_vec())
+    })?;
+
+    Ok(())
+}
+
+#[test]
+pub fn fingerprint_none_matching() {
+    init_log();
+
+    let err = negotiate(|_| {
+        fingerprint_line("sha-1", vec![1; 20]) + &fingerprint_line("sha-384", vec![2; 48])
+    })
+    .err()
+    .expect("fingerprint mismatch");
+    assert!(matches!(err, RtcError::RemoteSdp(_)));
+}
@@ -1,12 +1,14 @@
+#![cfg(feature = "openssl")]
+
 use std::net::Ipv4Addr;
 use std::time::Duration;
 
-use str0m::RtcConfig;
 use str0m::{Candidate, Event, RtcError};
+use str0m::{IceConfig, IceConnectionState, RtcConfig};
 use tracing::info_span;
 
 mod common;
-use common::{init_log, progress, TestRtc};
+use common::{init_log, progress, progress_with_loss, TestRtc};
 
 #[test]
 pub fn flappy_ice_lite_state() -> Result<(), RtcError> {
@@ -58,3 +60,55 @@ pub fn flappy_ice_lite_state() -> Result<(), RtcError> {
 
     Ok(())
 }
+
+#[test]
+pub fn lossy_ice_patient_config() -> Result<(), RtcError> {
+    init_log();
+
+    // Wait much longer than the defaults before giving up on a pair.
+    let ice_config = IceConfig::new()
+        .set_max_retransmits(20)
+        .set_consent_timeout(Duration::from_secs(60));
+
+    let rtc = RtcConfig::new().set_ice_config(ice_config).build();
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc);
+    let rtc = RtcConfig::new().set_ice_config(ice_config).build();
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let _ = change.add_channel("My little channel".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    loop {
+        progress_with_loss(&mut l, &mut r, 0.3)?;
+
+        if l.duration() > Duration::from_secs(120) {
+            break;
+        }
+    }
+
+    for t in [&l, &r] {
+        let disconnected = t
+            .events
+            .iter()
+            .any(|(_, e)| *e == Event::IceConnectionStateChange(IceConnectionState::Disconnected));
+        assert!(!disconnected);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::format::Codec;
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn h265_frames() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = Rtc::builder().enable_h265(true).build();
+    let rtc_r = Rtc::builder().enable_h265(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = *l
+        .codec_config()
+        .find(|p| p.spec().codec == Codec::H265)
+        .unwrap();
+    let pt = params.pt();
+    assert_eq!(params.spec().format.profile_id, Some(1));
+
+    // VPS, SPS, PPS and a slice that needs several packets.
+    let mut frame = vec![0, 0, 0, 1, 0x40, 0x01, 0x0c, 0x01];
+    frame.extend([0, 0, 0, 1, 0x42, 0x01, 0x01, 0x01, 0x60]);
+    frame.extend([0, 0, 0, 1, 0x44, 0x01, 0xc0, 0xf2]);
+    frame.extend([0, 0, 0, 1, 0x26, 0x01]);
+    frame.extend([0x42; 3000]);
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, frame.clone())?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let data: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert!(data.len() > 200, "Not enough MediaData: {}", data.len());
+    assert!(data.iter().all(|d| d.params.spec().codec == Codec::H265));
+
+    // The depacketized frames are in Annex B, same as written.
+    assert!(data.iter().all(|d| d.data == frame));
+
+    Ok(())
+}
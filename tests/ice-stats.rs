@@ -0,0 +1,83 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::stats::CheckState;
+use str0m::{Candidate, Event, RtcConfig, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn ice_stats() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc = RtcConfig::new()
+        .set_stats_interval(Some(Duration::from_secs(1)))
+        .build();
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc);
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1.clone());
+    r.add_local_candidate(host2.clone());
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("My little channel".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    loop {
+        if let Some(mut chan) = l.channel(cid) {
+            chan.write(false, "Hello world! ".as_bytes())
+                .expect("to write string");
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let stats = l.rtc.ice_stats();
+    let selected = stats.selected().expect("a selected pair");
+    assert_eq!(selected.local.addr(), host1.addr());
+    assert_eq!(selected.remote.addr(), host2.addr());
+    assert_eq!(selected.state, CheckState::Succeeded);
+    assert!(selected.nominated);
+    assert!(selected.rtt.is_some());
+    assert!(selected.requests_sent > 0);
+    assert!(selected.responses_received > 0);
+    assert!(selected.requests_received > 0);
+    assert!(selected.bytes_sent > 0);
+
+    let stats = r.rtc.ice_stats();
+    let selected = stats.selected().expect("a selected pair");
+    assert!(selected.bytes_received > 0);
+
+    let events = l
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::IceStats(_)))
+        .count();
+    assert!(events > 0);
+
+    Ok(())
+}
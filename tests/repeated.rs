@@ -1,3 +1,5 @@
+#![cfg(feature = "openssl")]
+
 use std::time::Duration;
 
 use str0m::format::Codec;
@@ -0,0 +1,229 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::change::SdpOffer;
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, IceConfig, RtcConfig, RtcError, RtcpMuxPolicy};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, progress_with_loss, TestRtc};
+
+#[test]
+pub fn rtcp_mux_negotiated() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc = RtcConfig::new()
+        .set_rtcp_mux_policy(RtcpMuxPolicy::Negotiate)
+        .build();
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc);
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let rtcp1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1001).into())?.with_component_id(2)?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1);
+    l.add_local_candidate(rtcp1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let offer_str = offer.to_sdp_string();
+    assert!(offer_str.contains("a=rtcp-mux\r\n"));
+    assert!(offer_str.contains("a=rtcp:1001 IN IP4 1.1.1.1\r\n"));
+    assert!(offer_str.contains(" 1001 typ host"));
+
+    // R requires rtcp-mux, which is the default.
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    let answer_str = answer.to_sdp_string();
+    assert!(answer_str.contains("a=rtcp-mux\r\n"));
+    assert!(!answer_str.contains("a=rtcp:"));
+
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    // The RTCP component is gone.
+    let stats = l.rtc.ice_stats();
+    assert!(stats.pairs.iter().all(|p| p.local.component_id() == 1));
+
+    Ok(())
+}
+
+#[test]
+pub fn rtcp_without_mux() -> Result<(), RtcError> {
+    init_log();
+
+    let config = RtcConfig::new().set_rtcp_mux_policy(RtcpMuxPolicy::Negotiate);
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), config.clone().build());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), config.build());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let rtcp1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1001).into())?.with_component_id(2)?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    let rtcp2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2001).into())?.with_component_id(2)?;
+    l.add_local_candidate(host1);
+    l.add_local_candidate(rtcp1);
+    r.add_local_candidate(host2);
+    r.add_local_candidate(rtcp2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    // Pretend L is a legacy endpoint that doesn't do rtcp-mux.
+    let offer = offer.to_sdp_string().replace("a=rtcp-mux\r\n", "");
+    let offer = SdpOffer::from_sdp_string(&offer).unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    let answer_str = answer.to_sdp_string();
+    assert!(!answer_str.contains("a=rtcp-mux"));
+    assert!(answer_str.contains("a=rtcp:2001 IN IP4 2.2.2.2\r\n"));
+
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let data_a = vec![1_u8; 80];
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, data_a.clone())?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(10) {
+            break;
+        }
+    }
+
+    let media_count = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::MediaData(_)))
+        .count();
+    assert!(media_count > 1700, "Not enough MediaData: {}", media_count);
+
+    // RTP and RTCP each go over the selected pair of their component.
+    for rtc in [&l.rtc, &r.rtc] {
+        let stats = rtc.ice_stats();
+        for component_id in [1, 2] {
+            let selected = stats
+                .pairs
+                .iter()
+                .find(|p| p.selected && p.local.component_id() == component_id)
+                .expect("a selected pair per component");
+            assert!(selected.bytes_sent > 0);
+            assert!(selected.bytes_received > 0);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn rtcp_without_mux_consent_expired() -> Result<(), RtcError> {
+    init_log();
+
+    let ice_config = IceConfig::new().set_consent_timeout(Duration::from_secs(10));
+    let config = RtcConfig::new()
+        .set_rtcp_mux_policy(RtcpMuxPolicy::Negotiate)
+        .set_ice_config(ice_config);
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), config.clone().build());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), config.build());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let rtcp1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1001).into())?.with_component_id(2)?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    let rtcp2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2001).into())?.with_component_id(2)?;
+    l.add_local_candidate(host1);
+    l.add_local_candidate(rtcp1);
+    r.add_local_candidate(host2);
+    r.add_local_candidate(rtcp2);
+
+    let mut change = l.sdp_api();
+    change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let offer = offer.to_sdp_string().replace("a=rtcp-mux\r\n", "");
+    let offer = SdpOffer::from_sdp_string(&offer).unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    // Let the nominations of both components complete.
+    let settle = l.last.max(r.last);
+    loop {
+        if l.last > settle + Duration::from_secs(3) {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    // Both components are selected before the peer goes away.
+    let stats = l.rtc.ice_stats();
+    for component_id in [1, 2] {
+        assert!(stats
+            .pairs
+            .iter()
+            .any(|p| p.selected && p.local.component_id() == component_id));
+    }
+
+    // The peer disappears, and the next timeout is past the consent timeout of
+    // both components, which makes them expire in the same tick.
+    let dropped = l.last.max(r.last) + Duration::from_secs(11);
+    l.last = dropped;
+    r.last = dropped;
+    loop {
+        if l.last > dropped + Duration::from_secs(1) {
+            break;
+        }
+        progress_with_loss(&mut l, &mut r, 1.0)?;
+    }
+
+    // One event per component, so losing RTCP isn't silent.
+    let mut expired: Vec<_> = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::IceConsentExpired(component_id) => Some(*component_id),
+            _ => None,
+        })
+        .collect();
+    expired.sort();
+    assert_eq!(expired, vec![1, 2]);
+
+    let stats = l.rtc.ice_stats();
+    assert!(stats.pairs.iter().all(|p| !p.selected));
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "openssl")]
+
 use std::collections::VecDeque;
 use std::time::Duration;
 
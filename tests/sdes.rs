@@ -0,0 +1,115 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::crypto::{SrtpMasterKey, SrtpProfile};
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, MediaProtection, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn sdes_audio() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = Rtc::builder()
+        .set_media_protection(MediaProtection::Sdes)
+        .build();
+    let rtc_r = Rtc::builder()
+        .set_media_protection(MediaProtection::Sdes)
+        .set_srtp_profiles(&[SrtpProfile::Aes128CmSha1_80])
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let offer_str = offer.to_sdp_string();
+    assert!(offer_str.contains("m=audio 9 RTP/SAVPF"));
+    assert!(offer_str.contains("a=crypto:1 AEAD_AES_128_GCM inline:"));
+    assert!(offer_str.contains("a=crypto:2 AES_CM_128_HMAC_SHA1_80 inline:"));
+    assert!(!offer_str.contains("a=fingerprint"));
+    assert!(!offer_str.contains("a=setup"));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+
+    let answer_str = answer.to_sdp_string();
+    assert!(answer_str.contains("a=crypto:2 AES_CM_128_HMAC_SHA1_80 inline:"));
+    assert!(!answer_str.contains("a=crypto:1"));
+
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let data_a = vec![1_u8; 80];
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, data_a.clone())?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    assert!(r.events.iter().any(|(_, e)| matches!(e, Event::Connected)));
+
+    let media_count = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::MediaData(_)))
+        .count();
+
+    assert!(media_count > 400, "Not enough MediaData: {}", media_count);
+
+    Ok(())
+}
+
+#[test]
+pub fn sdes_direct_api_keys() -> Result<(), RtcError> {
+    let mut rtc = Rtc::builder()
+        .set_media_protection(MediaProtection::Sdes)
+        .build();
+
+    let key = SrtpMasterKey::random(SrtpProfile::Aes128CmSha1_80);
+    let other = SrtpMasterKey::random(SrtpProfile::AeadAes128Gcm);
+
+    let mut api = rtc.direct_api();
+    assert!(api.set_srtp_master_keys(key.clone(), other).is_err());
+    api.set_srtp_master_keys(key.clone(), key.clone())?;
+
+    // Without SDES, keys can't be set.
+    let mut rtc = Rtc::new();
+    assert!(rtc
+        .direct_api()
+        .set_srtp_master_keys(key.clone(), key)
+        .is_err());
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "openssl")]
+
 mod common;
 use common::init_log;
 use common::negotiate;
@@ -10,7 +12,7 @@ use str0m::format::PayloadParams;
 use str0m::media::Direction;
 use str0m::media::MediaKind;
 use str0m::rtp::{Extension, ExtensionMap};
-use str0m::Rtc;
+use str0m::{IceConfig, NominationPolicy, Rtc};
 use tracing::info_span;
 use tracing::Span;
 
@@ -470,3 +472,23 @@ fn h264(pt: u8) -> PayloadParams {
         },
     )
 }
+
+#[test]
+fn ice_options_renomination() {
+    init_log();
+
+    let ice_config = IceConfig::new().set_nomination_policy(NominationPolicy::Renomination);
+    let mut l = Rtc::builder().set_ice_config(ice_config).build();
+    let mut r = Rtc::new();
+
+    let mut change = l.sdp_api();
+    change.add_channel("data".into());
+    let (offer, _) = change.apply().unwrap();
+    assert!(offer
+        .to_sdp_string()
+        .contains("a=ice-options:trickle renomination\r\n"));
+
+    // Renomination is not configured on the answering side.
+    let answer = r.sdp_api().accept_offer(offer).unwrap();
+    assert!(answer.to_sdp_string().contains("a=ice-options:trickle\r\n"));
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::crypto::SrtpProfile;
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn srtp_aead_aes_256_gcm() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = Rtc::builder().set_srtp_profiles(SrtpProfile::ALL).build();
+    let rtc_r = Rtc::builder()
+        .set_srtp_profiles(&[SrtpProfile::AeadAes256Gcm])
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let data_a = vec![1_u8; 80];
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, data_a.clone())?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let media_count = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::MediaData(_)))
+        .count();
+
+    assert!(media_count > 400, "Not enough MediaData: {}", media_count);
+
+    // What was negotiated is available both as event and on the Rtc.
+    let info = r
+        .events
+        .iter()
+        .find_map(|(_, e)| match e {
+            Event::DtlsConnected(v) => Some(v),
+            _ => None,
+        })
+        .expect("DtlsConnected event");
+    assert_eq!(info.srtp_profile, SrtpProfile::AeadAes256Gcm);
+    assert_eq!(Some(&**info), r.dtls_info());
+
+    let info_l = l.dtls_info().unwrap();
+    assert_eq!(info_l.srtp_profile, SrtpProfile::AeadAes256Gcm);
+    assert_ne!(info_l.active, info.active);
+    assert_eq!(info_l.cipher, info.cipher);
+    assert_eq!(info_l.remote_certificates.len(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,193 @@
+#![cfg(feature = "openssl")]
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::rtp::{ExtensionValues, Ssrc};
+use str0m::{Candidate, Event, MediaProtection, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn unencrypted_sdp() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = Rtc::builder()
+        .set_media_protection(MediaProtection::Unencrypted)
+        .build();
+    let rtc_r = Rtc::builder()
+        .set_media_protection(MediaProtection::Unencrypted)
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let offer_str = offer.to_sdp_string();
+    assert!(offer_str.contains("m=audio 9 RTP/AVPF"));
+    assert!(!offer_str.contains("a=fingerprint"));
+    assert!(!offer_str.contains("a=crypto"));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    assert!(answer.to_sdp_string().contains("m=audio 9 RTP/AVPF"));
+
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let data_a = vec![1_u8; 80];
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, data_a.clone())?;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    assert!(l.events.iter().any(|(_, e)| matches!(e, Event::Connected)));
+    assert!(r.events.iter().any(|(_, e)| matches!(e, Event::Connected)));
+
+    let media_count = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::MediaData(_)))
+        .count();
+
+    assert!(media_count > 400, "Not enough MediaData: {}", media_count);
+
+    Ok(())
+}
+
+#[test]
+pub fn unencrypted_rejects_dtls_offer() -> Result<(), RtcError> {
+    let mut l = Rtc::new();
+    let mut r = Rtc::builder()
+        .set_media_protection(MediaProtection::Unencrypted)
+        .build();
+
+    let mut change = l.sdp_api();
+    change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, _) = change.apply().unwrap();
+
+    assert!(matches!(
+        r.sdp_api().accept_offer(offer),
+        Err(RtcError::RemoteSdp(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+pub fn unencrypted_direct() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = Rtc::builder()
+        .set_media_protection(MediaProtection::Unencrypted)
+        .set_rtp_mode(true)
+        .build();
+    let rtc_r = Rtc::builder()
+        .set_media_protection(MediaProtection::Unencrypted)
+        .set_rtp_mode(true)
+        .set_reordering_size_audio(0)
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into())?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into())?;
+    l.add_local_candidate(host1.clone());
+    l.add_remote_candidate(host2.clone());
+    r.add_local_candidate(host2);
+    r.add_remote_candidate(host1);
+
+    let creds_l = l.direct_api().local_ice_credentials();
+    let creds_r = r.direct_api().local_ice_credentials();
+    l.direct_api().set_remote_ice_credentials(creds_r);
+    r.direct_api().set_remote_ice_credentials(creds_l);
+
+    // No DTLS to start, media flows once ICE connects.
+    l.direct_api().set_ice_controlling(true);
+    r.direct_api().set_ice_controlling(false);
+
+    let mid = "aud".into();
+    let ssrc_tx: Ssrc = 42.into();
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc_tx, None, mid, None);
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+
+    for i in 0..10_u16 {
+        let wallclock = l.start + l.duration();
+        let mut direct = l.direct_api();
+        let stream = direct.stream_tx(&ssrc_tx).unwrap();
+        stream.write_rtp(
+            pt,
+            (47_000 + i as u64).into(),
+            47_000_000 + i as u32 * 960,
+            wallclock,
+            false,
+            ExtensionValues::default(),
+            false,
+            vec![i as u8; 20],
+        )?;
+
+        for _ in 0..10 {
+            progress(&mut l, &mut r)?;
+        }
+    }
+
+    let packets: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::RtpPacket(p) => Some(p),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(packets.len(), 10);
+    assert_eq!(packets[3].payload, vec![3; 20]);
+
+    Ok(())
+}